@@ -1,5 +1,4 @@
-use clap::{CommandFactory, Parser};
-use kubex;
+use clap::{CommandFactory, Parser, Subcommand};
 
 #[derive(Debug, Parser)]
 #[command(name = "kubex", about = "Showcase dynamic context completion support")]
@@ -11,10 +10,19 @@ struct Cli {
     #[arg(long, add = kubex::namespace_value_completer())]
     /// Please type the namespace name you want to target.
     namespace: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Commands {
+    /// Print a static completion script for the shell.
+    Completions { shell: kubex::clap_complete::Shell },
 }
 
 fn main() -> anyhow::Result<()> {
-    kubex::clap_complete::CompleteEnv::with_factory(Cli::command).complete();
+    kubex::claputil::install_completions(Cli::command);
 
     let cli = Cli::parse();
     println!(
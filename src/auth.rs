@@ -0,0 +1,728 @@
+use std::{error::Error as _, io, process::Stdio};
+
+use chrono::{DateTime, Utc};
+use kube::{
+    Config,
+    client::AuthError,
+    config::{AuthInfo, ExecConfig, Kubeconfig},
+};
+
+/// Authentication failure recognized by [`classify_auth_error`] or [`preflight_auth`],
+/// with a message telling the user what to fix.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum AuthProblem {
+    /// The exec credential plugin could not be started, usually because it is not on `PATH`.
+    #[error(
+        "exec credential plugin `{}` is not found; install it or fix users[].user.exec.command in the kubeconfig",
+        command.as_deref().unwrap_or("(unknown)")
+    )]
+    ExecPluginNotFound { command: Option<String> },
+    /// The exec credential plugin exited with a failure.
+    #[error(
+        "exec credential plugin `{command}` failed{}{}; run it manually to see whether you need to log in again",
+        exit_code.map(|code| format!(" with exit code {code}")).unwrap_or_default(),
+        if stderr.is_empty() { String::new() } else { format!(": {stderr}") }
+    )]
+    ExecPluginFailed {
+        command: String,
+        exit_code: Option<i32>,
+        stderr: String,
+    },
+    /// The exec credential plugin succeeded but its output is not a usable credential.
+    #[error(
+        "exec credential plugin `{}` returned an invalid credential: {reason}",
+        command.as_deref().unwrap_or("(unknown)")
+    )]
+    ExecPluginOutputInvalid {
+        command: Option<String>,
+        reason: String,
+    },
+    /// The credential returned by the exec credential plugin has already expired.
+    #[error(
+        "exec credential plugin `{command}` returned a token that expired at {expired_at}; refresh your login"
+    )]
+    TokenExpired {
+        command: String,
+        expired_at: DateTime<Utc>,
+    },
+    /// The API server rejected the credential (401), e.g. because the token expired.
+    #[error("the server rejected the credentials ({message}); log in again or refresh the token")]
+    Unauthorized { message: String },
+}
+
+/// Recognizes authentication failures in `err`, including ones wrapped by the client's
+/// middleware, so that they can be reported with an actionable message.
+///
+/// Returns `None` if `err` is not an authentication failure.
+pub fn classify_auth_error(err: &kube::Error) -> Option<AuthProblem> {
+    match err {
+        kube::Error::Api(response) if response.code == 401 => {
+            return Some(AuthProblem::Unauthorized {
+                message: response.message.clone(),
+            });
+        }
+        kube::Error::Auth(err) => return classify(err),
+        _ => {}
+    }
+    let mut source = err.source();
+    while let Some(err) = source {
+        if let Some(err) = err.downcast_ref::<AuthError>() {
+            return classify(err);
+        }
+        source = err.source();
+    }
+    None
+}
+
+fn classify(err: &AuthError) -> Option<AuthProblem> {
+    match err {
+        AuthError::AuthExecStart(err) if err.kind() == io::ErrorKind::NotFound => {
+            Some(AuthProblem::ExecPluginNotFound { command: None })
+        }
+        AuthError::AuthExecStart(err) => Some(AuthProblem::ExecPluginFailed {
+            command: String::from("(unknown)"),
+            exit_code: None,
+            stderr: err.to_string(),
+        }),
+        AuthError::AuthExecRun { cmd, status, out } => Some(AuthProblem::ExecPluginFailed {
+            command: cmd.clone(),
+            exit_code: status.code(),
+            stderr: String::from_utf8_lossy(&out.stderr).trim().to_string(),
+        }),
+        AuthError::AuthExecParse(err) => Some(AuthProblem::ExecPluginOutputInvalid {
+            command: None,
+            reason: err.to_string(),
+        }),
+        AuthError::MissingCommand => Some(AuthProblem::ExecPluginNotFound { command: None }),
+        // NOTE: Command-based auth providers report spawn failures as a formatted string:
+        // "Executing {cmd} failed: {io::Error:?}".
+        AuthError::AuthExec(message) if message.contains("kind: NotFound") => {
+            Some(AuthProblem::ExecPluginNotFound {
+                command: message
+                    .strip_prefix("Executing ")
+                    .and_then(|rest| rest.split_once(" failed: "))
+                    .map(|(command, _)| command.to_string()),
+            })
+        }
+        AuthError::AuthExec(message) => Some(AuthProblem::ExecPluginOutputInvalid {
+            command: None,
+            reason: message.clone(),
+        }),
+        _ => None,
+    }
+}
+
+/// Runs the exec credential plugin of `config` once, so that a broken or logged-out plugin
+/// is reported up front with the command line that failed instead of on the first request.
+///
+/// Succeeds without doing anything if `config` does not use an exec credential plugin.
+///
+/// # Errors
+/// Returns the [`AuthProblem`] found, if any.
+pub async fn preflight_auth(config: &Config) -> Result<(), AuthProblem> {
+    let Some(exec) = &config.auth_info.exec else {
+        return Ok(());
+    };
+    let Some(program) = &exec.command else {
+        return Err(AuthProblem::ExecPluginNotFound { command: None });
+    };
+    let command_line = exec_command_line(program, exec);
+
+    let mut command = tokio::process::Command::new(program);
+    command
+        .args(exec.args.iter().flatten())
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    for env in exec.env.iter().flatten() {
+        if let (Some(name), Some(value)) = (env.get("name"), env.get("value")) {
+            command.env(name, value);
+        }
+    }
+    for name in exec.drop_env.iter().flatten() {
+        command.env_remove(name);
+    }
+    command.env(
+        "KUBERNETES_EXEC_INFO",
+        serde_json::json!({
+            "apiVersion": exec.api_version,
+            "kind": "ExecCredential",
+            // NOTE: The plugin cannot prompt, as its stdin is not attached to the terminal.
+            "spec": { "interactive": false },
+        })
+        .to_string(),
+    );
+
+    let output = command.output().await.map_err(|err| {
+        if err.kind() == io::ErrorKind::NotFound {
+            AuthProblem::ExecPluginNotFound {
+                command: Some(program.clone()),
+            }
+        } else {
+            AuthProblem::ExecPluginFailed {
+                command: command_line.clone(),
+                exit_code: None,
+                stderr: err.to_string(),
+            }
+        }
+    })?;
+    if !output.status.success() {
+        return Err(AuthProblem::ExecPluginFailed {
+            command: command_line,
+            exit_code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+
+    let invalid = |reason: String| AuthProblem::ExecPluginOutputInvalid {
+        command: Some(command_line.clone()),
+        reason,
+    };
+    let credential: serde_json::Value =
+        serde_json::from_slice(&output.stdout).map_err(|err| invalid(err.to_string()))?;
+    let status = credential
+        .get("status")
+        .ok_or_else(|| invalid(String::from("status is missing")))?;
+    if status.get("token").is_none() && status.get("clientCertificateData").is_none() {
+        return Err(invalid(String::from(
+            "status contains neither a token nor a client certificate",
+        )));
+    }
+    if let Some(expiration) = status
+        .get("expirationTimestamp")
+        .and_then(serde_json::Value::as_str)
+    {
+        let expired_at = DateTime::parse_from_rfc3339(expiration)
+            .map_err(|err| invalid(format!("malformed expirationTimestamp: {err}")))?
+            .with_timezone(&Utc);
+        if expired_at <= Utc::now() {
+            return Err(AuthProblem::TokenExpired {
+                command: command_line,
+                expired_at,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn exec_command_line(program: &str, exec: &ExecConfig) -> String {
+    std::iter::once(program)
+        .chain(exec.args.iter().flatten().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Authentication mechanism configured for a kubeconfig user, without any secret material.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AuthMethod {
+    /// Exec credential plugin running `command`.
+    Exec { command: Option<String> },
+    /// Legacy auth provider such as `oidc`.
+    AuthProvider { name: String },
+    /// Bearer token, inline or read from `file`.
+    Token { file: Option<String> },
+    /// Username and password.
+    Basic { username: Option<String> },
+    /// Client certificate, with its expiry if it could be inspected (requires the `x509` feature).
+    ClientCertificate { expires_at: Option<DateTime<Utc>> },
+    /// No credentials are configured.
+    None,
+}
+
+/// Summary of how a context authenticates, returned by [`auth_summary`].
+///
+/// [`Display`](std::fmt::Display) prints the mechanism, e.g. `exec plugin (aws)` or
+/// `client certificate expiring 2025-04-01`, and never any secret.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthSummary {
+    pub context: String,
+    /// Kubeconfig user the context refers to, if any.
+    pub user: Option<String>,
+    pub method: AuthMethod,
+}
+
+impl AuthSummary {
+    /// Checks if the client certificate has expired.
+    pub fn is_expired(&self) -> bool {
+        self.method.is_expired()
+    }
+}
+
+impl std::fmt::Display for AuthSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.method.fmt(f)
+    }
+}
+
+impl AuthMethod {
+    /// Checks if this is a client certificate that has expired.
+    pub fn is_expired(&self) -> bool {
+        matches!(
+            self,
+            Self::ClientCertificate { expires_at: Some(expires_at) } if *expires_at <= Utc::now()
+        )
+    }
+}
+
+/// Prints the mechanism like [`AuthSummary`] does.
+impl std::fmt::Display for AuthMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Exec { command } => {
+                let name = command
+                    .as_deref()
+                    .map(|command| command.rsplit(['/', '\\']).next().unwrap_or(command))
+                    .unwrap_or("unknown");
+                write!(f, "exec plugin ({name})")
+            }
+            Self::AuthProvider { name } => write!(f, "auth provider ({name})"),
+            Self::Token { file: Some(file) } => write!(f, "bearer token from {file}"),
+            Self::Token { file: None } => f.write_str("bearer token"),
+            Self::Basic {
+                username: Some(username),
+            } => write!(f, "basic auth as {username}"),
+            Self::Basic { username: None } => f.write_str("basic auth"),
+            Self::ClientCertificate {
+                expires_at: Some(expires_at),
+            } => {
+                let state = if self.is_expired() {
+                    "expired"
+                } else {
+                    "expiring"
+                };
+                write!(
+                    f,
+                    "client certificate {state} {}",
+                    expires_at.format("%Y-%m-%d")
+                )
+            }
+            Self::ClientCertificate { expires_at: None } => f.write_str("client certificate"),
+            Self::None => f.write_str("none"),
+        }
+    }
+}
+
+/// Summarizes how `context` authenticates according to the kubeconfig (honoring `KUBECONFIG`).
+///
+/// If several mechanisms are configured, the one taking precedence is reported, in the order
+/// exec plugin, auth provider, token, basic auth and client certificate.
+/// With the `x509` feature, the expiry of a client certificate is read from the certificate.
+///
+/// # Errors
+/// Returns an error if the kubeconfig cannot be read, if the context is not defined,
+/// or if the client certificate cannot be read or parsed.
+pub fn auth_summary(context: &str) -> anyhow::Result<AuthSummary> {
+    auth_summary_with(&crate::kubeconfig::read_merged_kubeconfig()?, context)
+}
+
+/// Same as [`auth_summary`], but looks `context` up in an already loaded `kubeconfig`.
+///
+/// # Errors
+/// See [`auth_summary`].
+pub fn auth_summary_with(kubeconfig: &Kubeconfig, context: &str) -> anyhow::Result<AuthSummary> {
+    let named_context = kubeconfig
+        .contexts
+        .iter()
+        .find(|c| c.name == context)
+        .ok_or_else(|| anyhow::anyhow!("context {context} is not found in kubeconfig"))?;
+    let user = named_context
+        .context
+        .as_ref()
+        .and_then(|ctx| ctx.user.clone());
+    let auth_info = user.as_ref().and_then(|user| {
+        kubeconfig
+            .auth_infos
+            .iter()
+            .find(|auth_info| &auth_info.name == user)
+            .and_then(|auth_info| auth_info.auth_info.as_ref())
+    });
+    Ok(AuthSummary {
+        context: context.to_string(),
+        user,
+        method: auth_info.map_or(Ok(AuthMethod::None), auth_method)?,
+    })
+}
+
+/// Returns the mechanism taking precedence in `auth_info`, see [`auth_summary`].
+pub(crate) fn auth_method(auth_info: &AuthInfo) -> anyhow::Result<AuthMethod> {
+    if let Some(exec) = &auth_info.exec {
+        return Ok(AuthMethod::Exec {
+            command: exec.command.clone(),
+        });
+    }
+    if let Some(provider) = &auth_info.auth_provider {
+        return Ok(AuthMethod::AuthProvider {
+            name: provider.name.clone(),
+        });
+    }
+    if auth_info.token.is_some() || auth_info.token_file.is_some() {
+        return Ok(AuthMethod::Token {
+            file: auth_info.token_file.clone(),
+        });
+    }
+    if auth_info.username.is_some() || auth_info.password.is_some() {
+        return Ok(AuthMethod::Basic {
+            username: auth_info.username.clone(),
+        });
+    }
+    if auth_info.client_certificate.is_some() || auth_info.client_certificate_data.is_some() {
+        return Ok(AuthMethod::ClientCertificate {
+            expires_at: certificate_expiry(auth_info)?,
+        });
+    }
+    Ok(AuthMethod::None)
+}
+
+#[cfg(feature = "x509")]
+fn certificate_expiry(auth_info: &AuthInfo) -> anyhow::Result<Option<DateTime<Utc>>> {
+    use anyhow::Context as _;
+    use base64::Engine as _;
+
+    let pem = match (
+        &auth_info.client_certificate_data,
+        &auth_info.client_certificate,
+    ) {
+        (Some(data), _) => base64::engine::general_purpose::STANDARD
+            .decode(data.trim())
+            .context("client-certificate-data is not valid base64")?,
+        (None, Some(path)) => std::fs::read(path)
+            .with_context(|| format!("failed to read client certificate {path}"))?,
+        (None, None) => return Ok(None),
+    };
+    let (_, pem) = x509_parser::pem::parse_x509_pem(&pem)
+        .map_err(|err| anyhow::anyhow!("client certificate is not valid PEM: {err}"))?;
+    let certificate = pem.parse_x509().map_err(|err| {
+        anyhow::anyhow!("client certificate is not a valid X.509 certificate: {err}")
+    })?;
+    let not_after = certificate.validity().not_after.timestamp();
+    Ok(DateTime::from_timestamp(not_after, 0))
+}
+
+#[cfg(not(feature = "x509"))]
+fn certificate_expiry(_auth_info: &AuthInfo) -> anyhow::Result<Option<DateTime<Utc>>> {
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::KubeconfigFixture;
+
+    /// Self-signed certificate valid from 2020-01-01 to 2025-04-01.
+    #[cfg(feature = "x509")]
+    const EXPIRED_CERTIFICATE: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIBhjCCASugAwIBAgIUDnExCDZg+IWKYqZtQmIJ4fPTKygwCgYIKoZIzj0EAwIw
+GDEWMBQGA1UEAwwNa3ViZXgtZXhwaXJlZDAeFw0yMDAxMDEwMDAwMDBaFw0yNTA0
+MDEwMDAwMDBaMBgxFjAUBgNVBAMMDWt1YmV4LWV4cGlyZWQwWTATBgcqhkjOPQIB
+BggqhkjOPQMBBwNCAASk2x7pc4HNvOBK0t2v+rjWcQXOIHrR55WeaBYD78AmrxVF
+HTDChdBrea3SZbOfHnrJzANP9vKqW/fXazPaW4Jeo1MwUTAdBgNVHQ4EFgQUULTw
+cBFQMUMjzZBLUSP6+AdtXUEwHwYDVR0jBBgwFoAUULTwcBFQMUMjzZBLUSP6+Adt
+XUEwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNJADBGAiEAlqSX589EJg9F
+WQc0nWWh55f+kzcFpVu3TLNq6q+eKeICIQDrspkfzTQ+/gvnoDMA3EYoySDc/Ni7
+65jFkp81FmonlQ==
+-----END CERTIFICATE-----
+";
+
+    /// Returns a kubeconfig with a context named after each user, where `users` maps
+    /// user names to their `users[].user` stanza.
+    fn users_kubeconfig(users: serde_json::Value) -> Kubeconfig {
+        let users = users.as_object().unwrap();
+        let mut fixture = KubeconfigFixture::new();
+        for user in users.keys() {
+            fixture = fixture.context(user, "prod", user);
+        }
+        let mut kubeconfig = fixture.kubeconfig().clone();
+        for auth_info in &mut kubeconfig.auth_infos {
+            auth_info.auth_info =
+                Some(serde_json::from_value(users[&auth_info.name].clone()).unwrap());
+        }
+        kubeconfig
+    }
+
+    #[test]
+    fn auth_summary_describes_each_mechanism_without_secrets() {
+        let kubeconfig = users_kubeconfig(serde_json::json!({
+            "exec": {
+                "exec": {
+                    "apiVersion": "client.authentication.k8s.io/v1beta1",
+                    "command": "/usr/local/bin/aws",
+                    "args": ["eks", "get-token"],
+                },
+                "token": "secret-token",
+            },
+            "oidc": { "auth-provider": { "name": "oidc", "config": { "id-token": "secret-id-token" } } },
+            "token": { "token": "secret-token" },
+            "token-file": { "tokenFile": "/var/run/kubex/token" },
+            "basic": { "username": "alice", "password": "secret-password" },
+            "password": { "password": "secret-password" },
+            "none": {},
+        }));
+        let summary = |context: &str| auth_summary_with(&kubeconfig, context).unwrap();
+
+        let exec = summary("exec");
+        assert_eq!(exec.context, "exec");
+        assert_eq!(exec.user.as_deref(), Some("exec"));
+        // The exec plugin takes precedence over the token.
+        assert_eq!(
+            exec.method,
+            AuthMethod::Exec {
+                command: Some(String::from("/usr/local/bin/aws")),
+            }
+        );
+        let cases = [
+            ("exec", "exec plugin (aws)"),
+            ("oidc", "auth provider (oidc)"),
+            ("token", "bearer token"),
+            ("token-file", "bearer token from /var/run/kubex/token"),
+            ("basic", "basic auth as alice"),
+            ("password", "basic auth"),
+            ("none", "none"),
+        ];
+        for (context, expected) in cases {
+            let summary = summary(context);
+            assert_eq!(summary.to_string(), expected);
+            assert!(!format!("{summary:?}").contains("secret-"), "{summary:?}");
+            assert!(!summary.is_expired());
+        }
+
+        let err = auth_summary_with(&kubeconfig, "staging").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "context staging is not found in kubeconfig"
+        );
+    }
+
+    #[test]
+    fn auth_summary_reads_kubeconfig_env() {
+        let _guard = KubeconfigFixture::new()
+            .context("prod", "prod-cluster", "admin")
+            .install()
+            .unwrap();
+        let summary = auth_summary("prod").unwrap();
+        assert_eq!(summary.user.as_deref(), Some("admin"));
+        assert_eq!(summary.method, AuthMethod::Token { file: None });
+    }
+
+    #[cfg(not(feature = "x509"))]
+    #[test]
+    fn auth_summary_does_not_inspect_client_certificates() {
+        let kubeconfig = users_kubeconfig(serde_json::json!({
+            "cert": { "client-certificate-data": "not base64", "client-key-data": "secret" },
+        }));
+        let summary = auth_summary_with(&kubeconfig, "cert").unwrap();
+        assert_eq!(
+            summary.method,
+            AuthMethod::ClientCertificate { expires_at: None }
+        );
+        assert_eq!(summary.to_string(), "client certificate");
+    }
+
+    #[cfg(feature = "x509")]
+    #[test]
+    fn auth_summary_reports_the_expiry_of_client_certificates() {
+        use base64::Engine as _;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("client.crt");
+        std::fs::write(&path, crate::testutil::TEST_CERTIFICATE).unwrap();
+        let encode = |pem: &str| base64::engine::general_purpose::STANDARD.encode(pem);
+        let kubeconfig = users_kubeconfig(serde_json::json!({
+            "expired": { "client-certificate-data": encode(EXPIRED_CERTIFICATE), "client-key-data": "secret" },
+            "valid": { "client-certificate": path, "client-key": "/secret.key" },
+            "missing": { "client-certificate": dir.path().join("missing.crt") },
+            "not-base64": { "client-certificate-data": "not base64" },
+            "not-pem": { "client-certificate-data": encode("not a certificate") },
+        }));
+        let summary = |context: &str| auth_summary_with(&kubeconfig, context);
+
+        let expired = summary("expired").unwrap();
+        assert!(expired.is_expired());
+        assert_eq!(expired.to_string(), "client certificate expired 2025-04-01");
+        let valid = summary("valid").unwrap();
+        assert!(!valid.is_expired());
+        assert_eq!(valid.to_string(), "client certificate expiring 2099-01-01");
+
+        let err = summary("missing").unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("failed to read client certificate"),
+            "{err}"
+        );
+        let err = summary("not-base64").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "client-certificate-data is not valid base64"
+        );
+        let err = summary("not-pem").unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("client certificate is not valid PEM"),
+            "{err}"
+        );
+    }
+
+    // NOTE: The exec plugins of these tests are shell scripts.
+    #[cfg(unix)]
+    mod exec {
+        use super::*;
+        use crate::testutil::{MockServer, status_response};
+
+        /// Returns a kubeconfig whose `prod` context runs `sh -c script` as its exec plugin,
+        /// with `$PLUGIN_ENV` set to `set`, against `server`.
+        fn exec_kubeconfig(script: &str, server: &str) -> Kubeconfig {
+            let yaml = serde_json::json!({
+                "apiVersion": "v1",
+                "kind": "Config",
+                "current-context": "prod",
+                "clusters": [{ "name": "prod", "cluster": { "server": server } }],
+                "contexts": [{ "name": "prod", "context": { "cluster": "prod", "user": "exec" } }],
+                "users": [{
+                    "name": "exec",
+                    "user": {
+                        "exec": {
+                            "apiVersion": "client.authentication.k8s.io/v1beta1",
+                            "command": "sh",
+                            "args": ["-c", script],
+                            "env": [{ "name": "PLUGIN_ENV", "value": "set" }],
+                        },
+                    },
+                }],
+            });
+            serde_json::from_value(yaml).unwrap()
+        }
+
+        async fn exec_config(script: &str) -> Config {
+            let kubeconfig = exec_kubeconfig(script, "https://127.0.0.1:6443");
+            Config::from_custom_kubeconfig(kubeconfig, &Default::default())
+                .await
+                .unwrap()
+        }
+
+        fn credential(status: serde_json::Value) -> String {
+            let credential = serde_json::json!({
+                "apiVersion": "client.authentication.k8s.io/v1beta1",
+                "kind": "ExecCredential",
+                "status": status,
+            });
+            format!("echo '{credential}'")
+        }
+
+        #[tokio::test]
+        async fn preflight_auth_accepts_a_valid_credential() {
+            let script = credential(serde_json::json!({ "token": "t" }));
+            preflight_auth(&exec_config(&script).await).await.unwrap();
+
+            // The plugin runs non-interactively with the configured environment.
+            let script = format!(
+                r#"[ "$PLUGIN_ENV" = set ] && echo "$KUBERNETES_EXEC_INFO" | grep -q '"interactive":false' && {}"#,
+                credential(serde_json::json!({
+                    "token": "t",
+                    "expirationTimestamp": "2999-01-01T00:00:00Z",
+                }))
+            );
+            preflight_auth(&exec_config(&script).await).await.unwrap();
+
+            let config = Config::new("https://127.0.0.1:6443".parse().unwrap());
+            preflight_auth(&config).await.unwrap();
+        }
+
+        #[tokio::test]
+        async fn preflight_auth_reports_each_plugin_failure() {
+            let err = preflight_auth(&exec_config("echo 'login required' >&2; exit 3").await)
+                .await
+                .unwrap_err();
+            assert_eq!(
+                err,
+                AuthProblem::ExecPluginFailed {
+                    command: String::from("sh -c echo 'login required' >&2; exit 3"),
+                    exit_code: Some(3),
+                    stderr: String::from("login required"),
+                }
+            );
+
+            let err = preflight_auth(&exec_config("echo not json").await)
+                .await
+                .unwrap_err();
+            assert!(
+                matches!(err, AuthProblem::ExecPluginOutputInvalid { .. }),
+                "{err}"
+            );
+            let script = credential(serde_json::json!({}));
+            let err = preflight_auth(&exec_config(&script).await)
+                .await
+                .unwrap_err();
+            assert!(
+                err.to_string()
+                    .ends_with("status contains neither a token nor a client certificate"),
+                "{err}"
+            );
+
+            let script = credential(serde_json::json!({
+                "token": "t",
+                "expirationTimestamp": "2020-01-01T00:00:00Z",
+            }));
+            let err = preflight_auth(&exec_config(&script).await)
+                .await
+                .unwrap_err();
+            let AuthProblem::TokenExpired { expired_at, .. } = &err else {
+                panic!("{err}");
+            };
+            assert_eq!(expired_at.to_rfc3339(), "2020-01-01T00:00:00+00:00");
+            assert!(err.to_string().contains("refresh your login"), "{err}");
+
+            let mut config = exec_config("true").await;
+            let exec = config.auth_info.exec.as_mut().unwrap();
+            exec.command = Some(String::from("kubex-missing-plugin"));
+            let err = preflight_auth(&config).await.unwrap_err();
+            assert_eq!(
+                err,
+                AuthProblem::ExecPluginNotFound {
+                    command: Some(String::from("kubex-missing-plugin")),
+                }
+            );
+        }
+
+        #[tokio::test]
+        async fn classify_auth_error_recognizes_failures_of_requests() {
+            let server = MockServer::start(|_| status_response(401));
+            let request = |script: &str| {
+                let kubeconfig = exec_kubeconfig(script, server.url());
+                async move {
+                    let config = Config::from_custom_kubeconfig(kubeconfig, &Default::default())
+                        .await
+                        .unwrap();
+                    // NOTE: The plugin may already run, and fail, when building the client.
+                    match kube::Client::try_from(config) {
+                        Ok(client) => client.apiserver_version().await.unwrap_err(),
+                        Err(err) => err,
+                    }
+                }
+            };
+
+            let err = request("echo 'session expired' >&2; exit 1").await;
+            let problem = classify_auth_error(&err);
+            assert!(
+                matches!(
+                    &problem,
+                    Some(AuthProblem::ExecPluginFailed { exit_code: Some(1), stderr, .. })
+                        if stderr == "session expired"
+                ),
+                "{err}: {problem:?}"
+            );
+
+            let err = request(&credential(serde_json::json!({ "token": "t" }))).await;
+            assert_eq!(
+                classify_auth_error(&err),
+                Some(AuthProblem::Unauthorized {
+                    message: String::from("mock error 401"),
+                })
+            );
+
+            let server = MockServer::start(|_| status_response(500));
+            let err = server.client().apiserver_version().await.unwrap_err();
+            assert_eq!(classify_auth_error(&err), None);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::APIResource;
+
+/// Verbs supported by the built-in resources in this catalog.
+const VERBS: &[&str] = &[
+    "create",
+    "delete",
+    "deletecollection",
+    "get",
+    "list",
+    "patch",
+    "update",
+    "watch",
+];
+
+/// `(group, version, name, singular name, kind, namespaced, short names, in "all" category)`
+type Entry = (
+    &'static str,
+    &'static str,
+    &'static str,
+    &'static str,
+    &'static str,
+    bool,
+    &'static [&'static str],
+    bool,
+);
+
+#[rustfmt::skip]
+const CATALOG: &[Entry] = &[
+    ("core", "v1", "componentstatuses", "componentstatus", "ComponentStatus", false, &["cs"], false),
+    ("core", "v1", "configmaps", "configmap", "ConfigMap", true, &["cm"], false),
+    ("core", "v1", "endpoints", "endpoints", "Endpoints", true, &["ep"], false),
+    ("core", "v1", "events", "event", "Event", true, &["ev"], false),
+    ("core", "v1", "limitranges", "limitrange", "LimitRange", true, &["limits"], false),
+    ("core", "v1", "namespaces", "namespace", "Namespace", false, &["ns"], false),
+    ("core", "v1", "nodes", "node", "Node", false, &["no"], false),
+    ("core", "v1", "persistentvolumeclaims", "persistentvolumeclaim", "PersistentVolumeClaim", true, &["pvc"], false),
+    ("core", "v1", "persistentvolumes", "persistentvolume", "PersistentVolume", false, &["pv"], false),
+    ("core", "v1", "pods", "pod", "Pod", true, &["po"], true),
+    ("core", "v1", "podtemplates", "podtemplate", "PodTemplate", true, &[], false),
+    ("core", "v1", "replicationcontrollers", "replicationcontroller", "ReplicationController", true, &["rc"], true),
+    ("core", "v1", "resourcequotas", "resourcequota", "ResourceQuota", true, &["quota"], false),
+    ("core", "v1", "secrets", "secret", "Secret", true, &[], false),
+    ("core", "v1", "serviceaccounts", "serviceaccount", "ServiceAccount", true, &["sa"], false),
+    ("core", "v1", "services", "service", "Service", true, &["svc"], true),
+    ("admissionregistration.k8s.io", "v1", "mutatingwebhookconfigurations", "mutatingwebhookconfiguration", "MutatingWebhookConfiguration", false, &[], false),
+    ("admissionregistration.k8s.io", "v1", "validatingwebhookconfigurations", "validatingwebhookconfiguration", "ValidatingWebhookConfiguration", false, &[], false),
+    ("apiextensions.k8s.io", "v1", "customresourcedefinitions", "customresourcedefinition", "CustomResourceDefinition", false, &["crd", "crds"], false),
+    ("apps", "v1", "controllerrevisions", "controllerrevision", "ControllerRevision", true, &[], false),
+    ("apps", "v1", "daemonsets", "daemonset", "DaemonSet", true, &["ds"], true),
+    ("apps", "v1", "deployments", "deployment", "Deployment", true, &["deploy"], true),
+    ("apps", "v1", "replicasets", "replicaset", "ReplicaSet", true, &["rs"], true),
+    ("apps", "v1", "statefulsets", "statefulset", "StatefulSet", true, &["sts"], true),
+    ("autoscaling", "v2", "horizontalpodautoscalers", "horizontalpodautoscaler", "HorizontalPodAutoscaler", true, &["hpa"], true),
+    ("batch", "v1", "cronjobs", "cronjob", "CronJob", true, &["cj"], true),
+    ("batch", "v1", "jobs", "job", "Job", true, &[], true),
+    ("certificates.k8s.io", "v1", "certificatesigningrequests", "certificatesigningrequest", "CertificateSigningRequest", false, &["csr"], false),
+    ("coordination.k8s.io", "v1", "leases", "lease", "Lease", true, &[], false),
+    ("discovery.k8s.io", "v1", "endpointslices", "endpointslice", "EndpointSlice", true, &[], false),
+    ("events.k8s.io", "v1", "events", "event", "Event", true, &["ev"], false),
+    ("networking.k8s.io", "v1", "ingressclasses", "ingressclass", "IngressClass", false, &[], false),
+    ("networking.k8s.io", "v1", "ingresses", "ingress", "Ingress", true, &["ing"], false),
+    ("networking.k8s.io", "v1", "networkpolicies", "networkpolicy", "NetworkPolicy", true, &["netpol"], false),
+    ("node.k8s.io", "v1", "runtimeclasses", "runtimeclass", "RuntimeClass", false, &[], false),
+    ("policy", "v1", "poddisruptionbudgets", "poddisruptionbudget", "PodDisruptionBudget", true, &["pdb"], false),
+    ("rbac.authorization.k8s.io", "v1", "clusterrolebindings", "clusterrolebinding", "ClusterRoleBinding", false, &[], false),
+    ("rbac.authorization.k8s.io", "v1", "clusterroles", "clusterrole", "ClusterRole", false, &[], false),
+    ("rbac.authorization.k8s.io", "v1", "rolebindings", "rolebinding", "RoleBinding", true, &[], false),
+    ("rbac.authorization.k8s.io", "v1", "roles", "role", "Role", true, &[], false),
+    ("scheduling.k8s.io", "v1", "priorityclasses", "priorityclass", "PriorityClass", false, &["pc"], false),
+    ("storage.k8s.io", "v1", "csidrivers", "csidriver", "CSIDriver", false, &[], false),
+    ("storage.k8s.io", "v1", "csinodes", "csinode", "CSINode", false, &[], false),
+    ("storage.k8s.io", "v1", "storageclasses", "storageclass", "StorageClass", false, &["sc"], false),
+    ("storage.k8s.io", "v1", "volumeattachments", "volumeattachment", "VolumeAttachment", false, &[], false),
+];
+
+/// Returns a static catalog of commonly used built-in `APIResource`s, usable without a cluster.
+///
+/// The catalog only covers resources served by Kubernetes itself, in their GA versions,
+/// so it cannot resolve CRDs and may differ from what a particular cluster serves.
+/// Like discovered resources, the core group is named "core".
+pub fn builtin_api_resources() -> Vec<APIResource> {
+    CATALOG
+        .iter()
+        .map(
+            |&(group, version, name, singular_name, kind, namespaced, short_names, all)| {
+                APIResource {
+                    group: Some(group.to_string()),
+                    version: Some(version.to_string()),
+                    name: name.to_string(),
+                    singular_name: singular_name.to_string(),
+                    kind: kind.to_string(),
+                    namespaced,
+                    short_names: (!short_names.is_empty())
+                        .then(|| short_names.iter().map(|s| s.to_string()).collect()),
+                    categories: all.then(|| vec!["all".to_string()]),
+                    verbs: VERBS.iter().map(|verb| verb.to_string()).collect(),
+                    storage_version_hash: None,
+                }
+            },
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn builtin_api_resources_resolve_by_name() {
+        let resources = builtin_api_resources();
+        let mut seen = HashSet::new();
+        for resource in &resources {
+            let group = resource.group.as_deref().unwrap();
+            assert!(seen.insert((group, &resource.name)), "{}", resource.name);
+            assert!(crate::is_builtin_group(group), "{group}");
+            let qualified = format!("{}.{group}", resource.name);
+            assert_eq!(
+                crate::find_resource(&qualified, &resources).as_ref(),
+                Some(resource)
+            );
+        }
+        let pods = crate::find_resource("po", &resources).unwrap();
+        assert_eq!(
+            (pods.group.as_deref(), pods.version.as_deref()),
+            (Some("core"), Some("v1"))
+        );
+        assert!(pods.namespaced);
+        assert!(!crate::find_resource("ns", &resources).unwrap().namespaced);
+    }
+}
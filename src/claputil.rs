@@ -1,98 +1,4414 @@
-use std::ffi::OsStr;
+use std::{
+    collections::HashSet,
+    env,
+    ffi::{OsStr, OsString},
+    fmt, fs,
+    future::Future,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::{Arc, OnceLock, PoisonError, RwLock},
+    thread,
+    time::{Duration, Instant},
+};
 
-use clap_complete::engine::{ArgValueCompleter, CompletionCandidate};
-use k8s_openapi::api::core::v1::Namespace;
-use kube::{Config, config::Kubeconfig};
-use tokio::{runtime::Handle, task};
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use clap::{
+    Arg, ArgAction, ArgMatches, Command, ValueEnum, ValueHint,
+    builder::{EnumValueParser, PossibleValue, StringValueParser, TypedValueParser},
+    value_parser,
+};
+use clap_complete::{
+    CompleteEnv, Shell,
+    engine::{ArgValueCompleter, CompletionCandidate},
+};
+use k8s_openapi::{
+    api::core::v1::{Namespace, Pod},
+    apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition,
+    apimachinery::pkg::apis::meta::v1::APIResource,
+};
+use kube::{
+    Client,
+    api::{ListParams, WatchParams},
+    config::{Kubeconfig, NamedContext},
+};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    runtime::{self, Handle, Runtime, RuntimeFlavor},
+    task,
+};
+
+use crate::{
+    AuthMethod, ClientOptions, FieldSelector, Impersonation, KubeIdentity, LabelSelector,
+    NamespaceSelection, ResourceRef,
+    auth::auth_method,
+    builtin::builtin_api_resources,
+    determine_context_with, determine_namespace_with,
+    discover::{
+        DiscoverClient, DiscoveryCacheFile, discovery_cache_file_name, is_older_than,
+        load_discovery_cache, save_discovery_cache,
+    },
+    dynamic, glob_match,
+    kubeconfig::{
+        cluster_entries, context_entries, read_kubeconfig_files, read_merged_kubeconfig,
+        sorted_current_first, suggest_contexts, validate_namespace_name,
+    },
+    match_resource,
+    output::{CUSTOM_OUTPUT_PREFIXES, OutputFormat},
+};
 
 /// Create an `ArgValueCompleter` that lists contexts from the active kubeconfig.
+///
+/// The current context is listed first, marked "[current]" in the help text.
+/// If no context matches the input, contexts resembling it are offered instead
+/// (see [`suggest_contexts`]), so that a mistyped name can still be completed.
+///
+/// Like the other completers of this module, it reads the files given by `--kubeconfig`
+/// (see [`kubeconfig_arg`]) on the command line being completed instead of the default
+/// kubeconfig.
 pub fn context_value_completer() -> ArgValueCompleter {
-    ArgValueCompleter::new(|input: &OsStr| -> Vec<CompletionCandidate> {
-        let kubeconfig = match Kubeconfig::read() {
-            Ok(config) => config,
-            Err(_) => return Vec::new(),
+    context_value_completer_with(default_completer_options())
+}
+
+/// Same as [`context_value_completer`], but configured by `options`, either
+/// [`CompleterOptions`] or [`ContextCompleterOptions`] to also leave out some contexts.
+pub fn context_value_completer_with(
+    options: impl Into<ContextCompleterOptions>,
+) -> ArgValueCompleter {
+    let options = options.into();
+    ArgValueCompleter::new(move |input: &OsStr| -> Vec<CompletionCandidate> {
+        let Some(mut kubeconfig) = completion_kubeconfig() else {
+            return Vec::new();
         };
+        // NOTE: An excluded current context is left out too, so nothing is marked current.
+        kubeconfig
+            .contexts
+            .retain(|context| options.is_offered(context));
 
         // Convert OsStr to &str with trimmed whitespace
         let input = input.to_string_lossy();
         let input = input.trim();
 
-        kubeconfig
-            .contexts
+        let candidates: Vec<CompletionCandidate> = options
+            .options
+            .match_mode
+            .filter(
+                sorted_current_first(context_entries(&kubeconfig)),
+                input,
+                |context| &context.name,
+            )
+            .into_iter()
+            .map(|context| {
+                // NOTE: Candidates without an order would be sorted before the current context.
+                if context.is_current {
+                    CompletionCandidate::new(context.name)
+                        .help(Some("[current]".into()))
+                        .display_order(Some(0))
+                } else {
+                    CompletionCandidate::new(context.name).display_order(Some(1))
+                }
+            })
+            .collect();
+        if !candidates.is_empty() {
+            return candidates;
+        }
+        suggest_contexts(input, &kubeconfig, MAX_CONTEXT_SUGGESTIONS)
+            .into_iter()
+            .map(CompletionCandidate::new)
+            .collect()
+    })
+}
+
+/// Predicate deciding whether a context is offered by [`context_value_completer_with`].
+type ContextPredicate = Arc<dyn Fn(&NamedContext) -> bool + Send + Sync>;
+
+/// Options of [`context_value_completer_with`]: the [`CompleterOptions`] shared by all
+/// completers, plus which contexts to leave out, e.g. machine-generated ones.
+///
+/// A context is offered only if it matches none of the [`Self::exclude`] patterns and
+/// satisfies the [`Self::filter`] predicate.
+#[derive(Clone, Default)]
+pub struct ContextCompleterOptions {
+    options: CompleterOptions,
+    exclude: Vec<String>,
+    filter: Option<ContextPredicate>,
+}
+
+impl fmt::Debug for ContextCompleterOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContextCompleterOptions")
+            .field("options", &self.options)
+            .field("exclude", &self.exclude)
+            .field("filter", &self.filter.is_some())
+            .finish()
+    }
+}
+
+impl From<CompleterOptions> for ContextCompleterOptions {
+    fn from(options: CompleterOptions) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+}
+
+impl ContextCompleterOptions {
+    /// Creates options from [`default_completer_options`], leaving out no context.
+    pub fn new() -> Self {
+        default_completer_options().into()
+    }
+
+    /// Sets the options shared by all completers, e.g. the match mode.
+    pub fn options(mut self, options: CompleterOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Leaves out the contexts whose name matches any of the glob `patterns`,
+    /// e.g. `ci-*` or `*-admin`, in which `*` matches any sequence.
+    pub fn exclude<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.exclude.extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Offers only the contexts for which `filter` returns true, replacing any previous filter.
+    pub fn filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&NamedContext) -> bool + Send + Sync + 'static,
+    {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Returns whether `context` is neither excluded nor filtered out.
+    fn is_offered(&self, context: &NamedContext) -> bool {
+        !self
+            .exclude
             .iter()
-            .filter(|named_context| named_context.name.starts_with(input))
-            .map(|named_context| CompletionCandidate::new(named_context.name.as_str()))
+            .any(|pattern| glob_match(pattern, &context.name))
+            && self.filter.as_ref().is_none_or(|filter| filter(context))
+    }
+}
+
+/// Create an `ArgValueCompleter` that lists the users (auth infos) of the active kubeconfig,
+/// with their authentication mechanism as help text, e.g. "exec plugin (aws)".
+/// Credentials are never shown.
+pub fn user_value_completer() -> ArgValueCompleter {
+    user_value_completer_with(default_completer_options())
+}
+
+/// Same as [`user_value_completer`], but configured by `options`.
+pub fn user_value_completer_with(options: CompleterOptions) -> ArgValueCompleter {
+    ArgValueCompleter::new(move |input: &OsStr| -> Vec<CompletionCandidate> {
+        let Some(kubeconfig) = completion_kubeconfig() else {
+            return Vec::new();
+        };
+        let input = input.to_string_lossy();
+        options
+            .match_mode
+            .filter(kubeconfig.auth_infos, input.trim(), |user| &user.name)
+            .into_iter()
+            .map(|user| {
+                // NOTE: A client certificate that cannot be inspected is still worth offering.
+                let help = user
+                    .auth_info
+                    .as_ref()
+                    .map_or(Ok(AuthMethod::None), auth_method)
+                    .map_or_else(
+                        |_| String::from("client certificate"),
+                        |method| method.to_string(),
+                    );
+                CompletionCandidate::new(user.name).help(Some(help.into()))
+            })
+            .collect()
+    })
+}
+
+/// Create an `ArgValueCompleter` that lists the clusters of the active kubeconfig,
+/// with their server URL as help text, marked "(insecure)" if the server's certificate
+/// is not verified. It never contacts the clusters.
+pub fn cluster_value_completer() -> ArgValueCompleter {
+    cluster_value_completer_with(default_completer_options())
+}
+
+/// Same as [`cluster_value_completer`], but configured by `options`.
+pub fn cluster_value_completer_with(options: CompleterOptions) -> ArgValueCompleter {
+    ArgValueCompleter::new(move |input: &OsStr| -> Vec<CompletionCandidate> {
+        let Some(kubeconfig) = completion_kubeconfig() else {
+            return Vec::new();
+        };
+        let input = input.to_string_lossy();
+        options
+            .match_mode
+            .filter(cluster_entries(&kubeconfig), input.trim(), |cluster| {
+                &cluster.name
+            })
+            .into_iter()
+            .map(|cluster| {
+                let server = cluster
+                    .server
+                    .as_deref()
+                    .map_or_else(|| String::from("no server"), truncate_help);
+                let help = if cluster.insecure_skip_tls_verify {
+                    format!("{server} (insecure)")
+                } else {
+                    server
+                };
+                CompletionCandidate::new(cluster.name).help(Some(help.into()))
+            })
             .collect()
     })
 }
 
+/// Maximum number of characters of a value shown in help text, e.g. a server URL.
+const MAX_HELP_VALUE_LENGTH: usize = 40;
+
+/// Shortens `value` to [`MAX_HELP_VALUE_LENGTH`] characters, ending it with "…" if cut.
+fn truncate_help(value: &str) -> String {
+    if value.chars().count() <= MAX_HELP_VALUE_LENGTH {
+        return value.to_string();
+    }
+    let mut truncated: String = value.chars().take(MAX_HELP_VALUE_LENGTH - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Number of similar contexts offered when no context matches the input.
+const MAX_CONTEXT_SUGGESTIONS: usize = 5;
+
+/// Settings shared by the completers of this module, passed to their `_with` variants,
+/// e.g. [`context_value_completer_with`].
+///
+/// The completers without options use [`default_completer_options`], which are the defaults
+/// of this type unless overridden by [`set_default_completer_options`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompleterOptions {
+    timeout: Duration,
+    cache_ttl: Option<Duration>,
+    cache_dir: Option<PathBuf>,
+    match_mode: MatchMode,
+    limit: Option<u32>,
+    offline_only: bool,
+}
+
+impl Default for CompleterOptions {
+    fn default() -> Self {
+        Self {
+            timeout: NAMESPACE_COMPLETION_TIMEOUT,
+            cache_ttl: None,
+            cache_dir: None,
+            match_mode: MatchMode::default(),
+            limit: None,
+            offline_only: false,
+        }
+    }
+}
+
+impl CompleterOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the time after which completers contacting the cluster give up,
+    /// [`NAMESPACE_COMPLETION_TIMEOUT`] by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the age up to which cached namespaces, CRDs and resources are used without
+    /// contacting the cluster, by default [`DEFAULT_NAMESPACE_CACHE_TTL`] for namespaces and
+    /// CRDs and [`RESOURCE_COMPLETION_CACHE_TTL`] for resources.
+    pub fn cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Caches the namespaces and resources of each context in the `namespaces` and `resources`
+    /// subdirectories of `dir`, e.g. `<config_dir>/<app_name>/completion`.
+    /// Nothing is cached by default.
+    pub fn cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    /// Sets how candidates are matched against the input, [`MatchMode::Prefix`] by default.
+    pub fn match_mode(mut self, mode: MatchMode) -> Self {
+        self.match_mode = mode;
+        self
+    }
+
+    /// Sets the maximum number of objects fetched from the cluster, by default 500 namespaces
+    /// and 200 objects of other resources.
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Never contacts the cluster if `offline_only` is true. Namespaces and resources are
+    /// then read from the cache regardless of its age, falling back to the namespaces set in
+    /// the kubeconfig and the built-in resources respectively, and objects are not completed.
+    pub fn offline_only(mut self, offline_only: bool) -> Self {
+        self.offline_only = offline_only;
+        self
+    }
+
+    /// Returns the namespace cache in [`Self::cache_dir`], if set.
+    fn namespace_cache(&self) -> Option<NamespaceCompletionCache> {
+        let cache = NamespaceCompletionCache::new(self.cache_dir.as_ref()?.join("namespaces"));
+        Some(match self.cache_ttl {
+            Some(ttl) => cache.with_ttl(ttl),
+            None => cache,
+        })
+    }
+
+    /// Returns the discovery cache of `context` in [`Self::cache_dir`], if set.
+    fn resource_cache_path(&self, context: &str) -> Option<PathBuf> {
+        Some(
+            self.cache_dir
+                .as_ref()?
+                .join("resources")
+                .join(discovery_cache_file_name(context)),
+        )
+    }
+}
+
+static DEFAULT_COMPLETER_OPTIONS: RwLock<Option<CompleterOptions>> = RwLock::new(None);
+
+/// Makes `options` the options of the completers without options created afterwards,
+/// e.g. [`namespace_value_completer`], so that a tool can set one policy for every completer.
+pub fn set_default_completer_options(options: CompleterOptions) {
+    // NOTE: The lock only guards a value being replaced as a whole, so a poisoned lock is fine.
+    *DEFAULT_COMPLETER_OPTIONS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = Some(options);
+}
+
+/// Returns the options set by [`set_default_completer_options`], or the default options.
+pub fn default_completer_options() -> CompleterOptions {
+    DEFAULT_COMPLETER_OPTIONS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+        .unwrap_or_default()
+}
+
+/// How completers match candidates against the input being completed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MatchMode {
+    /// Candidates starting with the input.
+    #[default]
+    Prefix,
+    /// Candidates containing the input, e.g. `payments` for
+    /// `arn:aws:eks:eu-west-1:123456789012:cluster/prod-payments`.
+    Substring,
+    /// Candidates containing the characters of the input in order, ignoring case, e.g.
+    /// `prdpay` for `prod-payments`. Candidates are ordered by how well they match,
+    /// preferring consecutive characters and characters at the start of words.
+    Fuzzy,
+}
+
+impl MatchMode {
+    /// Returns how well `candidate` matches `input`, higher being better,
+    /// or `None` if it does not match. Every candidate matches an empty input.
+    pub fn score(self, candidate: &str, input: &str) -> Option<i64> {
+        match self {
+            Self::Prefix => candidate.starts_with(input).then_some(0),
+            Self::Substring => candidate.contains(input).then_some(0),
+            Self::Fuzzy => fuzzy_score(candidate, input),
+        }
+    }
+
+    /// Keeps the `items` whose key matches `input`, ordered by score for [`Self::Fuzzy`]
+    /// and in their original order otherwise.
+    fn filter<T>(
+        self,
+        items: impl IntoIterator<Item = T>,
+        input: &str,
+        key: impl Fn(&T) -> &str,
+    ) -> Vec<T> {
+        filter_scored(items, |item| self.score(key(item), input))
+    }
+}
+
+/// Keeps the `items` with a score, ordered from the highest score.
+fn filter_scored<T>(
+    items: impl IntoIterator<Item = T>,
+    score: impl Fn(&T) -> Option<i64>,
+) -> Vec<T> {
+    let mut matched: Vec<(i64, T)> = items
+        .into_iter()
+        .filter_map(|item| Some((score(&item)?, item)))
+        .collect();
+    // NOTE: The sort is stable, so equally good candidates keep their order.
+    matched.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    matched.into_iter().map(|(_, item)| item).collect()
+}
+
+/// Scores `candidate` containing the characters of `input` in order, ignoring case.
+fn fuzzy_score(candidate: &str, input: &str) -> Option<i64> {
+    let mut input = input.chars().flat_map(char::to_lowercase).peekable();
+    let mut score = 0;
+    let mut previous: Option<char> = None;
+    let mut previous_matched = false;
+    let mut started = false;
+    for c in candidate.chars() {
+        let Some(&next) = input.peek() else {
+            break;
+        };
+        let word_start = previous.is_none_or(|p| !p.is_alphanumeric());
+        if c.to_lowercase().eq(std::iter::once(next)) {
+            input.next();
+            score += 1;
+            if previous_matched {
+                score += 5;
+            }
+            if word_start {
+                score += 3;
+            }
+            started = true;
+            previous_matched = true;
+        } else {
+            if started {
+                score -= 1;
+            }
+            previous_matched = false;
+        }
+        previous = Some(c);
+    }
+    input.peek().is_none().then_some(score)
+}
+
+/// Default time limit of the completers contacting the cluster, after which they offer
+/// no candidates, see [`CompleterOptions::timeout`].
+pub const NAMESPACE_COMPLETION_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Maximum number of namespaces fetched by [`namespace_value_completer`].
+const NAMESPACE_COMPLETION_LIMIT: u32 = 500;
+
+/// Default time for which [`NamespaceCompletionCache`] serves namespaces without refreshing them.
+pub const DEFAULT_NAMESPACE_CACHE_TTL: Duration = Duration::from_secs(30);
+
 /// Create an `ArgValueCompleter` that lists namespaces from the active kubeconfig.
 ///
 /// This function makes a network call to the Kubernetes cluster to retrieve the list of namespaces.
 /// As a result, it may be slow or fail silently (returning an empty list) in case of network issues,
-/// authentication failures, or missing permissions.
+/// authentication failures, or missing permissions. It gives up after
+/// [`NAMESPACE_COMPLETION_TIMEOUT`], so that an unreachable cluster does not freeze the shell,
+/// and fetches only the metadata of up to 500 namespaces. If there are more, a namespace
+/// typed in full is still offered.
+/// See [`cached_namespace_value_completer`] to avoid the call on repeated completions.
 ///
-/// When called within an existing Tokio runtime, it uses `block_in_place` to avoid panicking and
-/// blocks on the current runtime handle. If no runtime exists, it creates a new Tokio runtime to
-/// perform the network call.
+/// When called within a multi-threaded Tokio runtime, it uses `block_in_place` to avoid panicking
+/// and blocks on the current runtime handle. Otherwise, it performs the network call on a
+/// current-thread runtime created once per process, from another thread if called within a
+/// current-thread runtime.
 ///
-/// Limitation: The context specified by --context is not considered.
-/// See https://github.com/clap-rs/clap/issues/1910 for more details.
+/// Namespaces are listed from the context given by `--context` (or `-c`) earlier on the
+/// command line being completed, or from the current context if it is not given or not
+/// defined in the kubeconfig. As completers cannot see the parsed arguments
+/// (see https://github.com/clap-rs/clap/issues/1910), the command line is scanned instead.
+/// If the namespaces cannot be listed, those set by the contexts of the kubeconfig are offered
+/// instead, with "from kubeconfig" as help text.
+///
+/// The namespace of the context (or "default") comes first, marked "[current]",
+/// followed by the others in alphabetical order.
 pub fn namespace_value_completer() -> ArgValueCompleter {
-    ArgValueCompleter::new(|input: &OsStr| -> Vec<CompletionCandidate> {
-        let kubeconfig = match Kubeconfig::read() {
-            Ok(config) => config,
-            Err(_) => return Vec::new(),
-        };
+    namespace_value_completer_with(default_completer_options())
+}
+
+/// Same as [`namespace_value_completer`], but configured by `options`.
+pub fn namespace_value_completer_with(options: CompleterOptions) -> ArgValueCompleter {
+    let cache = options.namespace_cache();
+    ArgValueCompleter::new(move |input: &OsStr| {
+        complete_namespaces(input, cache.as_ref(), &options)
+    })
+}
+
+/// Same as [`namespace_value_completer`], but keeps the namespaces of each context in `cache`,
+/// so that completions within its TTL do not contact the cluster.
+///
+/// An expired cache is refreshed from the cluster, and still used if the cluster cannot be
+/// reached in time. A cache that cannot be read or parsed is ignored.
+/// See also [`CompleterOptions::cache_dir`].
+pub fn cached_namespace_value_completer(cache: NamespaceCompletionCache) -> ArgValueCompleter {
+    let options = default_completer_options();
+    ArgValueCompleter::new(move |input: &OsStr| complete_namespaces(input, Some(&cache), &options))
+}
+
+/// Files caching the namespaces of each context for [`cached_namespace_value_completer`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NamespaceCompletionCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl NamespaceCompletionCache {
+    /// Creates a cache storing files in `dir`, expiring after [`DEFAULT_NAMESPACE_CACHE_TTL`].
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl: DEFAULT_NAMESPACE_CACHE_TTL,
+        }
+    }
+
+    /// Creates a cache in the default directory for `app_name`,
+    /// i.e. `<config_dir>/<app_name>/completion/namespaces`, next to the discovery cache.
+    ///
+    /// Returns `None` if the platform has no config directory.
+    pub fn for_app(app_name: &str) -> Option<Self> {
+        Some(Self::new(
+            dirs::config_dir()?
+                .join(app_name)
+                .join("completion")
+                .join("namespaces"),
+        ))
+    }
 
-        let current_ctx = match &kubeconfig.current_context {
-            Some(name) => name,
-            None => return Vec::new(),
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Returns the file caching the namespaces of `context`.
+    pub fn path(&self, context: &str) -> PathBuf {
+        self.dir.join(discovery_cache_file_name(context))
+    }
+
+    fn load(&self, context: &str) -> Option<CachedNamespaces> {
+        let content = fs::read_to_string(self.path(context)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn store(&self, context: &str, namespaces: &[String]) {
+        let cached = CachedNamespaces {
+            updated_at: Utc::now(),
+            namespaces: namespaces.to_vec(),
         };
+        // NOTE: Failing to write only makes the next completion slower.
+        if let Ok(content) = serde_json::to_string(&cached)
+            && fs::create_dir_all(&self.dir).is_ok()
+        {
+            let _ = fs::write(self.path(context), content);
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedNamespaces {
+    updated_at: DateTime<Utc>,
+    namespaces: Vec<String>,
+}
+
+fn complete_namespaces(
+    input: &OsStr,
+    cache: Option<&NamespaceCompletionCache>,
+    options: &CompleterOptions,
+) -> Vec<CompletionCandidate> {
+    let Some(current_ctx) = completion_context() else {
+        return Vec::new();
+    };
+
+    let input_str = input.to_string_lossy();
+    let input_str = input_str.trim();
+
+    let cached = cache.and_then(|cache| Some((cache, cache.load(&current_ctx)?)));
+    let (namespaces, help) = match cached {
+        Some((cache, cached)) if !is_older_than(cached.updated_at, cache.ttl) => {
+            (cached.namespaces, None)
+        }
+        cached => match list_namespaces_unless_offline(&current_ctx, input_str, options) {
+            Some(namespaces) => {
+                if let Some(cache) = cache {
+                    cache.store(&current_ctx, &namespaces);
+                }
+                (namespaces, None)
+            }
+            None => match cached {
+                Some((_, cached)) => (cached.namespaces, None),
+                None => (kubeconfig_namespaces(), Some("from kubeconfig")),
+            },
+        },
+    };
+
+    let mut namespaces = namespaces;
+    namespaces.sort();
+    let current = completion_kubeconfig()
+        .map(|kubeconfig| determine_namespace_with(&kubeconfig, None, &current_ctx));
+    let mut namespaces = options
+        .match_mode
+        .filter(namespaces, input_str, String::as_str);
+    if let Some(index) = namespaces
+        .iter()
+        .position(|name| Some(name) == current.as_ref())
+    {
+        let current = namespaces.remove(index);
+        namespaces.insert(0, current);
+    }
+
+    namespaces
+        .into_iter()
+        .map(|name| {
+            // NOTE: Candidates without an order would be sorted before the current namespace.
+            if Some(&name) == current.as_ref() {
+                CompletionCandidate::new(name)
+                    .help(Some("[current]".into()))
+                    .display_order(Some(0))
+            } else {
+                CompletionCandidate::new(name)
+                    .help(help.map(Into::into))
+                    .display_order(Some(1))
+            }
+        })
+        .collect()
+}
+
+/// Lists the namespaces of `context` like [`list_namespaces`], or returns `None`
+/// without contacting the cluster if `options` are offline only.
+fn list_namespaces_unless_offline(
+    context: &str,
+    input: &str,
+    options: &CompleterOptions,
+) -> Option<Vec<String>> {
+    if options.offline_only {
+        return None;
+    }
+    block_on(list_namespaces(context, input, options)).flatten()
+}
+
+/// Returns the distinct namespaces set by the contexts of the kubeconfig, in kubeconfig order,
+/// offered when the namespaces cannot be listed from the cluster.
+fn kubeconfig_namespaces() -> Vec<String> {
+    let Some(kubeconfig) = completion_kubeconfig() else {
+        return Vec::new();
+    };
+    let mut seen = HashSet::new();
+    context_entries(&kubeconfig)
+        .into_iter()
+        .filter_map(|context| context.namespace)
+        .filter(|namespace| seen.insert(namespace.clone()))
+        .collect()
+}
+
+/// Returns the kubeconfig files given by `--kubeconfig` on the command line being completed.
+fn completion_kubeconfig_files() -> Vec<PathBuf> {
+    options_from_args(completion_args(), "--kubeconfig", "--kubeconfig")
+        .into_iter()
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Reads the kubeconfig of the command line being completed: the files given by
+/// `--kubeconfig` merged, or the default kubeconfig if none is given, with one entry per name.
+fn completion_kubeconfig() -> Option<Kubeconfig> {
+    let files = completion_kubeconfig_files();
+    let kubeconfig = if files.is_empty() {
+        read_merged_kubeconfig()
+    } else {
+        read_kubeconfig_files(&files)
+    };
+    let mut kubeconfig = logged("read kubeconfig", kubeconfig)?;
+    // NOTE: Merging skips the entries named like those of an earlier file, like kubectl does,
+    // but keeps duplicates within a file, which would be offered twice.
+    dedup_by_name(&mut kubeconfig.contexts, |context| &context.name);
+    dedup_by_name(&mut kubeconfig.clusters, |cluster| &cluster.name);
+    dedup_by_name(&mut kubeconfig.auth_infos, |user| &user.name);
+    Some(kubeconfig)
+}
+
+/// Removes the entries named like an earlier entry, keeping the first one as kube does
+/// when looking an entry up by name.
+fn dedup_by_name<T>(entries: &mut Vec<T>, name: impl Fn(&T) -> &str) {
+    let mut seen = HashSet::new();
+    entries.retain(|entry| seen.insert(name(entry).to_string()));
+}
+
+/// Returns the arguments of the process, which hold the command line being completed.
+fn completion_args() -> Vec<OsString> {
+    #[cfg(test)]
+    if let Some(args) = tests::COMPLETION_ARGS.with_borrow(Clone::clone) {
+        return args;
+    }
+    env::args_os().collect()
+}
+
+/// Command given to [`install_completions`], read by [`completion_arg`].
+static COMPLETION_COMMAND: OnceLock<fn() -> Command> = OnceLock::new();
+
+/// Returns the value of the argument `id` on the command line being completed, parsed with the
+/// command given to [`install_completions`], from the innermost subcommand that has it.
+fn completion_arg(id: &str) -> Option<String> {
+    let cmd = COMPLETION_COMMAND.get()?;
+    // NOTE: The shell passes the words being completed after `--`, the binary name first.
+    let words = completion_args()
+        .into_iter()
+        .skip_while(|arg| arg != "--")
+        .skip(1);
+    let matches = cmd().ignore_errors(true).try_get_matches_from(words).ok()?;
+    arg_value(&matches, id)
+}
+
+/// Returns the value of the argument `id` in the innermost subcommand of `matches` that has it.
+fn arg_value(matches: &ArgMatches, id: &str) -> Option<String> {
+    matches
+        .subcommand()
+        .and_then(|(_, matches)| arg_value(matches, id))
+        .or_else(|| matches.try_get_one::<String>(id).ok().flatten().cloned())
+        .filter(|value| !value.is_empty())
+}
+
+/// Environment variable given to [`context_arg_with_env`], read by [`completion_context`].
+static CONTEXT_ENV_VAR: OnceLock<&'static str> = OnceLock::new();
 
-        let options = kube::config::KubeConfigOptions {
-            context: Some(current_ctx.clone()),
-            ..Default::default()
+/// Returns the context of the command line being completed: the first of the one given by
+/// `--context`, the value of the variable given to [`context_arg_with_env`], and the current
+/// context. The first two are skipped unless defined in the kubeconfig.
+fn completion_context() -> Option<String> {
+    let kubeconfig = completion_kubeconfig()?;
+    let is_defined = |context: &String| kubeconfig.contexts.iter().any(|c| &c.name == context);
+    let context = option_from_args(completion_args(), "--context", "-c")
+        .filter(is_defined)
+        .or_else(|| {
+            CONTEXT_ENV_VAR
+                .get()
+                .and_then(|var| env::var(var).ok())
+                .filter(is_defined)
+        });
+    logged(
+        "determine context",
+        determine_context_with(&kubeconfig, &context),
+    )
+}
+
+/// Returns the namespace of the command line being completed: the one given by `--namespace`
+/// (or `-n`), or the namespace of `context` otherwise.
+fn completion_namespace(context: &str) -> String {
+    let namespace = option_from_args(completion_args(), "--namespace", "-n")
+        .filter(|namespace| validate_namespace_name(namespace).is_ok());
+    match completion_kubeconfig() {
+        Some(kubeconfig) => determine_namespace_with(&kubeconfig, namespace, context),
+        None => namespace.unwrap_or_else(|| String::from("default")),
+    }
+}
+
+/// Builds a client for `context` whose requests time out after `timeout`.
+async fn completion_client(context: &str, timeout: Duration) -> Option<Client> {
+    let build_future = async {
+        let options = ClientOptions::new()
+            .kubeconfig_files(completion_kubeconfig_files())
+            .context(context)
+            .connect_timeout(timeout)
+            .read_timeout(timeout);
+        logged("build client", options.build().await).map(|(client, _)| client)
+    };
+    timed(
+        &format!("build client for {context}"),
+        timeout,
+        build_future,
+    )
+    .await
+}
+
+/// Environment variable naming a file, or `stderr`, to which completers append what they do,
+/// e.g. which step failed and how long each step took, to diagnose completion that offers
+/// nothing. Nothing is written if it is unset.
+pub const COMPLETE_DEBUG_ENV: &str = "KUBEX_COMPLETE_DEBUG";
+
+/// Appends a timestamped `message` to the sink of [`COMPLETE_DEBUG_ENV`], if set.
+/// `message` is only built if so, and failing to write is ignored.
+fn debug_log(message: impl FnOnce() -> String) {
+    let Some(sink) = env::var_os(COMPLETE_DEBUG_ENV).filter(|sink| !sink.is_empty()) else {
+        return;
+    };
+    let line = format!(
+        "{} [pid {}] {}\n",
+        Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        process::id(),
+        message()
+    );
+    // NOTE: Completion must go on whatever happens to the log, so errors are ignored.
+    if sink == "stderr" {
+        let _ = io::stderr().write_all(line.as_bytes());
+    } else if let Ok(mut file) = fs::OpenOptions::new().create(true).append(true).open(sink) {
+        let _ = file.write_all(line.as_bytes());
+    }
+}
+
+/// Returns the value of `result`, logging its error with [`debug_log`] as the failure of `step`.
+fn logged<T, E: fmt::Display>(step: &str, result: Result<T, E>) -> Option<T> {
+    result
+        .inspect_err(|err| debug_log(|| format!("{step} failed: {err:#}")))
+        .ok()
+}
+
+/// Runs `future`, the `step` of a completer contacting the cluster, giving up after `timeout`,
+/// and logs with [`debug_log`] how long it took and whether it failed or timed out.
+async fn timed<T>(
+    step: &str,
+    timeout: Duration,
+    future: impl Future<Output = Option<T>>,
+) -> Option<T> {
+    let start = Instant::now();
+    let output = tokio::time::timeout(timeout, future).await;
+    let elapsed = start.elapsed();
+    debug_log(|| match &output {
+        Ok(Some(_)) => format!("{step} took {elapsed:?}"),
+        Ok(None) => format!("{step} failed after {elapsed:?}"),
+        Err(_) => format!("{step} timed out after {elapsed:?}"),
+    });
+    output.ok().flatten()
+}
+
+/// Lists the names of the namespaces of `context`, up to the limit of `options`,
+/// or returns `None` if they cannot be listed within its timeout.
+///
+/// If there are more namespaces than the limit, the namespace named `input` is looked up
+/// by a field selector and added, so that a namespace typed in full is offered anyway.
+async fn list_namespaces(
+    context: &str,
+    input: &str,
+    options: &CompleterOptions,
+) -> Option<Vec<String>> {
+    let list_future = async {
+        let client = completion_client(context, options.timeout).await?;
+
+        let namespaces: kube::Api<Namespace> = kube::Api::all(client);
+
+        let limit = options.limit.unwrap_or(NAMESPACE_COMPLETION_LIMIT).max(1) as usize;
+        let mut names = Vec::new();
+        let mut continue_token: Option<String> = None;
+        // NOTE: A server may return fewer namespaces than asked along with a continue token,
+        // so pages are followed until the limit is reached.
+        let truncated = loop {
+            let mut params = ListParams::default().limit((limit - names.len()) as u32);
+            if let Some(token) = &continue_token {
+                params = params.continue_token(token);
+            }
+            let ns_list = logged("list namespaces", namespaces.list_metadata(&params).await)?;
+            names.extend(ns_list.items.into_iter().filter_map(|ns| ns.metadata.name));
+            continue_token = ns_list.metadata.continue_.filter(|token| !token.is_empty());
+            if continue_token.is_none() || names.len() >= limit {
+                break continue_token.is_some();
+            }
         };
 
-        let input_str = input.to_string_lossy();
-        let input_str = input_str.trim();
+        if truncated
+            && validate_namespace_name(input).is_ok()
+            && !names.iter().any(|name| name == input)
+        {
+            let params = ListParams::default().fields(&format!("metadata.name={input}"));
+            if let Some(ns_list) =
+                logged("look up namespace", namespaces.list_metadata(&params).await)
+            {
+                names.extend(ns_list.items.into_iter().filter_map(|ns| ns.metadata.name));
+            }
+        }
+        Some(names)
+    };
+    // An unreachable cluster must not freeze the shell, so give up after the timeout.
+    timed(
+        &format!("list namespaces of {context}"),
+        options.timeout,
+        list_future,
+    )
+    .await
+}
 
-        let namespaces_future = async {
-            let config = match Config::from_custom_kubeconfig(kubeconfig, &options).await {
-                Ok(cfg) => cfg,
-                Err(_) => return Vec::new(),
-            };
+/// Runs `future` to completion from a synchronous completer,
+/// returning `None` if no runtime can be created.
+fn block_on<F>(future: F) -> Option<F::Output>
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    // If called on an existing Tokio runtime, `Runtime::block_on` will panic.
+    // Therefore, on a multi-threaded runtime, we use `block_in_place` to escape to a blocking
+    // thread, and from there we call `block_on` with the current handle. `block_in_place`
+    // panics on a current-thread runtime, whose only thread must not block, so the future is
+    // run on the completion runtime from another thread instead.
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            Some(task::block_in_place(move || handle.block_on(future)))
+        }
+        Ok(_) => thread::scope(|scope| {
+            scope
+                .spawn(|| completion_runtime().map(|rt| rt.block_on(future)))
+                .join()
+                .ok()
+                .flatten()
+        }),
+        Err(_) => completion_runtime().map(|rt| rt.block_on(future)),
+    }
+}
 
-            let client = match kube::Client::try_from(config) {
-                Ok(c) => c,
-                Err(_) => return Vec::new(),
-            };
+/// Returns the runtime of completers called outside of a runtime, created on first use
+/// and kept for later completions in the same process.
+fn completion_runtime() -> Option<&'static Runtime> {
+    static RUNTIME: OnceLock<Option<Runtime>> = OnceLock::new();
+    RUNTIME
+        .get_or_init(|| {
+            runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .ok()
+        })
+        .as_ref()
+}
 
-            let namespaces: kube::Api<Namespace> = kube::Api::all(client);
+/// Age up to which [`resource_value_completer`] uses the discovery cache without rediscovering,
+/// the same as kubectl's discovery cache.
+pub const RESOURCE_COMPLETION_CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
 
-            let ns_list = match namespaces.list(&Default::default()).await {
-                Ok(list) => list,
-                Err(_) => return Vec::new(),
-            };
+/// Create an `ArgValueCompleter` that lists resource types, like the `TYPE` of `kubectl get TYPE`.
+///
+/// Plural names and short names are offered, with help text showing the short names (or the
+/// plural name), group and scope, e.g. "deploy (apps, namespaced)", like `kubectl api-resources`.
+/// Group-qualified names (e.g. `deployments.apps`) are offered once the input contains a `.`,
+/// and for names resolving to resources of several groups, e.g. `ingresses.networking.k8s.io`.
+///
+/// Resources are read from the discovery cache at `cache_path` if it is younger than
+/// [`RESOURCE_COMPLETION_CACHE_TTL`]. Otherwise they are discovered from the context of the
+/// command line being completed (see [`namespace_value_completer`]) within
+/// [`NAMESPACE_COMPLETION_TIMEOUT`], and a complete discovery is saved to `cache_path`.
+/// If discovery fails, an expired cache is still used.
+///
+/// Without `cache_path`, the cache in [`CompleterOptions::cache_dir`] is used, if set.
+pub fn resource_value_completer(cache_path: Option<PathBuf>) -> ArgValueCompleter {
+    resource_value_completer_with(cache_path, default_completer_options())
+}
 
-            ns_list
-                .items
-                .iter()
-                .filter_map(|ns| ns.metadata.name.as_ref())
-                .filter(|name| name.starts_with(input_str))
-                .map(CompletionCandidate::new)
-                .collect()
-        };
-
-        // If called on an existing Tokio runtime, `Runtime::block_on` will panic.
-        // Therefore, if a runtime exists, we use `block_in_place` to escape to a blocking thread,
-        // and from there we call `block_on` with the current handle.
-        match Handle::try_current() {
-            Ok(handle) => task::block_in_place(move || handle.block_on(namespaces_future)),
-            Err(_) => tokio::runtime::Runtime::new()
-                .map(|rt| rt.block_on(namespaces_future))
+/// Same as [`resource_value_completer`], but configured by `options`.
+pub fn resource_value_completer_with(
+    cache_path: Option<PathBuf>,
+    options: CompleterOptions,
+) -> ArgValueCompleter {
+    ArgValueCompleter::new(move |input: &OsStr| {
+        complete_resources(input, cache_path.as_deref(), &options)
+    })
+}
+
+fn complete_resources(
+    input: &OsStr,
+    cache_path: Option<&Path>,
+    options: &CompleterOptions,
+) -> Vec<CompletionCandidate> {
+    let context = completion_context();
+    let cache_path = cache_path.map(Path::to_path_buf).or_else(|| {
+        context
+            .as_deref()
+            .and_then(|context| options.resource_cache_path(context))
+    });
+    let cached = cache_path
+        .as_deref()
+        .and_then(|path| load_discovery_cache(path).ok());
+    let ttl = options.cache_ttl.unwrap_or(RESOURCE_COMPLETION_CACHE_TTL);
+    let resources = match cached {
+        Some(cached) if !cached.is_expired(ttl) => cached.into_resources(),
+        Some(cached) if options.offline_only => cached.into_resources(),
+        None if options.offline_only => builtin_api_resources(),
+        cached => match context
+            .and_then(|context| {
+                block_on(discover_resources(
+                    &context,
+                    cache_path.as_deref(),
+                    options.timeout,
+                ))
+            })
+            .flatten()
+        {
+            Some(resources) => resources,
+            None => cached
+                .map(DiscoveryCacheFile::into_resources)
                 .unwrap_or_default(),
+        },
+    };
+
+    let input = input.to_string_lossy();
+    resource_candidates(&resources, input.trim(), options.match_mode)
+}
+
+/// Discovers the resources of `context`, saving them to `cache_path` if every group
+/// could be discovered, or returns `None` if discovery fails or times out.
+async fn discover_resources(
+    context: &str,
+    cache_path: Option<&Path>,
+    timeout: Duration,
+) -> Option<Vec<APIResource>> {
+    let discover_future = async {
+        let client = completion_client(context, timeout).await?;
+        let discovery = DiscoverClient::new(client).with_deadline(timeout);
+        let outcome = logged("discover resources", discovery.discover().await)?;
+        if outcome.is_complete()
+            && let Some(path) = cache_path
+        {
+            // NOTE: Failing to save only makes the next completion slower.
+            let _ = save_discovery_cache(path, &outcome.resources, None);
+        }
+        Some(outcome.resources)
+    };
+    timed(
+        &format!("discover resources of {context}"),
+        timeout,
+        discover_future,
+    )
+    .await
+}
+
+/// Returns the names of `resources` matching `input`, without duplicates.
+///
+/// Plural names are described by their short names, and short names by their plural name,
+/// along with the group and scope, e.g. "deploy (apps, namespaced)" for `deployments`.
+/// A plural name resolving to resources of several groups is also offered qualified by each group.
+fn resource_candidates(
+    resources: &[APIResource],
+    input: &str,
+    match_mode: MatchMode,
+) -> Vec<CompletionCandidate> {
+    let qualified = input.contains('.');
+    let is_ambiguous = |name: &str| {
+        resources
+            .iter()
+            .filter(|resource| match_resource(name, resource))
+            .map(|resource| resource.group.as_deref().unwrap_or_default())
+            .collect::<HashSet<_>>()
+            .len()
+            > 1
+    };
+    let mut seen = HashSet::new();
+    let mut candidates = Vec::new();
+    for resource in resources {
+        let group = match resource.group.as_deref() {
+            Some("core" | "") | None => None,
+            Some(group) => Some(group),
+        };
+        let details = format!(
+            "({}, {})",
+            group.unwrap_or("core"),
+            if resource.namespaced {
+                "namespaced"
+            } else {
+                "cluster-scoped"
+            }
+        );
+        let short_names = resource.short_names.as_deref().unwrap_or_default();
+        let plural_help = if short_names.is_empty() {
+            details.clone()
+        } else {
+            format!("{} {details}", short_names.join(","))
+        };
+        let names = std::iter::once((&resource.name, plural_help, true)).chain(
+            short_names
+                .iter()
+                .map(|short_name| (short_name, format!("{} {details}", resource.name), false)),
+        );
+        for (name, help, is_plural) in names {
+            // NOTE: Only plural names can be qualified by group, see `match_resource`.
+            let values = match (qualified, group) {
+                (true, Some(group)) if is_plural => vec![format!("{name}.{group}")],
+                (true, _) => continue,
+                (false, Some(group)) if is_plural && is_ambiguous(name) => {
+                    vec![name.clone(), format!("{name}.{group}")]
+                }
+                (false, _) => vec![name.clone()],
+            };
+            for value in values {
+                if seen.insert(value.clone()) {
+                    candidates.push((value, help.clone()));
+                }
+            }
+        }
+    }
+    match_mode
+        .filter(candidates, input, |(value, _)| value)
+        .into_iter()
+        .map(|(value, help)| CompletionCandidate::new(value).help(Some(help.into())))
+        .collect()
+}
+
+/// Maximum number of objects fetched by the completers of objects, e.g. [`pod_value_completer`].
+const OBJECT_COMPLETION_LIMIT: u32 = 200;
+
+/// Create an `ArgValueCompleter` that lists pods, with their status and readiness
+/// (e.g. "Running 2/2") as help text.
+///
+/// Pods are listed from the namespace given by `--namespace` (or `-n`) and the context given by
+/// `--context` on the command line being completed, falling back to the kubeconfig defaults.
+/// Up to 200 pods are fetched as a server-side table, which carries the columns of
+/// `kubectl get pods` instead of the whole objects, within [`NAMESPACE_COMPLETION_TIMEOUT`].
+pub fn pod_value_completer() -> ArgValueCompleter {
+    pod_value_completer_with(default_completer_options())
+}
+
+/// Same as [`pod_value_completer`], but configured by `options`.
+pub fn pod_value_completer_with(options: CompleterOptions) -> ArgValueCompleter {
+    ArgValueCompleter::new(move |input: &OsStr| -> Vec<CompletionCandidate> {
+        if options.offline_only {
+            return Vec::new();
+        }
+        let Some(context) = completion_context() else {
+            return Vec::new();
+        };
+        let namespace = completion_namespace(&context);
+        let input = input.to_string_lossy();
+        let pods = block_on(list_pods(&context, &namespace, &options))
+            .flatten()
+            .unwrap_or_default();
+
+        options
+            .match_mode
+            .filter(pods, input.trim(), |(name, _)| name)
+            .into_iter()
+            .map(|(name, help)| CompletionCandidate::new(name).help(help.map(Into::into)))
+            .collect()
+    })
+}
+
+/// Lists the pods of `namespace` with their status and readiness, or returns `None`
+/// if they cannot be listed within the timeout of `options`.
+async fn list_pods(
+    context: &str,
+    namespace: &str,
+    options: &CompleterOptions,
+) -> Option<Vec<(String, Option<String>)>> {
+    let path = format!("/api/v1/namespaces/{namespace}/pods");
+    let table = list_table(context, &path, options).await?;
+    Some(
+        table
+            .select(&["Name", "Status", "Ready"])
+            .into_iter()
+            .filter_map(|cells| {
+                let [name, status, ready] = cells[..] else {
+                    return None;
+                };
+                let help = [status, ready].into_iter().flatten().collect::<Vec<_>>();
+                Some((
+                    name?.to_string(),
+                    (!help.is_empty()).then(|| help.join(" ")),
+                ))
+            })
+            .collect(),
+    )
+}
+
+/// Create an `ArgValueCompleter` that lists service accounts, with their age
+/// (e.g. "age 3d") as help text.
+///
+/// Service accounts are listed like [`pod_value_completer`] does, from the namespace and context
+/// given on the command line.
+pub fn serviceaccount_value_completer() -> ArgValueCompleter {
+    serviceaccount_value_completer_with(default_completer_options())
+}
+
+/// Same as [`serviceaccount_value_completer`], but configured by `options`.
+pub fn serviceaccount_value_completer_with(options: CompleterOptions) -> ArgValueCompleter {
+    ArgValueCompleter::new(move |input: &OsStr| -> Vec<CompletionCandidate> {
+        if options.offline_only {
+            return Vec::new();
+        }
+        let Some(context) = completion_context() else {
+            return Vec::new();
+        };
+        let namespace = completion_namespace(&context);
+        let input = input.to_string_lossy();
+        let service_accounts = block_on(list_service_accounts(&context, &namespace, &options))
+            .flatten()
+            .unwrap_or_default();
+
+        options
+            .match_mode
+            .filter(service_accounts, input.trim(), |(name, _)| name)
+            .into_iter()
+            .map(|(name, age)| {
+                CompletionCandidate::new(name).help(age.map(|age| format!("age {age}").into()))
+            })
+            .collect()
+    })
+}
+
+/// Lists the service accounts of `namespace` with their age as printed by `kubectl get`,
+/// or returns `None` if they cannot be listed within the timeout of `options`.
+async fn list_service_accounts(
+    context: &str,
+    namespace: &str,
+    options: &CompleterOptions,
+) -> Option<Vec<(String, Option<String>)>> {
+    let path = format!("/api/v1/namespaces/{namespace}/serviceaccounts");
+    let table = list_table(context, &path, options).await?;
+    Some(
+        table
+            .select(&["Name", "Age"])
+            .into_iter()
+            .filter_map(|cells| {
+                let [name, age] = cells[..] else {
+                    return None;
+                };
+                Some((name?.to_string(), age.map(str::to_string)))
+            })
+            .collect(),
+    )
+}
+
+/// Create an `ArgValueCompleter` that lists nodes, with their roles and readiness
+/// (e.g. "worker, Ready") as help text.
+///
+/// Ready workers are listed first, then ready control-plane nodes, then nodes that are not ready.
+/// Nodes are fetched like [`pod_value_completer`] does, from the context given on the command line.
+pub fn node_value_completer() -> ArgValueCompleter {
+    node_value_completer_with(default_completer_options())
+}
+
+/// Same as [`node_value_completer`], but configured by `options`.
+pub fn node_value_completer_with(options: CompleterOptions) -> ArgValueCompleter {
+    ArgValueCompleter::new(move |input: &OsStr| -> Vec<CompletionCandidate> {
+        if options.offline_only {
+            return Vec::new();
+        }
+        let Some(context) = completion_context() else {
+            return Vec::new();
+        };
+        let input = input.to_string_lossy();
+        let nodes = block_on(list_nodes(&context, &options))
+            .flatten()
+            .unwrap_or_default();
+
+        let mut nodes = options
+            .match_mode
+            .filter(nodes, input.trim(), |node| &node.name);
+        nodes.sort_by_key(|node| (!node.ready, node.control_plane));
+        nodes
+            .into_iter()
+            .map(|node| {
+                let roles = if node.roles.is_empty() {
+                    "worker"
+                } else {
+                    &node.roles
+                };
+                let help = format!("{roles}, {}", node.status);
+                CompletionCandidate::new(node.name).help(Some(help.into()))
+            })
+            .collect()
+    })
+}
+
+struct NodeCandidate {
+    name: String,
+    /// Roles as printed by `kubectl get nodes`, e.g. "control-plane", empty if none.
+    roles: String,
+    /// Status as printed by `kubectl get nodes`, e.g. "Ready,SchedulingDisabled".
+    status: String,
+    ready: bool,
+    control_plane: bool,
+}
+
+/// Lists the nodes with their roles and status, or returns `None`
+/// if they cannot be listed within the timeout of `options`.
+async fn list_nodes(context: &str, options: &CompleterOptions) -> Option<Vec<NodeCandidate>> {
+    let table = list_table(context, "/api/v1/nodes", options).await?;
+    Some(
+        table
+            .select(&["Name", "Status", "Roles"])
+            .into_iter()
+            .filter_map(|cells| {
+                let [name, status, roles] = cells[..] else {
+                    return None;
+                };
+                let status = status.unwrap_or("Unknown");
+                let roles = roles.filter(|roles| *roles != "<none>").unwrap_or_default();
+                Some(NodeCandidate {
+                    name: name?.to_string(),
+                    roles: roles.to_string(),
+                    status: status.to_string(),
+                    ready: status.split(',').any(|condition| condition == "Ready"),
+                    control_plane: roles
+                        .split(',')
+                        .any(|role| role == "control-plane" || role == "master"),
+                })
+            })
+            .collect(),
+    )
+}
+
+/// Lists the objects at `path` as a server-side [`Table`], up to the limit of `options`,
+/// or returns `None` if they cannot be listed within its timeout.
+async fn list_table(context: &str, path: &str, options: &CompleterOptions) -> Option<Table> {
+    let list_future = async {
+        let client = completion_client(context, options.timeout).await?;
+        let mut request = kube::core::Request::new(path)
+            .list(&ListParams::default().limit(options.limit.unwrap_or(OBJECT_COMPLETION_LIMIT)))
+            .ok()?;
+        request.headers_mut().insert(
+            http::header::ACCEPT,
+            http::HeaderValue::from_static(TABLE_ACCEPT),
+        );
+        logged("list table", client.request::<Table>(request).await)
+    };
+    timed(&format!("list {path}"), options.timeout, list_future).await
+}
+
+/// Create an `ArgValueCompleter` that lists the containers of the pod given by the
+/// `--<pod_arg_name>` option on the command line being completed, e.g. `pod` for `--pod`,
+/// or by the positional argument with the id `pod_arg_name`, as in `logs POD -c`,
+/// like `kubectl logs -c` does. Init and ephemeral containers are included and marked
+/// "init" and "ephemeral" in the help text.
+///
+/// A positional pod is only found when completions are handled by [`install_completions`],
+/// which knows the command to parse the command line with.
+/// The pod may be given as `NAME` or `pod/NAME`. Nothing is offered until it is given.
+/// The pod is fetched like [`pod_value_completer`] does, from the namespace and context
+/// given on the command line.
+pub fn container_value_completer(pod_arg_name: &str) -> ArgValueCompleter {
+    container_value_completer_with(pod_arg_name, default_completer_options())
+}
+
+/// Same as [`container_value_completer`], but configured by `options`.
+pub fn container_value_completer_with(
+    pod_arg_name: &str,
+    options: CompleterOptions,
+) -> ArgValueCompleter {
+    let pod_flag = format!("--{pod_arg_name}");
+    let pod_arg_name = pod_arg_name.to_string();
+    ArgValueCompleter::new(move |input: &OsStr| -> Vec<CompletionCandidate> {
+        if options.offline_only {
+            return Vec::new();
         }
+        let Some(pod) = option_from_args(completion_args(), &pod_flag, &pod_flag)
+            .or_else(|| completion_arg(&pod_arg_name))
+        else {
+            return Vec::new();
+        };
+        let pod = pod
+            .strip_prefix("pods/")
+            .or_else(|| pod.strip_prefix("pod/"))
+            .or_else(|| pod.strip_prefix("po/"))
+            .unwrap_or(&pod);
+        let Some(context) = completion_context() else {
+            return Vec::new();
+        };
+        let namespace = completion_namespace(&context);
+        let input = input.to_string_lossy();
+        let containers = block_on(list_containers(&context, &namespace, pod, options.timeout))
+            .flatten()
+            .unwrap_or_default();
+
+        options
+            .match_mode
+            .filter(containers, input.trim(), |(name, _)| name)
+            .into_iter()
+            .map(|(name, help)| CompletionCandidate::new(name).help(help.map(Into::into)))
+            .collect()
+    })
+}
+
+/// Lists the containers of `pod`, with "init" or "ephemeral" for those kinds of containers,
+/// or returns `None` if the pod cannot be fetched within `timeout`.
+async fn list_containers(
+    context: &str,
+    namespace: &str,
+    pod: &str,
+    timeout: Duration,
+) -> Option<Vec<(String, Option<&'static str>)>> {
+    let get_future = async {
+        let client = completion_client(context, timeout).await?;
+        let pod = logged(
+            "get pod",
+            kube::Api::<Pod>::namespaced(client, namespace)
+                .get(pod)
+                .await,
+        )?;
+        let spec = pod.spec?;
+        let containers = spec.containers.into_iter().map(|c| (c.name, None));
+        let init_containers = spec
+            .init_containers
+            .into_iter()
+            .flatten()
+            .map(|c| (c.name, Some("init")));
+        let ephemeral_containers = spec
+            .ephemeral_containers
+            .into_iter()
+            .flatten()
+            .map(|c| (c.name, Some("ephemeral")));
+        Some(
+            containers
+                .chain(init_containers)
+                .chain(ephemeral_containers)
+                .collect(),
+        )
+    };
+    timed(&format!("get pod {namespace}/{pod}"), timeout, get_future).await
+}
+
+/// Create an `ArgValueCompleter` that lists the names of objects of `resource`, e.g. `deployments`,
+/// `deploy` or `certificates.cert-manager.io`, so that any resource type including CRDs
+/// can be completed.
+///
+/// `resource` is looked up among the built-in resources and then by discovery.
+/// Objects are listed like [`pod_value_completer`] does, but only their metadata is fetched,
+/// and cluster-scoped resources ignore the namespace. As field selectors only match names
+/// exactly, the input is matched against the fetched names.
+pub fn resource_name_completer(resource: &str) -> ArgValueCompleter {
+    resource_name_completer_with(resource, default_completer_options())
+}
+
+/// Same as [`resource_name_completer`], but configured by `options`.
+pub fn resource_name_completer_with(
+    resource: &str,
+    options: CompleterOptions,
+) -> ArgValueCompleter {
+    let target = ObjectCompletionTarget::Named(resource.to_string());
+    ArgValueCompleter::new(move |input: &OsStr| complete_object_names(input, &target, &options))
+}
+
+/// Same as [`resource_name_completer`], but for an already resolved `resource`.
+pub fn api_resource_name_completer(resource: APIResource) -> ArgValueCompleter {
+    api_resource_name_completer_with(resource, default_completer_options())
+}
+
+/// Same as [`api_resource_name_completer`], but configured by `options`.
+pub fn api_resource_name_completer_with(
+    resource: APIResource,
+    options: CompleterOptions,
+) -> ArgValueCompleter {
+    let target = ObjectCompletionTarget::Resolved(resource);
+    ArgValueCompleter::new(move |input: &OsStr| complete_object_names(input, &target, &options))
+}
+
+/// Create an `ArgValueCompleter` for resource references like `deployment/nginx`
+/// (see [`resource_ref_value_parser`]): the `TYPE` is completed like
+/// [`resource_value_completer`] does, and once it is followed by `/`, the `NAME` is completed
+/// like [`resource_name_completer`] does for that type.
+pub fn resource_ref_value_completer() -> ArgValueCompleter {
+    resource_ref_value_completer_with(default_completer_options())
+}
+
+/// Same as [`resource_ref_value_completer`], but configured by `options`.
+pub fn resource_ref_value_completer_with(options: CompleterOptions) -> ArgValueCompleter {
+    ArgValueCompleter::new(move |input: &OsStr| -> Vec<CompletionCandidate> {
+        let input_str = input.to_string_lossy();
+        let Some((resource, name)) = input_str.trim().split_once('/') else {
+            return complete_resources(input, None, &options);
+        };
+        let target = ObjectCompletionTarget::Named(resource.to_string());
+        complete_object_names(OsStr::new(name), &target, &options)
+            .into_iter()
+            .map(|candidate| {
+                let name = candidate.get_value().to_string_lossy();
+                CompletionCandidate::new(format!("{resource}/{name}"))
+            })
+            .collect()
     })
 }
+
+enum ObjectCompletionTarget {
+    Named(String),
+    Resolved(APIResource),
+}
+
+fn complete_object_names(
+    input: &OsStr,
+    target: &ObjectCompletionTarget,
+    options: &CompleterOptions,
+) -> Vec<CompletionCandidate> {
+    if options.offline_only {
+        return Vec::new();
+    }
+    let Some(context) = completion_context() else {
+        return Vec::new();
+    };
+    let namespace = completion_namespace(&context);
+    let input = input.to_string_lossy();
+    let names = block_on(list_object_names(&context, &namespace, target, options))
+        .flatten()
+        .unwrap_or_default();
+
+    options
+        .match_mode
+        .filter(names, input.trim(), String::as_str)
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// Lists the names of the objects of `target` in `namespace`, up to the limit of `options`,
+/// or returns `None` if the resource cannot be resolved or the objects cannot be listed
+/// within its timeout.
+async fn list_object_names(
+    context: &str,
+    namespace: &str,
+    target: &ObjectCompletionTarget,
+    options: &CompleterOptions,
+) -> Option<Vec<String>> {
+    let list_future = async {
+        let client = completion_client(context, options.timeout).await?;
+        let resource = match target {
+            ObjectCompletionTarget::Resolved(resource) => resource.clone(),
+            ObjectCompletionTarget::Named(name) => {
+                let resource_ref: ResourceRef = logged("parse resource", name.parse())?;
+                match resource_ref.resolve(&builtin_api_resources()) {
+                    Ok((resource, _)) => resource,
+                    Err(_) => {
+                        let resources = DiscoverClient::new(client.clone())
+                            .with_deadline(options.timeout)
+                            .list_api_resources()
+                            .await;
+                        let resources = logged("discover resources", resources)?;
+                        logged("resolve resource", resource_ref.resolve(&resources))?.0
+                    }
+                }
+            }
+        };
+        let namespace = resource.namespaced.then_some(namespace);
+        let api = logged("build API", dynamic::api_for(client, &resource, namespace))?;
+        let list = api
+            .list_metadata(
+                &ListParams::default().limit(options.limit.unwrap_or(OBJECT_COMPLETION_LIMIT)),
+            )
+            .await;
+        let list = logged("list objects", list)?;
+        Some(
+            list.items
+                .into_iter()
+                .filter_map(|object| object.metadata.name)
+                .collect(),
+        )
+    };
+    let step = match target {
+        ObjectCompletionTarget::Named(name) => format!("list {name}"),
+        ObjectCompletionTarget::Resolved(resource) => format!("list {}", resource.name),
+    };
+    timed(&step, options.timeout, list_future).await
+}
+
+/// Create an `ArgValueCompleter` that lists CustomResourceDefinitions by their full name,
+/// e.g. `certificates.cert-manager.io`, with their served versions and scope as help text,
+/// e.g. "v1 (namespaced)".
+///
+/// The input is also matched against the plural and the group alone, so that typing
+/// `cert-manager` offers the CRDs of that group. CRDs are listed like
+/// [`pod_value_completer`] does, up to 200 of them; as the versions and scope are in the
+/// spec, whole objects are fetched. With [`CompleterOptions::cache_dir`], the CRDs of each
+/// context are cached in its `crds` subdirectory like namespaces are.
+pub fn crd_value_completer() -> ArgValueCompleter {
+    crd_value_completer_with(default_completer_options())
+}
+
+/// Same as [`crd_value_completer`], but configured by `options`.
+pub fn crd_value_completer_with(options: CompleterOptions) -> ArgValueCompleter {
+    ArgValueCompleter::new(move |input: &OsStr| complete_crds(input, &options))
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct CrdCandidate {
+    name: String,
+    plural: String,
+    group: String,
+    help: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedCrds {
+    updated_at: DateTime<Utc>,
+    crds: Vec<CrdCandidate>,
+}
+
+fn complete_crds(input: &OsStr, options: &CompleterOptions) -> Vec<CompletionCandidate> {
+    let Some(context) = completion_context() else {
+        return Vec::new();
+    };
+    let cache_path = options
+        .cache_dir
+        .as_ref()
+        .map(|dir| dir.join("crds").join(discovery_cache_file_name(&context)));
+    let cached: Option<CachedCrds> = cache_path
+        .as_deref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok());
+    let ttl = options.cache_ttl.unwrap_or(DEFAULT_NAMESPACE_CACHE_TTL);
+    let crds = match cached {
+        Some(cached) if options.offline_only || !is_older_than(cached.updated_at, ttl) => {
+            cached.crds
+        }
+        _ if options.offline_only => Vec::new(),
+        cached => match block_on(list_crds(&context, options)).flatten() {
+            Some(crds) => {
+                if let Some(path) = &cache_path {
+                    store_crds(path, &crds);
+                }
+                crds
+            }
+            None => cached.map(|cached| cached.crds).unwrap_or_default(),
+        },
+    };
+
+    let input = input.to_string_lossy();
+    let input = input.trim();
+    filter_scored(crds, |crd| {
+        [&crd.name, &crd.plural, &crd.group]
+            .into_iter()
+            .filter_map(|key| options.match_mode.score(key, input))
+            .max()
+    })
+    .into_iter()
+    .map(|crd| CompletionCandidate::new(crd.name).help(Some(crd.help.into())))
+    .collect()
+}
+
+fn store_crds(path: &Path, crds: &[CrdCandidate]) {
+    let cached = CachedCrds {
+        updated_at: Utc::now(),
+        crds: crds.to_vec(),
+    };
+    // NOTE: Failing to write only makes the next completion slower.
+    if let Ok(content) = serde_json::to_string(&cached)
+        && let Some(dir) = path.parent()
+        && fs::create_dir_all(dir).is_ok()
+    {
+        let _ = fs::write(path, content);
+    }
+}
+
+/// Lists the CRDs of `context` with their served versions and scope, up to the limit of
+/// `options`, or returns `None` if they cannot be listed within its timeout.
+async fn list_crds(context: &str, options: &CompleterOptions) -> Option<Vec<CrdCandidate>> {
+    let list_future = async {
+        let client = completion_client(context, options.timeout).await?;
+        let list = kube::Api::<CustomResourceDefinition>::all(client)
+            .list(&ListParams::default().limit(options.limit.unwrap_or(OBJECT_COMPLETION_LIMIT)))
+            .await;
+        let list = logged("list CRDs", list)?;
+        Some(
+            list.items
+                .into_iter()
+                .filter_map(|crd| {
+                    let versions: Vec<&str> = crd
+                        .spec
+                        .versions
+                        .iter()
+                        .filter(|version| version.served)
+                        .map(|version| version.name.as_str())
+                        .collect();
+                    let scope = if crd.spec.scope == "Namespaced" {
+                        "namespaced"
+                    } else {
+                        "cluster-scoped"
+                    };
+                    Some(CrdCandidate {
+                        help: format!("{} ({scope})", versions.join(", ")),
+                        name: crd.metadata.name?,
+                        plural: crd.spec.names.plural,
+                        group: crd.spec.group,
+                    })
+                })
+                .collect(),
+        )
+    };
+    timed(
+        &format!("list CRDs of {context}"),
+        options.timeout,
+        list_future,
+    )
+    .await
+}
+
+/// `Accept` header requesting a list as a server-side `Table`, like kubectl does for printing.
+const TABLE_ACCEPT: &str = "application/json;as=Table;v=v1;g=meta.k8s.io,application/json";
+
+/// Server-side table of objects, as returned with [`TABLE_ACCEPT`].
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Table {
+    #[serde(default)]
+    column_definitions: Vec<TableColumn>,
+    #[serde(default)]
+    rows: Vec<TableRow>,
+}
+
+impl Table {
+    /// Returns the string cells of `columns` in each row, `None` where a column is missing
+    /// or its cell is not a string.
+    fn select(&self, columns: &[&str]) -> Vec<Vec<Option<&str>>> {
+        let positions: Vec<Option<usize>> = columns
+            .iter()
+            .map(|name| {
+                self.column_definitions
+                    .iter()
+                    .position(|column| column.name == *name)
+            })
+            .collect();
+        self.rows
+            .iter()
+            .map(|row| {
+                positions
+                    .iter()
+                    .map(|&position| row.cells.get(position?)?.as_str())
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TableColumn {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TableRow {
+    #[serde(default)]
+    cells: Vec<serde_json::Value>,
+}
+
+/// Returns the value of the last `long` (e.g. `--context`) or `short` (e.g. `-c`) option
+/// in `args`, a command line being completed.
+fn option_from_args(
+    args: impl IntoIterator<Item = OsString>,
+    long: &str,
+    short: &str,
+) -> Option<String> {
+    options_from_args(args, long, short).pop()
+}
+
+/// Returns the non-empty values of every `long` or `short` option in `args`, in order.
+fn options_from_args(
+    args: impl IntoIterator<Item = OsString>,
+    long: &str,
+    short: &str,
+) -> Vec<String> {
+    let mut found = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let arg = arg.to_string_lossy();
+        if let Some(value) = arg
+            .strip_prefix(long)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            found.push(value.to_string());
+        } else if (arg == long || arg == short)
+            && let Some(value) = args.next()
+        {
+            found.push(value.to_string_lossy().into_owned());
+        }
+    }
+    found.retain(|value| !value.is_empty());
+    found
+}
+
+/// Create the `-n/--namespace` argument, completed by [`namespace_value_completer`] and
+/// validated by [`namespace_value_parser`].
+///
+/// Read it back with `matches.get_one::<String>("namespace")`.
+pub fn namespace_arg() -> Arg {
+    namespace_arg_with_completer(namespace_value_completer())
+}
+
+/// Same as [`namespace_arg`], but completed by [`namespace_value_completer_with`] `options`.
+pub fn namespace_arg_with(options: CompleterOptions) -> Arg {
+    namespace_arg_with_completer(namespace_value_completer_with(options))
+}
+
+fn namespace_arg_with_completer(completer: ArgValueCompleter) -> Arg {
+    Arg::new("namespace")
+        .short('n')
+        .long("namespace")
+        .value_name("NAMESPACE")
+        .value_hint(ValueHint::Other)
+        .value_parser(namespace_value_parser())
+        .add(completer)
+        .help("Namespace to use, defaults to the namespace of the context")
+}
+
+/// Create a value parser accepting only valid namespace names (see [`validate_namespace_name`]),
+/// so that a name like `Team_A` is rejected with the rule it violates before any request is sent.
+///
+/// [`namespace_arg`] uses it. To accept any value there, e.g. for a field that is validated
+/// later, replace it with `namespace_arg().value_parser(clap::value_parser!(String))`.
+pub fn namespace_value_parser() -> impl TypedValueParser<Value = String> {
+    StringValueParser::new()
+        .try_map(|namespace| validate_namespace_name(&namespace).map(|()| namespace))
+}
+
+/// Create the `-A/--all-namespaces` flag, which conflicts with [`namespace_arg`].
+///
+/// The command must also have [`namespace_arg`] (or another argument with the id `namespace`),
+/// as clap rejects conflicts with unknown arguments. Read both back with [`namespace_selection_from_matches`].
+pub fn all_namespaces_arg() -> Arg {
+    Arg::new("all-namespaces")
+        .short('A')
+        .long("all-namespaces")
+        .action(ArgAction::SetTrue)
+        .conflicts_with("namespace")
+        .help("List the requested objects across all namespaces")
+}
+
+/// Extract the namespaces selected by [`namespace_arg`] and [`all_namespaces_arg`].
+/// A command without the all-namespaces flag always selects the given or default namespace.
+///
+/// # Errors
+/// Returns an error if both a namespace and all namespaces are given,
+/// see [`NamespaceSelection::from_flags`].
+pub fn namespace_selection_from_matches(
+    matches: &ArgMatches,
+) -> anyhow::Result<NamespaceSelection> {
+    let namespace = get_arg::<String>(matches, "namespace").cloned();
+    let all = get_arg::<bool>(matches, "all-namespaces").is_some_and(|all| *all);
+    NamespaceSelection::from_flags(namespace, all)
+}
+
+/// Create the `-o/--output` argument, parsed as an [`OutputFormat`].
+///
+/// Besides the formats listed in the help, `jsonpath=...` and `custom-columns=...` are
+/// accepted as [`OutputFormat::Custom`]. Read it back with [`output_format_from_matches`]
+/// and render objects with [`crate::output::render`].
+pub fn output_arg() -> Arg {
+    Arg::new("output")
+        .short('o')
+        .long("output")
+        .value_name("FORMAT")
+        .value_parser(OutputFormatParser)
+        .help("Output format")
+}
+
+/// Extract the format given by [`output_arg`], if any.
+pub fn output_format_from_matches(matches: &ArgMatches) -> Option<OutputFormat> {
+    matches.get_one::<OutputFormat>("output").cloned()
+}
+
+/// Parses [`OutputFormat`], reporting unknown values like a `ValueEnum` does.
+#[derive(Clone, Copy, Debug)]
+struct OutputFormatParser;
+
+impl TypedValueParser for OutputFormatParser {
+    type Value = OutputFormat;
+
+    fn parse_ref(
+        &self,
+        cmd: &Command,
+        arg: Option<&Arg>,
+        value: &OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        if let Some(value) = value.to_str()
+            && CUSTOM_OUTPUT_PREFIXES
+                .iter()
+                .any(|prefix| value.starts_with(prefix))
+        {
+            return Ok(OutputFormat::Custom(value.to_string()));
+        }
+        EnumValueParser::<OutputFormat>::new().parse_ref(cmd, arg, value)
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        Some(Box::new(
+            OutputFormat::value_variants()
+                .iter()
+                .filter_map(ValueEnum::to_possible_value),
+        ))
+    }
+}
+
+/// Create a value parser producing a [`ResourceRef`] from `TYPE`, `TYPE/NAME` or their
+/// group-qualified forms, e.g. `deployments.apps/nginx`, rejecting malformed references
+/// with the argument they were given to.
+///
+/// With derive, use it for a `Vec<ResourceRef>` of positional references:
+///
+/// ```no_run
+/// # use clap::Parser;
+/// # use kubex::{ResourceRef, claputil::{resource_ref_value_completer, resource_ref_value_parser}};
+/// #[derive(Parser)]
+/// struct Cli {
+///     #[arg(value_parser = resource_ref_value_parser(), add = resource_ref_value_completer())]
+///     resources: Vec<ResourceRef>,
+/// }
+/// ```
+pub fn resource_ref_value_parser() -> impl TypedValueParser<Value = ResourceRef> {
+    StringValueParser::new().try_map(|value| value.parse::<ResourceRef>())
+}
+
+/// Create the positional `TYPE[/NAME]...` argument, taking any number of resource references
+/// parsed by [`resource_ref_value_parser`] and completed by [`resource_ref_value_completer`].
+///
+/// Read it back with [`resource_refs_from_matches`].
+pub fn resource_ref_arg() -> Arg {
+    Arg::new("resources")
+        .value_name("TYPE[/NAME]")
+        .num_args(1..)
+        .action(ArgAction::Append)
+        .value_parser(resource_ref_value_parser())
+        .add(resource_ref_value_completer())
+        .help("Resource types or objects, e.g. pods or deployment/nginx")
+}
+
+/// Extract the references given to [`resource_ref_arg`], empty if none is given.
+pub fn resource_refs_from_matches(matches: &ArgMatches) -> Vec<ResourceRef> {
+    matches
+        .get_many::<ResourceRef>("resources")
+        .map(|refs| refs.cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Create the `-l/--selector` argument, parsed and validated as a [`LabelSelector`].
+///
+/// Read it back with [`list_params_from_matches`] or [`watch_params_from_matches`].
+pub fn selector_arg() -> Arg {
+    Arg::new("selector")
+        .short('l')
+        .long("selector")
+        .value_name("SELECTOR")
+        .value_parser(value_parser!(LabelSelector))
+        .help("Label selector to filter on, e.g. -l key1=value1,key2=value2")
+}
+
+/// Create the `--field-selector` argument, parsed and validated as a [`FieldSelector`].
+///
+/// Read it back with [`list_params_from_matches`] or [`watch_params_from_matches`].
+pub fn field_selector_arg() -> Arg {
+    Arg::new("field-selector")
+        .long("field-selector")
+        .value_name("SELECTOR")
+        .value_parser(value_parser!(FieldSelector))
+        .help("Field selector to filter on, e.g. --field-selector status.phase=Running")
+}
+
+/// Create the `--limit` argument, the maximum number of objects returned by a list request.
+///
+/// Read it back with [`list_params_from_matches`].
+pub fn limit_arg() -> Arg {
+    Arg::new("limit")
+        .long("limit")
+        .value_name("COUNT")
+        .value_parser(value_parser!(u32).range(1..))
+        .help("Maximum number of objects to return per list request")
+}
+
+/// Builds `ListParams` from [`selector_arg`], [`field_selector_arg`] and [`limit_arg`],
+/// any of which may be absent from the command.
+pub fn list_params_from_matches(matches: &ArgMatches) -> ListParams {
+    let mut params = ListParams::default();
+    if let Some(selector) = get_arg::<LabelSelector>(matches, "selector") {
+        params = params.labels(&selector.to_string());
+    }
+    if let Some(selector) = get_arg::<FieldSelector>(matches, "field-selector") {
+        params = params.fields(&selector.to_string());
+    }
+    if let Some(limit) = get_arg::<u32>(matches, "limit") {
+        params = params.limit(*limit);
+    }
+    params
+}
+
+/// Builds `WatchParams` from [`selector_arg`] and [`field_selector_arg`],
+/// either of which may be absent from the command.
+pub fn watch_params_from_matches(matches: &ArgMatches) -> WatchParams {
+    let mut params = WatchParams::default();
+    if let Some(selector) = get_arg::<LabelSelector>(matches, "selector") {
+        params = params.labels(&selector.to_string());
+    }
+    if let Some(selector) = get_arg::<FieldSelector>(matches, "field-selector") {
+        params = params.fields(&selector.to_string());
+    }
+    params
+}
+
+/// Returns the value of the argument `id`, or `None` if it is not given or not defined.
+fn get_arg<'a, T: Clone + Send + Sync + 'static>(
+    matches: &'a ArgMatches,
+    id: &str,
+) -> Option<&'a T> {
+    matches.try_get_one::<T>(id).ok().flatten()
+}
+
+/// Create the `--context` argument, completed by [`context_value_completer`].
+///
+/// Read it back with [`context_from_matches`] and pass it to [`crate::determine_context`],
+/// which falls back to the current context if it is not given.
+pub fn context_arg() -> Arg {
+    Arg::new("context")
+        .long("context")
+        .value_name("CONTEXT")
+        .add(context_value_completer())
+        .help("Kubeconfig context to use, defaults to the current context")
+}
+
+/// Same as [`context_arg`], but defaulting to the environment variable `var`,
+/// e.g. `KUBEX_CONTEXT`, so that a context can be chosen per shell session.
+///
+/// The context is then the first of:
+/// 1. The value of `--context`.
+/// 2. The value of `var`, if set and not empty.
+/// 3. The current context of the kubeconfig.
+///
+/// clap fills in the first two, so the value read back by [`context_from_matches`] is passed
+/// to [`crate::determine_context`] as an explicit context, which it uses as is. The completers
+/// of this module follow the same order; if this is called more than once, they read the
+/// first `var`.
+pub fn context_arg_with_env(var: &'static str) -> Arg {
+    let _ = CONTEXT_ENV_VAR.set(var);
+    context_arg().env(var)
+}
+
+/// Same as [`context_arg`], but parsed by [`context_value_parser`], so that a context
+/// that is not defined in the kubeconfig is rejected with similar contexts suggested.
+///
+/// It is opt-in, as some tools take contexts defined in other kubeconfig files.
+pub fn context_arg_validated() -> Arg {
+    context_arg().value_parser(context_value_parser())
+}
+
+/// Create a value parser accepting only the contexts defined in the kubeconfig, rejecting
+/// others with up to five similar contexts (see [`suggest_contexts`]), e.g.
+/// "context produciton is not found in kubeconfig (did you mean: production?)".
+///
+/// The kubeconfig is read like [`read_merged_kubeconfig`] does, honoring `KUBECONFIG` but not
+/// `--kubeconfig`, which a value parser cannot see. If it cannot be read or defines no context,
+/// any context is accepted, leaving the error to the client construction.
+pub fn context_value_parser() -> impl TypedValueParser<Value = String> {
+    StringValueParser::new().try_map(|context| {
+        ensure_context_defined(&context)?;
+        Ok::<_, anyhow::Error>(context)
+    })
+}
+
+fn ensure_context_defined(context: &str) -> anyhow::Result<()> {
+    // NOTE: An empty context, e.g. from an empty environment variable, means none is given.
+    if context.is_empty() {
+        return Ok(());
+    }
+    // NOTE: A kubeconfig without contexts, e.g. when no file exists, cannot tell either.
+    let Some(kubeconfig) = read_merged_kubeconfig()
+        .ok()
+        .filter(|kubeconfig| !kubeconfig.contexts.is_empty())
+    else {
+        return Ok(());
+    };
+    if kubeconfig.contexts.iter().any(|c| c.name == context) {
+        return Ok(());
+    }
+    let suggestions = suggest_contexts(context, &kubeconfig, MAX_CONTEXT_SUGGESTIONS);
+    if suggestions.is_empty() {
+        Err(anyhow::anyhow!(
+            "context {context} is not found in kubeconfig"
+        ))
+    } else {
+        Err(anyhow::anyhow!(
+            "context {context} is not found in kubeconfig (did you mean: {}?)",
+            suggestions.join(", ")
+        ))
+    }
+}
+
+/// Extract the context given by [`context_arg`], `None` if none is given or it is empty,
+/// e.g. from an empty environment variable.
+pub fn context_from_matches(matches: &ArgMatches) -> Option<String> {
+    matches
+        .get_one::<String>("context")
+        .filter(|context| !context.is_empty())
+        .cloned()
+}
+
+/// Create the `--kubeconfig` argument, which may be repeated to merge several files
+/// like `KUBECONFIG` does.
+///
+/// Read it back with [`kubeconfig_from_matches`] and pass the result to
+/// [`crate::ClientOptions::kubeconfig_files`]. The completers of this module also read
+/// the files given by it while completing the other arguments.
+pub fn kubeconfig_arg() -> Arg {
+    Arg::new("kubeconfig")
+        .long("kubeconfig")
+        .value_name("PATH")
+        .value_parser(value_parser!(PathBuf))
+        .value_hint(ValueHint::FilePath)
+        .action(ArgAction::Append)
+        .help(
+            "Path to the kubeconfig file to use, this flag can be repeated to merge multiple files",
+        )
+}
+
+/// Extract the files given by [`kubeconfig_arg`], empty if none is given.
+pub fn kubeconfig_from_matches(matches: &ArgMatches) -> Vec<PathBuf> {
+    matches
+        .get_many::<PathBuf>("kubeconfig")
+        .map(|paths| paths.cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Arguments selecting the kubeconfig, context, namespaces and impersonation of a command,
+/// to be flattened into a derived parser:
+///
+/// ```no_run
+/// # use clap::Parser;
+/// #[derive(Parser)]
+/// struct Cli {
+///     #[command(flatten)]
+///     kube: kubex::claputil::KubeArgs,
+/// }
+/// ```
+///
+/// The context and namespace are completed by [`context_value_completer`] and
+/// [`namespace_value_completer`]. The arguments have the same ids as [`kubeconfig_arg`],
+/// [`namespace_arg`], [`all_namespaces_arg`] and [`impersonation_args`], so the matching
+/// `*_from_matches` functions also work on the parsed matches.
+#[derive(Clone, Debug, Default, clap::Args)]
+#[command(about = None, long_about = None)]
+pub struct KubeArgs {
+    /// Path to the kubeconfig file to use, this flag can be repeated to merge multiple files
+    #[arg(long, value_name = "PATH", value_hint = ValueHint::FilePath)]
+    pub kubeconfig: Vec<PathBuf>,
+
+    /// Kubeconfig context to use, defaults to the current context
+    #[arg(long, value_name = "CONTEXT", add = context_value_completer())]
+    pub context: Option<String>,
+
+    /// Namespace to use, defaults to the namespace of the context
+    #[arg(
+        short,
+        long,
+        value_name = "NAMESPACE",
+        value_hint = ValueHint::Other,
+        value_parser = namespace_value_parser(),
+        add = namespace_value_completer()
+    )]
+    pub namespace: Option<String>,
+
+    /// List the requested objects across all namespaces
+    #[arg(id = "all-namespaces", short = 'A', long, conflicts_with = "namespace")]
+    pub all_namespaces: bool,
+
+    /// Username to impersonate for the operation, a regular user or a service account in a namespace
+    #[arg(id = "as", long = "as", value_name = "USER")]
+    pub as_user: Option<String>,
+
+    /// Group to impersonate for the operation, this flag can be repeated to specify multiple groups
+    #[arg(
+        id = "as-group",
+        long = "as-group",
+        value_name = "GROUP",
+        value_delimiter = ','
+    )]
+    pub as_groups: Vec<String>,
+
+    /// UID to impersonate for the operation
+    #[arg(id = "as-uid", long = "as-uid", value_name = "UID", requires = "as")]
+    pub as_uid: Option<String>,
+}
+
+impl KubeArgs {
+    /// Resolves the identity from the given kubeconfig files, or the default kubeconfig
+    /// if none is given, like [`crate::KubeIdentity::from_kubeconfig`].
+    ///
+    /// # Errors
+    /// Returns an error if the kubeconfig cannot be read or the context cannot be resolved.
+    pub fn identity(&self) -> anyhow::Result<KubeIdentity> {
+        if self.kubeconfig.is_empty() {
+            KubeIdentity::resolve(self.context.clone(), self.namespace.clone())
+        } else {
+            KubeIdentity::from_kubeconfig(
+                read_kubeconfig_files(&self.kubeconfig)?,
+                self.context.clone(),
+                self.namespace.clone(),
+            )
+        }
+    }
+
+    /// Returns the namespaces selected by `--namespace` and `--all-namespaces`.
+    ///
+    /// # Errors
+    /// See [`NamespaceSelection::from_flags`].
+    pub fn namespace_selection(&self) -> anyhow::Result<NamespaceSelection> {
+        NamespaceSelection::from_flags(self.namespace.clone(), self.all_namespaces)
+    }
+
+    /// Returns the impersonation given by `--as`, `--as-group` and `--as-uid`.
+    pub fn impersonation(&self) -> Impersonation {
+        Impersonation {
+            user: self.as_user.clone(),
+            groups: self.as_groups.clone(),
+            uid: self.as_uid.clone(),
+        }
+    }
+
+    /// Returns `ClientOptions` with these arguments applied, to set further options on.
+    pub fn client_options(&self) -> ClientOptions {
+        let mut options = ClientOptions::new()
+            .kubeconfig_files(self.kubeconfig.clone())
+            .impersonation(self.impersonation());
+        if let Some(context) = &self.context {
+            options = options.context(context);
+        }
+        if let Some(namespace) = &self.namespace {
+            options = options.namespace(namespace);
+        }
+        options
+    }
+
+    /// Builds a client for these arguments, defaulting to the resolved namespace.
+    ///
+    /// # Errors
+    /// See [`ClientOptions::config`].
+    pub async fn client(&self) -> anyhow::Result<Client> {
+        Ok(self.client_options().build().await?.0)
+    }
+}
+
+/// Create the `--as`, `--as-group` and `--as-uid` arguments, mirroring kubectl's
+/// impersonation flags. `--as-group` takes several groups, repeated or separated by commas,
+/// and `--as-uid` requires `--as`.
+///
+/// Read them back with [`impersonation_from_matches`] and pass the result to
+/// [`crate::ClientOptions::impersonation`].
+pub fn impersonation_args() -> Vec<Arg> {
+    vec![
+        Arg::new("as")
+            .long("as")
+            .value_name("USER")
+            .help("Username to impersonate for the operation, a regular user or a service account in a namespace"),
+        Arg::new("as-group")
+            .long("as-group")
+            .value_name("GROUP")
+            .action(ArgAction::Append)
+            .value_delimiter(',')
+            .help("Group to impersonate for the operation, this flag can be repeated to specify multiple groups"),
+        Arg::new("as-uid")
+            .long("as-uid")
+            .value_name("UID")
+            .requires("as")
+            .help("UID to impersonate for the operation"),
+    ]
+}
+
+/// Extract the impersonation given by the arguments of [`impersonation_args`].
+pub fn impersonation_from_matches(matches: &ArgMatches) -> Impersonation {
+    Impersonation {
+        user: matches.get_one::<String>("as").cloned(),
+        groups: matches
+            .get_many::<String>("as-group")
+            .map(|groups| groups.cloned().collect())
+            .unwrap_or_default(),
+        uid: matches.get_one::<String>("as-uid").cloned(),
+    }
+}
+
+/// Create the `--force-protected-namespaces` flag, which overrides a [`crate::NamespaceGuard`].
+///
+/// Read it back with [`force_protected_namespaces_from_matches`] and pass the result to
+/// [`crate::NamespaceGuard::allow_protected`]. [`crate::GuardError`] does not mention the flag,
+/// so add a hint such as "pass --force-protected-namespaces to override" when reporting it.
+pub fn force_protected_namespaces_arg() -> Arg {
+    Arg::new("force-protected-namespaces")
+        .long("force-protected-namespaces")
+        .action(ArgAction::SetTrue)
+        .help("Allow destructive operations in protected namespaces such as kube-system")
+}
+
+/// Extract whether the flag of [`force_protected_namespaces_arg`] is given.
+pub fn force_protected_namespaces_from_matches(matches: &ArgMatches) -> bool {
+    matches.get_flag("force-protected-namespaces")
+}
+
+/// Name of the subcommand created by [`completions_subcommand`].
+const COMPLETIONS_SUBCOMMAND: &str = "completions";
+
+/// Create the `completions <SHELL>` subcommand, which prints a static completion script
+/// for shells where dynamic completion is not set up. [`install_completions`] handles it.
+pub fn completions_subcommand() -> Command {
+    Command::new(COMPLETIONS_SUBCOMMAND)
+        .about("Print a static completion script for the shell")
+        .arg(
+            Arg::new("shell")
+                .required(true)
+                .value_parser(value_parser!(Shell)),
+        )
+}
+
+/// Handles shell completion for the command built by `cmd`, to be called first in `main`:
+///
+/// ```no_run
+/// # use clap::{CommandFactory, Parser};
+/// #[derive(Parser)]
+/// struct Cli {
+///     #[arg(long, add = kubex::context_value_completer())]
+///     context: Option<String>,
+/// }
+///
+/// fn main() {
+///     kubex::claputil::install_completions(Cli::command);
+///     let cli = Cli::parse();
+/// }
+/// ```
+///
+/// If the `COMPLETE` environment variable is set, i.e. the shell asks for completions,
+/// the candidates (or the registration script) are printed with the completers of this module
+/// and the process exits. Otherwise, if the command has the [`completions_subcommand`] and it
+/// is given right after the binary name, the static script is printed by
+/// [`generate_static_completions`] and the process exits. Otherwise this returns.
+pub fn install_completions(cmd: fn() -> Command) {
+    let _ = COMPLETION_COMMAND.set(cmd);
+    CompleteEnv::with_factory(cmd).complete();
+
+    let args: Vec<OsString> = env::args_os().collect();
+    if args.get(1).is_none_or(|arg| arg != COMPLETIONS_SUBCOMMAND) {
+        return;
+    }
+    let mut command = cmd();
+    let Some(subcommand) = command.find_subcommand(COMPLETIONS_SUBCOMMAND) else {
+        return;
+    };
+    let matches = subcommand
+        .clone()
+        .try_get_matches_from(&args[1..])
+        .unwrap_or_else(|err| err.exit());
+    // NOTE: A `completions` subcommand of the tool's own is left to the tool.
+    let Ok(Some(&shell)) = matches.try_get_one::<Shell>("shell") else {
+        return;
+    };
+    generate_static_completions(shell, &mut command, &mut io::stdout());
+    process::exit(0);
+}
+
+/// Writes the static completion script of `cmd` for `shell` to `writer`, completing the
+/// subcommands, flags and possible values known without running the binary.
+pub fn generate_static_completions(shell: Shell, cmd: &mut Command, writer: &mut dyn Write) {
+    let bin_name = cmd
+        .get_bin_name()
+        .unwrap_or_else(|| cmd.get_name())
+        .to_string();
+    clap_complete::generate(shell, cmd, bin_name, writer);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::testutil::{
+        KubeconfigFixture, MockServer, api_resource, discovery_response, install_all,
+        status_response,
+    };
+
+    thread_local! {
+        /// Command line read by [`completion_args`] instead of the arguments of the process.
+        pub(super) static COMPLETION_ARGS: RefCell<Option<Vec<OsString>>> =
+            const { RefCell::new(None) };
+    }
+
+    /// Completes `input` with `completer` as if the shell passed `words`.
+    fn complete_with_words(
+        completer: &ArgValueCompleter,
+        words: &[&str],
+        input: &str,
+    ) -> Vec<(String, Option<String>)> {
+        let args = ["kubex", "--"].iter().chain(words).map(OsString::from);
+        COMPLETION_ARGS.set(Some(args.collect()));
+        let candidates = completer.complete(OsStr::new(input));
+        COMPLETION_ARGS.set(None);
+        describe(candidates)
+    }
+
+    /// Returns the values and help texts of `candidates`.
+    fn describe(candidates: Vec<CompletionCandidate>) -> Vec<(String, Option<String>)> {
+        candidates
+            .into_iter()
+            .map(|candidate| {
+                (
+                    candidate.get_value().to_string_lossy().into_owned(),
+                    candidate.get_help().map(ToString::to_string),
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the values of `candidates`, without their help texts.
+    fn values(candidates: Vec<(String, Option<String>)>) -> Vec<String> {
+        candidates.into_iter().map(|(value, _)| value).collect()
+    }
+
+    /// Returns the metadata-only list of the namespaces `names`, as the API server answers
+    /// the namespace completer.
+    fn namespace_list(names: &[&str]) -> (u16, String) {
+        let items: Vec<serde_json::Value> = names
+            .iter()
+            .map(|name| {
+                serde_json::json!({
+                    "apiVersion": "meta.k8s.io/v1",
+                    "kind": "PartialObjectMetadata",
+                    "metadata": { "name": name },
+                })
+            })
+            .collect();
+        let body = serde_json::json!({
+            "apiVersion": "meta.k8s.io/v1",
+            "kind": "PartialObjectMetadataList",
+            "metadata": {},
+            "items": items,
+        });
+        (200, body.to_string())
+    }
+
+    /// Returns the server-side table with `columns` and `rows`, as the API server answers
+    /// the completers of objects.
+    fn table(columns: &[&str], rows: &[&[&str]]) -> (u16, String) {
+        let columns: Vec<serde_json::Value> = columns
+            .iter()
+            .map(|name| serde_json::json!({ "name": name, "type": "string" }))
+            .collect();
+        let rows: Vec<serde_json::Value> = rows
+            .iter()
+            .map(|cells| serde_json::json!({ "cells": cells }))
+            .collect();
+        let body = serde_json::json!({
+            "apiVersion": "meta.k8s.io/v1",
+            "kind": "Table",
+            "metadata": {},
+            "columnDefinitions": columns,
+            "rows": rows,
+        });
+        (200, body.to_string())
+    }
+
+    fn ingresses(group: &str, namespaced: bool) -> APIResource {
+        APIResource {
+            namespaced,
+            short_names: Some(vec![String::from("ing")]),
+            ..api_resource(group, "v1", "ingresses", "Ingress")
+        }
+    }
+
+    #[test]
+    fn resource_candidates_qualify_ambiguous_plural_names() {
+        let resources = [
+            ingresses("networking.k8s.io", true),
+            ingresses("example.com", false),
+        ];
+        assert_eq!(
+            describe(resource_candidates(&resources, "ingr", MatchMode::Prefix)),
+            [
+                (
+                    String::from("ingresses"),
+                    Some(String::from("ing (networking.k8s.io, namespaced)"))
+                ),
+                (
+                    String::from("ingresses.networking.k8s.io"),
+                    Some(String::from("ing (networking.k8s.io, namespaced)"))
+                ),
+                (
+                    String::from("ingresses.example.com"),
+                    Some(String::from("ing (example.com, cluster-scoped)"))
+                ),
+            ]
+        );
+        assert_eq!(
+            describe(resource_candidates(
+                &resources,
+                "ingresses.",
+                MatchMode::Prefix
+            )),
+            [
+                (
+                    String::from("ingresses.networking.k8s.io"),
+                    Some(String::from("ing (networking.k8s.io, namespaced)"))
+                ),
+                (
+                    String::from("ingresses.example.com"),
+                    Some(String::from("ing (example.com, cluster-scoped)"))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn resource_candidates_never_qualify_short_names() {
+        let resources = [
+            ingresses("networking.k8s.io", true),
+            ingresses("example.com", false),
+        ];
+        let candidates = describe(resource_candidates(&resources, "ing", MatchMode::Substring));
+        assert!(candidates.iter().any(|(value, _)| value == "ing"));
+        assert!(
+            !candidates
+                .iter()
+                .any(|(value, _)| value.starts_with("ing."))
+        );
+
+        let resources = crate::builtin::builtin_api_resources();
+        for (value, _) in describe(resource_candidates(&resources, ".", MatchMode::Substring)) {
+            assert!(
+                crate::find_resource(&value, &resources).is_some(),
+                "{value}"
+            );
+        }
+        let values: Vec<String> =
+            describe(resource_candidates(&resources, "deploy", MatchMode::Prefix))
+                .into_iter()
+                .map(|(value, _)| value)
+                .collect();
+        assert_eq!(values, ["deployments", "deploy"]);
+        let values: Vec<String> = describe(resource_candidates(
+            &resources,
+            "deployments.",
+            MatchMode::Prefix,
+        ))
+        .into_iter()
+        .map(|(value, _)| value)
+        .collect();
+        assert_eq!(values, ["deployments.apps"]);
+    }
+
+    #[test]
+    fn namespace_arg_rejects_invalid_namespace_names() {
+        let cmd = || Command::new("kubex").arg(namespace_arg());
+        let matches = cmd()
+            .try_get_matches_from(["kubex", "-n", "team-a"])
+            .unwrap();
+        assert_eq!(
+            matches.get_one::<String>("namespace").map(String::as_str),
+            Some("team-a")
+        );
+        let err = cmd()
+            .try_get_matches_from(["kubex", "--namespace", "Team_A"])
+            .unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::ValueValidation);
+        assert!(err.to_string().contains("contains 'T'"), "{err}");
+    }
+
+    #[test]
+    fn context_value_completer_offers_current_context_first() {
+        let _guard = KubeconfigFixture::new()
+            .context("dev", "dev-cluster", "dev-user")
+            .context("prod", "prod-cluster", "admin")
+            .context("prod-eu", "prod-cluster", "admin")
+            .current("prod")
+            .install()
+            .unwrap();
+        let completer = context_value_completer();
+        assert_eq!(
+            describe(completer.complete(OsStr::new(""))),
+            [
+                (String::from("prod"), Some(String::from("[current]"))),
+                (String::from("dev"), None),
+                (String::from("prod-eu"), None),
+            ]
+        );
+        assert_eq!(
+            describe(completer.complete(OsStr::new("prod-"))),
+            [(String::from("prod-eu"), None)]
+        );
+    }
+
+    #[test]
+    fn context_value_completer_suggests_contexts_for_typos() {
+        let fixture = KubeconfigFixture::new()
+            .context("production", "prod-cluster", "admin")
+            .context("production-eu", "prod-cluster", "admin")
+            .context("staging", "staging-cluster", "admin");
+        let file = fixture.write().unwrap();
+        let path = file.path().to_str().unwrap();
+        let completer = context_value_completer();
+        let complete = |input: &str| {
+            complete_with_words(
+                &completer,
+                &["--kubeconfig", path, "--context", input],
+                input,
+            )
+        };
+
+        assert_eq!(
+            complete("produciton"),
+            [
+                (String::from("production"), None),
+                (String::from("production-eu"), None),
+            ]
+        );
+        assert_eq!(complete("stagng"), [(String::from("staging"), None)]);
+        assert_eq!(complete("minikube"), []);
+    }
+
+    #[test]
+    fn namespace_value_completer_lists_namespaces_of_the_given_context() {
+        let east = MockServer::start(|_| namespace_list(&["east-b", "east-a"]));
+        let west = MockServer::start(|_| namespace_list(&["west-a"]));
+        let _guard = KubeconfigFixture::new()
+            .cluster("east", east.url())
+            .cluster("west", west.url())
+            .context("east", "east", "admin")
+            .context("west", "west", "admin")
+            .current("east")
+            .install()
+            .unwrap();
+        let completer = namespace_value_completer();
+        let complete = |words: &[&str]| values(complete_with_words(&completer, words, ""));
+
+        assert_eq!(
+            complete(&["kubex", "get", "pods", "-n", ""]),
+            ["east-a", "east-b"]
+        );
+        for words in [
+            &["kubex", "--context", "west", "get", "pods", "-n", ""][..],
+            &["kubex", "-c", "west", "-n", ""],
+            &["kubex", "--context=west", "-n", ""],
+            &["kubex", "--context", "east", "--context", "west", "-n", ""],
+        ] {
+            assert_eq!(complete(words), ["west-a"], "{words:?}");
+        }
+        // A context missing from the kubeconfig falls back to the current context.
+        assert_eq!(
+            complete(&["kubex", "--context", "north", "-n", ""]),
+            ["east-a", "east-b"]
+        );
+        assert_eq!(east.count("/api/v1/namespaces"), 2);
+        assert_eq!(west.count("/api/v1/namespaces"), 4);
+    }
+
+    #[test]
+    fn namespace_value_completer_gives_up_after_the_timeout() {
+        let server = MockServer::start(|_| {
+            thread::sleep(Duration::from_secs(5));
+            namespace_list(&["team-a"])
+        });
+        let _guard = KubeconfigFixture::new()
+            .cluster("slow", server.url())
+            .context("slow", "slow", "admin")
+            .namespace("team-b")
+            .current("slow")
+            .install()
+            .unwrap();
+        let completer = namespace_value_completer_with(
+            CompleterOptions::new().timeout(Duration::from_millis(200)),
+        );
+
+        let start = Instant::now();
+        let candidates = complete_with_words(&completer, &["kubex", "-n", ""], "");
+        let elapsed = start.elapsed();
+        assert!(elapsed < Duration::from_secs(2), "{elapsed:?}");
+        // The namespaces of the kubeconfig are offered instead.
+        assert_eq!(
+            candidates,
+            [(String::from("team-b"), Some(String::from("[current]")))]
+        );
+        assert_eq!(server.count("/api/v1/namespaces"), 1);
+    }
+
+    #[test]
+    fn namespace_completion_cache_serves_namespaces_within_its_ttl() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let up = Arc::new(AtomicBool::new(true));
+        let server = MockServer::start({
+            let up = up.clone();
+            move |_| {
+                if up.load(Ordering::SeqCst) {
+                    namespace_list(&["team-a", "team-b"])
+                } else {
+                    status_response(503)
+                }
+            }
+        });
+        let _guard = KubeconfigFixture::new()
+            .cluster("mock", server.url())
+            .context("mock", "mock", "admin")
+            .current("mock")
+            .install()
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let cache = NamespaceCompletionCache::new(dir.path());
+        let complete = |completer: &ArgValueCompleter| {
+            complete_with_words(completer, &["kubex", "-n", ""], "")
+        };
+        let namespaces = [
+            (String::from("team-a"), None),
+            (String::from("team-b"), None),
+        ];
+
+        let completer = cached_namespace_value_completer(cache.clone());
+        assert_eq!(complete(&completer), namespaces);
+        let cached: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(cache.path("mock")).unwrap()).unwrap();
+        assert_eq!(
+            cached["namespaces"],
+            serde_json::json!(["team-a", "team-b"])
+        );
+        assert_eq!(complete(&completer), namespaces);
+        assert_eq!(server.count("/api/v1/namespaces"), 1);
+
+        // An expired cache is refreshed, but still served if the cluster fails.
+        up.store(false, Ordering::SeqCst);
+        let expired = cached_namespace_value_completer(cache.clone().with_ttl(Duration::ZERO));
+        assert_eq!(complete(&expired), namespaces);
+        assert_eq!(server.count("/api/v1/namespaces"), 2);
+
+        // A corrupt cache is ignored and replaced.
+        up.store(true, Ordering::SeqCst);
+        fs::write(cache.path("mock"), "{").unwrap();
+        assert_eq!(complete(&completer), namespaces);
+        assert_eq!(server.count("/api/v1/namespaces"), 3);
+        assert_eq!(complete(&completer), namespaces);
+        assert_eq!(server.count("/api/v1/namespaces"), 3);
+
+        let options = CompleterOptions::new().cache_dir(dir.path().join("completion"));
+        assert_eq!(
+            complete(&namespace_value_completer_with(options)),
+            namespaces
+        );
+        let path = NamespaceCompletionCache::new(dir.path().join("completion").join("namespaces"))
+            .path("mock");
+        assert!(path.is_file(), "{}", path.display());
+    }
+
+    #[test]
+    fn resource_value_completer_discovers_and_caches_resources() {
+        let server = MockServer::start(discovery_response);
+        let _guard = KubeconfigFixture::new()
+            .cluster("mock", server.url())
+            .context("mock", "mock", "admin")
+            .current("mock")
+            .install()
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("resources.json");
+        let complete = |completer: &ArgValueCompleter, input: &str| {
+            complete_with_words(completer, &["kubex", "get", input], input)
+        };
+
+        let completer = resource_value_completer(Some(cache_path.clone()));
+        assert_eq!(
+            complete(&completer, "dep"),
+            [
+                (
+                    String::from("deployments"),
+                    Some(String::from("deploy (apps, namespaced)"))
+                ),
+                (
+                    String::from("deploy"),
+                    Some(String::from("deployments (apps, namespaced)"))
+                ),
+            ]
+        );
+        let requests = server.requests().len();
+        assert!(server.count("/apis/apps/v1") > 0);
+        let cached = load_discovery_cache(&cache_path).unwrap().into_resources();
+        assert!(cached.iter().any(|resource| resource.name == "deployments"));
+        assert_eq!(values(complete(&completer, "po")), ["pods", "po"]);
+        assert_eq!(server.requests().len(), requests);
+
+        // A fresh cache is used as is, and an expired one when discovery fails.
+        let widgets = [api_resource("example.com", "v1", "widgets", "Widget")];
+        save_discovery_cache(&cache_path, &widgets, None).unwrap();
+        assert_eq!(values(complete(&completer, "wid")), ["widgets"]);
+        assert_eq!(server.requests().len(), requests);
+        let down = MockServer::start(|_| status_response(500));
+        let file = KubeconfigFixture::new()
+            .cluster("down", down.url())
+            .context("down", "down", "admin")
+            .current("down")
+            .write()
+            .unwrap();
+        let expired = resource_value_completer_with(
+            Some(cache_path.clone()),
+            CompleterOptions::new().cache_ttl(Duration::ZERO),
+        );
+        let path = file.path().to_str().unwrap();
+        let candidates = complete_with_words(
+            &expired,
+            &["kubex", "--kubeconfig", path, "get", "wid"],
+            "wid",
+        );
+        assert_eq!(values(candidates), ["widgets"]);
+        assert!(!down.requests().is_empty());
+
+        // Offline, the built-in resources are offered without a cache.
+        let offline =
+            resource_value_completer_with(None, CompleterOptions::new().offline_only(true));
+        assert_eq!(
+            values(complete(&offline, "deployments.")),
+            ["deployments.apps"]
+        );
+        assert_eq!(server.requests().len(), requests);
+    }
+
+    #[test]
+    fn pod_value_completer_lists_pods_with_status() {
+        let server = MockServer::start(|request| match request.path.as_str() {
+            "/api/v1/namespaces/team-a/pods" => table(
+                &["Name", "Ready", "Status", "Restarts"],
+                &[
+                    &["web-0", "2/2", "Running", "0"],
+                    &["web-1", "0/2", "Pending", "0"],
+                ],
+            ),
+            "/api/v1/namespaces/team-b/pods" => table(
+                &["Name", "Ready", "Status"],
+                &[&["batch-0", "0/1", "Completed"]],
+            ),
+            _ => status_response(404),
+        });
+        let _guard = KubeconfigFixture::new()
+            .cluster("mock", server.url())
+            .context("mock", "mock", "admin")
+            .namespace("team-a")
+            .current("mock")
+            .install()
+            .unwrap();
+        let completer = pod_value_completer();
+
+        assert_eq!(
+            complete_with_words(&completer, &["kubex", "logs", "we"], "we"),
+            [
+                (String::from("web-0"), Some(String::from("Running 2/2"))),
+                (String::from("web-1"), Some(String::from("Pending 0/2"))),
+            ]
+        );
+        let request = &server.requests()[0];
+        let accept = request.header("accept");
+        assert!(
+            accept.iter().any(|value| value.contains("as=Table")),
+            "{accept:?}"
+        );
+        assert_eq!(request.query_param("limit").as_deref(), Some("200"));
+
+        assert_eq!(
+            complete_with_words(&completer, &["kubex", "logs", "-n", "team-b", ""], ""),
+            [(String::from("batch-0"), Some(String::from("Completed 0/1")))]
+        );
+        assert_eq!(server.count("/api/v1/namespaces/team-b/pods"), 1);
+    }
+
+    #[test]
+    fn resource_name_completer_lists_objects_of_any_resource() {
+        let server = MockServer::start(|request| match request.path.as_str() {
+            "/apis/apps/v1/namespaces/team-a/deployments" => namespace_list(&["web", "worker"]),
+            "/apis/apps/v1/namespaces/team-b/deployments" => namespace_list(&["batch"]),
+            "/apis/example.com/v1/namespaces/team-a/widgets" => namespace_list(&["gear"]),
+            "/apis/example.com/v1/gadgets" => namespace_list(&["probe"]),
+            _ => status_response(404),
+        });
+        let _guard = KubeconfigFixture::new()
+            .cluster("mock", server.url())
+            .context("mock", "mock", "admin")
+            .namespace("team-a")
+            .current("mock")
+            .install()
+            .unwrap();
+        let complete = |completer: &ArgValueCompleter, words: &[&str]| {
+            let input = words.last().unwrap();
+            values(complete_with_words(completer, words, input))
+        };
+
+        let deployments = resource_name_completer("deploy");
+        assert_eq!(
+            complete(&deployments, &["kubex", "get", "w"]),
+            ["web", "worker"]
+        );
+        let request = &server.requests()[0];
+        assert_eq!(request.path, "/apis/apps/v1/namespaces/team-a/deployments");
+        assert_eq!(request.query_param("limit").as_deref(), Some("200"));
+        assert_eq!(
+            complete(&deployments, &["kubex", "-n", "team-b", "get", ""]),
+            ["batch"]
+        );
+
+        // Resources unknown to kubex complete once resolved, and cluster-scoped ones
+        // ignore the namespace.
+        let widgets = api_resource("example.com", "v1", "widgets", "Widget");
+        let widgets = api_resource_name_completer(widgets);
+        assert_eq!(complete(&widgets, &["kubex", "get", ""]), ["gear"]);
+        let gadgets = APIResource {
+            namespaced: false,
+            ..api_resource("example.com", "v1", "gadgets", "Gadget")
+        };
+        let gadgets = api_resource_name_completer(gadgets);
+        assert_eq!(
+            complete(&gadgets, &["kubex", "-n", "team-b", "get", ""]),
+            ["probe"]
+        );
+
+        let refs = resource_ref_value_completer();
+        assert_eq!(
+            complete(&refs, &["kubex", "logs", "deployment/w"]),
+            ["deployment/web", "deployment/worker"]
+        );
+    }
+
+    #[test]
+    fn node_value_completer_lists_ready_workers_first() {
+        let server = MockServer::start(|request| match request.path.as_str() {
+            "/api/v1/nodes" => table(
+                &["Name", "Status", "Roles", "Age"],
+                &[
+                    &["cp-0", "Ready", "control-plane", "10d"],
+                    &["node-b", "NotReady", "<none>", "3d"],
+                    &["node-a", "Ready,SchedulingDisabled", "<none>", "3d"],
+                    &["node-c", "Ready", "worker,gpu", "1d"],
+                ],
+            ),
+            _ => status_response(404),
+        });
+        let _guard = KubeconfigFixture::new()
+            .cluster("mock", server.url())
+            .context("mock", "mock", "admin")
+            .namespace("team-a")
+            .current("mock")
+            .install()
+            .unwrap();
+        let completer = node_value_completer();
+        let help = |value: &str, help: &str| (String::from(value), Some(String::from(help)));
+
+        assert_eq!(
+            complete_with_words(&completer, &["kubex", "-n", "team-b", "drain", ""], ""),
+            [
+                help("node-a", "worker, Ready,SchedulingDisabled"),
+                help("node-c", "worker,gpu, Ready"),
+                help("cp-0", "control-plane, Ready"),
+                help("node-b", "worker, NotReady"),
+            ]
+        );
+        assert_eq!(
+            values(complete_with_words(
+                &completer,
+                &["kubex", "drain", "node"],
+                "node"
+            )),
+            ["node-a", "node-c", "node-b"]
+        );
+        assert_eq!(server.count("/api/v1/nodes"), 2);
+    }
+
+    #[test]
+    fn kubeconfig_arg_files_steer_the_completers() {
+        let matches = Command::new("kubex")
+            .arg(kubeconfig_arg())
+            .try_get_matches_from(["kubex", "--kubeconfig", "a.yaml", "--kubeconfig=b.yaml"])
+            .unwrap();
+        assert_eq!(
+            kubeconfig_from_matches(&matches),
+            [PathBuf::from("a.yaml"), PathBuf::from("b.yaml")]
+        );
+        let matches = Command::new("kubex")
+            .arg(kubeconfig_arg())
+            .try_get_matches_from(["kubex"])
+            .unwrap();
+        assert!(kubeconfig_from_matches(&matches).is_empty());
+
+        let _guard = KubeconfigFixture::new()
+            .context("home", "home", "admin")
+            .current("home")
+            .install()
+            .unwrap();
+        let east = KubeconfigFixture::new()
+            .context("east", "east", "admin")
+            .current("east")
+            .write()
+            .unwrap();
+        let west = KubeconfigFixture::new()
+            .context("west", "west", "admin")
+            .current("west")
+            .write()
+            .unwrap();
+        let (east, west) = (east.path().to_str().unwrap(), west.path().to_str().unwrap());
+        let completer = context_value_completer();
+        let complete = |words: &[&str]| complete_with_words(&completer, words, "");
+
+        // The files are merged like `KUBECONFIG` does, the first one setting the current context.
+        let west_flag = format!("--kubeconfig={west}");
+        assert_eq!(
+            complete(&["kubex", "--kubeconfig", east, &west_flag, "--context", ""]),
+            [
+                (String::from("east"), Some(String::from("[current]"))),
+                (String::from("west"), None),
+            ]
+        );
+        assert_eq!(
+            complete(&["kubex", "--kubeconfig", west, "--context", ""]),
+            [(String::from("west"), Some(String::from("[current]")))]
+        );
+        assert_eq!(
+            complete(&["kubex", "--context", ""]),
+            [(String::from("home"), Some(String::from("[current]")))]
+        );
+    }
+
+    #[test]
+    fn namespace_arg_accepts_the_kubectl_forms() {
+        let cmd = || Command::new("kubex").arg(namespace_arg());
+        for args in [
+            &["kubex", "-n", "team-a"][..],
+            &["kubex", "-nteam-a"],
+            &["kubex", "-n=team-a"],
+            &["kubex", "--namespace", "team-a"],
+            &["kubex", "--namespace=team-a"],
+        ] {
+            let matches = cmd().try_get_matches_from(args).unwrap();
+            assert_eq!(
+                matches.get_one::<String>("namespace").map(String::as_str),
+                Some("team-a"),
+                "{args:?}"
+            );
+        }
+        let matches = cmd().try_get_matches_from(["kubex"]).unwrap();
+        assert_eq!(matches.get_one::<String>("namespace"), None);
+        let help = cmd().render_help().to_string();
+        assert!(
+            help.contains("-n, --namespace <NAMESPACE>  Namespace to use, defaults to the namespace of the context"),
+            "{help}"
+        );
+
+        let _guard = KubeconfigFixture::new()
+            .cluster("down", "https://127.0.0.1:1")
+            .context("down", "down", "admin")
+            .namespace("team-b")
+            .current("down")
+            .install()
+            .unwrap();
+        let arg = namespace_arg_with(CompleterOptions::new().offline_only(true));
+        let completer = arg.get::<ArgValueCompleter>().unwrap();
+        assert_eq!(
+            complete_with_words(completer, &["kubex", "-n", ""], ""),
+            [(String::from("team-b"), Some(String::from("[current]")))]
+        );
+    }
+
+    #[test]
+    fn all_namespaces_arg_selects_namespaces_with_the_namespace_arg() {
+        let cmd = || {
+            Command::new("kubex")
+                .arg(namespace_arg())
+                .arg(all_namespaces_arg())
+        };
+        let selection = |args: &[&str]| {
+            let matches = cmd().try_get_matches_from(args).unwrap();
+            namespace_selection_from_matches(&matches).unwrap()
+        };
+
+        assert_eq!(selection(&["kubex", "-A"]), NamespaceSelection::All);
+        assert_eq!(
+            selection(&["kubex", "--all-namespaces"]),
+            NamespaceSelection::All
+        );
+        assert_eq!(
+            selection(&["kubex", "-n", "team-a"]),
+            NamespaceSelection::Named(String::from("team-a"))
+        );
+        assert_eq!(selection(&["kubex"]), NamespaceSelection::Default);
+        let err = cmd()
+            .try_get_matches_from(["kubex", "-n", "team-a", "-A"])
+            .unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::ArgumentConflict);
+
+        // A command without the flag always selects the given or default namespace.
+        let matches = Command::new("kubex")
+            .arg(namespace_arg())
+            .try_get_matches_from(["kubex", "-n", "team-a"])
+            .unwrap();
+        assert_eq!(
+            namespace_selection_from_matches(&matches).unwrap(),
+            NamespaceSelection::Named(String::from("team-a"))
+        );
+        let matches = Command::new("kubex")
+            .try_get_matches_from(["kubex"])
+            .unwrap();
+        assert_eq!(
+            namespace_selection_from_matches(&matches).unwrap(),
+            NamespaceSelection::Default
+        );
+    }
+
+    #[test]
+    fn output_arg_parses_formats_and_expressions() {
+        let cmd = || Command::new("kubex").arg(output_arg());
+        let format = |args: &[&str]| {
+            let matches = cmd().try_get_matches_from(args).unwrap();
+            output_format_from_matches(&matches)
+        };
+
+        assert_eq!(format(&["kubex", "-o", "json"]), Some(OutputFormat::Json));
+        assert_eq!(
+            format(&["kubex", "--output=yaml"]),
+            Some(OutputFormat::Yaml)
+        );
+        assert_eq!(format(&["kubex", "-o", "name"]), Some(OutputFormat::Name));
+        assert_eq!(format(&["kubex", "-owide"]), Some(OutputFormat::Wide));
+        assert_eq!(format(&["kubex"]), None);
+        assert_eq!(
+            format(&["kubex", "-o", "jsonpath={.metadata.name}"]),
+            Some(OutputFormat::Custom(String::from(
+                "jsonpath={.metadata.name}"
+            )))
+        );
+        assert_eq!(
+            format(&["kubex", "-o", "custom-columns=NAME:.metadata.name"]),
+            Some(OutputFormat::Custom(String::from(
+                "custom-columns=NAME:.metadata.name"
+            )))
+        );
+
+        let err = cmd()
+            .try_get_matches_from(["kubex", "-o", "xml"])
+            .unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::InvalidValue);
+        let err = err.to_string();
+        assert!(
+            err.contains("[possible values: json, yaml, name, wide]"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn list_params_are_built_from_the_selector_and_limit_args() {
+        let cmd = || {
+            Command::new("kubex")
+                .arg(selector_arg())
+                .arg(field_selector_arg())
+                .arg(limit_arg())
+        };
+        let matches = cmd()
+            .try_get_matches_from([
+                "kubex",
+                "-l",
+                "app=web,tier in (frontend,backend)",
+                "--field-selector",
+                "status.phase=Running",
+                "--limit",
+                "50",
+            ])
+            .unwrap();
+        // The selectors are validated and normalized, sorting the values of `in`.
+        let params = list_params_from_matches(&matches);
+        assert_eq!(
+            params.label_selector.as_deref(),
+            Some("app=web,tier in (backend,frontend)")
+        );
+        assert_eq!(
+            params.field_selector.as_deref(),
+            Some("status.phase=Running")
+        );
+        assert_eq!(params.limit, Some(50));
+        let params = watch_params_from_matches(&matches);
+        assert_eq!(
+            params.label_selector.as_deref(),
+            Some("app=web,tier in (backend,frontend)")
+        );
+        assert_eq!(
+            params.field_selector.as_deref(),
+            Some("status.phase=Running")
+        );
+
+        for args in [
+            &["kubex", "-l", "app in web"][..],
+            &["kubex", "--field-selector", "status.phase"],
+            &["kubex", "--limit", "0"],
+        ] {
+            let err = cmd().try_get_matches_from(args).unwrap_err();
+            assert_eq!(
+                err.kind(),
+                clap::error::ErrorKind::ValueValidation,
+                "{args:?}"
+            );
+        }
+
+        // The arguments may be absent from the command.
+        let matches = cmd().try_get_matches_from(["kubex"]).unwrap();
+        let params = list_params_from_matches(&matches);
+        assert_eq!(
+            (params.label_selector, params.field_selector, params.limit),
+            (None, None, None)
+        );
+        let matches = Command::new("kubex")
+            .try_get_matches_from(["kubex"])
+            .unwrap();
+        assert_eq!(list_params_from_matches(&matches).limit, None);
+        assert_eq!(watch_params_from_matches(&matches).label_selector, None);
+    }
+
+    #[tokio::test]
+    async fn kube_args_flatten_into_a_derived_parser() {
+        use clap::{CommandFactory, Parser};
+
+        #[derive(Debug, Parser)]
+        struct Cli {
+            #[command(flatten)]
+            kube: KubeArgs,
+        }
+
+        let file = KubeconfigFixture::new()
+            .cluster("dev", "https://dev.example.com")
+            .cluster("prod", "https://prod.example.com")
+            .context("dev", "dev", "admin")
+            .namespace("team-a")
+            .context("prod", "prod", "admin")
+            .current("dev")
+            .write()
+            .unwrap();
+        let path = file.path().to_str().unwrap();
+        let args = [
+            "kubex",
+            "--kubeconfig",
+            path,
+            "--context",
+            "prod",
+            "-n",
+            "team-b",
+            "--as",
+            "jane",
+            "--as-group",
+            "devs,ops",
+            "--as-uid",
+            "42",
+        ];
+        let cli = Cli::try_parse_from(args).unwrap();
+
+        let identity = cli.kube.identity().unwrap();
+        assert_eq!(identity.context(), "prod");
+        assert_eq!(identity.namespace(), "team-b");
+        assert_eq!(identity.server_url(), Some("https://prod.example.com"));
+        assert_eq!(
+            cli.kube.namespace_selection().unwrap(),
+            NamespaceSelection::Named(String::from("team-b"))
+        );
+        let impersonation = Impersonation {
+            user: Some(String::from("jane")),
+            groups: vec![String::from("devs"), String::from("ops")],
+            uid: Some(String::from("42")),
+        };
+        assert_eq!(cli.kube.impersonation(), impersonation);
+        let (config, identity) = cli.kube.client_options().config().await.unwrap();
+        assert_eq!(identity.context(), "prod");
+        assert_eq!(config.default_namespace, "team-b");
+        assert_eq!(config.auth_info.impersonate.as_deref(), Some("jane"));
+
+        // The ids match the builders, so the `*_from_matches` functions read them too.
+        let matches = Cli::command().try_get_matches_from(args).unwrap();
+        assert_eq!(kubeconfig_from_matches(&matches), [PathBuf::from(path)]);
+        assert_eq!(context_from_matches(&matches).as_deref(), Some("prod"));
+        assert_eq!(impersonation_from_matches(&matches), impersonation);
+        assert_eq!(
+            namespace_selection_from_matches(&matches).unwrap(),
+            NamespaceSelection::Named(String::from("team-b"))
+        );
+
+        let cli = Cli::try_parse_from(["kubex", "--kubeconfig", path, "-A"]).unwrap();
+        assert_eq!(
+            cli.kube.namespace_selection().unwrap(),
+            NamespaceSelection::All
+        );
+        let identity = cli.kube.identity().unwrap();
+        assert_eq!(
+            (identity.context(), identity.namespace()),
+            ("dev", "team-a")
+        );
+        assert!(cli.kube.impersonation().is_empty());
+        for (args, kind) in [
+            (
+                &["kubex", "-n", "team-b", "-A"][..],
+                clap::error::ErrorKind::ArgumentConflict,
+            ),
+            (
+                &["kubex", "--as-uid", "42"],
+                clap::error::ErrorKind::MissingRequiredArgument,
+            ),
+            (
+                &["kubex", "-n", "Team_B"],
+                clap::error::ErrorKind::ValueValidation,
+            ),
+        ] {
+            let err = Cli::try_parse_from(args).unwrap_err();
+            assert_eq!(err.kind(), kind, "{args:?}");
+        }
+    }
+
+    /// Completes the namespaces of a mock cluster, from whichever runtime the caller is on.
+    fn assert_completes_namespaces() {
+        let server = MockServer::start(|_| namespace_list(&["team-a", "team-b"]));
+        let _guard = KubeconfigFixture::new()
+            .cluster("mock", server.url())
+            .context("mock", "mock", "admin")
+            .current("mock")
+            .install()
+            .unwrap();
+        let completer = namespace_value_completer();
+        let candidates = complete_with_words(&completer, &["kubex", "-n", "team-"], "team-");
+        assert_eq!(values(candidates), ["team-a", "team-b"]);
+        assert_eq!(server.count("/api/v1/namespaces"), 1);
+    }
+
+    #[test]
+    fn completers_run_outside_a_runtime() {
+        assert_completes_namespaces();
+        // The completion runtime is kept for later completions.
+        assert_completes_namespaces();
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn completers_run_on_a_current_thread_runtime() {
+        assert_completes_namespaces();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn completers_run_on_a_multi_thread_runtime() {
+        assert_completes_namespaces();
+    }
+
+    #[test]
+    fn match_modes_filter_and_order_candidates() {
+        let names = ["prod-payments", "api-gateway", "payments", "staging"];
+        let filter = |mode: MatchMode, input: &str| mode.filter(names, input, |name| name);
+
+        assert_eq!(
+            filter(MatchMode::Prefix, "p"),
+            ["prod-payments", "payments"]
+        );
+        assert_eq!(filter(MatchMode::Prefix, ""), names);
+        assert_eq!(
+            filter(MatchMode::Substring, "pay"),
+            ["prod-payments", "payments"]
+        );
+        assert_eq!(filter(MatchMode::Substring, "PAY"), Vec::<&str>::new());
+        // Consecutive characters and word starts score higher than scattered ones.
+        assert_eq!(
+            filter(MatchMode::Fuzzy, "pay"),
+            ["payments", "prod-payments", "api-gateway"]
+        );
+        assert_eq!(filter(MatchMode::Fuzzy, "prdpay"), ["prod-payments"]);
+        assert_eq!(filter(MatchMode::Fuzzy, "STG"), ["staging"]);
+        assert_eq!(filter(MatchMode::Fuzzy, "ayp"), Vec::<&str>::new());
+        assert!(
+            MatchMode::Fuzzy.score("payments", "pay")
+                > MatchMode::Fuzzy.score("prod-payments", "pay")
+        );
+        assert_eq!(MatchMode::Fuzzy.score("staging", ""), Some(0));
+
+        let _guard = KubeconfigFixture::new()
+            .context(
+                "arn:aws:eks:eu-west-1:123456789012:cluster/prod-payments",
+                "eks",
+                "admin",
+            )
+            .context(
+                "arn:aws:eks:eu-west-1:123456789012:cluster/prod-orders",
+                "eks",
+                "admin",
+            )
+            .context("kind-payments", "kind", "admin")
+            .current("kind-payments")
+            .install()
+            .unwrap();
+        let complete = |mode: MatchMode, input: &str| {
+            let completer = context_value_completer_with(CompleterOptions::new().match_mode(mode));
+            values(describe(completer.complete(OsStr::new(input))))
+        };
+        assert_eq!(
+            complete(MatchMode::Substring, "payments"),
+            [
+                "kind-payments",
+                "arn:aws:eks:eu-west-1:123456789012:cluster/prod-payments",
+            ]
+        );
+        assert_eq!(
+            complete(MatchMode::Fuzzy, "prdord"),
+            ["arn:aws:eks:eu-west-1:123456789012:cluster/prod-orders"]
+        );
+        assert_eq!(complete(MatchMode::Prefix, "kind"), ["kind-payments"]);
+    }
+
+    #[test]
+    fn user_value_completer_describes_the_auth_mechanism() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config");
+        fs::write(
+            &path,
+            r#"apiVersion: v1
+kind: Config
+users:
+  - name: eks
+    user:
+      exec:
+        apiVersion: client.authentication.k8s.io/v1beta1
+        command: /usr/local/bin/aws
+        args: [eks, get-token]
+  - name: gke
+    user:
+      auth-provider:
+        name: gcp
+  - name: token
+    user:
+      token: secret-token
+  - name: token-file
+    user:
+      tokenFile: /var/run/kubex/token
+  - name: basic
+    user:
+      username: jane
+      password: secret-password
+  - name: cert
+    user:
+      client-certificate: /nonexistent/client.crt
+      client-key: /nonexistent/client.key
+  - name: anonymous
+    user: {}
+"#,
+        )
+        .unwrap();
+        let path = path.to_str().unwrap();
+        let completer = user_value_completer();
+        let complete = |input: &str| {
+            complete_with_words(
+                &completer,
+                &["kubex", "--kubeconfig", path, "--user", input],
+                input,
+            )
+        };
+        let help = |value: &str, help: &str| (String::from(value), Some(String::from(help)));
+
+        let candidates = complete("");
+        assert_eq!(
+            candidates,
+            [
+                help("eks", "exec plugin (aws)"),
+                help("gke", "auth provider (gcp)"),
+                help("token", "bearer token"),
+                help("token-file", "bearer token from /var/run/kubex/token"),
+                help("basic", "basic auth as jane"),
+                // A certificate that cannot be read is still offered.
+                help("cert", "client certificate"),
+                help("anonymous", "none"),
+            ]
+        );
+        assert!(!format!("{candidates:?}").contains("secret-"));
+        assert_eq!(
+            complete("tok"),
+            [
+                help("token", "bearer token"),
+                help("token-file", "bearer token from /var/run/kubex/token"),
+            ]
+        );
+    }
+
+    #[test]
+    fn cluster_value_completer_shows_the_server_of_each_cluster() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config");
+        fs::write(
+            &path,
+            r#"apiVersion: v1
+kind: Config
+clusters:
+  - name: prod
+    cluster:
+      server: https://prod.example.com
+  - name: eks
+    cluster:
+      server: https://0123456789abcdef0123456789abcdef.gr7.eu-west-1.eks.amazonaws.com
+  - name: kind
+    cluster:
+      server: https://127.0.0.1:6443
+      insecure-skip-tls-verify: true
+  - name: pending
+    cluster: {}
+"#,
+        )
+        .unwrap();
+        let path = path.to_str().unwrap();
+        let completer = cluster_value_completer();
+        let complete = |input: &str| {
+            complete_with_words(
+                &completer,
+                &["kubex", "--kubeconfig", path, "--cluster", input],
+                input,
+            )
+        };
+        let help = |value: &str, help: &str| (String::from(value), Some(String::from(help)));
+
+        assert_eq!(
+            complete(""),
+            [
+                help("prod", "https://prod.example.com"),
+                help("eks", "https://0123456789abcdef0123456789abcde…"),
+                help("kind", "https://127.0.0.1:6443 (insecure)"),
+                help("pending", "no server"),
+            ]
+        );
+        assert_eq!(
+            complete("k"),
+            [help("kind", "https://127.0.0.1:6443 (insecure)")]
+        );
+        assert_eq!(complete("staging"), []);
+    }
+
+    #[test]
+    fn namespace_value_completer_falls_back_to_kubeconfig_namespaces() {
+        let server = MockServer::start(|_| status_response(503));
+        let _guard = KubeconfigFixture::new()
+            .cluster("down", server.url())
+            .context("down", "down", "admin")
+            .namespace("team-b")
+            .context("ops", "down", "admin")
+            .namespace("monitoring")
+            .context("dev", "down", "admin")
+            .namespace("team-b")
+            .context("bare", "down", "admin")
+            .current("down")
+            .install()
+            .unwrap();
+        let expected = [
+            (String::from("team-b"), Some(String::from("[current]"))),
+            (
+                String::from("monitoring"),
+                Some(String::from("from kubeconfig")),
+            ),
+        ];
+
+        let completer = namespace_value_completer();
+        assert_eq!(
+            complete_with_words(&completer, &["kubex", "-n", ""], ""),
+            expected
+        );
+        assert_eq!(server.count("/api/v1/namespaces"), 1);
+        assert_eq!(
+            complete_with_words(&completer, &["kubex", "-n", "mon"], "mon"),
+            [(
+                String::from("monitoring"),
+                Some(String::from("from kubeconfig"))
+            )]
+        );
+
+        // Offline, the fallback is offered without contacting the cluster.
+        let offline = namespace_value_completer_with(CompleterOptions::new().offline_only(true));
+        assert_eq!(
+            complete_with_words(&offline, &["kubex", "-n", ""], ""),
+            expected
+        );
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn namespace_value_completer_puts_the_context_namespace_first() {
+        let server =
+            MockServer::start(|_| namespace_list(&["team-b", "kube-system", "default", "team-a"]));
+        let _guard = KubeconfigFixture::new()
+            .cluster("mock", server.url())
+            .context("mock", "mock", "admin")
+            .namespace("team-b")
+            .context("bare", "mock", "admin")
+            .current("mock")
+            .install()
+            .unwrap();
+        let completer = namespace_value_completer();
+        let complete = |words: &[&str]| {
+            let input = words.last().unwrap();
+            complete_with_words(&completer, words, input)
+        };
+        let namespace = |name: &str| (String::from(name), None);
+        let current = |name: &str| (String::from(name), Some(String::from("[current]")));
+
+        assert_eq!(
+            complete(&["kubex", "-n", ""]),
+            [
+                current("team-b"),
+                namespace("default"),
+                namespace("kube-system"),
+                namespace("team-a"),
+            ]
+        );
+        // A context without a namespace defaults to "default".
+        assert_eq!(
+            complete(&["kubex", "--context", "bare", "-n", ""]),
+            [
+                current("default"),
+                namespace("kube-system"),
+                namespace("team-a"),
+                namespace("team-b"),
+            ]
+        );
+        // The current namespace is only offered if it matches.
+        assert_eq!(
+            complete(&["kubex", "-n", "team"]),
+            [current("team-b"), namespace("team-a")]
+        );
+        assert_eq!(
+            complete(&["kubex", "-n", "kube"]),
+            [namespace("kube-system")]
+        );
+    }
+
+    #[test]
+    fn completer_options_are_honored_by_the_completers() {
+        let server = MockServer::start(|request| match request.path.as_str() {
+            "/api/v1/namespaces" => namespace_list(&["team-a", "team-b", "ops-team"]),
+            "/api/v1/namespaces/team-a/pods" => table(&["Name"], &[&["web-0"]]),
+            _ => status_response(404),
+        });
+        let _guard = KubeconfigFixture::new()
+            .cluster("mock", server.url())
+            .context("mock", "mock", "admin")
+            .namespace("team-a")
+            .current("mock")
+            .install()
+            .unwrap();
+        assert_eq!(default_completer_options(), CompleterOptions::default());
+        let words = ["kubex", "-n", "team"];
+
+        let options = CompleterOptions::new()
+            .limit(2)
+            .match_mode(MatchMode::Substring);
+        let namespaces = namespace_value_completer_with(options.clone());
+        assert_eq!(
+            values(complete_with_words(&namespaces, &words, "team")),
+            ["team-a", "ops-team", "team-b"]
+        );
+        let pods = pod_value_completer_with(options);
+        assert_eq!(
+            values(complete_with_words(&pods, &["kubex", "logs", "0"], "0")),
+            ["web-0"]
+        );
+        let limits: Vec<_> = server
+            .requests()
+            .iter()
+            .map(|request| request.query_param("limit"))
+            .collect();
+        assert_eq!(limits, [Some(String::from("2")), Some(String::from("2"))]);
+        let default_limits =
+            [namespace_value_completer(), pod_value_completer()].map(|completer| {
+                complete_with_words(&completer, &["kubex", "logs", ""], "");
+                server.requests().last().unwrap().query_param("limit")
+            });
+        assert_eq!(
+            default_limits,
+            [Some(String::from("500")), Some(String::from("200"))]
+        );
+
+        // Offline, no completer contacts the cluster.
+        let requests = server.requests().len();
+        let offline = CompleterOptions::new().offline_only(true);
+        for completer in [
+            namespace_value_completer_with(offline.clone()),
+            pod_value_completer_with(offline.clone()),
+            node_value_completer_with(offline.clone()),
+            serviceaccount_value_completer_with(offline.clone()),
+            resource_name_completer_with("deploy", offline.clone()),
+            container_value_completer_with("pod", offline.clone()),
+            crd_value_completer_with(offline.clone()),
+        ] {
+            complete_with_words(&completer, &["kubex", "--pod", "web-0", ""], "");
+        }
+        assert_eq!(server.requests().len(), requests);
+
+        // Cached namespaces are served within the TTL, and refreshed after it.
+        let dir = tempfile::tempdir().unwrap();
+        let cached = CompleterOptions::new().cache_dir(dir.path());
+        let complete = |options: &CompleterOptions| {
+            let completer = namespace_value_completer_with(options.clone());
+            values(complete_with_words(&completer, &words, "team"))
+        };
+        assert_eq!(complete(&cached), ["team-a", "team-b"]);
+        assert_eq!(complete(&cached), ["team-a", "team-b"]);
+        assert_eq!(server.requests().len(), requests + 1);
+        assert_eq!(
+            complete(&cached.cache_ttl(Duration::ZERO)),
+            ["team-a", "team-b"]
+        );
+        assert_eq!(server.requests().len(), requests + 2);
+    }
+
+    #[test]
+    fn crd_value_completer_matches_names_plurals_and_groups() {
+        fn crd(
+            plural: &str,
+            group: &str,
+            scope: &str,
+            versions: &[(&str, bool)],
+        ) -> serde_json::Value {
+            let versions: Vec<serde_json::Value> = versions
+                .iter()
+                .map(|(name, served)| {
+                    serde_json::json!({ "name": name, "served": served, "storage": served })
+                })
+                .collect();
+            serde_json::json!({
+                "apiVersion": "apiextensions.k8s.io/v1",
+                "kind": "CustomResourceDefinition",
+                "metadata": { "name": format!("{plural}.{group}") },
+                "spec": {
+                    "group": group,
+                    "names": { "plural": plural, "kind": "Kind" },
+                    "scope": scope,
+                    "versions": versions,
+                },
+            })
+        }
+        let server = MockServer::start(|request| match request.path.as_str() {
+            "/apis/apiextensions.k8s.io/v1/customresourcedefinitions" => {
+                let body = serde_json::json!({
+                    "apiVersion": "apiextensions.k8s.io/v1",
+                    "kind": "CustomResourceDefinitionList",
+                    "metadata": {},
+                    "items": [
+                        crd("certificates", "cert-manager.io", "Namespaced", &[("v1", true)]),
+                        crd("clusterissuers", "cert-manager.io", "Cluster", &[("v1alpha1", false), ("v1", true)]),
+                        crd("widgets", "example.com", "Namespaced", &[("v1", true), ("v2", true)]),
+                    ],
+                });
+                (200, body.to_string())
+            }
+            _ => status_response(404),
+        });
+        let _guard = KubeconfigFixture::new()
+            .cluster("mock", server.url())
+            .context("mock", "mock", "admin")
+            .current("mock")
+            .install()
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let completer = crd_value_completer_with(CompleterOptions::new().cache_dir(dir.path()));
+        let complete =
+            |input: &str| complete_with_words(&completer, &["kubex", "explain", input], input);
+        let help = |value: &str, help: &str| (String::from(value), Some(String::from(help)));
+
+        assert_eq!(
+            complete("cert-manager"),
+            [
+                help("certificates.cert-manager.io", "v1 (namespaced)"),
+                help("clusterissuers.cert-manager.io", "v1 (cluster-scoped)"),
+            ]
+        );
+        assert_eq!(
+            complete("wid"),
+            [help("widgets.example.com", "v1, v2 (namespaced)")]
+        );
+        assert_eq!(
+            complete("clusterissuers.c"),
+            [help(
+                "clusterissuers.cert-manager.io",
+                "v1 (cluster-scoped)"
+            )]
+        );
+        assert_eq!(complete("issuers"), []);
+        let request = &server.requests()[0];
+        assert_eq!(request.query_param("limit").as_deref(), Some("200"));
+
+        // The CRDs are listed once, then served from the cache.
+        assert_eq!(
+            server.count("/apis/apiextensions.k8s.io/v1/customresourcedefinitions"),
+            1
+        );
+        let cache = dir
+            .path()
+            .join("crds")
+            .join(discovery_cache_file_name("mock"));
+        assert!(cache.is_file(), "{}", cache.display());
+    }
+
+    #[test]
+    fn serviceaccount_value_completer_shows_the_age() {
+        let server = MockServer::start(|request| match request.path.as_str() {
+            "/api/v1/namespaces/team-a/serviceaccounts" => table(
+                &["Name", "Secrets", "Age"],
+                &[&["default", "0", "30d"], &["deployer", "0", "3d"]],
+            ),
+            "/api/v1/namespaces/team-b/serviceaccounts" => table(&["Name"], &[&["builder"]]),
+            _ => status_response(404),
+        });
+        let _guard = KubeconfigFixture::new()
+            .cluster("mock", server.url())
+            .context("mock", "mock", "admin")
+            .namespace("team-a")
+            .current("mock")
+            .install()
+            .unwrap();
+        let completer = serviceaccount_value_completer();
+
+        assert_eq!(
+            complete_with_words(&completer, &["kubex", "--serviceaccount", "de"], "de"),
+            [
+                (String::from("default"), Some(String::from("age 30d"))),
+                (String::from("deployer"), Some(String::from("age 3d"))),
+            ]
+        );
+        // Without an age column, no help is given.
+        assert_eq!(
+            complete_with_words(&completer, &["kubex", "-n", "team-b", ""], ""),
+            [(String::from("builder"), None)]
+        );
+        let accept = server.requests()[0].header("accept").join(",");
+        assert!(accept.contains("as=Table"), "{accept}");
+    }
+
+    #[test]
+    fn context_completer_options_leave_out_contexts() {
+        let _guard = KubeconfigFixture::new()
+            .context("prod", "prod-cluster", "admin")
+            .context("ci-1234", "ci-cluster", "ci")
+            .context("ci-5678", "ci-cluster", "ci")
+            .context("prod-admin", "prod-cluster", "admin")
+            .context("staging", "staging-cluster", "dev")
+            .current("ci-1234")
+            .install()
+            .unwrap();
+        let complete = |options: ContextCompleterOptions| {
+            let completer = context_value_completer_with(options);
+            describe(completer.complete(OsStr::new("")))
+        };
+        let context = |name: &str| (String::from(name), None);
+
+        // The excluded current context is left out, so nothing is marked current.
+        assert_eq!(
+            complete(ContextCompleterOptions::new().exclude(["ci-*", "*-admin"])),
+            [context("prod"), context("staging")]
+        );
+        assert_eq!(
+            complete(ContextCompleterOptions::new().filter(|context| {
+                context
+                    .context
+                    .as_ref()
+                    .is_some_and(|c| c.user.as_deref() == Some("admin"))
+            })),
+            [context("prod"), context("prod-admin")]
+        );
+        // Both apply together.
+        assert_eq!(
+            complete(
+                ContextCompleterOptions::new()
+                    .exclude(["prod-*"])
+                    .filter(|context| context.name != "staging")
+            ),
+            [
+                (String::from("ci-1234"), Some(String::from("[current]"))),
+                context("prod"),
+                context("ci-5678"),
+            ]
+        );
+        let options = ContextCompleterOptions::new()
+            .options(CompleterOptions::new().match_mode(MatchMode::Substring))
+            .exclude(["ci-*"]);
+        let completer = context_value_completer_with(options);
+        assert_eq!(
+            values(describe(completer.complete(OsStr::new("admin")))),
+            ["prod-admin"]
+        );
+    }
+
+    #[test]
+    fn completions_subcommand_prints_a_static_script() {
+        let cmd = || {
+            Command::new("kubex")
+                .arg(output_arg())
+                .subcommand(completions_subcommand())
+        };
+        let matches = cmd()
+            .try_get_matches_from(["kubex", "completions", "zsh"])
+            .unwrap();
+        let (name, matches) = matches.subcommand().unwrap();
+        assert_eq!(name, "completions");
+        assert_eq!(matches.get_one::<Shell>("shell"), Some(&Shell::Zsh));
+        for args in [
+            &["kubex", "completions", "tcsh"][..],
+            &["kubex", "completions"],
+        ] {
+            assert!(cmd().try_get_matches_from(args).is_err(), "{args:?}");
+        }
+
+        let mut script = Vec::new();
+        generate_static_completions(Shell::Bash, &mut cmd(), &mut script);
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("complete -F _kubex"), "{script}");
+        assert!(script.contains("completions"), "{script}");
+        // The possible values of the arguments are known without running the binary.
+        assert!(script.contains("json yaml name wide"), "{script}");
+    }
+
+    #[test]
+    fn resource_ref_arg_parses_references() {
+        let cmd = || Command::new("kubex").arg(resource_ref_arg());
+        let matches = cmd()
+            .try_get_matches_from([
+                "kubex",
+                "pods",
+                "deployment/nginx",
+                "certificates.v1.cert-manager.io/web",
+            ])
+            .unwrap();
+        assert_eq!(
+            resource_refs_from_matches(&matches),
+            [
+                ResourceRef::new("pods"),
+                ResourceRef::new("deployment").with_name("nginx"),
+                ResourceRef {
+                    group: Some(String::from("cert-manager.io")),
+                    version: Some(String::from("v1")),
+                    ..ResourceRef::new("certificates").with_name("web")
+                },
+            ]
+        );
+        let matches = cmd().try_get_matches_from(["kubex"]).unwrap();
+        assert!(resource_refs_from_matches(&matches).is_empty());
+
+        for (value, message) in [
+            ("deployment/", "name must not be empty after '/'"),
+            ("deployment//nginx", "double slash is not allowed"),
+            ("pod/a/b", "expected TYPE/NAME with a single slash"),
+            ("/nginx", "resource type must not be empty"),
+            ("deployments..apps", "resource type has an empty segment"),
+        ] {
+            let err = cmd().try_get_matches_from(["kubex", value]).unwrap_err();
+            assert_eq!(
+                err.kind(),
+                clap::error::ErrorKind::ValueValidation,
+                "{value}"
+            );
+            let err = err.to_string();
+            assert!(err.contains(message), "{err}");
+            assert!(err.contains("for '[TYPE[/NAME]]...'"), "{err}");
+        }
+
+        // The parser works on its own, e.g. with derive.
+        let parser = resource_ref_value_parser();
+        let arg = Arg::new("resource");
+        let parsed = parser.parse_ref(&Command::new("kubex"), Some(&arg), OsStr::new("svc/web"));
+        assert_eq!(parsed.unwrap(), ResourceRef::new("svc").with_name("web"));
+        let parsed = parser.parse_ref(&Command::new("kubex"), Some(&arg), OsStr::new("svc/"));
+        assert!(parsed.is_err());
+    }
+
+    #[test]
+    fn impersonation_args_mirror_kubectl() {
+        let cmd = || Command::new("kubex").args(impersonation_args());
+        let impersonation =
+            |args: &[&str]| impersonation_from_matches(&cmd().try_get_matches_from(args).unwrap());
+
+        assert_eq!(
+            impersonation(&[
+                "kubex",
+                "--as",
+                "system:serviceaccount:team-a:deployer",
+                "--as-group",
+                "devs,ops",
+                "--as-group=admins",
+                "--as-uid",
+                "42",
+            ]),
+            Impersonation {
+                user: Some(String::from("system:serviceaccount:team-a:deployer")),
+                groups: vec![
+                    String::from("devs"),
+                    String::from("ops"),
+                    String::from("admins"),
+                ],
+                uid: Some(String::from("42")),
+            }
+        );
+        assert_eq!(
+            impersonation(&["kubex", "--as", "jane"]),
+            Impersonation {
+                user: Some(String::from("jane")),
+                ..Impersonation::default()
+            }
+        );
+        assert!(impersonation(&["kubex"]).is_empty());
+
+        let err = cmd()
+            .try_get_matches_from(["kubex", "--as-uid", "42"])
+            .unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::MissingRequiredArgument);
+        let err = err.to_string();
+        assert!(err.contains("--as <USER>"), "{err}");
+    }
+
+    #[test]
+    fn completion_debug_log_records_failed_steps() {
+        let server = MockServer::start(|_| status_response(503));
+        let _guard = KubeconfigFixture::new()
+            .cluster("debug-cluster", server.url())
+            .context("debug-ctx", "debug-cluster", "admin")
+            .current("debug-ctx")
+            .install()
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("complete.log");
+        let completer = namespace_value_completer();
+        let lines = || {
+            fs::read_to_string(&log)
+                .unwrap_or_default()
+                .lines()
+                .filter(|line| line.contains("debug-ctx"))
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+
+        // SAFETY: Only the completers read the variable, and they tolerate any value.
+        unsafe { env::set_var(COMPLETE_DEBUG_ENV, &log) };
+        complete_with_words(&completer, &["kubex", "-n", ""], "");
+        // SAFETY: See above.
+        unsafe { env::remove_var(COMPLETE_DEBUG_ENV) };
+
+        // Each step is logged with how long it took.
+        let logged = lines();
+        let pid = format!("[pid {}]", process::id());
+        for step in [
+            "build client for debug-ctx took ",
+            "list namespaces of debug-ctx failed after ",
+        ] {
+            assert!(
+                logged
+                    .iter()
+                    .any(|line| line.contains(&format!("{pid} {step}"))),
+                "{logged:?}"
+            );
+        }
+        let log_content = fs::read_to_string(&log).unwrap();
+        assert!(
+            log_content.contains("list namespaces failed: "),
+            "{log_content}"
+        );
+
+        // Nothing is logged once the variable is unset.
+        complete_with_words(&completer, &["kubex", "-n", ""], "");
+        assert_eq!(lines(), logged);
+    }
+
+    #[test]
+    fn namespace_value_completer_pages_up_to_the_limit() {
+        let server = MockServer::start(|request| {
+            let page = |names: &[&str], continue_token: &str| {
+                let (status, body) = namespace_list(names);
+                let mut body: serde_json::Value = serde_json::from_str(&body).unwrap();
+                body["metadata"]["continue"] = continue_token.into();
+                (status, body.to_string())
+            };
+            if request.query_param("fieldSelector").as_deref() == Some("metadata.name=team-z") {
+                return namespace_list(&["team-z"]);
+            }
+            match request.query_param("continue").as_deref() {
+                None => page(&["team-a"], "page-2"),
+                Some("page-2") => page(&["team-b", "team-c"], "page-3"),
+                Some(_) => page(&["team-d"], ""),
+            }
+        });
+        let _guard = KubeconfigFixture::new()
+            .cluster("mock", server.url())
+            .context("mock", "mock", "admin")
+            .namespace("team-a")
+            .current("mock")
+            .install()
+            .unwrap();
+        let complete = |completer: &ArgValueCompleter, input: &str| {
+            values(complete_with_words(
+                completer,
+                &["kubex", "-n", input],
+                input,
+            ))
+        };
+
+        // Pages are followed until the list ends.
+        let completer = namespace_value_completer();
+        assert_eq!(
+            complete(&completer, ""),
+            ["team-a", "team-b", "team-c", "team-d"]
+        );
+        let requests = server.requests();
+        let accept = requests[0].header("accept").join(",");
+        assert!(accept.contains("as=PartialObjectMetadataList"), "{accept}");
+        let pages: Vec<_> = requests
+            .iter()
+            .map(|request| {
+                (
+                    request.query_param("limit"),
+                    request.query_param("continue"),
+                )
+            })
+            .collect();
+        assert_eq!(
+            pages,
+            [
+                (Some(String::from("500")), None),
+                (Some(String::from("499")), Some(String::from("page-2"))),
+                (Some(String::from("497")), Some(String::from("page-3"))),
+            ]
+        );
+
+        // A truncated list is completed with the namespace typed in full.
+        let limited = namespace_value_completer_with(CompleterOptions::new().limit(3));
+        assert_eq!(complete(&limited, ""), ["team-a", "team-b", "team-c"]);
+        assert_eq!(complete(&limited, "team-z"), ["team-z"]);
+        let lookups: Vec<_> = server
+            .requests()
+            .iter()
+            .filter_map(|request| request.query_param("fieldSelector"))
+            .collect();
+        assert_eq!(lookups, ["metadata.name=team-z"]);
+        // Nothing is looked up for an invalid name.
+        assert_eq!(complete(&limited, "Team_"), Vec::<String>::new());
+        assert_eq!(
+            server
+                .requests()
+                .iter()
+                .filter(|r| r.query_param("fieldSelector").is_some())
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn context_arg_with_env_defaults_to_the_variable() {
+        const VAR: &str = "KUBEX_TEST_COMPLETION_CONTEXT";
+        let east = MockServer::start(|_| namespace_list(&["east-a"]));
+        let west = MockServer::start(|_| namespace_list(&["west-a"]));
+        let _guard = KubeconfigFixture::new()
+            .cluster("env-east", east.url())
+            .cluster("env-west", west.url())
+            .context("env-east", "env-east", "admin")
+            .context("env-west", "env-west", "admin")
+            .current("env-east")
+            .install()
+            .unwrap();
+        let cmd = || Command::new("kubex").arg(context_arg_with_env(VAR));
+        let context =
+            |args: &[&str]| context_from_matches(&cmd().try_get_matches_from(args).unwrap());
+        let completer = namespace_value_completer();
+        let complete = |words: &[&str]| values(complete_with_words(&completer, words, ""));
+
+        assert_eq!(context(&["kubex"]), None);
+        assert_eq!(complete(&["kubex", "-n", ""]), ["east-a"]);
+
+        // SAFETY: The variable is only read by this test, and by completers while the
+        // kubeconfig lock held by `_guard` keeps other completion tests from running.
+        unsafe { env::set_var(VAR, "env-west") };
+        assert_eq!(context(&["kubex"]).as_deref(), Some("env-west"));
+        assert_eq!(
+            context(&["kubex", "--context", "env-east"]).as_deref(),
+            Some("env-east")
+        );
+        assert_eq!(complete(&["kubex", "-n", ""]), ["west-a"]);
+        assert_eq!(
+            complete(&["kubex", "--context", "env-east", "-n", ""]),
+            ["east-a"]
+        );
+
+        // An empty or undefined context is ignored.
+        // SAFETY: See above.
+        unsafe { env::set_var(VAR, "") };
+        assert_eq!(context(&["kubex"]), None);
+        assert_eq!(complete(&["kubex", "-n", ""]), ["east-a"]);
+        // SAFETY: See above.
+        unsafe { env::set_var(VAR, "env-north") };
+        assert_eq!(complete(&["kubex", "-n", ""]), ["east-a"]);
+        // SAFETY: See above.
+        unsafe { env::remove_var(VAR) };
+    }
+
+    #[test]
+    fn merged_kubeconfigs_give_one_candidate_per_name() {
+        let _guard = install_all(&[
+            KubeconfigFixture::new()
+                .cluster("prod", "https://prod-1.example.com")
+                .context("shared", "prod", "admin")
+                .namespace("team-a")
+                .context("first", "prod", "admin")
+                .namespace("team-b")
+                .current("shared"),
+            KubeconfigFixture::new()
+                .cluster("prod", "https://prod-2.example.com")
+                .cluster("staging", "https://staging.example.com")
+                .context("shared", "staging", "admin")
+                .namespace("team-c")
+                .context("second", "staging", "admin")
+                .namespace("team-a"),
+        ])
+        .unwrap();
+        let help = |value: &str, help: &str| (String::from(value), Some(String::from(help)));
+
+        assert_eq!(
+            describe(context_value_completer().complete(OsStr::new(""))),
+            [
+                help("shared", "[current]"),
+                (String::from("first"), None),
+                (String::from("second"), None),
+            ]
+        );
+        // The cluster of the file that wins the merge is described.
+        assert_eq!(
+            describe(cluster_value_completer().complete(OsStr::new(""))),
+            [
+                help("prod", "https://prod-1.example.com"),
+                help("staging", "https://staging.example.com"),
+            ]
+        );
+        assert_eq!(
+            values(describe(user_value_completer().complete(OsStr::new("")))),
+            ["admin"]
+        );
+        // The namespaces of the contexts left out by the merge are not offered.
+        let offline = namespace_value_completer_with(CompleterOptions::new().offline_only(true));
+        assert_eq!(
+            complete_with_words(&offline, &["kubex", "-n", ""], ""),
+            [
+                help("team-a", "[current]"),
+                help("team-b", "from kubeconfig"),
+            ]
+        );
+
+        // Duplicates within a file are offered once too.
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config");
+        fs::write(
+            &path,
+            r#"apiVersion: v1
+kind: Config
+current-context: dup
+clusters:
+  - name: dup
+    cluster:
+      server: https://dup-1.example.com
+  - name: dup
+    cluster:
+      server: https://dup-2.example.com
+contexts:
+  - name: dup
+    context:
+      cluster: dup
+      user: admin
+  - name: dup
+    context:
+      cluster: dup
+      user: admin
+users:
+  - name: admin
+    user: {}
+"#,
+        )
+        .unwrap();
+        let path = path.to_str().unwrap();
+        let words = ["kubex", "--kubeconfig", path, "--context", ""];
+        assert_eq!(
+            complete_with_words(&context_value_completer(), &words, ""),
+            [help("dup", "[current]")]
+        );
+        assert_eq!(
+            complete_with_words(&cluster_value_completer(), &words, ""),
+            [help("dup", "https://dup-1.example.com")]
+        );
+    }
+
+    #[test]
+    fn context_arg_validated_rejects_undefined_contexts() {
+        let cmd = || Command::new("kubex").arg(context_arg_validated());
+        let parse = |args: &[&str]| cmd().try_get_matches_from(args);
+
+        {
+            let _guard = KubeconfigFixture::new()
+                .context("production", "prod-cluster", "admin")
+                .context("production-eu", "prod-cluster", "admin")
+                .context("staging", "staging-cluster", "admin")
+                .current("staging")
+                .install()
+                .unwrap();
+            let matches = parse(&["kubex", "--context", "production"]).unwrap();
+            assert_eq!(
+                context_from_matches(&matches).as_deref(),
+                Some("production")
+            );
+            let matches = parse(&["kubex", "--context="]).unwrap();
+            assert_eq!(context_from_matches(&matches), None);
+
+            let err = parse(&["kubex", "--context", "produciton"]).unwrap_err();
+            assert_eq!(err.kind(), clap::error::ErrorKind::ValueValidation);
+            let err = err.to_string();
+            assert!(
+                err.contains("context produciton is not found in kubeconfig (did you mean: production, production-eu?)"),
+                "{err}"
+            );
+            let err = parse(&["kubex", "--context", "minikube"])
+                .unwrap_err()
+                .to_string();
+            assert!(
+                err.contains("context minikube is not found in kubeconfig\n"),
+                "{err}"
+            );
+        }
+
+        // Without contexts to check against, even in an unreadable kubeconfig, any context
+        // is accepted.
+        let guard = KubeconfigFixture::new().install().unwrap();
+        let matches = parse(&["kubex", "--context", "minikube"]).unwrap();
+        assert_eq!(context_from_matches(&matches).as_deref(), Some("minikube"));
+        fs::write(&guard.paths()[0], "clusters: {").unwrap();
+        let matches = parse(&["kubex", "--context", "minikube"]).unwrap();
+        assert_eq!(context_from_matches(&matches).as_deref(), Some("minikube"));
+    }
+
+    fn logs_command() -> Command {
+        Command::new("kubex").subcommand(
+            Command::new("logs")
+                .arg(Arg::new("pod").required(true))
+                .arg(
+                    Arg::new("container")
+                        .short('c')
+                        .long("container")
+                        .add(container_value_completer("pod")),
+                ),
+        )
+    }
+
+    #[test]
+    fn container_value_completer_lists_containers_of_flag_or_positional_pod() {
+        let server = MockServer::start(|request| match request.path.as_str() {
+            "/api/v1/namespaces/team-a/pods/web-0" => (
+                200,
+                serde_json::json!({
+                    "apiVersion": "v1",
+                    "kind": "Pod",
+                    "metadata": {"name": "web-0", "namespace": "team-a"},
+                    "spec": {
+                        "containers": [{"name": "web"}, {"name": "sidecar"}],
+                        "initContainers": [{"name": "migrate"}],
+                        "ephemeralContainers": [{"name": "debugger"}],
+                    },
+                })
+                .to_string(),
+            ),
+            _ => status_response(404),
+        });
+        let _guard = KubeconfigFixture::new()
+            .cluster("mock", server.url())
+            .context("mock", "mock", "dev")
+            .namespace("team-a")
+            .current("mock")
+            .install()
+            .unwrap();
+        let _ = COMPLETION_COMMAND.set(logs_command);
+        let completer = container_value_completer("pod");
+        let containers = [
+            (String::from("web"), None),
+            (String::from("sidecar"), None),
+            (String::from("migrate"), Some(String::from("init"))),
+            (String::from("debugger"), Some(String::from("ephemeral"))),
+        ];
+
+        assert_eq!(
+            complete_with_words(
+                &completer,
+                &["kubex", "exec", "--pod", "pod/web-0", "-c", ""],
+                ""
+            ),
+            containers
+        );
+        assert_eq!(
+            complete_with_words(&completer, &["kubex", "logs", "web-0", "-c", ""], ""),
+            containers
+        );
+        assert_eq!(
+            complete_with_words(
+                &completer,
+                &["kubex", "logs", "web-0", "--container", "s"],
+                "s"
+            ),
+            [(String::from("sidecar"), None)]
+        );
+        assert_eq!(
+            complete_with_words(&completer, &["kubex", "logs", "-c", ""], ""),
+            []
+        );
+        assert_eq!(server.count("/api/v1/namespaces/team-a/pods/web-0"), 3);
+    }
+}
@@ -1,71 +1,4220 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    pin::pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
 use futures::{
-    future::try_join_all,
+    Stream,
+    future::join_all,
     stream::{self, StreamExt},
 };
-use k8s_openapi::apimachinery::pkg::apis::meta::v1::APIResource;
-use kube::Client;
+use k8s_openapi::{
+    api::authorization::v1::{
+        ResourceAttributes, SelfSubjectAccessReview, SelfSubjectAccessReviewSpec,
+    },
+    apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition,
+    apimachinery::pkg::{
+        apis::meta::v1::{APIGroup, APIResource},
+        version::Info,
+    },
+};
+use kube::{
+    Api, Client, ResourceExt,
+    api::{GroupVersionKind, PostParams},
+    config::Kubeconfig,
+    runtime::{WatchStreamExt, watcher},
+};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{Mutex, OnceCell, RwLock},
+    task::JoinHandle,
+};
+
+use crate::{
+    Error, Gvk, PartialResolution, ResolutionPolicy, ResourceRef,
+    builtin::builtin_api_resources,
+    error::BoxError,
+    retry::{RetryPolicy, retry_with_policy},
+    skew::{COMPILED_KUBERNETES_MINOR, DEFAULT_SKEW_TOLERANCE, SkewReport, check_version_skew},
+};
+
+/// Source of API resources used by [`resolve_requested_resources_with`].
+///
+/// [`DiscoverClient`] talks to the API server; [`StaticDiscover`] serves a fixed list,
+/// and implementations may wrap one another (e.g. to add retries or caching).
+pub trait Discover: Send + Sync {
+    /// Lists API resources, excluding subresources.
+    fn list_api_resources(&self) -> impl Future<Output = anyhow::Result<Vec<APIResource>>> + Send;
+
+    /// Lists API resources, reporting the group/versions that failed to enumerate.
+    ///
+    /// By default, returns [`Self::list_api_resources`] without any failed group.
+    fn discover(&self) -> impl Future<Output = anyhow::Result<DiscoveryOutcome>> + Send {
+        async {
+            Ok(DiscoveryOutcome {
+                resources: self.list_api_resources().await?,
+                ..Default::default()
+            })
+        }
+    }
+
+    /// Returns the `gitVersion` of the server, if known. By default, `None`.
+    fn git_version(&self) -> impl Future<Output = Option<String>> + Send {
+        async { None }
+    }
+}
+
+/// [`Discover`] implementation serving a fixed list of resources, e.g. in tests.
+#[derive(Clone, Debug, Default)]
+pub struct StaticDiscover(pub Vec<APIResource>);
+
+impl Discover for StaticDiscover {
+    async fn list_api_resources(&self) -> anyhow::Result<Vec<APIResource>> {
+        Ok(self.0.clone())
+    }
+}
 
+#[derive(Clone)]
 pub struct DiscoverClient {
     client: Client,
+    retry_policy: RetryPolicy,
+    filter: DiscoveryFilter,
+    group_filter: GroupFilter,
+    all_versions: bool,
+    timeout: Option<Duration>,
+    deadline: Option<DiscoveryDeadline>,
+    /// Discovery cache file and its TTL consulted by [`Self::has_resource`].
+    cache: Option<(PathBuf, Duration)>,
+    server_info: OnceCell<Info>,
+}
+
+/// Finds the resource referenced by `resource_ref` in `resources`. An unqualified type
+/// is matched like [`crate::find_resource`] does.
+fn find_resource_ref(resource_ref: &ResourceRef, resources: &[APIResource]) -> Option<APIResource> {
+    if resource_ref.group.is_none() {
+        return crate::find_resource(&resource_ref.resource, resources);
+    }
+    resource_ref
+        .resolve(resources)
+        .ok()
+        .map(|(resource, _)| resource)
+}
+
+/// Checks if `err` is a 404 response of the API server.
+fn is_not_found(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<kube::Error>(),
+        Some(kube::Error::Api(response)) if response.code == 404
+    )
+}
+
+/// Overall time limit of a discovery, see [`DiscoverClient::with_deadline`].
+#[derive(Clone, Copy, Debug)]
+struct DiscoveryDeadline {
+    duration: Duration,
+    strict: bool,
 }
 
 impl DiscoverClient {
     pub fn new(client: Client) -> Self {
-        Self { client }
+        Self {
+            client,
+            retry_policy: RetryPolicy::default(),
+            filter: DiscoveryFilter::default(),
+            group_filter: GroupFilter::default(),
+            all_versions: false,
+            timeout: None,
+            deadline: None,
+            cache: None,
+            server_info: OnceCell::new(),
+        }
+    }
+
+    /// Retries each underlying discovery request according to `policy`.
+    ///
+    /// By default no retries are performed.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Filters discovered resources with `filter`. By default nothing is filtered.
+    pub fn with_filter(mut self, filter: DiscoveryFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Skips groups rejected by `filter`. Skipped groups cost no request.
+    ///
+    /// By default every group is discovered.
+    pub fn with_group_filter(mut self, filter: GroupFilter) -> Self {
+        self.group_filter = filter;
+        self
+    }
+
+    /// Keeps every served version of a resource instead of only the group's preferred one.
+    ///
+    /// By default, see [`dedupe_preferred`].
+    pub fn with_all_versions(mut self) -> Self {
+        self.all_versions = true;
+        self
+    }
+
+    /// Bounds each underlying discovery request by `timeout`.
+    ///
+    /// A group/version whose request times out is reported as failed, like any other failure.
+    /// By default, requests are only bounded by the client's own timeouts.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Bounds a whole discovery by `deadline`.
+    ///
+    /// Group/versions not enumerated in time are reported as failed and
+    /// [`DiscoveryOutcome::timed_out`] is set, so that the partial result can still be used.
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(DiscoveryDeadline {
+            duration: deadline,
+            strict: false,
+        });
+        self
+    }
+
+    /// Same as [`Self::with_deadline`], but fails the discovery when the deadline is exceeded.
+    pub fn with_strict_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(DiscoveryDeadline {
+            duration: deadline,
+            strict: true,
+        });
+        self
+    }
+
+    /// Makes [`Self::has_resource`] look `target` up in the discovery cache file at `path`
+    /// first, unless it is older than `ttl`.
+    pub fn with_cache(mut self, path: impl Into<PathBuf>, ttl: Duration) -> Self {
+        self.cache = Some((path.into(), ttl));
+        self
     }
 
+    /// Lists API resources, excluding subresources.
+    ///
+    /// The result is sorted by [`compare_api_resources`], independent of the server's ordering.
     pub async fn list_api_resources(&self) -> anyhow::Result<Vec<APIResource>> {
+        Ok(self.discover().await?.resources)
+    }
+
+    /// Lists API resources like [`Self::list_api_resources`], but reports the group/versions
+    /// that failed to enumerate (e.g. a broken aggregated APIService) instead of hiding them.
+    ///
+    /// # Errors
+    /// Returns an error only if the list of groups or core versions itself cannot be retrieved.
+    pub async fn discover(&self) -> anyhow::Result<DiscoveryOutcome> {
+        let mut outcome = self.discover_with_subresources().await?;
+        // Filter out subresources.
+        outcome
+            .resources
+            .retain(|resource| !is_subresource(resource));
+        Ok(outcome)
+    }
+
+    /// Lists API resources with their subresources (e.g. `deployments/scale`) grouped under them.
+    pub async fn list_api_resources_with_subresources(
+        &self,
+    ) -> anyhow::Result<Vec<DiscoveredResource>> {
+        Ok(group_subresources(
+            self.discover_with_subresources().await?.resources,
+        ))
+    }
+
+    async fn discover_with_subresources(&self) -> anyhow::Result<DiscoveryOutcome> {
+        let deadline = self.deadline_from_now();
+        // NOTE: The core group goes first, so that slow groups exceeding the deadline
+        // leave it enumerated.
+        let mut outcome = self.discover_core_api_resources(deadline).await?;
+        let groups = self.discover_api_groups_resources(deadline).await?;
+        outcome.resources.extend(groups.resources);
+        outcome.failed_groups.extend(groups.failed_groups);
+        outcome.preferred_versions.extend(groups.preferred_versions);
+        outcome.timed_out = outcome.timed_out || groups.timed_out;
+        if outcome.timed_out
+            && let Some(DiscoveryDeadline {
+                duration,
+                strict: true,
+            }) = self.deadline
+        {
+            return Err(anyhow::anyhow!(
+                "discovery did not finish within {duration:?}"
+            ));
+        }
+        outcome.resources = self
+            .filter
+            .apply(outcome.resources, &outcome.preferred_versions);
+        if !self.all_versions {
+            outcome.resources = dedupe_preferred(outcome.resources, &outcome.preferred_versions);
+        }
+        outcome.resources.sort_by(compare_api_resources);
+        Ok(outcome)
+    }
+
+    pub async fn list_api_groups_resources(&self) -> anyhow::Result<Vec<APIResource>> {
         Ok(self
-            .list_api_groups_resources()
+            .discover_api_groups_resources(self.deadline_from_now())
             .await?
-            .into_iter()
-            .chain(self.list_core_api_resources().await?)
-            // Filter out subresources.
-            .filter(|resource| !resource.name.contains("/"))
-            .collect())
+            .resources)
     }
 
-    pub async fn list_api_groups_resources(&self) -> anyhow::Result<Vec<APIResource>> {
-        let groups = self.client.list_api_groups().await?.groups;
-        let resources = stream::iter(groups)
+    async fn discover_api_groups_resources(
+        &self,
+        deadline: Option<Instant>,
+    ) -> anyhow::Result<DiscoveryOutcome> {
+        let groups = self
+            .bounded(deadline, || self.client.list_api_groups())
+            .await?
+            .groups
+            .into_iter()
+            .filter(|group| self.group_filter.matches(&group.name))
+            .collect::<Vec<_>>();
+        let preferred_versions = groups
+            .iter()
+            .filter_map(|group| {
+                let preferred = group.preferred_version.as_ref()?;
+                Some((group.name.clone(), preferred.version.clone()))
+            })
+            .collect();
+        let results = stream::iter(groups)
             .flat_map(|group| stream::iter(group.versions))
             .then(|version| async move {
-                let mut resources = self
-                    .client
-                    .list_api_group_resources(&version.group_version)
-                    .await?;
-                // NOTE: For some reason, `version` and `group` are None, so we need to set them manually.
-                for resource in &mut resources.resources {
-                    if let Some((group, version)) = version.group_version.split_once('/') {
-                        resource.group = Some(group.to_string());
-                        resource.version = Some(version.to_string());
-                    }
-                }
-                Ok::<_, anyhow::Error>(resources)
-            })
-            .flat_map(|api_resource_list| {
-                stream::iter(api_resource_list.unwrap_or_default().resources)
+                let result = self
+                    .bounded(deadline, || {
+                        self.client.list_api_group_resources(&version.group_version)
+                    })
+                    .await;
+                (version.group_version, result)
             })
             .collect::<Vec<_>>()
             .await;
-        Ok(resources)
+
+        let mut outcome = DiscoveryOutcome {
+            preferred_versions,
+            ..Default::default()
+        };
+        for (group_version, result) in results {
+            let (group, version) = group_version
+                .split_once('/')
+                .unwrap_or((group_version.as_str(), ""));
+            match result {
+                Ok(api_resource_list) => {
+                    // NOTE: For some reason, `version` and `group` are None, so we need to set them manually.
+                    outcome
+                        .resources
+                        .extend(api_resource_list.resources.into_iter().map(|mut resource| {
+                            resource.group = Some(group.to_string());
+                            resource.version = Some(version.to_string());
+                            resource
+                        }));
+                }
+                Err(_) => outcome
+                    .failed_groups
+                    .push((group.to_string(), version.to_string())),
+            }
+        }
+        outcome.timed_out = is_past(deadline) && !outcome.is_complete();
+        Ok(outcome)
     }
 
     pub async fn list_core_api_resources(&self) -> anyhow::Result<Vec<APIResource>> {
-        let versions = self.client.list_core_api_versions().await?.versions;
+        let outcome = self
+            .discover_core_api_resources(self.deadline_from_now())
+            .await?;
+        if let Some((_, version)) = outcome.failed_groups.first() {
+            return Err(anyhow::anyhow!(
+                "failed to list core API resources for {version}"
+            ));
+        }
+        Ok(outcome.resources)
+    }
+
+    /// Returns the server version reported by `/version`.
+    ///
+    /// The result is cached for the lifetime of this client.
+    pub async fn server_version(&self) -> anyhow::Result<Info> {
+        Ok(self
+            .server_info
+            .get_or_try_init(|| {
+                retry_with_policy(&self.retry_policy, || self.client.apiserver_version())
+            })
+            .await?
+            .clone())
+    }
+
+    /// Returns the server version as a comparable [`ServerVersion`].
+    ///
+    /// # Errors
+    /// Returns an error if the version cannot be retrieved or parsed.
+    pub async fn server_semver(&self) -> anyhow::Result<ServerVersion> {
+        let info = self.server_version().await?;
+        ServerVersion::parse(&info.git_version)
+            .ok_or_else(|| anyhow::anyhow!("unrecognized server version: {}", info.git_version))
+    }
+
+    /// Compares the server version with the Kubernetes version compiled into this crate,
+    /// allowing kubectl's skew of one minor version.
+    ///
+    /// # Errors
+    /// Returns an error if the version cannot be retrieved.
+    pub async fn check_version_skew(&self) -> anyhow::Result<SkewReport> {
+        Ok(check_version_skew(
+            &self.server_version().await?,
+            COMPILED_KUBERNETES_MINOR,
+            DEFAULT_SKEW_TOLERANCE,
+        ))
+    }
+
+    /// Fetches the OpenAPI schema of `resource`'s kind, e.g. to implement `kubectl explain`.
+    ///
+    /// The schema is looked up in `/openapi/v3/apis/{group}/{version}` (`/openapi/v3/api/v1` for the core group)
+    /// by its `x-kubernetes-group-version-kind` extension. Servers that do not serve OpenAPI v3
+    /// fall back to `/openapi/v2`.
+    pub async fn schema_for(&self, resource: &APIResource) -> anyhow::Result<ResourceSchema> {
+        let gvk = schema_gvk(resource)?;
+        let v3_path = if gvk.group.is_empty() {
+            format!("/openapi/v3/api/{}", gvk.version)
+        } else {
+            format!("/openapi/v3/apis/{}/{}", gvk.group, gvk.version)
+        };
+
+        let (document, definitions) = match self.get_json(&v3_path).await {
+            Ok(document) => (document, "/components/schemas"),
+            Err(kube::Error::Api(response)) if response.code == 404 => {
+                (self.get_json("/openapi/v2").await?, "/definitions")
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let schema = document
+            .pointer(definitions)
+            .and_then(serde_json::Value::as_object)
+            .and_then(|schemas| {
+                schemas
+                    .values()
+                    .find(|schema| schema_declares_gvk(schema, &gvk))
+            })
+            .cloned()
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "schema for {}/{}, Kind={} not found",
+                    gvk.group,
+                    gvk.version,
+                    gvk.kind
+                )
+            })?;
+        Ok(ResourceSchema {
+            gvk,
+            schema,
+            fetched_at: Utc::now(),
+        })
+    }
+
+    /// Same as [`Self::schema_for`], but caches the schema under `cache_dir` for `ttl`.
+    pub async fn schema_for_cached(
+        &self,
+        resource: &APIResource,
+        cache_dir: &Path,
+        ttl: Duration,
+    ) -> anyhow::Result<ResourceSchema> {
+        let gvk = schema_gvk(resource)?;
+        let path = cache_dir.join("schemas").join(format!(
+            "{}_{}_{}.json",
+            if gvk.group.is_empty() {
+                "core"
+            } else {
+                &gvk.group
+            },
+            gvk.version,
+            gvk.kind
+        ));
+        if let Ok(content) = fs::read_to_string(&path)
+            && let Ok(cached) = serde_json::from_str::<ResourceSchema>(&content)
+            && !is_older_than(cached.fetched_at, ttl)
+        {
+            return Ok(cached);
+        }
+
+        let schema = self.schema_for(resource).await?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string(&schema)?)?;
+        Ok(schema)
+    }
+
+    /// Captures the discovered resources and server version into a [`DiscoverySnapshot`],
+    /// e.g. to resolve against a cluster that is not reachable later.
+    ///
+    /// # Errors
+    /// Returns an error if discovery fails or is incomplete.
+    pub async fn snapshot(&self) -> anyhow::Result<DiscoverySnapshot> {
+        let outcome = DiscoverClient::discover(self).await?;
+        if !outcome.is_complete() {
+            return Err(anyhow::anyhow!(
+                "cannot snapshot an incomplete discovery (failed for {})",
+                outcome
+                    .failed_groups
+                    .iter()
+                    .map(|(group, version)| format!("{group}/{version}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        Ok(DiscoverySnapshot {
+            schema_version: DISCOVERY_SNAPSHOT_VERSION,
+            created_at: Utc::now(),
+            server_version: DiscoverClient::git_version(self).await,
+            preferred_versions: outcome.preferred_versions,
+            resources: outcome.resources,
+        })
+    }
+
+    /// Checks if the cluster serves `target`, returning the matching resource.
+    ///
+    /// `target` is parsed as a [`ResourceRef`] type, e.g. `deployments`, `pods.core` or
+    /// `deployments.v1.apps`. A group-qualified target only lists its group, at the given
+    /// version or the group's preferred one; other targets run a full discovery.
+    /// Groups rejected by [`Self::with_group_filter`] are never served.
+    ///
+    /// With [`Self::with_cache`], a resource found in an unexpired cache file is returned
+    /// without contacting the cluster.
+    pub async fn has_resource(&self, target: &str) -> anyhow::Result<Option<APIResource>> {
+        let resource_ref: ResourceRef = target.parse()?;
+        if let Some((path, ttl)) = &self.cache
+            && let Ok(cache) = load_discovery_cache(path)
+            && !cache.is_expired(*ttl)
+            && let Some(resource) =
+                find_resource_ref(&resource_ref, &self.group_filter.apply(cache.resources))
+        {
+            return Ok(Some(resource));
+        }
+
+        let Some(group) = resource_ref.group.as_deref() else {
+            return Ok(find_resource_ref(
+                &resource_ref,
+                &DiscoverClient::list_api_resources(self).await?,
+            ));
+        };
+        if !self.group_filter.matches(group) {
+            return Ok(None);
+        }
+
+        let version = match &resource_ref.version {
+            Some(version) => version.clone(),
+            None if group == "core" => {
+                let versions = self
+                    .bounded(None, || self.client.list_core_api_versions())
+                    .await?
+                    .versions;
+                let Some(version) = versions.into_iter().next() else {
+                    return Ok(None);
+                };
+                version
+            }
+            None => {
+                let api_group = match self
+                    .bounded(None, || async {
+                        let request = http::Request::get(format!("/apis/{group}"))
+                            .body(Vec::new())
+                            .map_err(kube::Error::HttpError)?;
+                        self.client.request::<APIGroup>(request).await
+                    })
+                    .await
+                {
+                    Ok(api_group) => api_group,
+                    Err(err) if is_not_found(&err) => return Ok(None),
+                    Err(err) => return Err(err),
+                };
+                let Some(version) = api_group
+                    .preferred_version
+                    .or_else(|| api_group.versions.into_iter().next())
+                else {
+                    return Ok(None);
+                };
+                version.version
+            }
+        };
+        let resource_list = if group == "core" {
+            self.bounded(None, || self.client.list_core_api_resources(&version))
+                .await
+        } else {
+            let group_version = format!("{group}/{version}");
+            self.bounded(None, || {
+                self.client.list_api_group_resources(&group_version)
+            })
+            .await
+        };
+        let resources: Vec<APIResource> = match resource_list {
+            Ok(resource_list) => resource_list
+                .resources
+                .into_iter()
+                .map(|mut resource| {
+                    resource.group = Some(group.to_string());
+                    resource.version = Some(version.clone());
+                    resource
+                })
+                .collect(),
+            Err(err) if is_not_found(&err) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        Ok(find_resource_ref(&resource_ref, &resources))
+    }
+
+    async fn get_json(&self, path: &str) -> Result<serde_json::Value, kube::Error> {
+        retry_with_policy(&self.retry_policy, || async {
+            let request = http::Request::get(path)
+                .body(Vec::new())
+                .map_err(kube::Error::HttpError)?;
+            self.client.request::<serde_json::Value>(request).await
+        })
+        .await
+    }
+
+    /// Returns the `gitVersion` of the server, or `None` if it cannot be retrieved.
+    async fn git_version(&self) -> Option<String> {
+        self.server_version()
+            .await
+            .ok()
+            .map(|info| info.git_version)
+    }
+
+    /// Lists API resources on which the current identity is allowed to perform `verb`
+    /// (in `namespace`, or cluster-wide if `None`).
+    ///
+    /// Each candidate is checked with a `SelfSubjectAccessReview`, running at most
+    /// `ACCESS_REVIEW_CONCURRENCY` reviews at once. Resources that do not support `verb`
+    /// at all are excluded without a review.
+    ///
+    /// If the reviews themselves are forbidden, every candidate is returned and
+    /// [`AccessibleResources::unchecked`] is set.
+    pub async fn list_accessible_api_resources(
+        &self,
+        verb: &str,
+        namespace: Option<&str>,
+    ) -> anyhow::Result<AccessibleResources> {
+        let candidates = self
+            .list_api_resources()
+            .await?
+            .into_iter()
+            .filter(|resource| resource.verbs.iter().any(|v| v == verb))
+            .collect::<Vec<_>>();
 
-        Ok(try_join_all(versions.into_iter().map(|version| async move {
-            let mut resources = self.client.list_core_api_resources(&version).await?;
-            // NOTE: For some reason, `version` is None, so we need to set them manually.
-            for resource in &mut resources.resources {
-                resource.group = Some("core".to_string());
-                resource.version = Some(version.clone());
+        let reviews: Api<SelfSubjectAccessReview> = Api::all(self.client.clone());
+        let results = stream::iter(&candidates)
+            .map(|resource| {
+                let reviews = &reviews;
+                async move {
+                    let review = SelfSubjectAccessReview {
+                        spec: SelfSubjectAccessReviewSpec {
+                            resource_attributes: Some(ResourceAttributes {
+                                group: Some(Self::review_group(resource)),
+                                version: resource.version.clone(),
+                                resource: Some(resource.name.clone()),
+                                verb: Some(verb.to_string()),
+                                namespace: namespace.map(str::to_string),
+                                ..Default::default()
+                            }),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    };
+                    let params = PostParams::default();
+                    retry_with_policy(&self.retry_policy, || reviews.create(&params, &review))
+                        .await
+                        .map(|review| review.status.is_some_and(|status| status.allowed))
+                }
+            })
+            .buffered(ACCESS_REVIEW_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut resources = Vec::new();
+        for (resource, result) in candidates.iter().zip(results) {
+            match result {
+                Ok(true) => resources.push(resource.clone()),
+                Ok(false) => {}
+                Err(kube::Error::Api(response)) if response.code == 403 => {
+                    return Ok(AccessibleResources {
+                        resources: candidates,
+                        unchecked: true,
+                        checked_at: Utc::now(),
+                    });
+                }
+                Err(err) => return Err(err.into()),
             }
-            Ok::<_, anyhow::Error>(resources)
+        }
+        Ok(AccessibleResources {
+            resources,
+            unchecked: false,
+            checked_at: Utc::now(),
+        })
+    }
+
+    /// Returns the group name as expected by access reviews, i.e. empty for the core group.
+    fn review_group(resource: &APIResource) -> String {
+        match resource.group.as_deref() {
+            Some("core") | None => String::new(),
+            Some(group) => group.to_string(),
+        }
+    }
+
+    async fn discover_core_api_resources(
+        &self,
+        deadline: Option<Instant>,
+    ) -> anyhow::Result<DiscoveryOutcome> {
+        if !self.group_filter.matches("core") {
+            return Ok(DiscoveryOutcome::default());
+        }
+        let versions = self
+            .bounded(deadline, || self.client.list_core_api_versions())
+            .await?
+            .versions;
+
+        let results = join_all(versions.into_iter().map(|version| async move {
+            let result = self
+                .bounded(deadline, || self.client.list_core_api_resources(&version))
+                .await;
+            (version, result)
         }))
-        .await?
+        .await;
+
+        let mut outcome = DiscoveryOutcome::default();
+        // NOTE: The core group has no preferred version; the first advertised one is used.
+        if let Some((version, _)) = results.first() {
+            outcome
+                .preferred_versions
+                .insert("core".to_string(), version.clone());
+        }
+        for (version, result) in results {
+            match result {
+                Ok(api_resource_list) => {
+                    // NOTE: For some reason, `version` is None, so we need to set them manually.
+                    outcome
+                        .resources
+                        .extend(api_resource_list.resources.into_iter().map(|mut resource| {
+                            resource.group = Some("core".to_string());
+                            resource.version = Some(version.clone());
+                            resource
+                        }));
+                }
+                Err(_) => outcome.failed_groups.push(("core".to_string(), version)),
+            }
+        }
+        outcome.timed_out = is_past(deadline) && !outcome.is_complete();
+        Ok(outcome)
+    }
+
+    fn deadline_from_now(&self) -> Option<Instant> {
+        self.deadline
+            .map(|deadline| Instant::now() + deadline.duration)
+    }
+
+    /// Runs `request` with the retry policy, bounded by the request timeout and `deadline`.
+    async fn bounded<T, F, Fut>(&self, deadline: Option<Instant>, request: F) -> anyhow::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, kube::Error>>,
+    {
+        let limit = [
+            self.timeout,
+            deadline.map(|deadline| deadline.saturating_duration_since(Instant::now())),
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        let request = retry_with_policy(&self.retry_policy, request);
+        match limit {
+            Some(limit) => tokio::time::timeout(limit, request)
+                .await
+                .map_err(|_| anyhow::anyhow!("discovery request timed out after {limit:?}"))?
+                .map_err(Into::into),
+            None => Ok(request.await?),
+        }
+    }
+}
+
+/// Checks if `deadline` has passed.
+fn is_past(deadline: Option<Instant>) -> bool {
+    deadline.is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+/// Kubernetes server version, ordered by major, minor and patch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ServerVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl ServerVersion {
+    /// Parses a `gitVersion` such as "v1.29.5", ignoring vendor suffixes and build metadata
+    /// like "v1.29.5-gke.1091002", "v1.28.3-eks-4f4795d" or "v1.30.2+k3s1".
+    pub fn parse(git_version: &str) -> Option<Self> {
+        let version = git_version.trim().trim_start_matches('v');
+        let core = version.split(['-', '+']).next().unwrap_or_default();
+        let mut numbers = core.split('.').map(|number| number.parse::<u32>().ok());
+        Some(Self {
+            major: numbers.next()??,
+            minor: numbers.next()??,
+            patch: numbers.next().flatten().unwrap_or(0),
+        })
+    }
+}
+
+impl fmt::Display for ServerVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// OpenAPI schema of a resource kind, returned by [`DiscoverClient::schema_for`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ResourceSchema {
+    /// The group/version/kind the schema was resolved for. The core group is empty.
+    pub gvk: GroupVersionKind,
+    pub schema: serde_json::Value,
+    pub fetched_at: DateTime<Utc>,
+}
+
+fn schema_gvk(resource: &APIResource) -> anyhow::Result<GroupVersionKind> {
+    Ok(Gvk::from_api_resource(resource)?.into_inner())
+}
+
+fn schema_declares_gvk(schema: &serde_json::Value, gvk: &GroupVersionKind) -> bool {
+    schema
+        .get("x-kubernetes-group-version-kind")
+        .and_then(serde_json::Value::as_array)
+        .is_some_and(|gvks| {
+            gvks.iter().any(|declared| {
+                declared.get("group").and_then(serde_json::Value::as_str) == Some(&gvk.group)
+                    && declared.get("version").and_then(serde_json::Value::as_str)
+                        == Some(&gvk.version)
+                    && declared.get("kind").and_then(serde_json::Value::as_str) == Some(&gvk.kind)
+            })
+        })
+}
+
+impl Discover for DiscoverClient {
+    async fn list_api_resources(&self) -> anyhow::Result<Vec<APIResource>> {
+        DiscoverClient::list_api_resources(self).await
+    }
+
+    async fn discover(&self) -> anyhow::Result<DiscoveryOutcome> {
+        DiscoverClient::discover(self).await
+    }
+
+    async fn git_version(&self) -> Option<String> {
+        DiscoverClient::git_version(self).await
+    }
+}
+
+/// Saves `resources` as a discovery cache, recording the server version known to `discovery`.
+async fn save_cache<D: Discover>(
+    discovery: &D,
+    path: &Path,
+    resources: &[APIResource],
+) -> crate::Result<()> {
+    save_discovery_cache(path, resources, discovery.git_version().await.as_deref())
+}
+
+/// Result of [`DiscoverClient::discover`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DiscoveryOutcome {
+    /// Resources from every group/version that enumerated successfully.
+    pub resources: Vec<APIResource>,
+    /// `(group, version)` pairs that failed to enumerate.
+    pub failed_groups: Vec<(String, String)>,
+    /// Preferred version of each group, keyed by group name ("core" for the core group).
+    pub preferred_versions: BTreeMap<String, String>,
+    /// Set if the deadline given by [`DiscoverClient::with_deadline`] was exceeded,
+    /// in which case the remaining group/versions are in `failed_groups`.
+    pub timed_out: bool,
+}
+
+/// Filter applied to discovered resources, see [`DiscoverClient::with_filter`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DiscoveryFilter {
+    /// Keeps only resources of each group's preferred version.
+    pub exclude_non_preferred: bool,
+    /// Drops alpha/beta versions (e.g. `v1beta1`) of a resource when a GA version (e.g. `v1`)
+    /// of the same resource in the same group exists.
+    pub exclude_pre_release_when_ga_exists: bool,
+}
+
+/// Allowlist and denylist of API groups, see [`DiscoverClient::with_group_filter`].
+///
+/// Patterns are globs where `*` matches any sequence, e.g. `*.istio.io`.
+/// The core group is named "core".
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GroupFilter {
+    allow: Vec<String>,
+    deny: Vec<String>,
+}
+
+impl GroupFilter {
+    /// Allows groups matching `pattern`. Once any pattern is allowed,
+    /// groups matching none of the allowed patterns are rejected.
+    pub fn allow(mut self, pattern: impl Into<String>) -> Self {
+        self.allow.push(pattern.into());
+        self
+    }
+
+    /// Rejects groups matching `pattern`, even if they are allowed.
+    pub fn deny(mut self, pattern: impl Into<String>) -> Self {
+        self.deny.push(pattern.into());
+        self
+    }
+
+    /// Checks if `group` passes the filter.
+    pub fn matches(&self, group: &str) -> bool {
+        (self.allow.is_empty()
+            || self
+                .allow
+                .iter()
+                .any(|pattern| crate::glob_match(pattern, group)))
+            && !self
+                .deny
+                .iter()
+                .any(|pattern| crate::glob_match(pattern, group))
+    }
+
+    /// Keeps the resources whose group passes the filter.
+    pub fn apply(&self, mut resources: Vec<APIResource>) -> Vec<APIResource> {
+        resources.retain(|resource| self.matches(resource.group.as_deref().unwrap_or("core")));
+        resources
+    }
+}
+
+impl DiscoveryFilter {
+    /// Applies the filter to `resources`, given the preferred version of each group.
+    ///
+    /// Resources of groups without a known preferred version are kept by `exclude_non_preferred`.
+    pub fn apply(
+        &self,
+        resources: Vec<APIResource>,
+        preferred_versions: &BTreeMap<String, String>,
+    ) -> Vec<APIResource> {
+        let ga_resources = resources
+            .iter()
+            .filter(|resource| resource.version.as_deref().is_some_and(is_ga_version))
+            .map(|resource| (resource.group.clone(), resource.name.clone()))
+            .collect::<Vec<_>>();
+
+        resources
+            .into_iter()
+            .filter(|resource| {
+                let preferred = resource
+                    .group
+                    .as_ref()
+                    .and_then(|group| preferred_versions.get(group));
+                !(self.exclude_non_preferred
+                    && preferred
+                        .is_some_and(|preferred| resource.version.as_ref() != Some(preferred)))
+            })
+            .filter(|resource| {
+                !(self.exclude_pre_release_when_ga_exists
+                    && !resource.version.as_deref().is_some_and(is_ga_version)
+                    && ga_resources.contains(&(resource.group.clone(), resource.name.clone())))
+            })
+            .collect()
+    }
+}
+
+/// Orders API resources deterministically, as used for discovery results:
+///
+/// 1. The core group first, then the other groups alphabetically.
+/// 2. Resource name (of the parent, for subresources).
+/// 3. Version.
+/// 4. Parents before their subresources, which are ordered by name.
+///
+/// This can be used to re-sort merged lists, e.g. `resources.sort_by(compare_api_resources)`.
+pub fn compare_api_resources(a: &APIResource, b: &APIResource) -> Ordering {
+    let key = |resource: &APIResource| {
+        let group = resource.group.clone().unwrap_or_default();
+        let (parent, subresource) = match resource.name.split_once('/') {
+            Some((parent, subresource)) => (parent.to_string(), Some(subresource.to_string())),
+            None => (resource.name.clone(), None),
+        };
+        (
+            group != "core",
+            group,
+            parent,
+            resource.version.clone(),
+            subresource,
+        )
+    };
+    key(a).cmp(&key(b))
+}
+
+/// Keeps one `APIResource` per group and name, choosing the group's preferred version
+/// from `group_preferences` (keyed by group name).
+///
+/// If the preferred version is unknown or does not serve the resource, the first occurrence is kept.
+/// The order of the remaining resources is preserved.
+pub fn dedupe_preferred(
+    resources: Vec<APIResource>,
+    group_preferences: &BTreeMap<String, String>,
+) -> Vec<APIResource> {
+    let is_preferred = |resource: &APIResource| {
+        resource
+            .group
+            .as_ref()
+            .and_then(|group| group_preferences.get(group))
+            .is_some_and(|preferred| resource.version.as_ref() == Some(preferred))
+    };
+
+    let mut deduped: Vec<APIResource> = Vec::with_capacity(resources.len());
+    let mut positions: HashMap<(Option<String>, String), usize> = HashMap::new();
+    for resource in resources {
+        let key = (resource.group.clone(), resource.name.clone());
+        match positions.get(&key) {
+            Some(&position) => {
+                if is_preferred(&resource) && !is_preferred(&deduped[position]) {
+                    deduped[position] = resource;
+                }
+            }
+            None => {
+                positions.insert(key, deduped.len());
+                deduped.push(resource);
+            }
+        }
+    }
+    deduped
+}
+
+/// Checks if `version` is a GA version such as `v1`, as opposed to `v1beta1` or `v2alpha1`.
+fn is_ga_version(version: &str) -> bool {
+    version
+        .strip_prefix('v')
+        .is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+}
+
+impl DiscoveryOutcome {
+    /// Checks if every group/version enumerated successfully.
+    pub fn is_complete(&self) -> bool {
+        self.failed_groups.is_empty()
+    }
+}
+
+/// Maximum number of concurrent access reviews issued by
+/// [`DiscoverClient::list_accessible_api_resources`].
+const ACCESS_REVIEW_CONCURRENCY: usize = 16;
+
+/// Result of [`DiscoverClient::list_accessible_api_resources`].
+///
+/// This can be cached separately from the discovery cache with
+/// [`save_access_cache`] and [`load_access_cache`], usually with a shorter TTL.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AccessibleResources {
+    pub resources: Vec<APIResource>,
+    /// Set if access reviews were forbidden, in which case `resources` is not filtered.
+    pub unchecked: bool,
+    pub checked_at: DateTime<Utc>,
+}
+
+impl AccessibleResources {
+    /// Checks if the result is older than `ttl`.
+    pub fn is_expired(&self, ttl: Duration) -> bool {
+        is_older_than(self.checked_at, ttl)
+    }
+}
+
+/// Loads an access check result stored at `path`.
+pub fn load_access_cache(path: &Path) -> crate::Result<AccessibleResources> {
+    let content = fs::read_to_string(path).map_err(|err| Error::cache(path, err))?;
+    serde_json::from_str(&content).map_err(|err| Error::cache(path, err))
+}
+
+/// Saves an access check result to `path`, creating parent directories as needed.
+pub fn save_access_cache(path: &Path, accessible: &AccessibleResources) -> crate::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| Error::cache(path, err))?;
+    }
+    let content = serde_json::to_string(accessible).map_err(|err| Error::cache(path, err))?;
+    fs::write(path, content).map_err(|err| Error::cache(path, err))
+}
+
+/// An API resource together with its subresources,
+/// as listed by [`DiscoverClient::list_api_resources_with_subresources`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiscoveredResource {
+    pub resource: APIResource,
+    /// Subresources of `resource`, named like `deployments/scale`.
+    pub subresources: Vec<APIResource>,
+}
+
+/// Whether a resource lives in a namespace or at cluster scope.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Scope {
+    Namespaced,
+    Cluster,
+}
+
+impl DiscoveredResource {
+    /// Wraps `resource` without any subresource.
+    pub fn new(resource: APIResource) -> Self {
+        Self {
+            resource,
+            subresources: Vec::new(),
+        }
+    }
+
+    pub fn scope(&self) -> Scope {
+        if self.resource.namespaced {
+            Scope::Namespaced
+        } else {
+            Scope::Cluster
+        }
+    }
+
+    /// Returns the group, or an empty string for the core group.
+    fn group(&self) -> &str {
+        match self.resource.group.as_deref() {
+            None | Some("core") => "",
+            Some(group) => group,
+        }
+    }
+
+    fn version(&self) -> &str {
+        self.resource.version.as_deref().unwrap_or_default()
+    }
+
+    /// Returns the `apiVersion` of the resource, e.g. "apps/v1", or just "v1" for the core group.
+    pub fn api_version(&self) -> String {
+        match self.group() {
+            "" => self.version().to_string(),
+            group => format!("{group}/{}", self.version()),
+        }
+    }
+
+    /// Returns the name qualified by the group, e.g. "deployments.apps", or just "pods"
+    /// for the core group.
+    pub fn qualified_name(&self) -> String {
+        match self.group() {
+            "" => self.resource.name.clone(),
+            group => format!("{}.{group}", self.resource.name),
+        }
+    }
+
+    /// Returns the group, version and kind, with an empty group for the core group.
+    pub fn gvk(&self) -> GroupVersionKind {
+        GroupVersionKind::gvk(self.group(), self.version(), &self.resource.kind)
+    }
+
+    /// Checks if the resource supports `verb`, e.g. "list" or "watch".
+    pub fn supports_verb(&self, verb: &str) -> bool {
+        self.resource.verbs.iter().any(|v| v == verb)
+    }
+
+    /// Returns the subresource named `name` (e.g. "scale" for `deployments/scale`).
+    pub fn subresource(&self, name: &str) -> Option<&APIResource> {
+        self.subresources.iter().find(|subresource| {
+            subresource
+                .name
+                .split_once('/')
+                .is_some_and(|(_, sub)| sub == name)
+        })
+    }
+
+    /// Checks if the resource has the `status` subresource.
+    pub fn has_status(&self) -> bool {
+        self.subresource("status").is_some()
+    }
+
+    /// Checks if the resource has the `scale` subresource.
+    pub fn has_scale(&self) -> bool {
+        self.subresource("scale").is_some()
+    }
+}
+
+/// Checks if `resource` is a subresource such as `pods/log`.
+pub fn is_subresource(resource: &APIResource) -> bool {
+    resource.name.contains('/')
+}
+
+/// Groups subresources under their parent resource of the same group and version.
+///
+/// The order of parent resources is preserved. Subresources whose parent is not in
+/// `resources` are dropped.
+pub fn group_subresources(resources: Vec<APIResource>) -> Vec<DiscoveredResource> {
+    let (subresources, parents): (Vec<_>, Vec<_>) = resources.into_iter().partition(is_subresource);
+
+    let mut discovered = parents
         .into_iter()
-        .flat_map(|api_resource_list| api_resource_list.resources)
-        .collect())
+        .map(DiscoveredResource::new)
+        .collect::<Vec<_>>();
+
+    for subresource in subresources {
+        let Some((parent_name, _)) = subresource.name.split_once('/') else {
+            continue;
+        };
+        if let Some(parent) = discovered.iter_mut().find(|parent| {
+            parent.resource.name == parent_name
+                && parent.resource.group == subresource.group
+                && parent.resource.version == subresource.version
+        }) {
+            parent.subresources.push(subresource);
+        }
+    }
+    discovered
+}
+
+/// In-process discovery cache layered above the file cache and live discovery.
+///
+/// Resources are kept in memory for `ttl`, so repeated resolutions within a long-running process
+/// neither re-read the cache file nor rediscover. The handle is cheap to clone, and concurrent
+/// callers that find the memory stale coalesce onto a single load.
+#[derive(Clone)]
+pub struct DiscoveryCache {
+    inner: Arc<DiscoveryCacheInner>,
+}
+
+struct DiscoveryCacheInner {
+    discovery: DiscoverClient,
+    cache_path: Option<PathBuf>,
+    ttl: Duration,
+    memory: RwLock<Option<MemoryEntry>>,
+    /// Serializes loads so that only one is in flight at a time.
+    loading: Mutex<()>,
+}
+
+struct MemoryEntry {
+    loaded_at: Instant,
+    /// Incremented on every load; used to detect that another caller already loaded.
+    generation: u64,
+    resources: Arc<Vec<APIResource>>,
+}
+
+impl DiscoveryCache {
+    /// Creates a cache backed by `discovery` and, if given, the discovery cache file at `cache_path`.
+    pub fn new(discovery: DiscoverClient, cache_path: Option<PathBuf>, ttl: Duration) -> Self {
+        Self {
+            inner: Arc::new(DiscoveryCacheInner {
+                discovery,
+                cache_path,
+                ttl,
+                memory: RwLock::new(None),
+                loading: Mutex::new(()),
+            }),
+        }
+    }
+
+    /// Returns the cached resources, loading them from the cache file or live discovery if stale.
+    pub async fn resources(&self) -> anyhow::Result<Arc<Vec<APIResource>>> {
+        if let Some(entry) = self.inner.memory.read().await.as_ref()
+            && entry.loaded_at.elapsed() <= self.inner.ttl
+        {
+            return Ok(entry.resources.clone());
+        }
+
+        let _loading = self.inner.loading.lock().await;
+        // NOTE: Another caller may have loaded while we were waiting.
+        if let Some(entry) = self.inner.memory.read().await.as_ref()
+            && entry.loaded_at.elapsed() <= self.inner.ttl
+        {
+            return Ok(entry.resources.clone());
+        }
+
+        let from_file = self
+            .inner
+            .cache_path
+            .as_deref()
+            .and_then(|path| load_discovery_cache(path).ok())
+            .filter(|cache| !cache.is_expired(self.inner.ttl))
+            .map(|cache| self.inner.discovery.group_filter.apply(cache.resources));
+        match from_file {
+            Some(resources) => Ok(self.store(resources).await),
+            None => self.discover().await,
+        }
+    }
+
+    /// Resolves `targets` against the cached resources.
+    ///
+    /// # Errors
+    /// Returns an error if the resources cannot be loaded or any target cannot be resolved.
+    pub async fn resolve(&self, targets: &[String]) -> anyhow::Result<Vec<(String, APIResource)>> {
+        Ok(crate::match_all_targets(
+            targets,
+            &self.resources().await?,
+            &ResolutionPolicy::default(),
+        )?)
+    }
+
+    /// Checks if `target` is among the cached resources, returning the matching resource.
+    /// `target` is parsed like [`DiscoverClient::has_resource`] does.
+    pub async fn has_resource(&self, target: &str) -> anyhow::Result<Option<APIResource>> {
+        let resource_ref: ResourceRef = target.parse()?;
+        Ok(find_resource_ref(&resource_ref, &self.resources().await?))
+    }
+
+    /// Runs live discovery and replaces both the in-memory copy and the cache file.
+    /// An incomplete discovery is returned without replacing either.
+    ///
+    /// Callers that were waiting on a refresh which completed in the meantime reuse its result.
+    pub async fn refresh(&self) -> anyhow::Result<Arc<Vec<APIResource>>> {
+        let generation = self.generation().await;
+        let _loading = self.inner.loading.lock().await;
+        if let Some(entry) = self.inner.memory.read().await.as_ref()
+            && entry.generation != generation
+        {
+            return Ok(entry.resources.clone());
+        }
+        self.discover().await
+    }
+
+    async fn generation(&self) -> u64 {
+        self.inner
+            .memory
+            .read()
+            .await
+            .as_ref()
+            .map_or(0, |entry| entry.generation)
+    }
+
+    /// Runs live discovery, storing the result in memory and the cache file only if complete.
+    async fn discover(&self) -> anyhow::Result<Arc<Vec<APIResource>>> {
+        let outcome = self.inner.discovery.discover().await?;
+        if !outcome.is_complete() {
+            // NOTE: Memoizing a partial result would hide the missing groups for the whole ttl,
+            // so the next call retries discovery instead.
+            return Ok(Arc::new(outcome.resources));
+        }
+        if let Some(path) = &self.inner.cache_path {
+            // NOTE: Failing to write the cache must not fail the discovery.
+            let _ = save_cache(&self.inner.discovery, path, &outcome.resources).await;
+        }
+        Ok(self.store(outcome.resources).await)
+    }
+
+    async fn store(&self, resources: Vec<APIResource>) -> Arc<Vec<APIResource>> {
+        let resources = Arc::new(resources);
+        let mut memory = self.inner.memory.write().await;
+        let generation = memory.as_ref().map_or(0, |entry| entry.generation) + 1;
+        *memory = Some(MemoryEntry {
+            loaded_at: Instant::now(),
+            generation,
+            resources: resources.clone(),
+        });
+        resources
+    }
+}
+
+/// Quiet period after a CRD change before [`spawn_crd_cache_invalidator`] refreshes the cache,
+/// so that a burst of changes results in a single refresh.
+const CRD_CHANGE_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Spawns a task that watches CustomResourceDefinitions and refreshes the discovery cache
+/// at `cache_path` whenever a CRD is added, removed, or changes its served versions.
+///
+/// Bursts of changes are debounced into a single refresh. The refresh runs discovery
+/// with `policy`; if discovery fails or is incomplete, the cache is deleted instead so that
+/// the next resolution falls back to live discovery.
+///
+/// The task runs until it is aborted through the returned handle.
+pub fn spawn_crd_cache_invalidator(
+    client: Client,
+    cache_path: PathBuf,
+    policy: RetryPolicy,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let crds: Api<CustomResourceDefinition> = Api::all(client.clone());
+        let discovery = DiscoverClient::new(client).with_retry_policy(policy);
+        let events = watcher(crds, watcher::Config::default()).default_backoff();
+        refresh_on_crd_changes(events, &discovery, &cache_path).await;
+    })
+}
+
+/// Refreshes the cache at `cache_path` through `discovery` after every burst of CRD watch
+/// `events` that changes the served versions, until `events` ends.
+async fn refresh_on_crd_changes<D: Discover>(
+    events: impl Stream<Item = Result<watcher::Event<CustomResourceDefinition>, watcher::Error>>,
+    discovery: &D,
+    cache_path: &Path,
+) {
+    let mut events = pin!(events);
+    let mut state = CrdServedVersions::default();
+    while let Some(event) = events.next().await {
+        if !state.apply(event) {
+            continue;
+        }
+        // Wait for the burst to settle before refreshing.
+        while let Ok(Some(event)) = tokio::time::timeout(CRD_CHANGE_DEBOUNCE, events.next()).await {
+            state.apply(event);
+        }
+        match discovery.discover().await {
+            Ok(outcome) if outcome.is_complete() => {
+                let _ = save_cache(discovery, cache_path, &outcome.resources).await;
+            }
+            _ => {
+                let _ = invalidate_discovery_cache(cache_path);
+            }
+        }
+    }
+}
+
+/// Served versions of every known CRD, used to tell relevant changes from status-only updates.
+#[derive(Default)]
+struct CrdServedVersions {
+    served: BTreeMap<String, Vec<String>>,
+    /// Objects collected during a (re-)list, replacing `served` once the list is done.
+    listing: Option<BTreeMap<String, Vec<String>>>,
+    initialized: bool,
+}
+
+impl CrdServedVersions {
+    /// Applies a watch event and returns whether it changed the set of served CRD versions.
+    fn apply(
+        &mut self,
+        event: Result<watcher::Event<CustomResourceDefinition>, watcher::Error>,
+    ) -> bool {
+        match event {
+            Ok(watcher::Event::Apply(crd)) => {
+                let (name, versions) = Self::entry(&crd);
+                self.served.insert(name, versions.clone()) != Some(versions)
+            }
+            Ok(watcher::Event::Delete(crd)) => self.served.remove(&crd.name_any()).is_some(),
+            Ok(watcher::Event::Init) => {
+                self.listing = Some(BTreeMap::new());
+                false
+            }
+            Ok(watcher::Event::InitApply(crd)) => {
+                let (name, versions) = Self::entry(&crd);
+                self.listing.get_or_insert_default().insert(name, versions);
+                false
+            }
+            Ok(watcher::Event::InitDone) => {
+                let listed = self.listing.take().unwrap_or_default();
+                // NOTE: The first list only establishes the baseline.
+                let changed = self.initialized && listed != self.served;
+                self.served = listed;
+                self.initialized = true;
+                changed
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn entry(crd: &CustomResourceDefinition) -> (String, Vec<String>) {
+        let versions = crd
+            .spec
+            .versions
+            .iter()
+            .filter(|version| version.served)
+            .map(|version| version.name.clone())
+            .collect();
+        (crd.name_any(), versions)
+    }
+}
+
+/// Version of the [`DiscoverySnapshot`] format. Snapshots store resources like the discovery
+/// cache does, so it is bumped together with [`DISCOVERY_CACHE_VERSION`].
+pub const DISCOVERY_SNAPSHOT_VERSION: u32 = DISCOVERY_CACHE_VERSION;
+
+/// Exported discovery result, see [`DiscoverClient::snapshot`].
+///
+/// A snapshot implements [`Discover`], so targets can be resolved against it fully offline
+/// with [`resolve_requested_resources_with`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DiscoverySnapshot {
+    schema_version: u32,
+    created_at: DateTime<Utc>,
+    /// `gitVersion` of the server the snapshot was taken from.
+    server_version: Option<String>,
+    /// Preferred version of each group ("core" for the core group).
+    preferred_versions: BTreeMap<String, String>,
+    resources: Vec<APIResource>,
+}
+
+impl DiscoverySnapshot {
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    /// Returns the `gitVersion` of the server the snapshot was taken from, if recorded.
+    pub fn server_version(&self) -> Option<&str> {
+        self.server_version.as_deref()
+    }
+
+    pub fn preferred_versions(&self) -> &BTreeMap<String, String> {
+        &self.preferred_versions
+    }
+
+    pub fn resources(&self) -> &[APIResource] {
+        &self.resources
+    }
+
+    /// Writes the snapshot as JSON to `writer`.
+    pub fn to_writer<W: io::Write>(&self, writer: W) -> anyhow::Result<()> {
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    /// Reads a snapshot written by [`Self::to_writer`].
+    ///
+    /// # Errors
+    /// Returns an error if the snapshot cannot be parsed or was written in another format.
+    pub fn from_reader<R: io::Read>(reader: R) -> anyhow::Result<Self> {
+        let snapshot: Self = serde_json::from_reader(reader)?;
+        if snapshot.schema_version != DISCOVERY_SNAPSHOT_VERSION {
+            return Err(anyhow::anyhow!(
+                "unsupported discovery snapshot version {} (expected {DISCOVERY_SNAPSHOT_VERSION})",
+                snapshot.schema_version
+            ));
+        }
+        Ok(snapshot)
+    }
+}
+
+impl Discover for DiscoverySnapshot {
+    async fn list_api_resources(&self) -> anyhow::Result<Vec<APIResource>> {
+        Ok(self.resources.clone())
+    }
+
+    async fn discover(&self) -> anyhow::Result<DiscoveryOutcome> {
+        Ok(DiscoveryOutcome {
+            resources: self.resources.clone(),
+            preferred_versions: self.preferred_versions.clone(),
+            ..Default::default()
+        })
+    }
+
+    async fn git_version(&self) -> Option<String> {
+        self.server_version.clone()
+    }
+}
+
+/// Application directory name used by [`resolve_requested_resources_cached`].
+const DEFAULT_CACHE_APP_NAME: &str = "kubex";
+
+/// Version of the [`DiscoveryCacheFile`] format, bumped on incompatible changes.
+pub const DISCOVERY_CACHE_VERSION: u32 = 1;
+
+/// On-disk representation of the discovery cache.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DiscoveryCacheFile {
+    /// Format version; caches written before versioning default to 0.
+    #[serde(default)]
+    version: u32,
+    updated_at: DateTime<Utc>,
+    /// `gitVersion` of the server the cache was written against.
+    #[serde(default)]
+    server_version: Option<String>,
+    resources: Vec<APIResource>,
+}
+
+impl DiscoveryCacheFile {
+    /// Creates a cache of `resources` updated now.
+    pub fn new(resources: Vec<APIResource>) -> Self {
+        Self {
+            version: DISCOVERY_CACHE_VERSION,
+            updated_at: Utc::now(),
+            server_version: None,
+            resources,
+        }
+    }
+
+    pub fn updated_at(&self) -> DateTime<Utc> {
+        self.updated_at
+    }
+
+    /// Returns the `gitVersion` of the server the cache was written against, if recorded.
+    pub fn server_version(&self) -> Option<&str> {
+        self.server_version.as_deref()
+    }
+
+    pub fn resources(&self) -> &[APIResource] {
+        &self.resources
+    }
+
+    pub fn into_resources(self) -> Vec<APIResource> {
+        self.resources
+    }
+
+    /// Returns the time elapsed since the cache was updated.
+    ///
+    /// An `updated_at` in the future (e.g. due to clock skew) yields zero.
+    pub fn age(&self) -> Duration {
+        (Utc::now() - self.updated_at).to_std().unwrap_or_default()
+    }
+
+    /// Checks if the cache is older than `ttl`. A TTL too large to represent never expires.
+    pub fn is_expired(&self, ttl: Duration) -> bool {
+        is_older_than(self.updated_at, ttl)
+    }
+}
+
+/// Loads the discovery cache stored at `path`.
+///
+/// `.yaml`/`.yml` files are parsed as YAML, and files with any other extension except `.json`
+/// are tried as JSON and then as YAML.
+///
+/// # Errors
+/// Returns [`Error::Cache`] if the file cannot be read or parsed, or was written in a format
+/// other than [`DISCOVERY_CACHE_VERSION`], e.g. by an older release.
+pub fn load_discovery_cache(path: &Path) -> crate::Result<DiscoveryCacheFile> {
+    let content = fs::read_to_string(path).map_err(|err| Error::cache(path, err))?;
+    let cache: DiscoveryCacheFile = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => serde_json::from_str(&content).map_err(BoxError::from),
+        Some("yaml" | "yml") => parse_yaml_cache(&content),
+        _ => serde_json::from_str(&content)
+            .map_err(BoxError::from)
+            .or_else(|err| parse_yaml_cache(&content).map_err(|_| err)),
+    }
+    .map_err(|err| Error::cache(path, err))?;
+    if cache.version != DISCOVERY_CACHE_VERSION {
+        return Err(Error::cache(
+            path,
+            format!(
+                "unsupported discovery cache version {} (expected {DISCOVERY_CACHE_VERSION})",
+                cache.version
+            ),
+        ));
+    }
+    Ok(cache)
+}
+
+fn parse_yaml_cache(content: &str) -> Result<DiscoveryCacheFile, BoxError> {
+    Ok(serde_yaml::from_str(content)?)
+}
+
+fn encode_yaml_cache(cache: &DiscoveryCacheFile) -> Result<String, BoxError> {
+    Ok(serde_yaml::to_string(cache)?)
+}
+
+/// Saves `resources` to `path` as a discovery cache, creating parent directories as needed.
+///
+/// `server_version` is the `gitVersion` of the server the resources were discovered from.
+/// The cache is written as YAML if `path` ends with `.yaml`/`.yml`, and as JSON otherwise.
+pub fn save_discovery_cache(
+    path: &Path,
+    resources: &[APIResource],
+    server_version: Option<&str>,
+) -> crate::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| Error::cache(path, err))?;
+    }
+    let cache = DiscoveryCacheFile {
+        server_version: server_version.map(str::to_string),
+        ..DiscoveryCacheFile::new(resources.to_vec())
+    };
+    let content = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("yaml" | "yml") => encode_yaml_cache(&cache),
+        _ => serde_json::to_string(&cache).map_err(BoxError::from),
+    }
+    .map_err(|err| Error::cache(path, err))?;
+    fs::write(path, content).map_err(|err| Error::cache(path, err))
+}
+
+/// Invalidates the discovery cache at `path` by deleting it.
+///
+/// A missing file is not an error.
+pub fn invalidate_discovery_cache(path: &Path) -> crate::Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(Error::cache(path, err)),
+    }
+}
+
+/// Summary of [`prune_discovery_caches`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PruneSummary {
+    /// Cache files that were removed.
+    pub removed: Vec<PathBuf>,
+    /// Number of cache files that were kept because they are still young enough.
+    pub retained: usize,
+}
+
+/// Extensions of the discovery cache files written by [`save_discovery_cache`],
+/// which [`prune_discovery_caches`] and [`prune_orphaned_caches`] consider.
+const CACHE_FILE_EXTENSIONS: &[&str] = &["json", "yaml", "yml"];
+
+fn has_cache_file_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| CACHE_FILE_EXTENSIONS.contains(&ext))
+}
+
+/// Removes discovery cache files in `dir` whose `updated_at` is older than `older_than`.
+///
+/// Only `*.json`, `*.yaml` and `*.yml` files that parse as a discovery cache are considered;
+/// anything else in `dir` is left untouched.
+pub fn prune_discovery_caches(dir: &Path, older_than: Duration) -> crate::Result<PruneSummary> {
+    let mut summary = PruneSummary::default();
+    for entry in fs::read_dir(dir).map_err(|err| Error::cache(dir, err))? {
+        let path = entry.map_err(|err| Error::cache(dir, err))?.path();
+        if !has_cache_file_extension(&path) {
+            continue;
+        }
+        let Ok(cache) = load_discovery_cache(&path) else {
+            continue;
+        };
+        if cache.is_expired(older_than) {
+            fs::remove_file(&path).map_err(|err| Error::cache(&path, err))?;
+            summary.removed.push(path);
+        } else {
+            summary.retained += 1;
+        }
+    }
+    Ok(summary)
+}
+
+/// Removes discovery cache files in `cache_dir` that belong to contexts not present in `kubeconfig`,
+/// returning the paths that were removed (or would be removed when `dry_run` is set).
+///
+/// Only files following the [`discovery_cache_file_name`] scheme are considered, with any
+/// extension [`save_discovery_cache`] writes; files that cannot be attributed to a context
+/// are never removed.
+pub fn prune_orphaned_caches(
+    cache_dir: &Path,
+    kubeconfig: &Kubeconfig,
+    dry_run: bool,
+) -> crate::Result<Vec<PathBuf>> {
+    let known: HashSet<String> = kubeconfig
+        .contexts
+        .iter()
+        .map(|context| cache_file_stem(&context.name))
+        .collect();
+
+    let mut removed = Vec::new();
+    for entry in fs::read_dir(cache_dir).map_err(|err| Error::cache(cache_dir, err))? {
+        let path = entry.map_err(|err| Error::cache(cache_dir, err))?.path();
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if !is_discovery_cache_file_name(file_name)
+            || path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .is_some_and(|stem| known.contains(stem))
+        {
+            continue;
+        }
+        if !dry_run {
+            fs::remove_file(&path).map_err(|err| Error::cache(&path, err))?;
+        }
+        removed.push(path);
+    }
+    removed.sort();
+    Ok(removed)
+}
+
+/// Checks if `file_name` follows the `<sanitized-context>-<8 hex digits>.<ext>` scheme,
+/// where `<ext>` is one of [`CACHE_FILE_EXTENSIONS`].
+fn is_discovery_cache_file_name(file_name: &str) -> bool {
+    let Some((sanitized, hash)) = file_name
+        .rsplit_once('.')
+        .filter(|(_, ext)| CACHE_FILE_EXTENSIONS.contains(ext))
+        .and_then(|(stem, _)| stem.rsplit_once('-'))
+    else {
+        return false;
+    };
+    hash.len() == 8
+        && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        && sanitize_context_name(sanitized) == sanitized
+}
+
+/// Returns the default discovery cache path for `context`,
+/// i.e. `<config_dir>/<app_name>/discovery/<sanitized-context>-<hash>.json`.
+///
+/// Returns `None` if the platform has no config directory.
+pub fn default_discovery_cache_path(app_name: &str, context: &str) -> Option<PathBuf> {
+    Some(
+        dirs::config_dir()?
+            .join(app_name)
+            .join("discovery")
+            .join(discovery_cache_file_name(context)),
+    )
+}
+
+/// Returns the cache file name for `context`.
+///
+/// Characters other than ASCII alphanumerics, `-`, `_` and `.` are replaced with `_`,
+/// and a short hash of the raw context name is appended so that contexts which
+/// sanitize to the same string (e.g. "prod/us-east" and "prod_us-east") do not share a file.
+pub fn discovery_cache_file_name(context: &str) -> String {
+    format!("{}.json", cache_file_stem(context))
+}
+
+/// Returns [`discovery_cache_file_name`] without the extension.
+fn cache_file_stem(context: &str) -> String {
+    format!("{}-{}", sanitize_context_name(context), short_hash(context))
+}
+
+fn sanitize_context_name(context: &str) -> String {
+    context
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// FNV-1a (64-bit) truncated to 8 hex digits.
+///
+/// NOTE: `std::hash::DefaultHasher` is not guaranteed to be stable across Rust releases,
+/// so a fixed algorithm is used to keep cache file names stable.
+fn short_hash(value: &str) -> String {
+    let hash = value.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    });
+    format!("{:08x}", hash as u32)
+}
+
+/// How [`resolve_requested_resources`] uses the discovery cache.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CacheStrategy {
+    /// Uses the cache only while it is younger than the TTL.
+    #[default]
+    Strict,
+    /// Uses an expired cache that is at most `max_stale` past the TTL immediately,
+    /// and refreshes the cache file in the background for the next call.
+    StaleWhileRevalidate { max_stale: Duration },
+    /// Uses the cache regardless of its age and never runs live discovery.
+    CacheOnly,
+    /// Neither reads nor writes the cache.
+    BypassCache,
+}
+
+/// Upper bound for a background refresh spawned by [`CacheStrategy::StaleWhileRevalidate`].
+const BACKGROUND_REFRESH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Notable step taken by [`resolve_requested_resources`], reported to a [`DiscoveryCacheObserver`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiscoveryCacheEvent {
+    /// The cache was used as is.
+    CacheHit {
+        age: Duration,
+    },
+    /// The cache file does not exist, or it is fresh but does not resolve every target.
+    CacheMiss,
+    /// The cache is expired or was written against another server version.
+    CacheStale {
+        age: Duration,
+    },
+    /// The cache file exists but could not be loaded.
+    CacheLoadFailed {
+        error: String,
+    },
+    /// Live discovery succeeded, with `failed_groups` groups that could not be enumerated.
+    LiveDiscoverySucceeded {
+        failed_groups: usize,
+    },
+    LiveDiscoveryFailed {
+        error: String,
+    },
+    /// A cache that did not qualify as a hit was used anyway, either by
+    /// [`CacheStrategy::StaleWhileRevalidate`] or because live discovery failed.
+    StaleCacheUsed {
+        age: Duration,
+    },
+    /// The built-in catalog was used. See [`ResolveOptions::fallback_to_builtin`].
+    BuiltinCatalogUsed,
+}
+
+/// Receives [`DiscoveryCacheEvent`]s, e.g. to bridge them to a metrics system.
+pub trait DiscoveryCacheObserver: fmt::Debug + Send + Sync {
+    fn observe(&self, event: &DiscoveryCacheEvent);
+}
+
+/// [`DiscoveryCacheObserver`] that records every event it receives.
+#[derive(Debug, Default)]
+pub struct CountingCacheObserver {
+    events: std::sync::Mutex<Vec<DiscoveryCacheEvent>>,
+}
+
+impl CountingCacheObserver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the events received so far, in order.
+    pub fn events(&self) -> Vec<DiscoveryCacheEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// Returns the number of events received so far that satisfy `predicate`.
+    pub fn count(&self, predicate: impl Fn(&DiscoveryCacheEvent) -> bool) -> usize {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| predicate(e))
+            .count()
+    }
+}
+
+impl DiscoveryCacheObserver for CountingCacheObserver {
+    fn observe(&self, event: &DiscoveryCacheEvent) {
+        self.events.lock().unwrap().push(event.clone());
+    }
+}
+
+/// Reports the event built by `event` to `observer`, if any.
+fn notify(
+    observer: Option<&Arc<dyn DiscoveryCacheObserver>>,
+    event: impl FnOnce() -> DiscoveryCacheEvent,
+) {
+    if let Some(observer) = observer {
+        observer.observe(&event());
+    }
+}
+
+/// Options for [`resolve_requested_resources`].
+#[derive(Clone, Debug)]
+pub struct ResolveOptions {
+    /// Skips reading the cache, but still rewrites it after live discovery,
+    /// like `--cache-refresh`.
+    pub force_refresh: bool,
+    /// Writes the cache after live discovery. Disable this to only read caches,
+    /// e.g. when the config directory is read-only. Defaults to `true`.
+    pub write_cache: bool,
+    pub strategy: CacheStrategy,
+    /// Retries each live discovery request according to this policy, if given.
+    pub retry_policy: Option<RetryPolicy>,
+    /// Treats the cache as stale if it was written against another server version.
+    ///
+    /// This costs one request to `/version` whenever the cache is read.
+    pub check_server_version: bool,
+    /// How targets are matched against the discovered resources.
+    pub resolution: ResolutionPolicy,
+    /// Resolves against the built-in catalog as a last resort, when neither live discovery
+    /// nor the cache is available. See [`PartialResolution::assumed`].
+    pub fallback_to_builtin: bool,
+    /// Notified of cache hits, misses and the discovery path taken.
+    pub observer: Option<Arc<dyn DiscoveryCacheObserver>>,
+    /// Skips groups rejected by this filter, both in live discovery and when reading the cache.
+    pub group_filter: GroupFilter,
+}
+
+impl Default for ResolveOptions {
+    fn default() -> Self {
+        Self {
+            force_refresh: false,
+            write_cache: true,
+            strategy: CacheStrategy::default(),
+            retry_policy: None,
+            check_server_version: false,
+            resolution: ResolutionPolicy::default(),
+            fallback_to_builtin: false,
+            observer: None,
+            group_filter: GroupFilter::default(),
+        }
+    }
+}
+
+/// Resolves `targets` into `APIResource`s, consulting the discovery cache at `cache_path` if given.
+///
+/// With [`CacheStrategy::Strict`], resolution follows this order:
+/// 1. If the cache is younger than `ttl` and every target resolves against it, uses the cache.
+/// 2. Otherwise runs live discovery and rewrites the cache.
+/// 3. If live discovery fails, falls back to the (stale) cache when one could be loaded.
+///
+/// Groups that fail to enumerate during live discovery do not fail the resolution
+/// unless a requested target cannot be resolved.
+///
+/// See [`CacheStrategy`] and [`ResolveOptions`] for the other behaviors.
+///
+/// Targets are matched according to [`ResolveOptions::resolution`]. The target "all" may yield
+/// several entries in the result, each paired with "all".
+///
+/// # Errors
+/// Returns [`Error::Discovery`] if discovery fails without a usable cache,
+/// [`Error::CacheUnavailable`] if [`CacheStrategy::CacheOnly`] finds no cache,
+/// or [`Error::Unresolved`] if any target cannot be resolved.
+pub async fn resolve_requested_resources(
+    client: Client,
+    targets: &[String],
+    cache_path: Option<&Path>,
+    ttl: Duration,
+    options: &ResolveOptions,
+) -> crate::Result<Vec<(String, APIResource)>> {
+    resolve_requested_resources_partial(client, targets, cache_path, ttl, options)
+        .await?
+        .into_result()
+}
+
+/// Same as [`resolve_requested_resources`], but reports unresolved targets in the result
+/// instead of failing, so that interactive tools can proceed with what resolved.
+///
+/// The cache, live discovery and fallback logic is identical; unresolved targets are
+/// determined against the freshest resource set that could be obtained.
+/// Short-name conflicts settled by the policy, which [`resolve_requested_resources`] settles
+/// silently, are reported in [`PartialResolution::conflicts`].
+///
+/// # Errors
+/// Returns an error if discovery fails without a usable cache,
+/// or if a target is ambiguous in strict mode.
+pub async fn resolve_requested_resources_partial(
+    client: Client,
+    targets: &[String],
+    cache_path: Option<&Path>,
+    ttl: Duration,
+    options: &ResolveOptions,
+) -> crate::Result<PartialResolution> {
+    let discovery = DiscoverClient::new(client)
+        .with_retry_policy(options.retry_policy.clone().unwrap_or_default())
+        .with_group_filter(options.group_filter.clone());
+    resolve_requested_resources_partial_with(&discovery, targets, cache_path, ttl, options).await
+}
+
+/// Same as [`resolve_requested_resources`], but discovers resources through `discovery`.
+///
+/// [`ResolveOptions::retry_policy`] is ignored; wrap `discovery` instead to retry.
+///
+/// # Errors
+/// See [`resolve_requested_resources`].
+pub async fn resolve_requested_resources_with<D: Discover + Clone + 'static>(
+    discovery: &D,
+    targets: &[String],
+    cache_path: Option<&Path>,
+    ttl: Duration,
+    options: &ResolveOptions,
+) -> crate::Result<Vec<(String, APIResource)>> {
+    resolve_requested_resources_partial_with(discovery, targets, cache_path, ttl, options)
+        .await?
+        .into_result()
+}
+
+/// Same as [`resolve_requested_resources_partial`], but discovers resources through `discovery`.
+///
+/// [`ResolveOptions::retry_policy`] is ignored; wrap `discovery` instead to retry.
+///
+/// # Errors
+/// See [`resolve_requested_resources_partial`].
+pub async fn resolve_requested_resources_partial_with<D: Discover + Clone + 'static>(
+    discovery: &D,
+    targets: &[String],
+    cache_path: Option<&Path>,
+    ttl: Duration,
+    options: &ResolveOptions,
+) -> crate::Result<PartialResolution> {
+    let ResolveOptions {
+        force_refresh,
+        write_cache,
+        strategy,
+        retry_policy: _,
+        check_server_version,
+        ref resolution,
+        fallback_to_builtin,
+        ref observer,
+        ref group_filter,
+    } = *options;
+    let observer = observer.as_ref();
+    let match_targets =
+        |resources: &[APIResource]| crate::match_targets_partial(targets, resources, resolution);
+    let match_builtin = || {
+        notify(observer, || DiscoveryCacheEvent::BuiltinCatalogUsed);
+        Ok(PartialResolution {
+            assumed: true,
+            ..match_targets(&builtin_api_resources())?
+        })
+    };
+
+    let cache = cache_path
+        .filter(|_| !force_refresh && strategy != CacheStrategy::BypassCache)
+        .and_then(|path| match load_discovery_cache(path) {
+            Ok(cache) => Some(DiscoveryCacheFile {
+                resources: group_filter.apply(cache.resources),
+                ..cache
+            }),
+            Err(err) => {
+                notify(observer, || {
+                    if err.is_cache_not_found() {
+                        DiscoveryCacheEvent::CacheMiss
+                    } else {
+                        DiscoveryCacheEvent::CacheLoadFailed {
+                            error: err.to_string(),
+                        }
+                    }
+                });
+                None
+            }
+        });
+
+    if strategy == CacheStrategy::CacheOnly {
+        return match cache {
+            Some(cache) => {
+                notify(observer, || DiscoveryCacheEvent::CacheHit {
+                    age: cache.age(),
+                });
+                match_targets(&cache.resources)
+            }
+            None if fallback_to_builtin => match_builtin(),
+            None => Err(Error::CacheUnavailable),
+        };
+    }
+
+    if let Some(cache) = &cache {
+        let version_matches = !check_server_version
+            || cache.server_version.is_some()
+                && cache.server_version == discovery.git_version().await;
+
+        let fresh = version_matches && !cache.is_expired(ttl);
+        if fresh
+            && let Ok(matched) = match_targets(&cache.resources)
+            && matched.is_complete()
+        {
+            notify(observer, || DiscoveryCacheEvent::CacheHit {
+                age: cache.age(),
+            });
+            return Ok(matched);
+        }
+        notify(observer, || {
+            if fresh {
+                DiscoveryCacheEvent::CacheMiss
+            } else {
+                DiscoveryCacheEvent::CacheStale { age: cache.age() }
+            }
+        });
+
+        if let CacheStrategy::StaleWhileRevalidate { max_stale } = strategy
+            && version_matches
+            && let Some(path) = cache_path
+            && !cache.is_expired(ttl.saturating_add(max_stale))
+            && let Ok(matched) = match_targets(&cache.resources)
+            && matched.is_complete()
+        {
+            notify(observer, || DiscoveryCacheEvent::StaleCacheUsed {
+                age: cache.age(),
+            });
+            if write_cache {
+                spawn_cache_refresh(discovery.clone(), path.to_path_buf(), group_filter.clone());
+            }
+            return Ok(matched);
+        }
+    }
+
+    match discovery.discover().await {
+        Ok(mut outcome) => {
+            outcome.resources = group_filter.apply(outcome.resources);
+            notify(observer, || DiscoveryCacheEvent::LiveDiscoverySucceeded {
+                failed_groups: outcome.failed_groups.len(),
+            });
+            // NOTE: An incomplete result is not cached, so that resources of the failed groups
+            // are not hidden until the cache expires.
+            if let Some(path) = cache_path
+                && write_cache
+                && strategy != CacheStrategy::BypassCache
+                && outcome.is_complete()
+            {
+                // NOTE: Failing to write the cache must not fail the resolution.
+                let _ = save_cache(discovery, path, &outcome.resources).await;
+            }
+            Ok(PartialResolution {
+                failed_groups: outcome.failed_groups,
+                ..match_targets(&outcome.resources)?
+            })
+        }
+        Err(err) => {
+            notify(observer, || DiscoveryCacheEvent::LiveDiscoveryFailed {
+                error: err.to_string(),
+            });
+            match cache {
+                Some(cache) => {
+                    notify(observer, || DiscoveryCacheEvent::StaleCacheUsed {
+                        age: cache.age(),
+                    });
+                    match_targets(&cache.resources)
+                }
+                None if fallback_to_builtin => match_builtin(),
+                None => Err(Error::discovery(err)),
+            }
+        }
+    }
+}
+
+/// Refreshes the cache at `path` on a detached task, swallowing any failure.
+/// Like the live path of the resolution, an incomplete result is not cached.
+///
+/// NOTE: The task is not awaited, so a short-lived process may exit before it finishes.
+fn spawn_cache_refresh<D: Discover + 'static>(
+    discovery: D,
+    path: PathBuf,
+    group_filter: GroupFilter,
+) {
+    tokio::spawn(async move {
+        if let Ok(Ok(outcome)) =
+            tokio::time::timeout(BACKGROUND_REFRESH_TIMEOUT, discovery.discover()).await
+            && outcome.is_complete()
+        {
+            let resources = group_filter.apply(outcome.resources);
+            let _ = save_cache(&discovery, &path, &resources).await;
+        }
+    });
+}
+
+/// Same as [`resolve_requested_resources`], but uses [`default_discovery_cache_path`] for `context`.
+pub async fn resolve_requested_resources_cached(
+    client: Client,
+    context: &str,
+    targets: &[String],
+    ttl: Duration,
+) -> crate::Result<Vec<(String, APIResource)>> {
+    let cache_path = default_discovery_cache_path(DEFAULT_CACHE_APP_NAME, context);
+    resolve_requested_resources(
+        client,
+        targets,
+        cache_path.as_deref(),
+        ttl,
+        &ResolveOptions::default(),
+    )
+    .await
+}
+
+pub(crate) fn is_older_than(timestamp: DateTime<Utc>, ttl: Duration) -> bool {
+    // NOTE: A TTL too large for `TimeDelta` never expires.
+    match TimeDelta::from_std(ttl) {
+        Ok(ttl) => Utc::now() - timestamp > ttl,
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::sync::atomic;
+
+    use super::*;
+    use crate::testutil::{
+        MockRequest, MockServer, api_resource, discovery_response, status_response,
+    };
+
+    /// Writes a discovery cache of `resources` updated `age` ago to `path`.
+    fn write_cache_file(path: &Path, resources: Vec<APIResource>, age: Duration) {
+        save_discovery_cache(path, &resources, None).unwrap();
+        let cache = DiscoveryCacheFile {
+            updated_at: Utc::now() - TimeDelta::from_std(age).unwrap(),
+            ..load_discovery_cache(path).unwrap()
+        };
+        let content = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml" | "yml") => encode_yaml_cache(&cache).unwrap(),
+            _ => serde_json::to_string(&cache).unwrap(),
+        };
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn prune_discovery_caches_removes_only_stale_caches() {
+        let dir = tempfile::tempdir().unwrap();
+        let hour = Duration::from_secs(60 * 60);
+        write_cache_file(&dir.path().join("fresh.json"), Vec::new(), Duration::ZERO);
+        write_cache_file(&dir.path().join("stale.json"), Vec::new(), 2 * hour);
+        fs::write(dir.path().join("broken.json"), "{").unwrap();
+        fs::write(dir.path().join("notes.txt"), "not a cache").unwrap();
+        write_cache_file(&dir.path().join("fresh.yaml"), Vec::new(), Duration::ZERO);
+        write_cache_file(&dir.path().join("stale.yaml"), Vec::new(), 2 * hour);
+        write_cache_file(&dir.path().join("stale.yml"), Vec::new(), 2 * hour);
+        let mut expected = vec![
+            dir.path().join("stale.json"),
+            dir.path().join("stale.yaml"),
+            dir.path().join("stale.yml"),
+        ];
+
+        let mut summary = prune_discovery_caches(dir.path(), hour).unwrap();
+        summary.removed.sort();
+        expected.sort();
+        assert_eq!(summary.removed, expected);
+        assert_eq!(summary.retained, 2);
+        for path in &expected {
+            assert!(!path.exists(), "{path:?}");
+        }
+        for name in ["fresh.json", "broken.json", "notes.txt"] {
+            assert!(dir.path().join(name).exists(), "{name}");
+        }
+    }
+
+    #[test]
+    fn prune_orphaned_caches_matches_every_cache_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let kubeconfig = crate::testutil::KubeconfigFixture::new()
+            .context("prod", "prod-cluster", "admin")
+            .kubeconfig()
+            .clone();
+        let kept = dir.path().join(discovery_cache_file_name("prod"));
+        let orphans = [
+            dir.path().join(discovery_cache_file_name("old")),
+            dir.path()
+                .join(format!("{}.yaml", cache_file_stem("old-eks"))),
+            dir.path()
+                .join(format!("{}.yml", cache_file_stem("old-gke"))),
+        ];
+        let unknown = [
+            dir.path().join(format!("{}.yaml", cache_file_stem("prod"))),
+            dir.path().join("custom.json"),
+            dir.path().join(format!("{}.txt", cache_file_stem("old"))),
+        ];
+        for path in orphans.iter().chain(&unknown).chain([&kept]) {
+            fs::write(path, "{}").unwrap();
+        }
+
+        let mut expected = orphans.to_vec();
+        expected.sort();
+        assert_eq!(
+            prune_orphaned_caches(dir.path(), &kubeconfig, true).unwrap(),
+            expected
+        );
+        assert!(orphans.iter().all(|path| path.exists()));
+        assert_eq!(
+            prune_orphaned_caches(dir.path(), &kubeconfig, false).unwrap(),
+            expected
+        );
+        assert!(orphans.iter().all(|path| !path.exists()));
+        assert!(unknown.iter().chain([&kept]).all(|path| path.exists()));
+    }
+
+    #[test]
+    fn discovery_cache_round_trips_as_json_and_yaml() {
+        let dir = tempfile::tempdir().unwrap();
+        let resources = crate::builtin::builtin_api_resources();
+        for name in ["cache.json", "cache.yaml", "cache.yml", "cache"] {
+            let path = dir.path().join(name);
+            save_discovery_cache(&path, &resources, Some("v1.33.1")).unwrap();
+            let cache = load_discovery_cache(&path).unwrap();
+            assert_eq!(cache.resources(), resources, "{name}");
+            assert_eq!(cache.server_version(), Some("v1.33.1"), "{name}");
+        }
+        assert!(
+            fs::read_to_string(dir.path().join("cache.yaml"))
+                .unwrap()
+                .contains("server_version: v1.33.1")
+        );
+    }
+
+    #[test]
+    fn load_discovery_cache_rejects_other_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+        write_cache_file(&path, Vec::new(), Duration::ZERO);
+        assert_eq!(
+            load_discovery_cache(&path).unwrap().version,
+            DISCOVERY_CACHE_VERSION
+        );
+
+        let mut cache: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        cache.as_object_mut().unwrap().remove("version");
+        fs::write(&path, cache.to_string()).unwrap();
+        let err = load_discovery_cache(&path).unwrap_err();
+        assert!(err.is_cache() && !err.is_cache_not_found(), "{err}");
+
+        cache["version"] = serde_json::json!(DISCOVERY_CACHE_VERSION + 1);
+        fs::write(&path, cache.to_string()).unwrap();
+        assert!(load_discovery_cache(&path).is_err());
+    }
+
+    #[tokio::test]
+    async fn snapshot_round_trips_and_resolves_offline() {
+        let server = MockServer::start(discovery_response);
+        let snapshot = DiscoverClient::new(server.client())
+            .snapshot()
+            .await
+            .unwrap();
+        assert_eq!(snapshot.server_version(), Some("v1.33.1"));
+        assert_eq!(server.count("/version"), 1);
+        assert!(
+            server
+                .requests()
+                .iter()
+                .all(|request| request.method == "GET" && request.body.is_empty())
+        );
+
+        let mut exported = Vec::new();
+        snapshot.to_writer(&mut exported).unwrap();
+        let loaded = DiscoverySnapshot::from_reader(exported.as_slice()).unwrap();
+        assert_eq!(loaded, snapshot);
+
+        let resolved = resolve_requested_resources_with(
+            &loaded,
+            &[String::from("po"), String::from("deploy")],
+            None,
+            Duration::ZERO,
+            &ResolveOptions::default(),
+        )
+        .await
+        .unwrap();
+        let resolved: Vec<(&str, &str, Option<&str>)> = resolved
+            .iter()
+            .map(|(target, resource)| {
+                (
+                    target.as_str(),
+                    resource.name.as_str(),
+                    resource.group.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            resolved,
+            [
+                ("po", "pods", Some("core")),
+                ("deploy", "deployments", Some("apps"))
+            ]
+        );
+
+        let mut other_version: serde_json::Value = serde_json::from_slice(&exported).unwrap();
+        other_version["schema_version"] = serde_json::json!(DISCOVERY_SNAPSHOT_VERSION + 1);
+        let err = DiscoverySnapshot::from_reader(other_version.to_string().as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("unsupported"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn stale_while_revalidate_answers_from_cache_and_refreshes_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+        let hour = Duration::from_secs(60 * 60);
+        let pods = api_resource("core", "v1", "pods", "Pod");
+        let deployments = api_resource("apps", "v1", "deployments", "Deployment");
+        write_cache_file(&path, vec![pods.clone()], 2 * hour);
+        let stale_at = load_discovery_cache(&path).unwrap().updated_at;
+
+        let discovery = StaticDiscover(vec![pods, deployments.clone()]);
+        let observer = Arc::new(CountingCacheObserver::new());
+        let options = ResolveOptions {
+            strategy: CacheStrategy::StaleWhileRevalidate {
+                max_stale: 2 * hour,
+            },
+            observer: Some(observer.clone()),
+            ..Default::default()
+        };
+        let resolved = resolve_requested_resources_with(
+            &discovery,
+            &[String::from("pods")],
+            Some(&path),
+            hour,
+            &options,
+        )
+        .await
+        .unwrap();
+        assert_eq!(resolved[0].1.name, "pods");
+        let events = observer.events();
+        assert!(
+            matches!(
+                events.as_slice(),
+                [
+                    DiscoveryCacheEvent::CacheStale { .. },
+                    DiscoveryCacheEvent::StaleCacheUsed { .. }
+                ]
+            ),
+            "{events:?}"
+        );
+        // NOTE: The current-thread runtime has not run the refresh task yet.
+        assert_eq!(load_discovery_cache(&path).unwrap().updated_at, stale_at);
+
+        let refreshed = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                tokio::task::yield_now().await;
+                if let Ok(cache) = load_discovery_cache(&path)
+                    && cache.updated_at > stale_at
+                {
+                    return cache;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(!refreshed.is_expired(hour));
+        assert!(refreshed.resources().contains(&deployments));
+
+        // NOTE: Past `max_stale`, the cache is not used and discovery runs in the foreground.
+        write_cache_file(&path, Vec::new(), 4 * hour);
+        let observer = Arc::new(CountingCacheObserver::new());
+        let options = ResolveOptions {
+            observer: Some(observer.clone()),
+            ..options
+        };
+        resolve_requested_resources_with(
+            &discovery,
+            &[String::from("deployments")],
+            Some(&path),
+            hour,
+            &options,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            observer.count(|event| matches!(event, DiscoveryCacheEvent::StaleCacheUsed { .. })),
+            0
+        );
+        assert!(!load_discovery_cache(&path).unwrap().is_expired(hour));
+    }
+
+    #[tokio::test]
+    async fn discover_client_retries_with_its_policy() {
+        let seen = atomic::AtomicUsize::new(0);
+        let server = MockServer::start(move |request| {
+            if request.path == "/api/v1" && seen.fetch_add(1, atomic::Ordering::SeqCst) < 2 {
+                status_response(503)
+            } else {
+                discovery_response(request)
+            }
+        });
+        let policy = RetryPolicy::new(2).with_initial_backoff(Duration::from_millis(1));
+        let outcome = DiscoverClient::new(server.client())
+            .with_retry_policy(policy)
+            .discover()
+            .await
+            .unwrap();
+        assert!(outcome.is_complete(), "{outcome:?}");
+        assert_eq!(server.count("/api/v1"), 3);
+
+        let server = MockServer::start(|request| match request.path.as_str() {
+            "/apis" => status_response(401),
+            _ => discovery_response(request),
+        });
+        let policy = RetryPolicy::new(2).with_initial_backoff(Duration::from_millis(1));
+        let err = DiscoverClient::new(server.client())
+            .with_retry_policy(policy)
+            .discover()
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("401"), "{err:#}");
+        assert_eq!(server.count("/apis"), 1);
+    }
+
+    /// Serves [`discovery_response`] plus a `metrics.k8s.io` group whose resources fail to list,
+    /// like a broken aggregated APIService.
+    fn broken_metrics_response(request: &MockRequest) -> (u16, String) {
+        match request.path.as_str() {
+            "/apis" => {
+                let (_, body) = discovery_response(request);
+                let mut groups: serde_json::Value = serde_json::from_str(&body).unwrap();
+                let metrics = serde_json::json!({
+                    "name": "metrics.k8s.io",
+                    "versions": [{ "groupVersion": "metrics.k8s.io/v1beta1", "version": "v1beta1" }],
+                    "preferredVersion": { "groupVersion": "metrics.k8s.io/v1beta1", "version": "v1beta1" },
+                });
+                groups["groups"].as_array_mut().unwrap().push(metrics);
+                (200, groups.to_string())
+            }
+            "/apis/metrics.k8s.io/v1beta1" => status_response(503),
+            _ => discovery_response(request),
+        }
+    }
+
+    #[tokio::test]
+    async fn discover_reports_failed_groups_and_keeps_the_rest() {
+        let server = MockServer::start(broken_metrics_response);
+        let discovery = DiscoverClient::new(server.client());
+        let outcome = discovery.discover().await.unwrap();
+        assert_eq!(
+            outcome.failed_groups,
+            [(String::from("metrics.k8s.io"), String::from("v1beta1"))]
+        );
+        assert!(!outcome.is_complete());
+        assert!(!outcome.timed_out);
+        let names: Vec<&str> = outcome.resources.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["namespaces", "pods", "services", "deployments"]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+        let resolution = resolve_requested_resources_partial_with(
+            &discovery,
+            &[String::from("deploy"), String::from("podmetrics")],
+            Some(&path),
+            Duration::from_secs(60),
+            &ResolveOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(resolution.matched.len(), 1);
+        assert_eq!(resolution.unresolved, [String::from("podmetrics")]);
+        assert_eq!(resolution.failed_groups, outcome.failed_groups);
+        assert!(!path.exists(), "an incomplete discovery must not be cached");
+
+        let err = resolve_requested_resources_with(
+            &discovery,
+            &[String::from("podmetrics")],
+            None,
+            Duration::ZERO,
+            &ResolveOptions::default(),
+        )
+        .await
+        .unwrap_err();
+        let Error::Unresolved(unresolved) = err else {
+            panic!("{err:?}");
+        };
+        assert_eq!(unresolved.failed_groups, outcome.failed_groups);
+    }
+
+    #[test]
+    fn group_subresources_attaches_subresources_to_their_parent() {
+        let resources = vec![
+            api_resource("apps", "v1", "deployments/scale", "Scale"),
+            api_resource("apps", "v1", "deployments", "Deployment"),
+            api_resource("apps", "v1", "deployments/status", "Deployment"),
+            api_resource(
+                "apps",
+                "v1beta1",
+                "deployments/rollback",
+                "DeploymentRollback",
+            ),
+            api_resource("core", "v1", "pods", "Pod"),
+            api_resource("core", "v1", "services/proxy", "ServiceProxyOptions"),
+        ];
+        let discovered = group_subresources(resources);
+        let names: Vec<(&str, Vec<&str>)> = discovered
+            .iter()
+            .map(|discovered| {
+                (
+                    discovered.resource.name.as_str(),
+                    discovered
+                        .subresources
+                        .iter()
+                        .map(|subresource| subresource.name.as_str())
+                        .collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                (
+                    "deployments",
+                    vec!["deployments/scale", "deployments/status"]
+                ),
+                ("pods", vec![]),
+            ]
+        );
+        assert!(discovered[0].has_scale() && discovered[0].has_status());
+        assert_eq!(
+            discovered[0].subresource("scale").map(|s| s.kind.as_str()),
+            Some("Scale")
+        );
+        assert!(!discovered[1].has_status());
+    }
+
+    #[tokio::test]
+    async fn list_api_resources_with_subresources_groups_discovered_subresources() {
+        let server = MockServer::start(discovery_response);
+        let discovery = DiscoverClient::new(server.client());
+        let discovered = discovery
+            .list_api_resources_with_subresources()
+            .await
+            .unwrap();
+        let pods = discovered
+            .iter()
+            .find(|discovered| discovered.resource.name == "pods")
+            .unwrap();
+        assert!(pods.subresource("log").is_some());
+        assert!(!pods.has_scale());
+        let deployments = discovered
+            .iter()
+            .find(|discovered| discovered.resource.name == "deployments")
+            .unwrap();
+        assert!(deployments.has_scale());
+        assert!(discovered.iter().all(|d| !is_subresource(&d.resource)));
+
+        let resources = discovery.list_api_resources().await.unwrap();
+        assert!(!resources.iter().any(is_subresource));
+        assert_eq!(resources.len(), discovered.len());
+    }
+
+    #[test]
+    fn discovered_resource_describes_core_and_grouped_resources() {
+        let mut namespaces = api_resource("core", "v1", "namespaces", "Namespace");
+        namespaces.namespaced = false;
+        namespaces.verbs = ["get", "list", "create"].map(String::from).to_vec();
+        let mut bare_core = api_resource("", "v1", "pods", "Pod");
+        bare_core.group = None;
+
+        for (resource, scope, api_version, qualified_name) in [
+            (namespaces, Scope::Cluster, "v1", "namespaces"),
+            (bare_core, Scope::Namespaced, "v1", "pods"),
+            (
+                api_resource("", "v1", "services", "Service"),
+                Scope::Namespaced,
+                "v1",
+                "services",
+            ),
+            (
+                api_resource("apps", "v1", "deployments", "Deployment"),
+                Scope::Namespaced,
+                "apps/v1",
+                "deployments.apps",
+            ),
+            (
+                api_resource("example.com", "v1beta1", "widgets", "Widget"),
+                Scope::Namespaced,
+                "example.com/v1beta1",
+                "widgets.example.com",
+            ),
+        ] {
+            let discovered = DiscoveredResource::new(resource.clone());
+            assert_eq!(discovered.scope(), scope, "{}", resource.name);
+            assert_eq!(discovered.api_version(), api_version);
+            assert_eq!(discovered.qualified_name(), qualified_name);
+            let gvk = discovered.gvk();
+            assert_eq!(
+                (gvk.group.as_str(), gvk.kind.as_str()),
+                (
+                    api_version.rsplit_once('/').map_or("", |(g, _)| g),
+                    resource.kind.as_str()
+                )
+            );
+            assert_eq!(gvk.version, resource.version.unwrap());
+        }
+
+        let mut namespaces = api_resource("core", "v1", "namespaces", "Namespace");
+        namespaces.verbs = ["get", "list", "create"].map(String::from).to_vec();
+        let discovered = DiscoveredResource::new(namespaces);
+        assert!(discovered.supports_verb("create"));
+        assert!(!discovered.supports_verb("watch"));
+        assert!(!discovered.supports_verb("Create"));
+    }
+
+    #[tokio::test]
+    async fn discovered_resources_from_the_cluster_match_their_api_version() {
+        let server = MockServer::start(discovery_response);
+        let discovered = DiscoverClient::new(server.client())
+            .list_api_resources_with_subresources()
+            .await
+            .unwrap();
+        let describe = |name: &str| {
+            let discovered = discovered
+                .iter()
+                .find(|discovered| discovered.resource.name == name)
+                .unwrap();
+            (
+                discovered.scope(),
+                discovered.api_version(),
+                discovered.qualified_name(),
+            )
+        };
+        assert_eq!(
+            describe("pods"),
+            (Scope::Namespaced, "v1".to_string(), "pods".to_string())
+        );
+        assert_eq!(
+            describe("namespaces"),
+            (Scope::Cluster, "v1".to_string(), "namespaces".to_string())
+        );
+        assert_eq!(
+            describe("deployments"),
+            (
+                Scope::Namespaced,
+                "apps/v1".to_string(),
+                "deployments.apps".to_string()
+            )
+        );
+    }
+
+    const ACCESS_REVIEWS_PATH: &str = "/apis/authorization.k8s.io/v1/selfsubjectaccessreviews";
+
+    /// Serves discovery and answers access reviews, allowing only pods and deployments
+    /// in the `team-a` namespace.
+    fn access_review_response(request: &MockRequest) -> (u16, String) {
+        if request.path != ACCESS_REVIEWS_PATH {
+            return discovery_response(request);
+        }
+        let mut review: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+        let attributes = &review["spec"]["resourceAttributes"];
+        let allowed = attributes["namespace"] == "team-a"
+            && matches!(
+                (
+                    attributes["group"].as_str(),
+                    attributes["resource"].as_str()
+                ),
+                (Some(""), Some("pods")) | (Some("apps"), Some("deployments"))
+            );
+        review["status"] = serde_json::json!({ "allowed": allowed });
+        (201, review.to_string())
+    }
+
+    #[tokio::test]
+    async fn list_accessible_api_resources_keeps_allowed_resources() {
+        let server = MockServer::start(access_review_response);
+        let discovery = DiscoverClient::new(server.client());
+        let accessible = discovery
+            .list_accessible_api_resources("list", Some("team-a"))
+            .await
+            .unwrap();
+        assert!(!accessible.unchecked);
+        let names: Vec<&str> = accessible
+            .resources
+            .iter()
+            .map(|r| r.name.as_str())
+            .collect();
+        assert_eq!(names, ["pods", "deployments"]);
+        assert_eq!(server.count(ACCESS_REVIEWS_PATH), 4);
+        assert!(
+            server
+                .requests()
+                .iter()
+                .filter(|request| request.path == ACCESS_REVIEWS_PATH)
+                .all(|request| request.method == "POST"
+                    && request.body.contains("\"verb\":\"list\""))
+        );
+
+        let accessible = discovery
+            .list_accessible_api_resources("list", None)
+            .await
+            .unwrap();
+        assert!(accessible.resources.is_empty() && !accessible.unchecked);
+
+        let accessible = discovery
+            .list_accessible_api_resources("delete", Some("team-a"))
+            .await
+            .unwrap();
+        assert!(accessible.resources.is_empty());
+        assert_eq!(
+            server.count(ACCESS_REVIEWS_PATH),
+            8,
+            "no review for unsupported verbs"
+        );
+    }
+
+    #[tokio::test]
+    async fn list_accessible_api_resources_is_unchecked_when_reviews_are_forbidden() {
+        let server = MockServer::start(|request| match request.path.as_str() {
+            ACCESS_REVIEWS_PATH => status_response(403),
+            _ => discovery_response(request),
+        });
+        let accessible = DiscoverClient::new(server.client())
+            .list_accessible_api_resources("get", Some("team-a"))
+            .await
+            .unwrap();
+        assert!(accessible.unchecked);
+        assert_eq!(accessible.resources.len(), 4);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("access").join("team-a.json");
+        save_access_cache(&path, &accessible).unwrap();
+        let loaded = load_access_cache(&path).unwrap();
+        assert_eq!(loaded, accessible);
+        assert!(!loaded.is_expired(Duration::from_secs(60)));
+    }
+
+    /// [`Discover`] serving a fixed list of resources and counting the discoveries,
+    /// including those of its clones.
+    #[derive(Clone, Default)]
+    struct CountingDiscover {
+        resources: Vec<APIResource>,
+        discoveries: Arc<atomic::AtomicUsize>,
+    }
+
+    impl CountingDiscover {
+        fn discoveries(&self) -> usize {
+            self.discoveries.load(atomic::Ordering::SeqCst)
+        }
+    }
+
+    impl Discover for CountingDiscover {
+        async fn list_api_resources(&self) -> anyhow::Result<Vec<APIResource>> {
+            self.discoveries.fetch_add(1, atomic::Ordering::SeqCst);
+            Ok(self.resources.clone())
+        }
+    }
+
+    fn crd(name: &str, served: &[&str]) -> CustomResourceDefinition {
+        use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::{
+            CustomResourceDefinitionSpec, CustomResourceDefinitionVersion,
+        };
+
+        CustomResourceDefinition {
+            metadata: kube::api::ObjectMeta {
+                name: Some(name.to_string()),
+                ..Default::default()
+            },
+            spec: CustomResourceDefinitionSpec {
+                versions: ["v1alpha1", "v1"]
+                    .into_iter()
+                    .map(|version| CustomResourceDefinitionVersion {
+                        name: version.to_string(),
+                        served: served.contains(&version),
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn crd_changes_are_debounced_into_single_refreshes() {
+        use watcher::Event;
+
+        let (sender, receiver) = futures::channel::mpsc::unbounded();
+        tokio::spawn(async move {
+            let step = Duration::from_millis(500);
+            let events = [
+                (Duration::ZERO, Event::Init),
+                (
+                    Duration::ZERO,
+                    Event::InitApply(crd("a.example.com", &["v1"])),
+                ),
+                (Duration::ZERO, Event::InitDone),
+                // A burst of changes, each within the debounce of the previous one.
+                (step, Event::Apply(crd("b.example.com", &["v1"]))),
+                (step, Event::Apply(crd("c.example.com", &["v1"]))),
+                (
+                    step,
+                    Event::Apply(crd("b.example.com", &["v1alpha1", "v1"])),
+                ),
+                // Status-only updates do not change the served versions.
+                (5 * step, Event::Apply(crd("a.example.com", &["v1"]))),
+                (10 * step, Event::Delete(crd("c.example.com", &["v1"]))),
+            ];
+            for (delay, event) in events {
+                tokio::time::sleep(delay).await;
+                sender.unbounded_send(Ok(event)).unwrap();
+            }
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+        let discovery = CountingDiscover {
+            resources: vec![api_resource("example.com", "v1", "widgets", "Widget")],
+            ..Default::default()
+        };
+        refresh_on_crd_changes(receiver, &discovery, &path).await;
+
+        assert_eq!(discovery.discoveries(), 2);
+        assert_eq!(
+            load_discovery_cache(&path).unwrap().resources(),
+            discovery.resources
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn discovery_cache_coalesces_concurrent_loads() {
+        let server = MockServer::start(|request| {
+            // NOTE: A slow server keeps the first load in flight while the others arrive.
+            std::thread::sleep(Duration::from_millis(50));
+            discovery_response(request)
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+        let cache = DiscoveryCache::new(
+            DiscoverClient::new(server.client()),
+            Some(path.clone()),
+            Duration::from_secs(60),
+        );
+
+        let loads = join_all((0..8).map(|_| cache.resources())).await;
+        assert!(
+            loads
+                .iter()
+                .all(|resources| resources.as_ref().unwrap().len() == 4)
+        );
+        assert_eq!(server.count("/apis"), 1);
+        assert!(path.exists());
+
+        cache.resolve(&[String::from("deploy")]).await.unwrap();
+        assert!(cache.has_resource("pods").await.unwrap().is_some());
+        assert_eq!(server.count("/apis"), 1);
+
+        let refreshes = join_all((0..4).map(|_| cache.refresh())).await;
+        assert!(refreshes.iter().all(Result::is_ok));
+        assert_eq!(server.count("/apis"), 2);
+
+        // A new process reads the cache file instead of discovering.
+        let cache = DiscoveryCache::new(
+            DiscoverClient::new(server.client()),
+            Some(path),
+            Duration::from_secs(60),
+        );
+        assert_eq!(cache.resources().await.unwrap().len(), 4);
+        assert_eq!(server.count("/apis"), 2);
+    }
+
+    #[tokio::test]
+    async fn discovery_cache_does_not_memoize_incomplete_discoveries() {
+        let server = MockServer::start(broken_metrics_response);
+        let cache = DiscoveryCache::new(
+            DiscoverClient::new(server.client()),
+            None,
+            Duration::from_secs(60),
+        );
+        assert_eq!(cache.resources().await.unwrap().len(), 4);
+        assert_eq!(cache.resources().await.unwrap().len(), 4);
+        assert_eq!(server.count("/apis"), 2);
+    }
+
+    #[tokio::test]
+    async fn check_server_version_refreshes_caches_of_other_versions() {
+        let server = MockServer::start(discovery_response);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+        let pods = api_resource("core", "v1", "pods", "Pod");
+        let resolve = |check_server_version: bool| {
+            let (discovery, path) = (DiscoverClient::new(server.client()), &path);
+            async move {
+                let observer = Arc::new(CountingCacheObserver::new());
+                let options = ResolveOptions {
+                    check_server_version,
+                    observer: Some(observer.clone()),
+                    ..Default::default()
+                };
+                let targets = [String::from("pods")];
+                resolve_requested_resources_with(
+                    &discovery,
+                    &targets,
+                    Some(path),
+                    Duration::from_secs(60),
+                    &options,
+                )
+                .await
+                .unwrap();
+                observer.events()
+            }
+        };
+        let is_hit = |events: &[DiscoveryCacheEvent]| {
+            matches!(events, [DiscoveryCacheEvent::CacheHit { .. }])
+        };
+
+        // Mismatching: the cache is stale and rewritten with the live version.
+        save_discovery_cache(&path, std::slice::from_ref(&pods), Some("v1.32.0")).unwrap();
+        let events = resolve(true).await;
+        assert!(
+            matches!(
+                events.as_slice(),
+                [
+                    DiscoveryCacheEvent::CacheStale { .. },
+                    DiscoveryCacheEvent::LiveDiscoverySucceeded { failed_groups: 0 }
+                ]
+            ),
+            "{events:?}"
+        );
+        assert_eq!(server.count("/apis"), 1);
+        let cache = load_discovery_cache(&path).unwrap();
+        assert_eq!(cache.server_version(), Some("v1.33.1"));
+
+        // Matching: the cache is used, at the cost of one request to /version.
+        let version_requests = server.count("/version");
+        assert!(is_hit(&resolve(true).await));
+        assert_eq!(server.count("/version"), version_requests + 1);
+        assert_eq!(server.count("/apis"), 1);
+
+        // Flag off: the version is not checked at all.
+        save_discovery_cache(&path, &[pods], Some("v1.32.0")).unwrap();
+        let version_requests = server.count("/version");
+        assert!(is_hit(&resolve(false).await));
+        assert_eq!(server.count("/version"), version_requests);
+        assert_eq!(server.count("/apis"), 1);
+    }
+
+    #[test]
+    fn server_version_parses_vendor_git_versions() {
+        let cases = [
+            ("v1.33.1", Some((1, 33, 1))),
+            ("v1.29.5-gke.1091002", Some((1, 29, 5))),
+            ("v1.28.3-eks-4f4795d", Some((1, 28, 3))),
+            ("v1.30.2+k3s1", Some((1, 30, 2))),
+            ("v1.27.16+rke2r1", Some((1, 27, 16))),
+            ("v1.31.0-alpha.3+abcdef", Some((1, 31, 0))),
+            ("1.26", Some((1, 26, 0))),
+            (" v1.25.0 ", Some((1, 25, 0))),
+            ("", None),
+            ("v1", None),
+            ("vX.Y.Z", None),
+        ];
+        for (git_version, expected) in cases {
+            let parsed = ServerVersion::parse(git_version)
+                .map(|version| (version.major, version.minor, version.patch));
+            assert_eq!(parsed, expected, "{git_version:?}");
+        }
+
+        let parse = |git_version| ServerVersion::parse(git_version).unwrap();
+        assert!(parse("v1.29.5-gke.1091002") >= parse("v1.29.0"));
+        assert!(parse("v1.30.2+k3s1") > parse("v1.29.15"));
+        assert!(parse("v1.9.0") < parse("v1.10.0"));
+        assert_eq!(parse("v1.30.2+k3s1").to_string(), "1.30.2");
+    }
+
+    #[tokio::test]
+    async fn server_version_is_fetched_once_per_client() {
+        let server = MockServer::start(discovery_response);
+        let discovery = DiscoverClient::new(server.client());
+        assert_eq!(
+            discovery.server_version().await.unwrap().git_version,
+            "v1.33.1"
+        );
+        assert_eq!(
+            discovery.server_semver().await.unwrap(),
+            ServerVersion {
+                major: 1,
+                minor: 33,
+                patch: 1
+            }
+        );
+        assert_eq!(
+            DiscoverClient::git_version(&discovery).await.as_deref(),
+            Some("v1.33.1")
+        );
+        assert_eq!(server.count("/version"), 1);
+
+        let server = MockServer::start(|_| status_response(500));
+        assert!(
+            DiscoverClient::new(server.client())
+                .server_semver()
+                .await
+                .is_err()
+        );
+    }
+
+    /// Serves an OpenAPI v3 document for `apps/v1` and a v2 document for the rest.
+    fn openapi_response(request: &MockRequest) -> (u16, String) {
+        let schema = |group: &str, version: &str, kind: &str| {
+            serde_json::json!({
+                "description": format!("{kind} schema"),
+                "type": "object",
+                "x-kubernetes-group-version-kind": [
+                    { "group": group, "version": version, "kind": kind },
+                ],
+            })
+        };
+        let body = match request.path.as_str() {
+            "/openapi/v3/apis/apps/v1" => serde_json::json!({
+                "openapi": "3.0.0",
+                "components": { "schemas": {
+                    "io.k8s.api.apps.v1.DaemonSet": schema("apps", "v1", "DaemonSet"),
+                    "io.k8s.api.apps.v1.Deployment": schema("apps", "v1", "Deployment"),
+                }},
+            }),
+            "/openapi/v2" => serde_json::json!({
+                "swagger": "2.0",
+                "definitions": {
+                    "io.k8s.api.core.v1.Pod": schema("", "v1", "Pod"),
+                    "io.k8s.api.batch.v1.Job": schema("batch", "v1", "Job"),
+                },
+            }),
+            _ => return status_response(404),
+        };
+        (200, body.to_string())
+    }
+
+    #[tokio::test]
+    async fn schema_for_finds_the_schema_declaring_the_gvk() {
+        let server = MockServer::start(openapi_response);
+        let discovery = DiscoverClient::new(server.client());
+
+        let deployments = api_resource("apps", "v1", "deployments", "Deployment");
+        let schema = discovery.schema_for(&deployments).await.unwrap();
+        assert_eq!(
+            schema.gvk,
+            GroupVersionKind::gvk("apps", "v1", "Deployment")
+        );
+        assert_eq!(schema.schema["description"], "Deployment schema");
+        assert_eq!(server.count("/openapi/v2"), 0);
+
+        // Core resources are looked up without a group, falling back to OpenAPI v2.
+        let pods = api_resource("core", "v1", "pods", "Pod");
+        let schema = discovery.schema_for(&pods).await.unwrap();
+        assert_eq!(schema.gvk, GroupVersionKind::gvk("", "v1", "Pod"));
+        assert_eq!(schema.schema["description"], "Pod schema");
+        assert_eq!(server.count("/openapi/v3/api/v1"), 1);
+        assert_eq!(server.count("/openapi/v2"), 1);
+
+        let widgets = api_resource("example.com", "v1", "widgets", "Widget");
+        let err = discovery.schema_for(&widgets).await.unwrap_err();
+        assert!(err.to_string().contains("Kind=Widget not found"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn schema_for_cached_reuses_fresh_schemas() {
+        let server = MockServer::start(openapi_response);
+        let discovery = DiscoverClient::new(server.client());
+        let dir = tempfile::tempdir().unwrap();
+        let deployments = api_resource("apps", "v1", "deployments", "Deployment");
+        let ttl = Duration::from_secs(60);
+
+        let fetched = discovery
+            .schema_for_cached(&deployments, dir.path(), ttl)
+            .await
+            .unwrap();
+        assert!(dir.path().join("schemas/apps_v1_Deployment.json").exists());
+        let cached = discovery
+            .schema_for_cached(&deployments, dir.path(), ttl)
+            .await
+            .unwrap();
+        assert_eq!(cached, fetched);
+        assert_eq!(server.count("/openapi/v3/apis/apps/v1"), 1);
+
+        discovery
+            .schema_for_cached(&deployments, dir.path(), Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(server.count("/openapi/v3/apis/apps/v1"), 2);
+    }
+
+    /// Returns the `name.version.group` of each of `resources`.
+    fn versioned_names(resources: &[APIResource]) -> Vec<String> {
+        resources
+            .iter()
+            .map(|resource| {
+                format!(
+                    "{}.{}.{}",
+                    resource.name,
+                    resource.version.as_deref().unwrap_or_default(),
+                    resource.group.as_deref().unwrap_or_default()
+                )
+            })
+            .collect()
+    }
+
+    /// Resources of `autoscaling` (v2 preferred over v1), a pre-release-only `flowcontrol`
+    /// and `example.com`, whose preferred version is unknown.
+    fn multi_version_resources() -> Vec<APIResource> {
+        vec![
+            api_resource("autoscaling", "v1", "horizontalpodautoscalers", "HPA"),
+            api_resource("autoscaling", "v2", "horizontalpodautoscalers", "HPA"),
+            api_resource("autoscaling", "v2beta2", "horizontalpodautoscalers", "HPA"),
+            api_resource("flowcontrol", "v1beta3", "flowschemas", "FlowSchema"),
+            api_resource("example.com", "v1alpha1", "widgets", "Widget"),
+            api_resource("example.com", "v1", "widgets", "Widget"),
+        ]
+    }
+
+    fn preferred_versions() -> BTreeMap<String, String> {
+        BTreeMap::from([
+            (String::from("autoscaling"), String::from("v2")),
+            (String::from("flowcontrol"), String::from("v1beta3")),
+        ])
+    }
+
+    #[test]
+    fn discovery_filter_drops_non_preferred_and_pre_release_versions() {
+        let cases = [
+            (
+                DiscoveryFilter::default(),
+                versioned_names(&multi_version_resources()),
+            ),
+            (
+                DiscoveryFilter {
+                    exclude_non_preferred: true,
+                    ..Default::default()
+                },
+                vec![
+                    String::from("horizontalpodautoscalers.v2.autoscaling"),
+                    String::from("flowschemas.v1beta3.flowcontrol"),
+                    String::from("widgets.v1alpha1.example.com"),
+                    String::from("widgets.v1.example.com"),
+                ],
+            ),
+            (
+                DiscoveryFilter {
+                    exclude_pre_release_when_ga_exists: true,
+                    ..Default::default()
+                },
+                vec![
+                    String::from("horizontalpodautoscalers.v1.autoscaling"),
+                    String::from("horizontalpodautoscalers.v2.autoscaling"),
+                    String::from("flowschemas.v1beta3.flowcontrol"),
+                    String::from("widgets.v1.example.com"),
+                ],
+            ),
+            (
+                DiscoveryFilter {
+                    exclude_non_preferred: true,
+                    exclude_pre_release_when_ga_exists: true,
+                },
+                vec![
+                    String::from("horizontalpodautoscalers.v2.autoscaling"),
+                    String::from("flowschemas.v1beta3.flowcontrol"),
+                    String::from("widgets.v1.example.com"),
+                ],
+            ),
+        ];
+        for (filter, expected) in cases {
+            let filtered = filter.apply(multi_version_resources(), &preferred_versions());
+            assert_eq!(versioned_names(&filtered), expected, "{filter:?}");
+        }
+    }
+
+    #[test]
+    fn is_ga_version_accepts_only_plain_versions() {
+        for version in ["v1", "v2", "v10"] {
+            assert!(is_ga_version(version), "{version}");
+        }
+        for version in ["v1beta1", "v2alpha1", "v", "1", "", "vv1"] {
+            assert!(!is_ga_version(version), "{version}");
+        }
+    }
+
+    #[test]
+    fn is_older_than_respects_ttl_boundaries() {
+        let minute = Duration::from_secs(60);
+        let ago = |secs: i64| Utc::now() - TimeDelta::seconds(secs);
+        assert!(!is_older_than(ago(59), minute));
+        assert!(is_older_than(ago(61), minute));
+        assert!(is_older_than(ago(1), Duration::ZERO));
+        // NOTE: Timestamps in the future, e.g. due to clock skew, are never expired.
+        assert!(!is_older_than(ago(-60), Duration::ZERO));
+        // NOTE: TTLs too large for `TimeDelta` never expire instead of overflowing.
+        assert!(!is_older_than(DateTime::<Utc>::MIN_UTC, Duration::MAX));
+        assert!(is_older_than(DateTime::<Utc>::MIN_UTC, minute));
+    }
+
+    #[test]
+    fn discovery_cache_file_exposes_its_contents() {
+        let pods = api_resource("core", "v1", "pods", "Pod");
+        let cache = DiscoveryCacheFile::new(vec![pods.clone()]);
+        assert!(cache.age() < Duration::from_secs(1));
+        assert!(!cache.is_expired(Duration::from_secs(60)));
+        assert_eq!(cache.server_version(), None);
+        assert_eq!(cache.resources(), std::slice::from_ref(&pods));
+
+        let cache = DiscoveryCacheFile {
+            updated_at: Utc::now() + TimeDelta::hours(1),
+            ..cache
+        };
+        assert_eq!(cache.age(), Duration::ZERO);
+        assert!(!cache.is_expired(Duration::ZERO));
+        assert_eq!(cache.into_resources(), [pods]);
+    }
+
+    #[tokio::test]
+    async fn force_refresh_and_write_cache_control_the_cache_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+        let ttl = Duration::from_secs(60 * 60);
+        let pods = api_resource("core", "v1", "pods", "Pod");
+        let deployments = api_resource("apps", "v1", "deployments", "Deployment");
+        let discovery = CountingDiscover {
+            resources: vec![pods.clone(), deployments.clone()],
+            ..Default::default()
+        };
+        let resolve = |options: ResolveOptions| {
+            let (discovery, path) = (&discovery, &path);
+            async move {
+                resolve_requested_resources_with(
+                    discovery,
+                    &[String::from("pods")],
+                    Some(path),
+                    ttl,
+                    &options,
+                )
+                .await
+                .unwrap()
+            }
+        };
+        let cached = || load_discovery_cache(&path).unwrap().into_resources();
+
+        write_cache_file(&path, vec![pods.clone()], Duration::ZERO);
+        resolve(ResolveOptions::default()).await;
+        assert_eq!(discovery.discoveries(), 0);
+
+        resolve(ResolveOptions {
+            force_refresh: true,
+            write_cache: false,
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(discovery.discoveries(), 1);
+        assert_eq!(cached(), std::slice::from_ref(&pods));
+
+        resolve(ResolveOptions {
+            force_refresh: true,
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(discovery.discoveries(), 2);
+        assert_eq!(cached(), [pods.clone(), deployments]);
+
+        invalidate_discovery_cache(&path).unwrap();
+        resolve(ResolveOptions {
+            strategy: CacheStrategy::BypassCache,
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(discovery.discoveries(), 3);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn dedupe_preferred_keeps_the_preferred_version() {
+        let deduped = dedupe_preferred(multi_version_resources(), &preferred_versions());
+        assert_eq!(
+            versioned_names(&deduped),
+            [
+                "horizontalpodautoscalers.v2.autoscaling",
+                "flowschemas.v1beta3.flowcontrol",
+                "widgets.v1alpha1.example.com",
+            ]
+        );
+
+        // A preferred version not serving the resource keeps the first occurrence.
+        let preferred = BTreeMap::from([(String::from("autoscaling"), String::from("v3"))]);
+        let deduped = dedupe_preferred(multi_version_resources(), &preferred);
+        assert_eq!(
+            versioned_names(&deduped)[0],
+            "horizontalpodautoscalers.v1.autoscaling"
+        );
+        assert_eq!(deduped.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn discover_client_keeps_every_version_on_request() {
+        let server = MockServer::start(|request| match request.path.as_str() {
+            "/apis" => (
+                200,
+                serde_json::json!({
+                    "kind": "APIGroupList",
+                    "apiVersion": "v1",
+                    "groups": [{
+                        "name": "autoscaling",
+                        "versions": [
+                            { "groupVersion": "autoscaling/v1", "version": "v1" },
+                            { "groupVersion": "autoscaling/v2", "version": "v2" },
+                        ],
+                        "preferredVersion": { "groupVersion": "autoscaling/v2", "version": "v2" },
+                    }],
+                })
+                .to_string(),
+            ),
+            "/apis/autoscaling/v1" | "/apis/autoscaling/v2" => (
+                200,
+                serde_json::json!({
+                    "kind": "APIResourceList",
+                    "groupVersion": request.path.trim_start_matches("/apis/"),
+                    "resources": [{
+                        "name": "horizontalpodautoscalers",
+                        "singularName": "horizontalpodautoscaler",
+                        "kind": "HorizontalPodAutoscaler",
+                        "namespaced": true,
+                        "verbs": ["get", "list"],
+                    }],
+                })
+                .to_string(),
+            ),
+            _ => discovery_response(request),
+        });
+        let hpas = |resources: Vec<APIResource>| {
+            versioned_names(&resources)
+                .into_iter()
+                .filter(|name| name.starts_with("horizontalpodautoscalers."))
+                .collect::<Vec<_>>()
+        };
+
+        let discovery = DiscoverClient::new(server.client());
+        assert_eq!(
+            hpas(discovery.list_api_resources().await.unwrap()),
+            ["horizontalpodautoscalers.v2.autoscaling"]
+        );
+        let discovery = discovery.with_all_versions();
+        assert_eq!(
+            hpas(discovery.list_api_resources().await.unwrap()),
+            [
+                "horizontalpodautoscalers.v1.autoscaling",
+                "horizontalpodautoscalers.v2.autoscaling"
+            ]
+        );
+    }
+
+    #[test]
+    fn compare_api_resources_orders_independently_of_input_order() {
+        let expected = [
+            "namespaces.v1.core",
+            "pods.v1.core",
+            "pods/log.v1.core",
+            "pods/status.v1.core",
+            "deployments.v1.apps",
+            "deployments/scale.v1.apps",
+            "horizontalpodautoscalers.v1.autoscaling",
+            "horizontalpodautoscalers.v2.autoscaling",
+            "widgets.v1.example.com",
+        ];
+        let resources: Vec<APIResource> = expected
+            .iter()
+            .map(|name| {
+                let (name, rest) = name.split_once('.').unwrap();
+                let (version, group) = rest.split_once('.').unwrap();
+                api_resource(group, version, name, "Kind")
+            })
+            .collect();
+
+        // Every rotation of a few fixed shuffles sorts back to the same order.
+        let shuffles = [
+            [4, 0, 8, 2, 6, 1, 7, 3, 5],
+            [8, 7, 6, 5, 4, 3, 2, 1, 0],
+            [1, 3, 5, 7, 0, 2, 4, 6, 8],
+        ];
+        for shuffle in shuffles {
+            for rotation in 0..shuffle.len() {
+                let mut shuffled: Vec<APIResource> =
+                    shuffle.iter().map(|&i| resources[i].clone()).collect();
+                shuffled.rotate_left(rotation);
+                shuffled.sort_by(compare_api_resources);
+                assert_eq!(
+                    versioned_names(&shuffled),
+                    expected,
+                    "{shuffle:?} {rotation}"
+                );
+            }
+        }
+    }
+
+    /// [`Discover`] whose discovery always fails, like an unreachable cluster.
+    #[derive(Clone)]
+    struct FailingDiscover;
+
+    impl Discover for FailingDiscover {
+        async fn list_api_resources(&self) -> anyhow::Result<Vec<APIResource>> {
+            Err(anyhow::anyhow!("connection refused"))
+        }
+    }
+
+    #[tokio::test]
+    async fn partial_resolution_reports_unresolved_targets() {
+        let discovery = StaticDiscover(crate::builtin::builtin_api_resources());
+        let targets = [
+            String::from("widgets"),
+            String::from("po"),
+            String::from("gadgets"),
+            String::from("deployments.apps"),
+        ];
+        let resolution = resolve_requested_resources_partial_with(
+            &discovery,
+            &targets,
+            None,
+            Duration::ZERO,
+            &ResolveOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert!(!resolution.is_complete());
+        assert!(!resolution.assumed);
+        let matched: Vec<(&str, &str)> = resolution
+            .matched
+            .iter()
+            .map(|(target, resource)| (target.as_str(), resource.name.as_str()))
+            .collect();
+        assert_eq!(
+            matched,
+            [("po", "pods"), ("deployments.apps", "deployments")]
+        );
+        assert_eq!(resolution.unresolved, ["widgets", "gadgets"]);
+
+        let err = resolution.into_result().unwrap_err();
+        assert!(
+            matches!(&err, Error::Unresolved(unresolved) if unresolved.targets == ["widgets", "gadgets"]),
+            "{err:?}"
+        );
+        assert_eq!(
+            err.to_string(),
+            "failed to resolve resources: widgets, gadgets"
+        );
+    }
+
+    #[tokio::test]
+    async fn partial_resolution_uses_the_stale_cache_when_discovery_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+        let pods = api_resource("core", "v1", "pods", "Pod");
+        write_cache_file(&path, vec![pods], Duration::from_secs(2 * 60 * 60));
+
+        let resolution = resolve_requested_resources_partial_with(
+            &FailingDiscover,
+            &[String::from("pods"), String::from("deployments")],
+            Some(&path),
+            Duration::from_secs(60 * 60),
+            &ResolveOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(resolution.matched.len(), 1);
+        assert_eq!(resolution.unresolved, ["deployments"]);
+
+        invalidate_discovery_cache(&path).unwrap();
+        let err = resolve_requested_resources_partial_with(
+            &FailingDiscover,
+            &[String::from("pods")],
+            Some(&path),
+            Duration::from_secs(60 * 60),
+            &ResolveOptions::default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.is_discovery(), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn builtin_catalog_is_the_last_resort() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+        let targets = [String::from("deploy"), String::from("widgets")];
+        let observer = Arc::new(CountingCacheObserver::new());
+        let options = ResolveOptions {
+            fallback_to_builtin: true,
+            observer: Some(observer.clone()),
+            ..Default::default()
+        };
+        let resolve = |options: ResolveOptions| {
+            let (targets, path) = (&targets, &path);
+            async move {
+                resolve_requested_resources_partial_with(
+                    &FailingDiscover,
+                    targets,
+                    Some(path),
+                    Duration::from_secs(60),
+                    &options,
+                )
+                .await
+            }
+        };
+
+        let resolution = resolve(options.clone()).await.unwrap();
+        assert!(resolution.assumed);
+        assert_eq!(resolution.matched[0].1.group.as_deref(), Some("apps"));
+        assert_eq!(resolution.unresolved, ["widgets"]);
+        assert_eq!(
+            observer.events().last(),
+            Some(&DiscoveryCacheEvent::BuiltinCatalogUsed)
+        );
+
+        let resolution = resolve(ResolveOptions {
+            strategy: CacheStrategy::CacheOnly,
+            ..options.clone()
+        })
+        .await
+        .unwrap();
+        assert!(resolution.assumed);
+
+        // A cache, even a stale one, is preferred over the catalog.
+        write_cache_file(
+            &path,
+            vec![api_resource("example.com", "v1", "widgets", "Widget")],
+            Duration::from_secs(60 * 60),
+        );
+        let resolution = resolve(options.clone()).await.unwrap();
+        assert!(!resolution.assumed);
+        assert_eq!(resolution.unresolved, ["deploy"]);
+
+        invalidate_discovery_cache(&path).unwrap();
+        let err = resolve(ResolveOptions::default()).await.unwrap_err();
+        assert!(err.is_discovery(), "{err:?}");
+        let err = resolve(ResolveOptions {
+            strategy: CacheStrategy::CacheOnly,
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+        assert!(matches!(err, Error::CacheUnavailable), "{err:?}");
+    }
+
+    /// Returns the names of `events`, without their data.
+    fn event_names(events: &[DiscoveryCacheEvent]) -> Vec<&'static str> {
+        events
+            .iter()
+            .map(|event| match event {
+                DiscoveryCacheEvent::CacheHit { .. } => "CacheHit",
+                DiscoveryCacheEvent::CacheMiss => "CacheMiss",
+                DiscoveryCacheEvent::CacheStale { .. } => "CacheStale",
+                DiscoveryCacheEvent::CacheLoadFailed { .. } => "CacheLoadFailed",
+                DiscoveryCacheEvent::LiveDiscoverySucceeded { .. } => "LiveDiscoverySucceeded",
+                DiscoveryCacheEvent::LiveDiscoveryFailed { .. } => "LiveDiscoveryFailed",
+                DiscoveryCacheEvent::StaleCacheUsed { .. } => "StaleCacheUsed",
+                DiscoveryCacheEvent::BuiltinCatalogUsed => "BuiltinCatalogUsed",
+            })
+            .collect()
+    }
+
+    /// Resolves `target` through `discovery` and the cache at `path`, returning the events.
+    async fn observe_resolution<D: Discover + Clone + 'static>(
+        discovery: &D,
+        target: &str,
+        path: &Path,
+        ttl: Duration,
+    ) -> Vec<DiscoveryCacheEvent> {
+        let observer = Arc::new(CountingCacheObserver::new());
+        let options = ResolveOptions {
+            observer: Some(observer.clone()),
+            ..Default::default()
+        };
+        let targets = [target.to_string()];
+        let _ = resolve_requested_resources_partial_with(
+            discovery,
+            &targets,
+            Some(path),
+            ttl,
+            &options,
+        )
+        .await;
+        observer.events()
+    }
+
+    #[tokio::test]
+    async fn observer_receives_the_path_taken_by_each_resolution() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+        let hour = Duration::from_secs(60 * 60);
+        let pods = api_resource("core", "v1", "pods", "Pod");
+        let live = StaticDiscover(vec![pods.clone()]);
+
+        let events = observe_resolution(&live, "pods", &path, hour).await;
+        assert_eq!(
+            event_names(&events),
+            ["CacheMiss", "LiveDiscoverySucceeded"]
+        );
+        assert_eq!(
+            events[1],
+            DiscoveryCacheEvent::LiveDiscoverySucceeded { failed_groups: 0 }
+        );
+
+        let events = observe_resolution(&live, "pods", &path, hour).await;
+        assert_eq!(event_names(&events), ["CacheHit"]);
+
+        // A fresh cache not resolving every target is a miss.
+        let events = observe_resolution(&live, "deployments", &path, hour).await;
+        assert_eq!(
+            event_names(&events),
+            ["CacheMiss", "LiveDiscoverySucceeded"]
+        );
+
+        fs::write(&path, "{").unwrap();
+        let events = observe_resolution(&live, "pods", &path, hour).await;
+        assert_eq!(
+            event_names(&events),
+            ["CacheLoadFailed", "LiveDiscoverySucceeded"]
+        );
+
+        write_cache_file(&path, vec![pods.clone()], 2 * hour);
+        let events = observe_resolution(&FailingDiscover, "pods", &path, hour).await;
+        assert_eq!(
+            event_names(&events),
+            ["CacheStale", "LiveDiscoveryFailed", "StaleCacheUsed"]
+        );
+        assert!(matches!(events[0], DiscoveryCacheEvent::CacheStale { age } if age >= 2 * hour));
+        assert!(matches!(
+            &events[1],
+            DiscoveryCacheEvent::LiveDiscoveryFailed { error } if error.contains("connection refused")
+        ));
+    }
+
+    #[tokio::test]
+    async fn static_discover_serves_its_resources_as_a_complete_discovery() {
+        let resources = vec![api_resource("core", "v1", "pods", "Pod")];
+        let discovery = StaticDiscover(resources.clone());
+        let outcome = Discover::discover(&discovery).await.unwrap();
+        assert_eq!(
+            outcome,
+            DiscoveryOutcome {
+                resources,
+                ..Default::default()
+            }
+        );
+        assert_eq!(Discover::git_version(&discovery).await, None);
+    }
+
+    /// [`Discover`] wrapping another one and reporting `metrics.k8s.io/v1beta1` as failed.
+    #[derive(Clone)]
+    struct BrokenMetricsDiscover<D>(D);
+
+    impl<D: Discover> Discover for BrokenMetricsDiscover<D> {
+        async fn list_api_resources(&self) -> anyhow::Result<Vec<APIResource>> {
+            self.0.list_api_resources().await
+        }
+
+        async fn discover(&self) -> anyhow::Result<DiscoveryOutcome> {
+            let mut outcome = self.0.discover().await?;
+            outcome
+                .failed_groups
+                .push((String::from("metrics.k8s.io"), String::from("v1beta1")));
+            Ok(outcome)
+        }
+
+        async fn git_version(&self) -> Option<String> {
+            Some(String::from("v1.33.1"))
+        }
+    }
+
+    #[tokio::test]
+    async fn resolution_uses_every_method_of_a_wrapping_discover() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+        let discovery = BrokenMetricsDiscover(StaticDiscover(vec![api_resource(
+            "core", "v1", "pods", "Pod",
+        )]));
+        let options = ResolveOptions {
+            check_server_version: true,
+            ..Default::default()
+        };
+        let resolution = resolve_requested_resources_partial_with(
+            &discovery,
+            &[String::from("pods")],
+            Some(&path),
+            Duration::from_secs(60),
+            &options,
+        )
+        .await
+        .unwrap();
+        assert!(resolution.is_complete());
+        assert_eq!(resolution.failed_groups.len(), 1);
+        assert!(!path.exists(), "an incomplete discovery must not be cached");
+
+        // Served from the cache, which must have been written against the same version.
+        let discovery = BrokenMetricsDiscover(FailingDiscover);
+        save_discovery_cache(
+            &path,
+            &[api_resource("core", "v1", "pods", "Pod")],
+            Some("v1.33.1"),
+        )
+        .unwrap();
+        let resolved = resolve_requested_resources_with(
+            &discovery,
+            &[String::from("pods")],
+            Some(&path),
+            Duration::from_secs(60),
+            &options,
+        )
+        .await
+        .unwrap();
+        assert_eq!(resolved.len(), 1);
+    }
+
+    /// Serves [`discovery_response`] plus a `slow.example.com` group whose resources take
+    /// 500ms to list.
+    fn slow_group_response(request: &MockRequest) -> (u16, String) {
+        match request.path.as_str() {
+            "/apis" => {
+                let (_, body) = discovery_response(request);
+                let mut groups: serde_json::Value = serde_json::from_str(&body).unwrap();
+                let slow = serde_json::json!({
+                    "name": "slow.example.com",
+                    "versions": [{ "groupVersion": "slow.example.com/v1", "version": "v1" }],
+                    "preferredVersion": { "groupVersion": "slow.example.com/v1", "version": "v1" },
+                });
+                groups["groups"].as_array_mut().unwrap().push(slow);
+                (200, groups.to_string())
+            }
+            "/apis/slow.example.com/v1" => {
+                std::thread::sleep(Duration::from_millis(500));
+                let body = serde_json::json!({
+                    "kind": "APIResourceList",
+                    "groupVersion": "slow.example.com/v1",
+                    "resources": [],
+                });
+                (200, body.to_string())
+            }
+            _ => discovery_response(request),
+        }
+    }
+
+    #[tokio::test]
+    async fn request_timeout_fails_only_slow_groups() {
+        let server = MockServer::start(slow_group_response);
+        let outcome = DiscoverClient::new(server.client())
+            .with_timeout(Duration::from_millis(100))
+            .discover()
+            .await
+            .unwrap();
+        assert_eq!(
+            outcome.failed_groups,
+            [(String::from("slow.example.com"), String::from("v1"))]
+        );
+        assert!(!outcome.timed_out);
+        assert_eq!(outcome.resources.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn deadline_reports_a_partial_discovery_unless_strict() {
+        let server = MockServer::start(slow_group_response);
+        let outcome = DiscoverClient::new(server.client())
+            .with_deadline(Duration::from_millis(200))
+            .discover()
+            .await
+            .unwrap();
+        assert!(outcome.timed_out);
+        assert_eq!(
+            outcome.failed_groups,
+            [(String::from("slow.example.com"), String::from("v1"))]
+        );
+        assert_eq!(outcome.resources.len(), 4);
+
+        let err = DiscoverClient::new(server.client())
+            .with_strict_deadline(Duration::from_millis(200))
+            .discover()
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("did not finish within"), "{err}");
+
+        let outcome = DiscoverClient::new(server.client())
+            .with_deadline(Duration::from_secs(5))
+            .discover()
+            .await
+            .unwrap();
+        assert!(outcome.is_complete() && !outcome.timed_out);
+    }
+
+    #[tokio::test]
+    async fn has_resource_lists_only_the_target_group() {
+        let server = MockServer::start(discovery_response);
+        let discovery = DiscoverClient::new(server.client());
+        let paths = |server: &MockServer| {
+            server
+                .requests()
+                .into_iter()
+                .map(|request| request.path)
+                .collect::<Vec<_>>()
+        };
+
+        let found = discovery.has_resource("deployments.apps").await.unwrap();
+        assert_eq!(found.unwrap().name, "deployments");
+        assert_eq!(paths(&server), ["/apis/apps", "/apis/apps/v1"]);
+
+        let server = MockServer::start(discovery_response);
+        let discovery = DiscoverClient::new(server.client());
+        assert!(
+            discovery
+                .has_resource("deployments.v1.apps")
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(discovery.has_resource("po.core").await.unwrap().is_some());
+        assert!(
+            discovery
+                .has_resource("widgets.example.com")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            discovery
+                .has_resource("widgets.v1.apps")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(
+            paths(&server),
+            [
+                "/apis/apps/v1",
+                "/api",
+                "/api/v1",
+                "/apis/example.com",
+                "/apis/apps/v1"
+            ]
+        );
+
+        // Unqualified targets need a full discovery.
+        assert!(discovery.has_resource("svc").await.unwrap().is_some());
+        assert_eq!(server.count("/apis"), 1);
+    }
+
+    #[tokio::test]
+    async fn has_resource_honors_the_group_filter_and_the_cache() {
+        let server = MockServer::start(discovery_response);
+        let discovery = DiscoverClient::new(server.client())
+            .with_group_filter(GroupFilter::default().deny("apps"));
+        assert!(
+            discovery
+                .has_resource("deployments.apps")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(discovery.has_resource("deploy").await.unwrap().is_none());
+        assert_eq!(server.count("/apis/apps"), 0);
+        assert_eq!(server.count("/apis/apps/v1"), 0);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+        let widgets = api_resource("example.com", "v1", "widgets", "Widget");
+        write_cache_file(&path, vec![widgets.clone()], Duration::ZERO);
+        let server = MockServer::start(discovery_response);
+        let discovery =
+            DiscoverClient::new(server.client()).with_cache(&path, Duration::from_secs(60));
+        assert_eq!(
+            discovery.has_resource("widgets").await.unwrap(),
+            Some(widgets)
+        );
+        assert!(server.requests().is_empty());
+
+        // Targets missing from the cache are looked up in the cluster.
+        assert!(
+            discovery
+                .has_resource("pods.v1.core")
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert_eq!(server.count("/api/v1"), 1);
+
+        // The in-process cache answers from memory after the first load.
+        let server = MockServer::start(discovery_response);
+        let cache = DiscoveryCache::new(
+            DiscoverClient::new(server.client()),
+            None,
+            Duration::from_secs(60),
+        );
+        assert!(
+            cache
+                .has_resource("deployments.apps")
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(cache.has_resource("po").await.unwrap().is_some());
+        assert!(cache.has_resource("widgets").await.unwrap().is_none());
+        assert_eq!(server.count("/apis"), 1);
+    }
+
+    #[test]
+    fn group_filter_allows_and_denies_globs() {
+        let filter = GroupFilter::default();
+        assert!(filter.matches("core") && filter.matches("networking.istio.io"));
+
+        let filter = GroupFilter::default().deny("*.istio.io");
+        assert!(!filter.matches("networking.istio.io"));
+        assert!(filter.matches("istio.io"));
+        assert!(filter.matches("apps"));
+
+        let filter = GroupFilter::default()
+            .allow("core")
+            .allow("*.istio.io")
+            .deny("security.istio.io");
+        for (group, expected) in [
+            ("core", true),
+            ("networking.istio.io", true),
+            ("security.istio.io", false),
+            ("apps", false),
+        ] {
+            assert_eq!(filter.matches(group), expected, "{group}");
+        }
+
+        let mut pods = api_resource("", "v1", "pods", "Pod");
+        pods.group = None;
+        let resources = vec![
+            pods,
+            api_resource("apps", "v1", "deployments", "Deployment"),
+            api_resource("networking.istio.io", "v1", "gateways", "Gateway"),
+        ];
+        let names = |resources: Vec<APIResource>| {
+            resources
+                .into_iter()
+                .map(|resource| resource.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(filter.apply(resources.clone())), ["pods", "gateways"]);
+        assert_eq!(
+            names(GroupFilter::default().deny("core").apply(resources)),
+            ["deployments", "gateways"]
+        );
+    }
+
+    /// Serves discovery with the `networking.istio.io` and `security.istio.io` groups added.
+    fn istio_response(request: &MockRequest) -> (u16, String) {
+        let path = request.path.as_str();
+        if path == "/apis" {
+            let (_, body) = discovery_response(request);
+            let mut groups: serde_json::Value = serde_json::from_str(&body).unwrap();
+            for group in ["networking.istio.io", "security.istio.io"] {
+                groups["groups"]
+                    .as_array_mut()
+                    .unwrap()
+                    .push(serde_json::json!({
+                        "name": group,
+                        "versions": [{ "groupVersion": format!("{group}/v1"), "version": "v1" }],
+                        "preferredVersion": { "groupVersion": format!("{group}/v1"), "version": "v1" },
+                    }));
+            }
+            return (200, groups.to_string());
+        }
+        let Some(group) = path
+            .strip_prefix("/apis/")
+            .and_then(|path| path.strip_suffix("/v1"))
+            .filter(|group| group.ends_with(".istio.io"))
+        else {
+            return discovery_response(request);
+        };
+        let (name, kind) = match group {
+            "networking.istio.io" => ("gateways", "Gateway"),
+            _ => ("peerauthentications", "PeerAuthentication"),
+        };
+        let body = serde_json::json!({
+            "kind": "APIResourceList",
+            "groupVersion": format!("{group}/v1"),
+            "resources": [{
+                "name": name,
+                "singularName": "",
+                "kind": kind,
+                "namespaced": true,
+                "verbs": ["get", "list", "watch"],
+            }],
+        });
+        (200, body.to_string())
+    }
+
+    #[tokio::test]
+    async fn group_filter_skips_filtered_groups_without_requests() {
+        let server = MockServer::start(istio_response);
+        let groups = |resources: &[APIResource]| {
+            resources
+                .iter()
+                .map(|resource| resource.group.clone().unwrap())
+                .collect::<BTreeSet<_>>()
+        };
+        let resources = DiscoverClient::new(server.client())
+            .list_api_resources()
+            .await
+            .unwrap();
+        assert_eq!(
+            groups(&resources),
+            BTreeSet::from(
+                ["apps", "core", "networking.istio.io", "security.istio.io"].map(String::from)
+            )
+        );
+
+        let server = MockServer::start(istio_response);
+        let resources = DiscoverClient::new(server.client())
+            .with_group_filter(GroupFilter::default().deny("*.istio.io"))
+            .list_api_resources()
+            .await
+            .unwrap();
+        assert_eq!(
+            groups(&resources),
+            BTreeSet::from(["apps", "core"].map(String::from))
+        );
+        assert_eq!(server.count("/apis/networking.istio.io/v1"), 0);
+        assert_eq!(server.count("/apis/security.istio.io/v1"), 0);
+        assert_eq!(server.count("/apis/apps/v1"), 1);
+
+        let server = MockServer::start(istio_response);
+        let resources = DiscoverClient::new(server.client())
+            .with_group_filter(GroupFilter::default().allow("networking.istio.io"))
+            .list_api_resources()
+            .await
+            .unwrap();
+        assert_eq!(
+            groups(&resources),
+            BTreeSet::from(["networking.istio.io".to_string()])
+        );
+        let paths = server
+            .requests()
+            .into_iter()
+            .map(|request| request.path)
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["/apis", "/apis/networking.istio.io/v1"]);
+    }
+
+    #[tokio::test]
+    async fn group_filter_applies_to_cached_resources() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+        write_cache_file(
+            &path,
+            vec![
+                api_resource("apps", "v1", "deployments", "Deployment"),
+                api_resource("networking.istio.io", "v1", "gateways", "Gateway"),
+            ],
+            Duration::ZERO,
+        );
+        let server = MockServer::start(istio_response);
+        let options = ResolveOptions {
+            group_filter: GroupFilter::default().deny("*.istio.io"),
+            ..Default::default()
+        };
+        let resolve = |targets: &[&str], options: &ResolveOptions| {
+            let targets = targets.iter().map(ToString::to_string).collect::<Vec<_>>();
+            let (client, path, options) = (server.client(), path.clone(), options.clone());
+            async move {
+                resolve_requested_resources_partial(
+                    client,
+                    &targets,
+                    Some(&path),
+                    Duration::from_secs(60),
+                    &options,
+                )
+                .await
+                .unwrap()
+            }
+        };
+
+        let resolution = resolve(&["deployments"], &options).await;
+        assert_eq!(resolution.matched.len(), 1);
+        assert!(server.requests().is_empty());
+
+        // The filtered cache misses, and so does live discovery, without fetching the group.
+        let resolution = resolve(&["gateways"], &options).await;
+        assert_eq!(resolution.unresolved, ["gateways"]);
+        assert_eq!(server.count("/apis"), 1);
+        assert_eq!(server.count("/apis/networking.istio.io/v1"), 0);
+
+        // The filtered discovery cached only what passed the filter.
+        let resolution = resolve(&["gateways"], &ResolveOptions::default()).await;
+        assert!(resolution.is_complete());
+        assert_eq!(server.count("/apis"), 2);
+        assert_eq!(server.count("/apis/networking.istio.io/v1"), 1);
+
+        let discovery = DiscoverClient::new(server.client())
+            .with_cache(&path, Duration::from_secs(60))
+            .with_group_filter(GroupFilter::default().deny("*.istio.io"));
+        assert!(
+            discovery
+                .has_resource("deployments")
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            discovery
+                .has_resource("gateways.networking.istio.io")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(server.count("/apis"), 2);
+        assert_eq!(server.count("/apis/networking.istio.io"), 0);
+    }
+
+    #[test]
+    fn invalidate_discovery_cache_deletes_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+        write_cache_file(&path, Vec::new(), Duration::ZERO);
+        invalidate_discovery_cache(&path).unwrap();
+        assert!(!path.exists());
+        invalidate_discovery_cache(&path).unwrap();
+    }
+
+    #[test]
+    fn discovery_cache_file_name_sanitizes_and_hashes_context() {
+        let cases = [
+            ("prod/us-east", "prod_us-east-eb66f27d.json"),
+            ("prod_us-east", "prod_us-east-6d5b630d.json"),
+            (
+                "arn:aws:eks:eu-west-1:123456789012:cluster/prod",
+                "arn_aws_eks_eu-west-1_123456789012_cluster_prod-d83047e6.json",
+            ),
+        ];
+        for (context, expected) in cases {
+            assert_eq!(discovery_cache_file_name(context), expected, "{context}");
+            assert!(is_discovery_cache_file_name(expected), "{expected}");
+        }
+        assert_ne!(
+            discovery_cache_file_name("prod/us-east"),
+            discovery_cache_file_name("prod_us-east")
+        );
+    }
+
+    #[test]
+    fn default_discovery_cache_path_is_under_app_directory() {
+        let Some(path) = default_discovery_cache_path("kubex", "prod/us-east") else {
+            return;
+        };
+        assert!(
+            path.ends_with("kubex/discovery/prod_us-east-eb66f27d.json"),
+            "{path:?}"
+        );
     }
 }
@@ -2,7 +2,7 @@ use std::borrow::Cow;
 
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::APIResource;
 use kube::{
-    Resource,
+    Api, Client, Resource,
     api::{ObjectMeta, TypeMeta},
     core::DynamicResourceScope,
 };
@@ -80,3 +80,95 @@ impl Resource for DynamicObject {
         &mut self.metadata
     }
 }
+
+/// Builds the `Api` for `resource` in `namespace`, or in the default namespace of `client`
+/// if `namespace` is `None`.
+///
+/// Cluster-scoped resources take no namespace. To list a namespaced resource across every
+/// namespace, use [`crate::namespace::dynamic_api_for`] with [`crate::NamespaceSelection::All`].
+///
+/// # Errors
+/// Returns an error if `namespace` is given for a cluster-scoped resource.
+pub fn api_for(
+    client: Client,
+    resource: &APIResource,
+    namespace: Option<&str>,
+) -> anyhow::Result<Api<DynamicObject>> {
+    match (resource.namespaced, namespace) {
+        (true, Some(namespace)) => Ok(Api::namespaced_with(client, namespace, resource)),
+        (true, None) => Ok(Api::default_namespaced_with(client, resource)),
+        (false, None) => Ok(Api::all_with(client, resource)),
+        (false, Some(namespace)) => Err(anyhow::anyhow!(
+            "{} is cluster-scoped and cannot be used in namespace {namespace}",
+            resource.name
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{MockServer, api_resource};
+
+    fn client(server: &MockServer) -> Client {
+        let mut config = kube::Config::new(server.url().parse().unwrap());
+        config.default_namespace = String::from("team-a");
+        Client::try_from(config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn api_for_respects_the_scope_of_the_resource() {
+        let server = MockServer::start(|_| (404, String::new()));
+        let client = client(&server);
+        let deployments = api_resource("apps", "v1", "deployments", "Deployment");
+        let mut namespaces = api_resource("core", "v1", "namespaces", "Namespace");
+        namespaces.namespaced = false;
+
+        let url = |resource: &APIResource, namespace: Option<&str>| {
+            api_for(client.clone(), resource, namespace).map(|api| api.resource_url().to_string())
+        };
+        assert_eq!(
+            url(&deployments, Some("team-b")).unwrap(),
+            "/apis/apps/v1/namespaces/team-b/deployments"
+        );
+        assert_eq!(
+            url(&deployments, None).unwrap(),
+            "/apis/apps/v1/namespaces/team-a/deployments"
+        );
+        assert_eq!(url(&namespaces, None).unwrap(), "/api/v1/namespaces");
+        let err = url(&namespaces, Some("team-b")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "namespaces is cluster-scoped and cannot be used in namespace team-b"
+        );
+    }
+
+    #[tokio::test]
+    async fn dynamic_objects_are_listed_from_the_resource_path() {
+        let server = MockServer::start(|request| {
+            let body = serde_json::json!({
+                "apiVersion": "v1",
+                "kind": "PodList",
+                "metadata": {},
+                "items": [{
+                    "apiVersion": "v1",
+                    "kind": "Pod",
+                    "metadata": { "name": "web", "namespace": "team-a" },
+                    "spec": { "nodeName": request.path },
+                }],
+            });
+            (200, body.to_string())
+        });
+        let pods = api_resource("core", "v1", "pods", "Pod");
+        assert_eq!(DynamicObject::api_version(&pods), "v1");
+        let api = api_for(client(&server), &pods, None).unwrap();
+        let list = api.list(&Default::default()).await.unwrap();
+        let pod = &list.items[0];
+        assert_eq!(pod.metadata.name.as_deref(), Some("web"));
+        assert_eq!(pod.types.as_ref().unwrap().kind, "Pod");
+        assert_eq!(
+            pod.data["spec"]["nodeName"],
+            "/api/v1/namespaces/team-a/pods"
+        );
+    }
+}
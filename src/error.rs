@@ -0,0 +1,213 @@
+use std::{io, path::PathBuf};
+
+use kube::config::KubeconfigError;
+
+use crate::UnresolvedTargets;
+
+pub(crate) type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Errors returned by the context helpers, resource resolution and the discovery cache.
+///
+/// It converts into `anyhow::Error` like any other error, so `?` keeps working for callers using anyhow.
+/// Underlying errors are exposed as [`std::error::Error::source`] rather than repeated in the
+/// message, so print the whole chain, e.g. with anyhow's `{:#}`, to see them.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("failed to read kubeconfig")]
+    KubeconfigRead(#[from] KubeconfigError),
+    #[error("kubeconfig contains no contexts")]
+    NoContexts,
+    #[error("current_context is not set")]
+    NoCurrentContext,
+    /// The context is not defined in the kubeconfig, which defines `available` instead.
+    #[error(
+        "context {name} is not found in kubeconfig{}",
+        suggestion(name, available)
+    )]
+    ContextNotFound {
+        name: String,
+        available: Vec<String>,
+    },
+    /// A short name is claimed by several groups and strict resolution refused to pick one.
+    #[error("short name {short_name} is ambiguous: {}", candidates.join(", "))]
+    AmbiguousShortName {
+        short_name: String,
+        /// Group-qualified names of the candidates, in order of preference.
+        candidates: Vec<String>,
+    },
+    #[error(transparent)]
+    Unresolved(#[from] UnresolvedTargets),
+    #[error("discovery failed")]
+    Discovery(#[source] BoxError),
+    /// [`crate::discover::CacheStrategy::CacheOnly`] was requested without a usable cache.
+    #[error("discovery cache is not available")]
+    CacheUnavailable,
+    #[error("failed to use discovery cache {}", path.display())]
+    Cache {
+        path: PathBuf,
+        #[source]
+        source: BoxError,
+    },
+}
+
+impl Error {
+    pub(crate) fn discovery(err: anyhow::Error) -> Self {
+        Self::Discovery(err.into())
+    }
+
+    pub(crate) fn cache(path: impl Into<PathBuf>, err: impl Into<BoxError>) -> Self {
+        Self::Cache {
+            path: path.into(),
+            source: err.into(),
+        }
+    }
+
+    pub fn is_kubeconfig_read(&self) -> bool {
+        matches!(self, Self::KubeconfigRead(_))
+    }
+
+    pub fn is_no_current_context(&self) -> bool {
+        matches!(self, Self::NoCurrentContext)
+    }
+
+    pub fn is_context_not_found(&self) -> bool {
+        matches!(self, Self::ContextNotFound { .. })
+    }
+
+    pub fn is_unresolved(&self) -> bool {
+        matches!(self, Self::Unresolved(_))
+    }
+
+    pub fn is_discovery(&self) -> bool {
+        matches!(self, Self::Discovery(_))
+    }
+
+    /// Checks if this is a discovery cache error, including [`Error::CacheUnavailable`].
+    pub fn is_cache(&self) -> bool {
+        matches!(self, Self::Cache { .. } | Self::CacheUnavailable)
+    }
+
+    /// Checks if this is a discovery cache error caused by a missing file.
+    pub fn is_cache_not_found(&self) -> bool {
+        match self {
+            Self::Cache { source, .. } => source
+                .downcast_ref::<io::Error>()
+                .is_some_and(|err| err.kind() == io::ErrorKind::NotFound),
+            _ => false,
+        }
+    }
+}
+
+/// Number of suggestions listed when a context does not exist.
+const MAX_CONTEXT_SUGGESTIONS: usize = 3;
+
+fn suggestion(name: &str, available: &[String]) -> String {
+    if available.is_empty() {
+        return " (no contexts are defined)".to_string();
+    }
+    let suggestions = crate::suggest_names(
+        name,
+        available.iter().map(String::as_str),
+        MAX_CONTEXT_SUGGESTIONS,
+    );
+    if suggestions.is_empty() {
+        String::new()
+    } else {
+        format!(" (did you mean: {}?)", suggestions.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the messages of `err` and its sources, outermost first.
+    fn chain(err: &(dyn std::error::Error + 'static)) -> Vec<String> {
+        std::iter::successors(Some(err), |err| err.source())
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn sources_are_not_repeated_in_messages() {
+        let errors = [
+            Error::from(KubeconfigError::FindPath),
+            Error::discovery(anyhow::anyhow!("503 Service Unavailable")),
+            Error::cache("/tmp/cache.json", io::Error::other("disk full")),
+        ];
+        for err in errors {
+            let messages = chain(&err);
+            assert!(messages.len() > 1, "{messages:?}");
+            for (i, message) in messages.iter().enumerate() {
+                for inner in &messages[i + 1..] {
+                    assert!(!message.contains(inner.as_str()), "{messages:?}");
+                }
+            }
+            let rendered = format!("{:#}", anyhow::Error::from(err));
+            let innermost = messages.last().unwrap();
+            assert_eq!(
+                rendered.matches(innermost.as_str()).count(),
+                1,
+                "{rendered}"
+            );
+        }
+    }
+
+    #[test]
+    fn predicates_tell_context_failures_apart() {
+        use crate::{determine_context, determine_context_strict, testutil::KubeconfigFixture};
+
+        let predicates = |err: &Error| {
+            [
+                err.is_kubeconfig_read(),
+                err.is_no_current_context(),
+                err.is_context_not_found(),
+                err.is_unresolved(),
+                err.is_discovery(),
+                err.is_cache(),
+            ]
+        };
+
+        let guard = KubeconfigFixture::new()
+            .context("prod", "prod-cluster", "admin")
+            .install()
+            .unwrap();
+        let err = determine_context(&None).unwrap_err();
+        assert_eq!(
+            predicates(&err),
+            [false, true, false, false, false, false],
+            "{err}"
+        );
+        let err = determine_context_strict(&Some(String::from("staging"))).unwrap_err();
+        assert_eq!(
+            predicates(&err),
+            [false, false, true, false, false, false],
+            "{err}"
+        );
+
+        std::fs::write(&guard.paths()[0], "contexts: [").unwrap();
+        let err = determine_context(&None).unwrap_err();
+        assert_eq!(
+            predicates(&err),
+            [true, false, false, false, false, false],
+            "{err}"
+        );
+
+        let err = Error::cache(
+            "/tmp/kubex-missing.json",
+            io::Error::from(io::ErrorKind::NotFound),
+        );
+        assert_eq!(
+            predicates(&err),
+            [false, false, false, false, false, true],
+            "{err}"
+        );
+        assert!(err.is_cache_not_found());
+        assert!(
+            Error::CacheUnavailable.is_cache() && !Error::CacheUnavailable.is_cache_not_found()
+        );
+    }
+}
@@ -0,0 +1,162 @@
+/// Namespaces protected by every [`NamespaceGuard`].
+pub const DEFAULT_PROTECTED_NAMESPACES: &[&str] = &["kube-system", "kube-public"];
+
+/// Safety rail refusing destructive operations, such as deletes and patches,
+/// in protected namespaces unless explicitly overridden.
+///
+/// Protected namespaces are [`DEFAULT_PROTECTED_NAMESPACES`] and the given patterns,
+/// in which `*` matches any sequence, e.g. `kube-*`.
+///
+/// Clients do not enforce the guard: call [`Self::check`] before each destructive operation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NamespaceGuard {
+    protected: Vec<String>,
+    allow_protected: bool,
+}
+
+/// Error returned by [`NamespaceGuard::check`] for an operation in a protected namespace.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("refusing to {operation} in protected namespace {namespace} (matches {pattern})")]
+pub struct GuardError {
+    pub namespace: String,
+    pub operation: String,
+    /// Protected pattern the namespace matched.
+    pub pattern: String,
+}
+
+impl Default for NamespaceGuard {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl NamespaceGuard {
+    /// Creates a guard protecting [`DEFAULT_PROTECTED_NAMESPACES`] and `protected`.
+    pub fn new(protected: Vec<String>) -> Self {
+        Self {
+            protected: DEFAULT_PROTECTED_NAMESPACES
+                .iter()
+                .map(|namespace| namespace.to_string())
+                .chain(protected)
+                .collect(),
+            allow_protected: false,
+        }
+    }
+
+    /// Allows operations in protected namespaces if `allow` is true,
+    /// e.g. from [`crate::claputil::force_protected_namespaces_from_matches`].
+    pub fn allow_protected(mut self, allow: bool) -> Self {
+        self.allow_protected = allow;
+        self
+    }
+
+    /// Returns the protected pattern `namespace` matches, if any, regardless of the override.
+    pub fn protected_pattern(&self, namespace: &str) -> Option<&str> {
+        self.protected
+            .iter()
+            .find(|pattern| crate::glob_match(pattern, namespace))
+            .map(String::as_str)
+    }
+
+    /// Checks if `operation`, e.g. "delete pods", may run in `namespace`.
+    ///
+    /// # Errors
+    /// Returns [`GuardError`] if `namespace` is protected and the override is not set.
+    pub fn check(&self, namespace: &str, operation: &str) -> Result<(), GuardError> {
+        if self.allow_protected {
+            return Ok(());
+        }
+        match self.protected_pattern(namespace) {
+            Some(pattern) => Err(GuardError {
+                namespace: namespace.to_string(),
+                operation: operation.to_string(),
+                pattern: pattern.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Command;
+
+    use super::*;
+    use crate::{
+        claputil::{force_protected_namespaces_arg, force_protected_namespaces_from_matches},
+        testutil::KubeconfigFixture,
+    };
+
+    #[test]
+    fn check_allows_unprotected_namespaces_only() {
+        let guard = NamespaceGuard::default();
+        assert_eq!(guard.check("default", "delete pods"), Ok(()));
+        assert_eq!(guard.check("kube-system-backup", "delete pods"), Ok(()));
+        assert_eq!(
+            guard.check("kube-system", "delete pods"),
+            Err(GuardError {
+                namespace: String::from("kube-system"),
+                operation: String::from("delete pods"),
+                pattern: String::from("kube-system"),
+            })
+        );
+        let err = guard.check("kube-public", "patch configmaps").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "refusing to patch configmaps in protected namespace kube-public (matches kube-public)"
+        );
+    }
+
+    #[test]
+    fn check_matches_the_given_patterns() {
+        let guard = NamespaceGuard::new(vec![String::from("kube-*"), String::from("*-prod")]);
+        // The defaults come first, so they are reported for the namespaces they match.
+        assert_eq!(guard.protected_pattern("kube-system"), Some("kube-system"));
+        assert_eq!(guard.protected_pattern("kube-node-lease"), Some("kube-*"));
+        assert_eq!(guard.protected_pattern("payments-prod"), Some("*-prod"));
+        assert_eq!(guard.protected_pattern("payments-staging"), None);
+        assert!(guard.check("payments-prod", "delete pods").is_err());
+        assert_eq!(guard.check("payments-staging", "delete pods"), Ok(()));
+    }
+
+    #[test]
+    fn check_is_overridden_by_the_force_flag() {
+        let command = Command::new("kubex").arg(force_protected_namespaces_arg());
+        let guard = |args: &[&str]| {
+            let matches = command.clone().try_get_matches_from(args).unwrap();
+            NamespaceGuard::new(vec![String::from("*-prod")])
+                .allow_protected(force_protected_namespaces_from_matches(&matches))
+        };
+
+        let guarded = guard(&["kubex"]);
+        assert!(guarded.check("kube-system", "delete pods").is_err());
+        assert!(guarded.check("payments-prod", "delete pods").is_err());
+
+        let forced = guard(&["kubex", "--force-protected-namespaces"]);
+        assert_eq!(forced.check("kube-system", "delete pods"), Ok(()));
+        assert_eq!(forced.check("payments-prod", "delete pods"), Ok(()));
+        // The override does not hide which pattern a namespace matches.
+        assert_eq!(forced.protected_pattern("payments-prod"), Some("*-prod"));
+    }
+
+    #[test]
+    fn check_applies_to_the_namespace_of_the_context() {
+        let _guard = KubeconfigFixture::new()
+            .context("admin", "prod-cluster", "admin")
+            .namespace("kube-system")
+            .context("dev", "dev-cluster", "dev-user")
+            .namespace("team-a")
+            .install()
+            .unwrap();
+        let guard = NamespaceGuard::default();
+
+        let namespace = crate::determine_namespace(None, "admin");
+        assert_eq!(namespace, "kube-system");
+        assert!(guard.check(&namespace, "delete pods").is_err());
+        let namespace = crate::determine_namespace(None, "dev");
+        assert_eq!(guard.check(&namespace, "delete pods"), Ok(()));
+        // An explicit namespace takes precedence over the context's.
+        let namespace = crate::determine_namespace(Some(String::from("kube-public")), "dev");
+        assert!(guard.check(&namespace, "delete pods").is_err());
+    }
+}
@@ -0,0 +1,190 @@
+use std::{fmt, str::FromStr};
+
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::APIResource;
+use kube::api::GroupVersionKind;
+
+/// Group, version and kind in their string forms, e.g. `apps/v1/Deployment`.
+///
+/// Parsing accepts the slash form `GROUP/VERSION/KIND` and kubectl's dotted form
+/// `KIND.VERSION.GROUP`. The core group is empty and omitted from both forms,
+/// i.e. `v1/Pod` and `Pod.v1`. [`Display`](fmt::Display) prints the slash form.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Gvk(pub GroupVersionKind);
+
+impl Gvk {
+    pub fn new(group: &str, version: &str, kind: &str) -> Self {
+        Self(GroupVersionKind::gvk(group, version, kind))
+    }
+
+    /// Returns the group, version and kind of `resource`, with an empty group for the core group.
+    ///
+    /// # Errors
+    /// Returns an error if the version of `resource` is unknown.
+    pub fn from_api_resource(resource: &APIResource) -> anyhow::Result<Self> {
+        let version = resource
+            .version
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("version of {} is unknown", resource.name))?;
+        Ok(Self::new(
+            api_resource_group(resource),
+            version,
+            &resource.kind,
+        ))
+    }
+
+    /// Checks if `resource` has this group, version and kind.
+    pub fn matches(&self, resource: &APIResource) -> bool {
+        api_resource_group(resource) == self.0.group
+            && resource.version.as_deref() == Some(self.0.version.as_str())
+            && resource.kind == self.0.kind
+    }
+
+    pub fn into_inner(self) -> GroupVersionKind {
+        self.0
+    }
+}
+
+/// Returns the group of `resource`, which is empty for the core group.
+fn api_resource_group(resource: &APIResource) -> &str {
+    match resource.group.as_deref() {
+        Some("core") | None => "",
+        Some(group) => group,
+    }
+}
+
+impl From<GroupVersionKind> for Gvk {
+    fn from(gvk: GroupVersionKind) -> Self {
+        Self(gvk)
+    }
+}
+
+impl From<Gvk> for GroupVersionKind {
+    fn from(gvk: Gvk) -> Self {
+        gvk.0
+    }
+}
+
+impl fmt::Display for Gvk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.0.group.is_empty() {
+            write!(f, "{}/", self.0.group)?;
+        }
+        write!(f, "{}/{}", self.0.version, self.0.kind)
+    }
+}
+
+impl FromStr for Gvk {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        // NOTE: The core group is omitted rather than empty, so an empty group is invalid.
+        let parts = if s.contains('/') {
+            match s.split('/').collect::<Vec<_>>().as_slice() {
+                [version, kind] => Some(("", *version, *kind)),
+                [group, version, kind] if !group.is_empty() => Some((*group, *version, *kind)),
+                _ => None,
+            }
+        } else {
+            match s.splitn(3, '.').collect::<Vec<_>>().as_slice() {
+                [kind, version] => Some(("", *version, *kind)),
+                [kind, version, group] if !group.is_empty() => Some((*group, *version, *kind)),
+                _ => None,
+            }
+        };
+        match parts {
+            Some((group, version, kind)) if !version.is_empty() && !kind.is_empty() => {
+                Ok(Self::new(group, version, kind))
+            }
+            _ => Err(anyhow::anyhow!(
+                "invalid group/version/kind {s}: expected GROUP/VERSION/KIND or KIND.VERSION.GROUP"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::api_resource;
+
+    #[test]
+    fn gvk_parses_slash_and_dotted_forms() {
+        let cases = [
+            ("apps/v1/Deployment", Gvk::new("apps", "v1", "Deployment")),
+            ("v1/Pod", Gvk::new("", "v1", "Pod")),
+            (
+                "cert-manager.io/v1/Certificate",
+                Gvk::new("cert-manager.io", "v1", "Certificate"),
+            ),
+            ("Deployment.v1.apps", Gvk::new("apps", "v1", "Deployment")),
+            ("Pod.v1", Gvk::new("", "v1", "Pod")),
+            (
+                "Certificate.v1.cert-manager.io",
+                Gvk::new("cert-manager.io", "v1", "Certificate"),
+            ),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(s.parse::<Gvk>().unwrap(), expected, "{s}");
+        }
+    }
+
+    #[test]
+    fn gvk_rejects_incomplete_forms() {
+        for s in [
+            "",
+            "Pod",
+            "/v1/Pod",
+            "apps//Deployment",
+            "apps/v1/",
+            "a/b/c/d",
+            "Pod.",
+            ".v1",
+            "Deployment.v1.",
+        ] {
+            let err = s.parse::<Gvk>().unwrap_err();
+            assert!(
+                err.to_string().starts_with("invalid group/version/kind"),
+                "{s}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn gvk_displays_the_slash_form() {
+        for s in [
+            "apps/v1/Deployment",
+            "v1/Pod",
+            "cert-manager.io/v1/Certificate",
+        ] {
+            assert_eq!(s.parse::<Gvk>().unwrap().to_string(), s);
+        }
+        assert_eq!(
+            "Deployment.v1.apps".parse::<Gvk>().unwrap().to_string(),
+            "apps/v1/Deployment"
+        );
+    }
+
+    #[test]
+    fn gvk_matches_api_resources() {
+        let deployments = api_resource("apps", "v1", "deployments", "Deployment");
+        let pods = api_resource("core", "v1", "pods", "Pod");
+        let gvk = Gvk::from_api_resource(&deployments).unwrap();
+        assert_eq!(gvk, Gvk::new("apps", "v1", "Deployment"));
+        assert!(gvk.matches(&deployments));
+        assert!(!gvk.matches(&pods));
+        assert!(!Gvk::new("apps", "v1beta1", "Deployment").matches(&deployments));
+
+        let core = Gvk::from_api_resource(&pods).unwrap();
+        assert_eq!(core.to_string(), "v1/Pod");
+        let mut bare = pods.clone();
+        bare.group = None;
+        assert!(core.matches(&pods) && core.matches(&bare));
+
+        bare.version = None;
+        let err = Gvk::from_api_resource(&bare).unwrap_err();
+        assert_eq!(err.to_string(), "version of pods is unknown");
+
+        let inner: GroupVersionKind = core.clone().into();
+        assert_eq!(Gvk::from(inner), core);
+    }
+}
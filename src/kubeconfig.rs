@@ -0,0 +1,1389 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process, thread,
+    time::{Duration, Instant},
+};
+
+use kube::config::{Kubeconfig, KubeconfigError};
+
+/// Returns the kubeconfig files that make up the merged kubeconfig, in merge order.
+///
+/// These are the existing files listed in `KUBECONFIG`, or `~/.kube/config` if `KUBECONFIG`
+/// is unset or empty. Like kubectl, files listed in `KUBECONFIG` that do not exist are skipped.
+pub fn kubeconfig_sources() -> Vec<PathBuf> {
+    let listed = listed_kubeconfig_paths();
+    if listed.is_empty() {
+        return default_kubeconfig_path().into_iter().collect();
+    }
+    listed.into_iter().filter(|path| path.exists()).collect()
+}
+
+/// Reads the kubeconfig as kubectl does, merging every file of [`kubeconfig_sources`].
+///
+/// The first file to define a cluster, user or context of a given name wins, and the
+/// current context comes from the first file that sets one.
+///
+/// # Errors
+/// Returns an error if a file cannot be read or parsed, or if `KUBECONFIG` is unset
+/// and the home directory cannot be determined.
+pub fn read_merged_kubeconfig() -> Result<Kubeconfig, KubeconfigError> {
+    let listed = listed_kubeconfig_paths();
+    if listed.is_empty() {
+        return Kubeconfig::read_from(default_kubeconfig_path().ok_or(KubeconfigError::FindPath)?);
+    }
+    let existing: Vec<PathBuf> = listed.into_iter().filter(|path| path.exists()).collect();
+    read_kubeconfig_files(&existing)
+}
+
+/// Reads and merges the kubeconfig files at `paths` in the same way as
+/// [`read_merged_kubeconfig`] merges the files of `KUBECONFIG`, e.g. those given by
+/// repeated `--kubeconfig` options.
+///
+/// # Errors
+/// Returns an error if a file cannot be read or parsed. Unlike files listed in `KUBECONFIG`,
+/// missing files are not skipped.
+pub fn read_kubeconfig_files(paths: &[PathBuf]) -> Result<Kubeconfig, KubeconfigError> {
+    paths
+        .iter()
+        .try_fold(Kubeconfig::default(), |merged, path| {
+            Kubeconfig::read_from(path).and_then(|next| merged.merge(next))
+        })
+}
+
+/// Returns the non-empty paths listed in `KUBECONFIG`.
+fn listed_kubeconfig_paths() -> Vec<PathBuf> {
+    env::var_os("KUBECONFIG")
+        .map(|value| {
+            env::split_paths(&value)
+                .filter(|path| !path.as_os_str().is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn default_kubeconfig_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".kube").join("config"))
+}
+
+/// Delay after a change during which further changes are coalesced into one event.
+#[cfg(feature = "watch")]
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Event emitted by [`watch_kubeconfig`].
+#[cfg(feature = "watch")]
+#[cfg_attr(docsrs, doc(cfg(feature = "watch")))]
+#[derive(Debug)]
+pub enum KubeconfigEvent {
+    /// A kubeconfig file changed; holds the merged kubeconfig read after the change.
+    Changed(Kubeconfig),
+    /// A kubeconfig file changed but the merged kubeconfig cannot be read,
+    /// e.g. while an editor is halfway through saving it.
+    Malformed(KubeconfigError),
+}
+
+/// Watches every file contributing to the merged kubeconfig, emitting an event with the
+/// re-read kubeconfig whenever one of them changes.
+///
+/// The files are those of `KUBECONFIG`, including listed files that do not exist yet,
+/// or `~/.kube/config`. Their directories are watched rather than the files themselves,
+/// so that editors replacing a file by renaming are noticed, and changes within
+/// a short delay are coalesced into a single event. Watching stops when the stream is dropped.
+///
+/// # Errors
+/// Returns an error if no kubeconfig file can be determined or the watcher cannot be set up.
+#[cfg(feature = "watch")]
+#[cfg_attr(docsrs, doc(cfg(feature = "watch")))]
+pub fn watch_kubeconfig() -> anyhow::Result<impl futures::Stream<Item = KubeconfigEvent>> {
+    use notify::Watcher as _;
+
+    let mut files = listed_kubeconfig_paths();
+    if files.is_empty() {
+        files.extend(default_kubeconfig_path());
+    }
+    if files.is_empty() {
+        return Err(anyhow::anyhow!("no kubeconfig file to watch"));
+    }
+    // NOTE: Paths reported by notify are absolute, so compare absolute paths.
+    let files: Vec<PathBuf> = files
+        .into_iter()
+        .map(|file| std::path::absolute(&file).unwrap_or(file))
+        .collect();
+
+    let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+    let watched = files.clone();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event
+            && !event.kind.is_access()
+            && event.paths.iter().any(|path| watched.contains(path))
+        {
+            let _ = sender.send(());
+        }
+    })?;
+    let mut directories: Vec<&Path> = files.iter().filter_map(|file| file.parent()).collect();
+    directories.sort();
+    directories.dedup();
+    for directory in directories {
+        // NOTE: A missing directory cannot be watched; files created there later are missed.
+        if directory.is_dir() {
+            watcher.watch(directory, notify::RecursiveMode::NonRecursive)?;
+        }
+    }
+
+    Ok(futures::stream::unfold(
+        (watcher, receiver),
+        |(watcher, mut receiver)| async move {
+            receiver.recv().await?;
+            // Coalesce the burst of events an editor produces while saving.
+            while let Ok(Some(())) = tokio::time::timeout(WATCH_DEBOUNCE, receiver.recv()).await {}
+            let event = match read_merged_kubeconfig() {
+                Ok(kubeconfig) => KubeconfigEvent::Changed(kubeconfig),
+                Err(err) => KubeconfigEvent::Malformed(err),
+            };
+            Some((event, (watcher, receiver)))
+        },
+    ))
+}
+
+/// A context defined in the kubeconfig.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContextEntry {
+    pub name: String,
+    pub cluster: Option<String>,
+    pub namespace: Option<String>,
+    pub user: Option<String>,
+    /// Set if this is the kubeconfig's current context.
+    pub is_current: bool,
+}
+
+/// Lists the contexts of the kubeconfig (honoring `KUBECONFIG`), in kubeconfig order.
+///
+/// # Errors
+/// Returns an error if the kubeconfig cannot be read.
+pub fn list_contexts() -> anyhow::Result<Vec<ContextEntry>> {
+    Ok(context_entries(&read_merged_kubeconfig()?))
+}
+
+/// Same as [`list_contexts`], but reads the kubeconfig at `path`.
+///
+/// # Errors
+/// Returns an error if the kubeconfig cannot be read.
+pub fn list_contexts_from(path: impl AsRef<Path>) -> anyhow::Result<Vec<ContextEntry>> {
+    Ok(context_entries(&Kubeconfig::read_from(path)?))
+}
+
+/// Lists the contexts of `kubeconfig`, in kubeconfig order.
+pub fn context_entries(kubeconfig: &Kubeconfig) -> Vec<ContextEntry> {
+    kubeconfig
+        .contexts
+        .iter()
+        .map(|named_context| {
+            let context = named_context.context.as_ref();
+            ContextEntry {
+                name: named_context.name.clone(),
+                cluster: context.map(|ctx| ctx.cluster.clone()),
+                namespace: context.and_then(|ctx| ctx.namespace.clone()),
+                user: context.and_then(|ctx| ctx.user.clone()),
+                is_current: kubeconfig.current_context.as_deref() == Some(&named_context.name),
+            }
+        })
+        .collect()
+}
+
+/// Lists the names of the contexts matching the glob `pattern` (e.g. "prod-*"),
+/// in kubeconfig order. `*` matches any sequence; other characters match literally.
+///
+/// # Errors
+/// Returns an error if the kubeconfig cannot be read.
+pub fn contexts_matching(pattern: &str) -> anyhow::Result<Vec<String>> {
+    Ok(read_merged_kubeconfig()?
+        .contexts
+        .into_iter()
+        .map(|context| context.name)
+        .filter(|name| crate::glob_match(pattern, name))
+        .collect())
+}
+
+/// Returns up to `limit` context names of `kubeconfig` resembling `input`, best first,
+/// e.g. `production` and `production-eu` for a mistyped `produciton`.
+///
+/// Contexts starting with `input` rank first, then contexts containing it, then contexts
+/// within a few edits of it (or of their beginning). Matching ignores case.
+/// Contexts resembling none of these ways are not suggested.
+pub fn suggest_contexts(input: &str, kubeconfig: &Kubeconfig, limit: usize) -> Vec<String> {
+    crate::suggest_names(
+        input,
+        kubeconfig
+            .contexts
+            .iter()
+            .map(|context| context.name.as_str()),
+        limit,
+    )
+}
+
+/// Moves the current context to the front, keeping the order of the others.
+pub fn sorted_current_first(mut entries: Vec<ContextEntry>) -> Vec<ContextEntry> {
+    entries.sort_by_key(|entry| !entry.is_current);
+    entries
+}
+
+/// A cluster defined in the kubeconfig, without any credentials.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClusterEntry {
+    pub name: String,
+    /// API server URL.
+    pub server: Option<String>,
+    /// Set if the server's certificate is not verified.
+    pub insecure_skip_tls_verify: bool,
+    /// Set if a certificate authority is configured, either as a file or inline.
+    pub has_certificate_authority: bool,
+    /// Set if requests go through a proxy (`proxy-url`).
+    pub has_proxy_url: bool,
+}
+
+/// Lists the clusters of the kubeconfig (honoring `KUBECONFIG`), in kubeconfig order.
+///
+/// # Errors
+/// Returns an error if the kubeconfig cannot be read.
+pub fn list_clusters() -> anyhow::Result<Vec<ClusterEntry>> {
+    Ok(cluster_entries(&read_merged_kubeconfig()?))
+}
+
+/// Lists the clusters of `kubeconfig`, in kubeconfig order.
+pub fn cluster_entries(kubeconfig: &Kubeconfig) -> Vec<ClusterEntry> {
+    kubeconfig
+        .clusters
+        .iter()
+        .map(|named_cluster| {
+            let cluster = named_cluster.cluster.as_ref();
+            ClusterEntry {
+                name: named_cluster.name.clone(),
+                server: cluster.and_then(|c| c.server.clone()),
+                insecure_skip_tls_verify: cluster
+                    .and_then(|c| c.insecure_skip_tls_verify)
+                    .unwrap_or(false),
+                has_certificate_authority: cluster.is_some_and(|c| {
+                    c.certificate_authority.is_some() || c.certificate_authority_data.is_some()
+                }),
+                has_proxy_url: cluster.is_some_and(|c| c.proxy_url.is_some()),
+            }
+        })
+        .collect()
+}
+
+/// Returns the cluster that `context` refers to in the kubeconfig (honoring `KUBECONFIG`).
+///
+/// # Errors
+/// Returns an error if the kubeconfig cannot be read, or if the context or its cluster is not defined.
+pub fn cluster_for_context(context: &str) -> anyhow::Result<ClusterEntry> {
+    find_cluster_for_context(&read_merged_kubeconfig()?, context)
+}
+
+/// Same as [`cluster_for_context`], but looks `context` up in an already loaded `kubeconfig`.
+///
+/// # Errors
+/// Returns an error if the context or its cluster is not defined.
+pub fn find_cluster_for_context(
+    kubeconfig: &Kubeconfig,
+    context: &str,
+) -> anyhow::Result<ClusterEntry> {
+    let named_context = kubeconfig
+        .contexts
+        .iter()
+        .find(|c| c.name == context)
+        .ok_or_else(|| anyhow::anyhow!("context {context} is not found in kubeconfig"))?;
+    let cluster_name = named_context
+        .context
+        .as_ref()
+        .map(|ctx| ctx.cluster.as_str())
+        .ok_or_else(|| anyhow::anyhow!("context {context} does not refer to a cluster"))?;
+    cluster_entries(kubeconfig)
+        .into_iter()
+        .find(|cluster| cluster.name == cluster_name)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "cluster {cluster_name} referenced by context {context} is not found in kubeconfig"
+            )
+        })
+}
+
+/// Returns the API server URL of the cluster that `context` refers to
+/// in the kubeconfig (honoring `KUBECONFIG`).
+///
+/// # Errors
+/// Returns an error if the kubeconfig cannot be read, if the context or its cluster
+/// is not defined, or if the cluster has no server URL.
+pub fn cluster_server_url(context: &str) -> anyhow::Result<String> {
+    server_url_of(&read_merged_kubeconfig()?, context)
+}
+
+/// Same as [`cluster_server_url`], but reads the kubeconfig at `path`.
+///
+/// # Errors
+/// See [`cluster_server_url`].
+pub fn cluster_server_url_from(path: impl AsRef<Path>, context: &str) -> anyhow::Result<String> {
+    server_url_of(&Kubeconfig::read_from(path)?, context)
+}
+
+fn server_url_of(kubeconfig: &Kubeconfig, context: &str) -> anyhow::Result<String> {
+    let cluster = find_cluster_for_context(kubeconfig, context)?;
+    cluster
+        .server
+        .ok_or_else(|| anyhow::anyhow!("cluster {} has no server URL", cluster.name))
+}
+
+/// Checks if `url` likely points at a cluster running on the local machine,
+/// such as kind, minikube or Docker Desktop.
+///
+/// Loopback addresses, `localhost`, `*.local`, `*.localhost`, `host.docker.internal`
+/// and minikube's default VM networks (`192.168.49.0/24`, `192.168.99.0/24` on port 8443)
+/// are considered local. This is a heuristic and must not be relied upon for security.
+pub fn is_local_cluster(url: &str) -> bool {
+    let Ok(uri) = url.parse::<http::Uri>() else {
+        return false;
+    };
+    let Some(host) = uri.host() else {
+        return false;
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = host.parse::<std::net::IpAddr>() {
+        let minikube = match ip {
+            std::net::IpAddr::V4(ip) => {
+                let [a, b, c, _] = ip.octets();
+                a == 192 && b == 168 && (c == 49 || c == 99) && uri.port_u16() == Some(8443)
+            }
+            std::net::IpAddr::V6(_) => false,
+        };
+        return ip.is_loopback() || minikube;
+    }
+    let host = host.to_ascii_lowercase();
+    host == "localhost"
+        || host == "host.docker.internal"
+        || host.ends_with(".local")
+        || host.ends_with(".localhost")
+}
+
+/// Switches the current context to `name`, persisting it to the kubeconfig like
+/// `kubectl config use-context`.
+///
+/// With several files in `KUBECONFIG`, the file that sets `current-context` is edited,
+/// or the first file if none does. See [`set_current_context_in`] for how the file is written.
+///
+/// # Errors
+/// Returns an error if the kubeconfig cannot be read or written, or if `name` is not a context.
+pub fn set_current_context(name: &str) -> anyhow::Result<()> {
+    let kubeconfig = read_merged_kubeconfig()?;
+    ensure_context_defined(&kubeconfig, name)?;
+    let sources = kubeconfig_sources();
+    let target = sources
+        .iter()
+        .find(|path| {
+            Kubeconfig::read_from(path).is_ok_and(|config| config.current_context.is_some())
+        })
+        .or(sources.first())
+        .ok_or_else(|| anyhow::anyhow!("no kubeconfig file to write"))?;
+    write_current_context(target, name)
+}
+
+/// Switches the current context of the kubeconfig at `path` to `name`.
+///
+/// Only `current-context` is rewritten; other fields, including unknown ones, are kept.
+/// Comments and formatting are kept too, unless the file is laid out in a way that cannot be
+/// edited line by line (e.g. flow style), in which case it is re-serialized.
+/// The file is replaced atomically while holding `<path>.lock`, like kubectl.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or written, or if `name` is not a context in it.
+pub fn set_current_context_in(path: impl AsRef<Path>, name: &str) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    ensure_context_defined(&Kubeconfig::read_from(path)?, name)?;
+    write_current_context(path, name)
+}
+
+fn write_current_context(path: &Path, name: &str) -> anyhow::Result<()> {
+    edit_kubeconfig_file(
+        path,
+        |document| {
+            document.insert("current-context".into(), name.into());
+            Ok(())
+        },
+        |content| set_current_context_text(content, name),
+    )
+}
+
+/// Sets the default namespace of `context`, persisting it to the kubeconfig like
+/// `kubectl config set-context <context> --namespace`.
+///
+/// With several files in `KUBECONFIG`, the first file defining `context` is edited.
+/// See [`set_current_context_in`] for how the file is written.
+///
+/// Returns the previous namespace of the context, if any.
+///
+/// # Errors
+/// Returns an error if `namespace` is not a valid namespace name, `context` is not defined,
+/// or the kubeconfig cannot be read or written.
+pub fn set_namespace_for_context(context: &str, namespace: &str) -> anyhow::Result<Option<String>> {
+    validate_namespace_name(namespace).map_err(anyhow::Error::msg)?;
+    let target = kubeconfig_sources()
+        .into_iter()
+        .find(|path| {
+            Kubeconfig::read_from(path)
+                .is_ok_and(|config| ensure_context_defined(&config, context).is_ok())
+        })
+        .ok_or_else(|| anyhow::anyhow!("context {context} is not found in kubeconfig"))?;
+    write_context_namespace(&target, context, namespace)
+}
+
+/// Same as [`set_namespace_for_context`], but edits the kubeconfig at `path`.
+///
+/// # Errors
+/// See [`set_namespace_for_context`].
+pub fn set_namespace_for_context_in(
+    path: impl AsRef<Path>,
+    context: &str,
+    namespace: &str,
+) -> anyhow::Result<Option<String>> {
+    validate_namespace_name(namespace).map_err(anyhow::Error::msg)?;
+    write_context_namespace(path.as_ref(), context, namespace)
+}
+
+fn write_context_namespace(
+    path: &Path,
+    context: &str,
+    namespace: &str,
+) -> anyhow::Result<Option<String>> {
+    edit_kubeconfig_file(
+        path,
+        |document| {
+            let named_context = document
+                .get_mut("contexts")
+                .and_then(serde_yaml::Value::as_sequence_mut)
+                .and_then(|contexts| {
+                    contexts.iter_mut().find(|c| {
+                        c.get("name").and_then(serde_yaml::Value::as_str) == Some(context)
+                    })
+                })
+                .and_then(serde_yaml::Value::as_mapping_mut)
+                .ok_or_else(|| anyhow::anyhow!("context {context} is not found in kubeconfig"))?;
+            let body = named_context
+                .entry("context".into())
+                .or_insert_with(|| serde_yaml::Mapping::new().into());
+            if body.is_null() {
+                *body = serde_yaml::Mapping::new().into();
+            }
+            let body = body
+                .as_mapping_mut()
+                .ok_or_else(|| anyhow::anyhow!("context {context} is malformed"))?;
+            let previous = body
+                .insert("namespace".into(), namespace.into())
+                .and_then(|previous| previous.as_str().map(str::to_string));
+            Ok(previous)
+        },
+        |content| set_context_namespace_text(content, context, namespace),
+    )
+}
+
+/// Checks that `namespace` is a valid namespace name, i.e. an RFC 1123 label:
+/// at most 63 lowercase alphanumerics or `-`, starting and ending with an alphanumeric.
+///
+/// # Errors
+/// Returns a message naming the first rule `namespace` violates.
+pub fn validate_namespace_name(namespace: &str) -> Result<(), String> {
+    let violation = if namespace.is_empty() {
+        String::from("must not be empty")
+    } else if namespace.chars().count() > 63 {
+        format!(
+            "must be at most 63 characters, but is {} characters long",
+            namespace.chars().count()
+        )
+    } else if let Some(c) = namespace
+        .chars()
+        .find(|c| !(c.is_ascii_lowercase() || c.is_ascii_digit() || *c == '-'))
+    {
+        format!("must consist of lowercase alphanumeric characters or '-', but contains {c:?}")
+    } else if namespace.starts_with('-') || namespace.ends_with('-') {
+        String::from("must start and end with an alphanumeric character")
+    } else {
+        return Ok(());
+    };
+    Err(format!("invalid namespace name {namespace:?}: {violation}"))
+}
+
+fn ensure_context_defined(kubeconfig: &Kubeconfig, name: &str) -> anyhow::Result<()> {
+    if kubeconfig.contexts.iter().any(|c| c.name == name) {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("context {name} is not found in kubeconfig"))
+    }
+}
+
+/// How long [`edit_kubeconfig_file`] waits for another writer to release the lock.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Applies `edit` to the YAML document of the kubeconfig at `path` and writes it back atomically,
+/// holding `<path>.lock` meanwhile.
+///
+/// `edit_in_place` makes the same edit on the text of the file, so that comments and formatting
+/// are kept. Its result is written only if it parses to the document edited by `edit`;
+/// otherwise, or if it returns `None`, the edited document is re-serialized.
+///
+/// A symlinked `path` is resolved first, so the edit is written through to its target.
+fn edit_kubeconfig_file<T>(
+    path: &Path,
+    edit: impl FnOnce(&mut serde_yaml::Mapping) -> anyhow::Result<T>,
+    edit_in_place: impl FnOnce(&str) -> Option<String>,
+) -> anyhow::Result<T> {
+    // NOTE: Renaming over the symlink itself would replace it with a regular file.
+    let path = &fs::canonicalize(path)
+        .map_err(|err| anyhow::anyhow!("failed to resolve {}: {err}", path.display()))?;
+    let _lock = LockFile::acquire(path)?;
+
+    let content = fs::read_to_string(path)?;
+    let mut document: serde_yaml::Value = serde_yaml::from_str(&content)?;
+    let mapping = document
+        .as_mapping_mut()
+        .ok_or_else(|| anyhow::anyhow!("{} is not a kubeconfig", path.display()))?;
+    let result = edit(mapping)?;
+
+    let content = match edit_in_place(&content).filter(|edited| {
+        serde_yaml::from_str::<serde_yaml::Value>(edited).is_ok_and(|edited| edited == document)
+    }) {
+        Some(edited) => edited,
+        None => serde_yaml::to_string(&document)?,
+    };
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".tmp-{}", process::id()));
+    let tmp = PathBuf::from(tmp);
+    fs::write(&tmp, content)?;
+    if let Err(err) = fs::set_permissions(&tmp, fs::metadata(path)?.permissions())
+        .and_then(|()| fs::rename(&tmp, path))
+    {
+        let _ = fs::remove_file(&tmp);
+        return Err(err.into());
+    }
+    Ok(result)
+}
+
+/// Sets the top-level `current-context` in the kubeconfig text `content`, line by line.
+fn set_current_context_text(content: &str, name: &str) -> Option<String> {
+    let value = yaml_scalar(name)?;
+    let mut lines: Vec<String> = content.split_inclusive('\n').map(String::from).collect();
+    match lines
+        .iter()
+        .position(|line| split_key(line).is_some_and(|(key, _)| key == "current-context"))
+    {
+        Some(i) => lines[i] = with_value(&lines[i], &value),
+        None => {
+            let eol = lines.first().map_or("\n", |line| match line_ending(line) {
+                "" => "\n",
+                eol => eol,
+            });
+            let eol = eol.to_string();
+            if let Some(last) = lines.last_mut()
+                && line_ending(last).is_empty()
+            {
+                last.push_str(&eol);
+            }
+            lines.push(format!("current-context: {value}{eol}"));
+        }
+    }
+    Some(lines.concat())
+}
+
+/// Sets the namespace of `context` in the kubeconfig text `content`, line by line.
+///
+/// Only block-style `contexts` whose `context` is a non-empty block mapping are handled.
+fn set_context_namespace_text(content: &str, context: &str, namespace: &str) -> Option<String> {
+    let value = yaml_scalar(namespace)?;
+    let mut lines: Vec<String> = content.split_inclusive('\n').map(String::from).collect();
+    let is_blank = |line: &str| {
+        let line = line.trim();
+        line.is_empty() || line.starts_with('#')
+    };
+    let indentation = |line: &str| line.len() - line.trim_start_matches(' ').len();
+
+    // NOTE: Sequence items may be indented or not, but their dashes line up.
+    let start = lines.iter().position(|line| {
+        split_key(line).is_some_and(|(key, rest)| key == "contexts" && is_blank(rest))
+    })? + 1;
+    let end = (start..lines.len())
+        .find(|&i| {
+            !is_blank(&lines[i]) && indentation(&lines[i]) == 0 && !lines[i].starts_with('-')
+        })
+        .unwrap_or(lines.len());
+    let dash = (start..end)
+        .find(|&i| !is_blank(&lines[i]))
+        .map(|i| indentation(&lines[i]))?;
+    let items: Vec<usize> = (start..end)
+        .filter(|&i| indentation(&lines[i]) == dash && lines[i][dash..].starts_with('-'))
+        .collect();
+
+    for (n, &first) in items.iter().enumerate() {
+        let last = items.get(n + 1).copied().unwrap_or(end);
+        // Keys of the item: the one following the dash, and those lined up with it.
+        let after_dash = &lines[first][dash + 1..];
+        let key_indent = dash + 1 + after_dash.len() - after_dash.trim_start().len();
+        let keys: Vec<(usize, &str, &str)> = (first..last)
+            .filter(|&i| {
+                i == first || (!is_blank(&lines[i]) && indentation(&lines[i]) == key_indent)
+            })
+            .filter_map(|i| {
+                let (key, rest) = split_key(lines[i].get(key_indent..)?)?;
+                Some((i, key, rest))
+            })
+            .collect();
+        let is_context = keys.iter().any(|&(_, key, rest)| {
+            key == "name"
+                && serde_yaml::from_str::<serde_yaml::Value>(rest)
+                    .is_ok_and(|name| name.as_str() == Some(context))
+        });
+        if !is_context {
+            continue;
+        }
+
+        let &(body_start, _, rest) = keys.iter().find(|&&(_, key, _)| key == "context")?;
+        if !is_blank(rest) {
+            return None;
+        }
+        let body: Vec<usize> = (body_start + 1..last)
+            .take_while(|&i| is_blank(&lines[i]) || indentation(&lines[i]) > key_indent)
+            .filter(|&i| !is_blank(&lines[i]))
+            .collect();
+        let body_indent = indentation(&lines[*body.first()?]);
+        let existing = body.iter().copied().find(|&i| {
+            indentation(&lines[i]) == body_indent
+                && split_key(&lines[i][body_indent..]).is_some_and(|(key, _)| key == "namespace")
+        });
+        match existing {
+            Some(i) => lines[i] = with_value(&lines[i], &value),
+            None => {
+                let after = *body.last()?;
+                let eol = match line_ending(&lines[body_start]) {
+                    "" => "\n".to_string(),
+                    eol => eol.to_string(),
+                };
+                if line_ending(&lines[after]).is_empty() {
+                    lines[after].push_str(&eol);
+                }
+                let line = format!("{}namespace: {value}{eol}", " ".repeat(body_indent));
+                lines.insert(after + 1, line);
+            }
+        }
+        return Some(lines.concat());
+    }
+    None
+}
+
+/// Formats `value` as a single-line YAML scalar, quoted if it would otherwise read as
+/// another type (e.g. `'123'`).
+fn yaml_scalar(value: &str) -> Option<String> {
+    let scalar = serde_yaml::to_string(value).ok()?;
+    let scalar = scalar.trim_end();
+    (!scalar.contains('\n')).then(|| scalar.to_string())
+}
+
+/// Splits an unindented `key: value` line into the key and what follows the colon.
+fn split_key(line: &str) -> Option<(&str, &str)> {
+    let (key, rest) = line.split_once(':')?;
+    let is_key = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    (is_key && (rest.is_empty() || rest.starts_with(char::is_whitespace))).then_some((key, rest))
+}
+
+/// Replaces the value of the `key: value` line `line`, keeping its indentation,
+/// trailing comment and line ending.
+fn with_value(line: &str, value: &str) -> String {
+    let eol = line_ending(line);
+    let line = &line[..line.len() - eol.len()];
+    let colon = line.find(':').map_or(line.len(), |i| i + 1);
+    let (head, rest) = line.split_at(colon);
+    let comment = rest.rfind(" #").map_or("", |i| &rest[i..]);
+    format!("{head} {value}{comment}{eol}")
+}
+
+fn line_ending(line: &str) -> &str {
+    if line.ends_with("\r\n") {
+        "\r\n"
+    } else if line.ends_with('\n') {
+        "\n"
+    } else {
+        ""
+    }
+}
+
+/// Exclusive lock on a kubeconfig file, compatible with kubectl's `<file>.lock`.
+struct LockFile(PathBuf);
+
+impl LockFile {
+    fn acquire(path: &Path) -> anyhow::Result<Self> {
+        let mut lock = path.as_os_str().to_owned();
+        lock.push(".lock");
+        let lock = PathBuf::from(lock);
+        let started = Instant::now();
+        loop {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&lock)
+            {
+                Ok(_) => return Ok(Self(lock)),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                    if started.elapsed() > LOCK_TIMEOUT {
+                        return Err(anyhow::anyhow!(
+                            "timed out waiting for {} to be released",
+                            lock.display()
+                        ));
+                    }
+                    thread::sleep(Duration::from_millis(50));
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{KubeconfigFixture, install_all};
+
+    #[test]
+    fn validate_namespace_name_accepts_rfc1123_labels() {
+        let max_length = "a".repeat(63);
+        for namespace in [
+            "default",
+            "kube-system",
+            "team-a",
+            "a",
+            "0",
+            "a1-b2",
+            &max_length,
+        ] {
+            assert_eq!(validate_namespace_name(namespace), Ok(()), "{namespace}");
+        }
+    }
+
+    #[test]
+    fn validate_namespace_name_rejects_invalid_names() {
+        let too_long = "a".repeat(64);
+        let cases = [
+            ("", "must not be empty"),
+            ("Team_A", "contains 'T'"),
+            ("team_a", "contains '_'"),
+            ("team.a", "contains '.'"),
+            ("-team", "must start and end with an alphanumeric character"),
+            ("team-", "must start and end with an alphanumeric character"),
+            (
+                &too_long,
+                "must be at most 63 characters, but is 64 characters long",
+            ),
+        ];
+        for (namespace, violation) in cases {
+            let err = validate_namespace_name(namespace).unwrap_err();
+            assert!(err.contains(violation), "{namespace}: {err}");
+        }
+    }
+
+    #[test]
+    fn list_contexts_marks_current_context() {
+        let _guard = KubeconfigFixture::new()
+            .context("dev", "dev-cluster", "dev-user")
+            .namespace("team-a")
+            .context("prod", "prod-cluster", "admin")
+            .current("prod")
+            .install()
+            .unwrap();
+        let contexts = list_contexts().unwrap();
+        assert_eq!(
+            contexts,
+            vec![
+                ContextEntry {
+                    name: String::from("dev"),
+                    cluster: Some(String::from("dev-cluster")),
+                    namespace: Some(String::from("team-a")),
+                    user: Some(String::from("dev-user")),
+                    is_current: false,
+                },
+                ContextEntry {
+                    name: String::from("prod"),
+                    cluster: Some(String::from("prod-cluster")),
+                    namespace: None,
+                    user: Some(String::from("admin")),
+                    is_current: true,
+                },
+            ]
+        );
+        let names: Vec<String> = sorted_current_first(contexts)
+            .into_iter()
+            .map(|context| context.name)
+            .collect();
+        assert_eq!(names, ["prod", "dev"]);
+    }
+
+    #[test]
+    fn list_contexts_from_reads_the_given_file() {
+        let _guard = KubeconfigFixture::new()
+            .context("dev", "dev-cluster", "dev-user")
+            .install()
+            .unwrap();
+        let file = KubeconfigFixture::new()
+            .context("prod", "prod-cluster", "admin")
+            .context("staging", "staging-cluster", "admin")
+            .current("staging")
+            .write()
+            .unwrap();
+        let contexts = list_contexts_from(file.path()).unwrap();
+        let names: Vec<(&str, bool)> = contexts
+            .iter()
+            .map(|context| (context.name.as_str(), context.is_current))
+            .collect();
+        assert_eq!(names, [("prod", false), ("staging", true)]);
+        assert!(list_contexts_from(file.path().with_file_name("missing")).is_err());
+    }
+
+    #[test]
+    fn context_entries_handle_dangling_current_and_empty_contexts() {
+        let mut kubeconfig = KubeconfigFixture::new()
+            .context("dev", "dev-cluster", "dev-user")
+            .current("deleted")
+            .kubeconfig()
+            .clone();
+        kubeconfig.contexts.push(kube::config::NamedContext {
+            name: String::from("empty"),
+            context: None,
+        });
+        let entries = context_entries(&kubeconfig);
+        assert!(entries.iter().all(|entry| !entry.is_current));
+        assert_eq!(
+            entries[1],
+            ContextEntry {
+                name: String::from("empty"),
+                cluster: None,
+                namespace: None,
+                user: None,
+                is_current: false,
+            }
+        );
+        // NOTE: Without a current context, the order is kept as is.
+        let names: Vec<String> = sorted_current_first(entries)
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        assert_eq!(names, ["dev", "empty"]);
+    }
+
+    #[test]
+    fn list_clusters_reports_tls_and_proxy_without_credentials() {
+        let mut kubeconfig = KubeconfigFixture::new()
+            .cluster("plain", "https://plain.example.com")
+            .cluster("secured", "https://secured.example.com")
+            .context("secured", "secured", "admin")
+            .context("broken", "missing", "admin")
+            .kubeconfig()
+            .clone();
+        let secured = kubeconfig.clusters[1].cluster.as_mut().unwrap();
+        secured.certificate_authority_data = Some(String::from("Y2E="));
+        secured.insecure_skip_tls_verify = Some(true);
+        secured.proxy_url = Some(String::from("http://proxy:3128"));
+        // NOTE: `context` added a placeholder for the missing cluster; drop it.
+        kubeconfig
+            .clusters
+            .retain(|cluster| cluster.name != "missing");
+
+        let clusters = cluster_entries(&kubeconfig);
+        assert_eq!(
+            clusters,
+            [
+                ClusterEntry {
+                    name: String::from("plain"),
+                    server: Some(String::from("https://plain.example.com")),
+                    insecure_skip_tls_verify: false,
+                    has_certificate_authority: false,
+                    has_proxy_url: false,
+                },
+                ClusterEntry {
+                    name: String::from("secured"),
+                    server: Some(String::from("https://secured.example.com")),
+                    insecure_skip_tls_verify: true,
+                    has_certificate_authority: true,
+                    has_proxy_url: true,
+                },
+            ]
+        );
+        assert_eq!(
+            find_cluster_for_context(&kubeconfig, "secured").unwrap(),
+            clusters[1]
+        );
+        let err = find_cluster_for_context(&kubeconfig, "broken").unwrap_err();
+        assert!(err.to_string().contains("cluster missing"), "{err}");
+        let err = find_cluster_for_context(&kubeconfig, "staging").unwrap_err();
+        assert!(err.to_string().contains("context staging"), "{err}");
+    }
+
+    #[test]
+    fn list_clusters_reads_kubeconfig_env() {
+        let _guard = KubeconfigFixture::new()
+            .cluster("prod-cluster", "https://prod.example.com")
+            .context("prod", "prod-cluster", "admin")
+            .install()
+            .unwrap();
+        let clusters = list_clusters().unwrap();
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(
+            cluster_for_context("prod").unwrap().server.as_deref(),
+            Some("https://prod.example.com")
+        );
+    }
+
+    #[test]
+    fn read_merged_kubeconfig_lets_the_first_file_win() {
+        let guard = install_all(&[
+            KubeconfigFixture::new()
+                .cluster("shared", "https://first.example.com")
+                .context("dev", "shared", "dev-user")
+                .namespace("team-a"),
+            KubeconfigFixture::new()
+                .cluster("shared", "https://second.example.com")
+                .context("dev", "shared", "dev-user")
+                .namespace("team-b")
+                .context("prod", "prod-cluster", "admin")
+                .current("prod"),
+            KubeconfigFixture::new()
+                .context("staging", "staging-cluster", "admin")
+                .current("staging"),
+        ])
+        .unwrap();
+        let kubeconfig = read_merged_kubeconfig().unwrap();
+        assert_eq!(kubeconfig.current_context.as_deref(), Some("prod"));
+        let names: Vec<&str> = kubeconfig
+            .contexts
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(names, ["dev", "prod", "staging"]);
+        assert_eq!(crate::determine_namespace(None, "dev"), "team-a");
+        assert_eq!(
+            cluster_server_url("dev").unwrap(),
+            "https://first.example.com"
+        );
+
+        // Missing and empty entries are skipped.
+        let missing = guard.paths()[0].with_file_name("missing");
+        let listed = [missing.as_path(), Path::new(""), guard.paths()[1].as_path()];
+        // SAFETY: The guard holds the lock serializing changes to `KUBECONFIG`.
+        unsafe { env::set_var("KUBECONFIG", env::join_paths(listed).unwrap()) };
+        assert_eq!(kubeconfig_sources(), [guard.paths()[1].clone()]);
+        let kubeconfig = read_merged_kubeconfig().unwrap();
+        assert_eq!(kubeconfig.contexts.len(), 2);
+        assert_eq!(crate::determine_namespace(None, "dev"), "team-b");
+    }
+
+    #[test]
+    fn cluster_server_url_from_follows_the_context() {
+        let mut kubeconfig = KubeconfigFixture::new()
+            .cluster("prod-cluster", "https://prod.example.com:6443")
+            .context("prod", "prod-cluster", "admin")
+            .context("serverless", "serverless", "admin")
+            .kubeconfig()
+            .clone();
+        kubeconfig.clusters[1].cluster.as_mut().unwrap().server = None;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config");
+        fs::write(&path, serde_yaml::to_string(&kubeconfig).unwrap()).unwrap();
+
+        assert_eq!(
+            cluster_server_url_from(&path, "prod").unwrap(),
+            "https://prod.example.com:6443"
+        );
+        let err = cluster_server_url_from(&path, "serverless").unwrap_err();
+        assert_eq!(err.to_string(), "cluster serverless has no server URL");
+        assert!(cluster_server_url_from(&path, "staging").is_err());
+    }
+
+    #[test]
+    fn is_local_cluster_recognizes_local_servers() {
+        for (url, expected) in [
+            ("https://127.0.0.1:6443", true),
+            ("https://127.0.0.53", true),
+            ("https://[::1]:6443", true),
+            ("https://localhost:6443", true),
+            ("https://LOCALHOST", true),
+            ("https://kind-control-plane.localhost", true),
+            ("https://k3s.local:6443", true),
+            ("https://host.docker.internal:6443", true),
+            ("https://192.168.49.2:8443", true),
+            ("https://192.168.99.100:8443", true),
+            ("https://192.168.49.2:6443", false),
+            ("https://192.168.1.10:8443", false),
+            ("https://10.0.0.1", false),
+            ("https://prod.example.com", false),
+            ("https://localhost.example.com", false),
+            ("not a url", false),
+            ("", false),
+        ] {
+            assert_eq!(is_local_cluster(url), expected, "{url}");
+        }
+    }
+
+    #[test]
+    fn list_contexts_merges_kubeconfig_files() {
+        let guard = install_all(&[
+            KubeconfigFixture::new()
+                .context("dev", "dev-cluster", "dev-user")
+                .current("dev"),
+            KubeconfigFixture::new()
+                .context("prod", "prod-cluster", "admin")
+                .current("prod"),
+        ])
+        .unwrap();
+        let names: Vec<(String, bool)> = list_contexts()
+            .unwrap()
+            .into_iter()
+            .map(|context| (context.name, context.is_current))
+            .collect();
+        assert_eq!(
+            names,
+            [(String::from("dev"), true), (String::from("prod"), false)]
+        );
+        assert_eq!(kubeconfig_sources(), guard.paths());
+    }
+
+    /// Kubeconfig with comments and a field order that re-serializing would not reproduce.
+    const COMMENTED_KUBECONFIG: &str = "\
+# Managed by hand.
+kind: Config
+apiVersion: v1
+current-context: dev # switched often
+contexts:
+- name: dev
+  context:
+    user: dev-user
+    cluster: dev-cluster
+- name: prod
+  context:
+    # Production, be careful.
+    cluster: prod-cluster
+    user: admin
+    namespace: team-a
+clusters:
+- name: dev-cluster
+  cluster: {server: 'https://127.0.0.1:6443'}
+- name: prod-cluster
+  cluster: {server: 'https://127.0.0.1:6443'}
+users:
+- name: dev-user
+  user: {token: dev}
+- name: admin
+  user: {token: admin}
+";
+
+    #[test]
+    fn set_current_context_in_keeps_comments_and_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config");
+        fs::write(&path, COMMENTED_KUBECONFIG).unwrap();
+
+        set_current_context_in(&path, "prod").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            COMMENTED_KUBECONFIG.replace(
+                "current-context: dev # switched often",
+                "current-context: prod # switched often"
+            )
+        );
+        assert!(!path.with_extension("lock").exists());
+
+        let err = set_current_context_in(&path, "staging").unwrap_err();
+        assert!(err.to_string().contains("staging"), "{err}");
+        assert_eq!(
+            Kubeconfig::read_from(&path)
+                .unwrap()
+                .current_context
+                .unwrap(),
+            "prod"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn set_current_context_in_writes_through_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("config");
+        let link = dir.path().join("link");
+        fs::write(&target, COMMENTED_KUBECONFIG).unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        set_current_context_in(&link, "prod").unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+        let content = fs::read_to_string(&target).unwrap();
+        assert!(content.starts_with("# Managed by hand.\n"), "{content}");
+        assert!(content.contains("current-context: prod # switched often\n"));
+    }
+
+    #[test]
+    fn set_current_context_in_adds_missing_current_context() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config");
+        let content = COMMENTED_KUBECONFIG.replace("current-context: dev # switched often\n", "");
+        fs::write(&path, content.trim_end()).unwrap();
+
+        set_current_context_in(&path, "prod").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!("{}\ncurrent-context: prod\n", content.trim_end())
+        );
+    }
+
+    #[test]
+    fn set_current_context_in_quotes_and_falls_back_to_reserializing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config");
+        KubeconfigFixture::new()
+            .context("123", "cluster", "user")
+            .context("dev", "cluster", "user")
+            .current("dev")
+            .write_to(&path)
+            .unwrap();
+        set_current_context_in(&path, "123").unwrap();
+        assert!(
+            fs::read_to_string(&path)
+                .unwrap()
+                .contains("current-context: '123'")
+        );
+        assert_eq!(
+            Kubeconfig::read_from(&path)
+                .unwrap()
+                .current_context
+                .unwrap(),
+            "123"
+        );
+
+        // NOTE: Flow style cannot be edited line by line.
+        fs::write(
+            &path,
+            "{contexts: [{name: dev, context: {cluster: c}}], current-context: x}",
+        )
+        .unwrap();
+        set_current_context_in(&path, "dev").unwrap();
+        assert_eq!(
+            Kubeconfig::read_from(&path)
+                .unwrap()
+                .current_context
+                .unwrap(),
+            "dev"
+        );
+    }
+
+    #[test]
+    fn set_current_context_edits_the_file_setting_it() {
+        let guard = install_all(&[
+            KubeconfigFixture::new().context("dev", "dev-cluster", "dev-user"),
+            KubeconfigFixture::new()
+                .context("prod", "prod-cluster", "admin")
+                .current("prod"),
+        ])
+        .unwrap();
+        set_current_context("dev").unwrap();
+        let [first, second] = guard.paths() else {
+            panic!("{:?}", guard.paths());
+        };
+        assert_eq!(Kubeconfig::read_from(first).unwrap().current_context, None);
+        assert_eq!(
+            Kubeconfig::read_from(second)
+                .unwrap()
+                .current_context
+                .unwrap(),
+            "dev"
+        );
+        assert_eq!(crate::determine_context(&None).unwrap(), "dev");
+        assert!(set_current_context("staging").is_err());
+    }
+
+    #[test]
+    fn set_namespace_for_context_in_keeps_comments_and_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config");
+        fs::write(&path, COMMENTED_KUBECONFIG).unwrap();
+
+        let previous = set_namespace_for_context_in(&path, "prod", "team-b").unwrap();
+        assert_eq!(previous.as_deref(), Some("team-a"));
+        let expected = COMMENTED_KUBECONFIG.replace("namespace: team-a", "namespace: team-b");
+        assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+
+        let previous = set_namespace_for_context_in(&path, "dev", "team-c").unwrap();
+        assert_eq!(previous, None);
+        let expected = expected.replace(
+            "    cluster: dev-cluster\n",
+            "    cluster: dev-cluster\n    namespace: team-c\n",
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+
+        for (context, namespace) in [("staging", "team-a"), ("dev", "Team_A")] {
+            assert!(set_namespace_for_context_in(&path, context, namespace).is_err());
+            assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn set_namespace_for_context_in_handles_kubectl_layouts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config");
+        // As written by kubectl, with the context before its name, and with indented items.
+        for (content, expected) in [
+            (
+                "contexts:\n- context:\n    cluster: c\n    user: u\n  name: dev\n",
+                "contexts:\n- context:\n    cluster: c\n    user: u\n    namespace: '0'\n  name: dev\n",
+            ),
+            (
+                "contexts:\n  - name: dev\n    context:\n      cluster: c # local\n",
+                "contexts:\n  - name: dev\n    context:\n      cluster: c # local\n      namespace: '0'\n",
+            ),
+            (
+                "contexts:\r\n- name: dev\r\n  context:\r\n    namespace: a\r\n",
+                "contexts:\r\n- name: dev\r\n  context:\r\n    namespace: '0'\r\n",
+            ),
+        ] {
+            fs::write(&path, content).unwrap();
+            set_namespace_for_context_in(&path, "dev", "0").unwrap();
+            assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+        }
+
+        // NOTE: A flow-style context cannot be edited line by line.
+        fs::write(&path, "contexts:\n- name: dev\n  context: {cluster: c}\n").unwrap();
+        set_namespace_for_context_in(&path, "dev", "team-a").unwrap();
+        let kubeconfig = Kubeconfig::read_from(&path).unwrap();
+        let context = kubeconfig.contexts[0].context.as_ref().unwrap();
+        assert_eq!(context.namespace.as_deref(), Some("team-a"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn set_namespace_for_context_writes_through_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("config");
+        fs::write(&target, COMMENTED_KUBECONFIG).unwrap();
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        let guard =
+            install_all(&[KubeconfigFixture::new().context("dev", "other", "other")]).unwrap();
+        // SAFETY: The guard holds the lock serializing changes to `KUBECONFIG`.
+        unsafe {
+            env::set_var(
+                "KUBECONFIG",
+                env::join_paths([guard.paths()[0].as_path(), link.as_path()]).unwrap(),
+            );
+        }
+
+        // The first file defining the context is edited.
+        assert_eq!(
+            set_namespace_for_context("prod", "team-b")
+                .unwrap()
+                .as_deref(),
+            Some("team-a")
+        );
+        assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!(
+            fs::read_to_string(&target).unwrap(),
+            COMMENTED_KUBECONFIG.replace("namespace: team-a", "namespace: team-b")
+        );
+        set_namespace_for_context("dev", "team-c").unwrap();
+        let first = Kubeconfig::read_from(&guard.paths()[0]).unwrap();
+        assert_eq!(
+            first.contexts[0]
+                .context
+                .as_ref()
+                .unwrap()
+                .namespace
+                .as_deref(),
+            Some("team-c")
+        );
+        assert!(!fs::read_to_string(&target).unwrap().contains("team-c"));
+    }
+
+    #[test]
+    fn suggest_contexts_ranks_prefixes_before_edits() {
+        let fixture = KubeconfigFixture::new()
+            .context("production", "prod-cluster", "admin")
+            .context("production-eu", "prod-cluster", "admin")
+            .context("prod-us", "prod-cluster", "admin")
+            .context("staging", "staging-cluster", "dev-user")
+            .context("dev", "dev-cluster", "dev-user");
+        let suggest =
+            |input: &str, limit: usize| suggest_contexts(input, fixture.kubeconfig(), limit);
+
+        // Transposed and missing characters.
+        assert_eq!(suggest("produciton", 5), ["production", "production-eu"]);
+        assert_eq!(suggest("stagng", 5), ["staging"]);
+        assert_eq!(suggest("prodution-eu", 5), ["production-eu", "production"]);
+        // Prefixes rank first, shortest first, then names containing the input.
+        assert_eq!(
+            suggest("PROD", 5),
+            ["prod-us", "production", "production-eu"]
+        );
+        assert_eq!(suggest("ction", 5), ["production", "production-eu"]);
+        assert_eq!(suggest("prod", 2), ["prod-us", "production"]);
+        assert_eq!(suggest("prod", 0), Vec::<String>::new());
+        // Names too far from the input are not suggested.
+        assert_eq!(suggest("minikube", 5), Vec::<String>::new());
+        assert_eq!(suggest("dev", 5), ["dev"]);
+    }
+
+    #[cfg(feature = "watch")]
+    async fn next_event(
+        events: &mut (impl futures::Stream<Item = KubeconfigEvent> + Unpin),
+    ) -> KubeconfigEvent {
+        use futures::StreamExt as _;
+
+        tokio::time::timeout(Duration::from_secs(10), events.next())
+            .await
+            .expect("no event within 10 seconds")
+            .expect("the stream ended")
+    }
+
+    #[cfg(feature = "watch")]
+    #[tokio::test]
+    async fn watch_kubeconfig_emits_the_reread_kubeconfig() {
+        let fixture = KubeconfigFixture::new()
+            .context("dev", "dev-cluster", "dev-user")
+            .context("prod", "prod-cluster", "admin")
+            .current("dev");
+        let guard = fixture.install().unwrap();
+        let path = &guard.paths()[0];
+        let mut events = Box::pin(watch_kubeconfig().unwrap());
+
+        // Both writes fall within the debounce delay, so a single event reports the last one.
+        set_current_context_in(path, "prod").unwrap();
+        set_namespace_for_context_in(path, "prod", "team-a").unwrap();
+        let KubeconfigEvent::Changed(kubeconfig) = next_event(&mut events).await else {
+            panic!("expected a change");
+        };
+        assert_eq!(kubeconfig.current_context.as_deref(), Some("prod"));
+        let namespace = context_entries(&kubeconfig)
+            .into_iter()
+            .find(|context| context.name == "prod")
+            .and_then(|context| context.namespace);
+        assert_eq!(namespace.as_deref(), Some("team-a"));
+
+        fs::write(path, "contexts: [").unwrap();
+        let event = next_event(&mut events).await;
+        assert!(matches!(event, KubeconfigEvent::Malformed(_)), "{event:?}");
+
+        // Editors replacing the file by renaming are noticed as well.
+        let replacement = path.with_file_name("config.swp");
+        fixture.current("prod").write_to(&replacement).unwrap();
+        fs::rename(&replacement, path).unwrap();
+        let event = next_event(&mut events).await;
+        assert!(
+            matches!(&event, KubeconfigEvent::Changed(kubeconfig) if kubeconfig.current_context.as_deref() == Some("prod")),
+            "{event:?}"
+        );
+    }
+}
@@ -5,13 +5,54 @@ pub use clap_complete;
 pub use k8s_openapi;
 pub use kube;
 
+pub mod auth;
+pub use auth::{
+    AuthMethod, AuthProblem, AuthSummary, auth_summary, classify_auth_error, preflight_auth,
+};
+pub mod builtin;
 pub mod claputil;
 pub use claputil::{context_value_completer, namespace_value_completer};
 pub mod discover;
 pub mod dynamic;
+pub mod error;
+pub use error::{Error, Result};
+pub mod guard;
+pub use guard::{GuardError, NamespaceGuard};
+pub mod gvk;
+pub use gvk::Gvk;
+pub mod identity;
+pub use identity::{
+    ClientOptions, Impersonation, KubeIdentity, client_and_namespace_for_context,
+    client_for_context, client_for_context_in, for_each_context,
+};
+pub mod kubeconfig;
+pub use kubeconfig::{
+    ClusterEntry, ContextEntry, cluster_for_context, cluster_server_url, cluster_server_url_from,
+    contexts_matching, is_local_cluster, kubeconfig_sources, list_clusters, list_contexts,
+    list_contexts_from, read_kubeconfig_files, read_merged_kubeconfig, set_current_context,
+    set_current_context_in, set_namespace_for_context, set_namespace_for_context_in,
+    suggest_contexts, validate_namespace_name,
+};
+#[cfg(feature = "watch")]
+pub use kubeconfig::{KubeconfigEvent, watch_kubeconfig};
+pub mod namespace;
+pub use namespace::{NamespaceSelection, ResolvedNamespaces, namespace_exists, verify_namespace};
+pub mod output;
+pub use output::OutputFormat;
+pub mod resource_ref;
+pub use resource_ref::{ResourceRef, parse_resource_args, resource_targets};
+pub mod retry;
+pub mod selector;
+pub use selector::{FieldSelector, LabelSelector, SelectorParams};
+pub mod skew;
+pub use skew::{SkewReport, check_version_skew};
+#[cfg(any(test, feature = "test-util"))]
+pub mod testutil;
+
+use std::path::Path;
 
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::APIResource;
-use kube::config::Kubeconfig;
+use kube::config::{Kubeconfig, KubeconfigError};
 
 /// Detects the Kubernetes context based on the provided `context` argument.
 ///
@@ -19,18 +60,158 @@ use kube::config::Kubeconfig;
 /// 1. Uses the context if explicitly specified.
 /// 2. Retrieves the current context from the kubeconfig file.
 ///
+/// The kubeconfig is read like kubectl does: from the files listed in `KUBECONFIG`
+/// (separated by `:`, or `;` on Windows) merged together, where the first file setting
+/// `current-context` wins, or from `~/.kube/config` if `KUBECONFIG` is unset.
+///
+/// # Errors
+/// Returns an error if the kubeconfig file cannot be read, contains no contexts,
+/// or if no current context is set in the kubeconfig.
+pub fn determine_context(context: &Option<String>) -> Result<String> {
+    match context {
+        Some(context) => Ok(context.to_string()),
+        _ => determine_context_with(&kubeconfig::read_merged_kubeconfig()?, context),
+    }
+}
+
+/// Same as [`determine_context`], but uses the already loaded `kubeconfig`.
+///
 /// # Errors
-/// Returns an error if the kubeconfig file cannot be read or if no current context is set in the kubeconfig.
-pub fn determine_context(context: &Option<String>) -> anyhow::Result<String> {
+/// Returns an error if `kubeconfig` contains no contexts, or if no current context is set.
+pub fn determine_context_with(kubeconfig: &Kubeconfig, context: &Option<String>) -> Result<String> {
     match context {
         Some(context) => Ok(context.to_string()),
-        _ => {
-            let kubeconfig = Kubeconfig::read()?;
-            Ok(kubeconfig
-                .current_context
-                .ok_or_else(|| anyhow::anyhow!("current_context is not set"))?)
+        _ => current_context(kubeconfig),
+    }
+}
+
+/// Same as [`determine_context`], but reads the kubeconfig at `path`, like `kubectl --kubeconfig`.
+///
+/// # Errors
+/// Returns an error if the kubeconfig file cannot be read, contains no contexts,
+/// or if no current context is set in the kubeconfig.
+pub fn determine_context_from(path: impl AsRef<Path>, context: &Option<String>) -> Result<String> {
+    match context {
+        Some(context) => Ok(context.to_string()),
+        _ => determine_context_with(&Kubeconfig::read_from(path)?, context),
+    }
+}
+
+/// Same as [`determine_context`], but checks that an explicitly specified context exists
+/// in the kubeconfig.
+///
+/// Use [`determine_context`] to reference contexts defined in other kubeconfig files.
+///
+/// # Errors
+/// In addition to the errors of [`determine_context`], returns [`Error::ContextNotFound`]
+/// if the specified context does not exist.
+pub fn determine_context_strict(context: &Option<String>) -> Result<String> {
+    let kubeconfig = kubeconfig::read_merged_kubeconfig()?;
+    match context {
+        Some(context) => {
+            ensure_context_exists(&kubeconfig, context)?;
+            Ok(context.to_string())
         }
+        _ => current_context(&kubeconfig),
+    }
+}
+
+fn ensure_context_exists(kubeconfig: &Kubeconfig, context: &str) -> Result<()> {
+    if kubeconfig.contexts.iter().any(|c| c.name == context) {
+        return Ok(());
+    }
+    Err(Error::ContextNotFound {
+        name: context.to_string(),
+        available: kubeconfig.contexts.iter().map(|c| c.name.clone()).collect(),
+    })
+}
+
+/// Returns up to `limit` of `candidates` closest to `target` by edit distance, closest first,
+/// skipping those farther than `max_distance`.
+pub(crate) fn closest_matches<'a>(
+    target: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+    limit: usize,
+    max_distance: usize,
+) -> Vec<&'a str> {
+    let mut candidates: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(target, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    candidates.sort();
+    candidates
+        .into_iter()
+        .take(limit)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+/// Returns up to `limit` of `candidates` resembling `input`, best first,
+/// as described in [`kubeconfig::suggest_contexts`].
+pub(crate) fn suggest_names<'a>(
+    input: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+    limit: usize,
+) -> Vec<String> {
+    let input = input.to_lowercase();
+    let input_len = input.chars().count();
+    let max_distance = (input_len / 3).max(2);
+    // NOTE: Scores sort ascending: (tier, distance to the beginning, distance to the whole name).
+    let mut scored: Vec<((u8, usize, usize), &str)> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let name = candidate.to_lowercase();
+            let extra = name.chars().count().saturating_sub(input_len);
+            let score = if name.starts_with(&input) {
+                (0, 0, extra)
+            } else if name.contains(&input) {
+                (1, 0, extra)
+            } else {
+                let head: String = name.chars().take(input_len).collect();
+                let distance = edit_distance(&input, &name);
+                let head_distance = edit_distance(&input, &head).min(distance);
+                if head_distance > max_distance {
+                    return None;
+                }
+                (2, head_distance, distance)
+            };
+            Some((score, candidate))
+        })
+        .collect();
+    scored.sort();
+    scored.dedup_by(|a, b| a.1 == b.1);
+    scored
+        .into_iter()
+        .take(limit)
+        .map(|(_, candidate)| candidate.to_string())
+        .collect()
+}
+
+/// Levenshtein distance between `a` and `b`, counted in chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut curr = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            curr[j + 1] = (prev[j] + usize::from(ca != *cb))
+                .min(prev[j + 1] + 1)
+                .min(curr[j] + 1);
+        }
+        prev = curr;
+    }
+    prev[b.len()]
+}
+
+fn current_context(kubeconfig: &Kubeconfig) -> Result<String> {
+    if kubeconfig.contexts.is_empty() {
+        return Err(Error::NoContexts);
     }
+    kubeconfig
+        .current_context
+        .clone()
+        .ok_or(Error::NoCurrentContext)
 }
 
 /// Determines the Kubernetes namespace based on the provided `namespace` and `context`.
@@ -39,27 +220,212 @@ pub fn determine_context(context: &Option<String>) -> anyhow::Result<String> {
 /// 1. Uses the namespace if explicitly specified.
 /// 2. Retrieves the default namespace associated with the current context from kubeconfig.
 /// 3. Uses "default".
+///
+/// Every failure falls back to "default"; see [`determine_namespace_strict`] to tell them apart.
 pub fn determine_namespace(namespace: Option<String>, context: &str) -> String {
     if let Some(ns) = namespace {
         return ns;
     }
-
-    match Kubeconfig::read() {
-        Ok(kubeconfig) => kubeconfig
-            .contexts
-            .iter()
-            .find(|c| Some(c.name.as_str()) == Some(context))
-            .and_then(|context| {
-                context
-                    .context
-                    .as_ref()
-                    .and_then(|ctx| ctx.namespace.clone())
-            })
-            .unwrap_or_else(|| String::from("default")),
+    match kubeconfig::read_merged_kubeconfig() {
+        Ok(kubeconfig) => determine_namespace_with(&kubeconfig, None, context),
         Err(_) => String::from("default"),
     }
 }
 
+/// Same as [`determine_namespace`], but uses the already loaded `kubeconfig`.
+pub fn determine_namespace_with(
+    kubeconfig: &Kubeconfig,
+    namespace: Option<String>,
+    context: &str,
+) -> String {
+    namespace
+        .or_else(|| context_namespace(kubeconfig, context))
+        .unwrap_or_else(|| String::from("default"))
+}
+
+/// Environment variables overriding the kubeconfig defaults in context and namespace
+/// resolution, e.g. `KUBEX_CONTEXT` set by a CI image. No variable is consulted by default;
+/// the consuming tool chooses their names.
+///
+/// With a `ResolutionEnv`, the context is determined in this order:
+/// 1. The explicitly specified context.
+/// 2. The value of `context_var`.
+/// 3. The current context of the kubeconfig.
+///
+/// And the namespace in this order:
+/// 1. The explicitly specified namespace.
+/// 2. The value of `namespace_var`.
+/// 3. The namespace of the context in the kubeconfig.
+/// 4. "default".
+///
+/// Variables that are unset, empty or not valid Unicode are skipped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResolutionEnv<'a> {
+    pub context_var: Option<&'a str>,
+    pub namespace_var: Option<&'a str>,
+}
+
+impl<'a> ResolutionEnv<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_context_var(mut self, var: &'a str) -> Self {
+        self.context_var = Some(var);
+        self
+    }
+
+    pub fn with_namespace_var(mut self, var: &'a str) -> Self {
+        self.namespace_var = Some(var);
+        self
+    }
+
+    /// Returns the context set by `context_var`, if any.
+    pub fn context(&self) -> Option<String> {
+        self.context_var.and_then(non_empty_var)
+    }
+
+    /// Returns the namespace set by `namespace_var`, if any.
+    pub fn namespace(&self) -> Option<String> {
+        self.namespace_var.and_then(non_empty_var)
+    }
+
+    /// Same as [`crate::determine_context`], but consults `context_var` before the kubeconfig.
+    ///
+    /// # Errors
+    /// See [`crate::determine_context`].
+    pub fn determine_context(&self, context: &Option<String>) -> Result<String> {
+        determine_context(&context.clone().or_else(|| self.context()))
+    }
+
+    /// Same as [`crate::determine_namespace`], but consults `namespace_var` before the kubeconfig.
+    pub fn determine_namespace(&self, namespace: Option<String>, context: &str) -> String {
+        determine_namespace(namespace.or_else(|| self.namespace()), context)
+    }
+}
+
+fn non_empty_var(var: &str) -> Option<String> {
+    std::env::var(var).ok().filter(|value| !value.is_empty())
+}
+
+/// Reason why [`determine_namespace_strict`] could not determine a namespace.
+#[derive(Debug)]
+pub enum NamespaceError {
+    KubeconfigUnreadable(KubeconfigError),
+    /// The context is not defined in the kubeconfig.
+    ContextNotFound(String),
+    /// The context sets no namespace. Callers wanting kubectl's behavior can use "default".
+    NoNamespaceSet,
+}
+
+impl std::fmt::Display for NamespaceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::KubeconfigUnreadable(err) => write!(f, "failed to read kubeconfig: {err}"),
+            Self::ContextNotFound(context) => {
+                write!(f, "context {context} is not found in kubeconfig")
+            }
+            Self::NoNamespaceSet => write!(f, "no namespace is set for the context"),
+        }
+    }
+}
+
+impl std::error::Error for NamespaceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::KubeconfigUnreadable(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Same as [`determine_namespace`], but reports why the namespace could not be determined
+/// instead of falling back to "default".
+///
+/// # Errors
+/// See [`NamespaceError`].
+pub fn determine_namespace_strict(
+    namespace: Option<String>,
+    context: &str,
+) -> Result<String, NamespaceError> {
+    if let Some(ns) = namespace {
+        return Ok(ns);
+    }
+
+    let kubeconfig =
+        kubeconfig::read_merged_kubeconfig().map_err(NamespaceError::KubeconfigUnreadable)?;
+    let Some(named) = kubeconfig.contexts.iter().find(|c| c.name == context) else {
+        return Err(NamespaceError::ContextNotFound(context.to_string()));
+    };
+    named
+        .context
+        .as_ref()
+        .and_then(|ctx| ctx.namespace.clone())
+        .ok_or(NamespaceError::NoNamespaceSet)
+}
+
+/// Path where the namespace of a pod's service account is mounted.
+pub const SERVICE_ACCOUNT_NAMESPACE_PATH: &str =
+    "/var/run/secrets/kubernetes.io/serviceaccount/namespace";
+
+/// Same as [`determine_namespace`], but when the kubeconfig cannot be read and the process runs
+/// in a pod, uses the pod's namespace from [`SERVICE_ACCOUNT_NAMESPACE_PATH`] instead of "default".
+pub fn determine_namespace_auto(namespace: Option<String>, context: &str) -> String {
+    determine_namespace_auto_with(
+        namespace,
+        context,
+        Path::new(SERVICE_ACCOUNT_NAMESPACE_PATH),
+    )
+}
+
+/// Same as [`determine_namespace_auto`], but reads the service account namespace from
+/// `service_account_namespace_path`.
+pub fn determine_namespace_auto_with(
+    namespace: Option<String>,
+    context: &str,
+    service_account_namespace_path: &Path,
+) -> String {
+    if let Some(ns) = namespace {
+        return ns;
+    }
+
+    match kubeconfig::read_merged_kubeconfig() {
+        Ok(kubeconfig) => context_namespace(&kubeconfig, context),
+        Err(_) => in_cluster_namespace(service_account_namespace_path),
+    }
+    .unwrap_or_else(|| String::from("default"))
+}
+
+/// Reads the service account namespace at `path`, trimming surrounding whitespace.
+///
+/// Returns `None` if the file cannot be read or is blank.
+pub fn in_cluster_namespace(path: &Path) -> Option<String> {
+    let namespace = std::fs::read_to_string(path).ok()?;
+    let namespace = namespace.trim();
+    (!namespace.is_empty()).then(|| namespace.to_string())
+}
+
+/// Checks if the process runs in a pod, i.e. `KUBERNETES_SERVICE_HOST` is set
+/// and the service account namespace is mounted.
+pub fn is_in_cluster() -> bool {
+    std::env::var_os("KUBERNETES_SERVICE_HOST").is_some()
+        && Path::new(SERVICE_ACCOUNT_NAMESPACE_PATH).is_file()
+}
+
+/// Returns the namespace set for `context` in `kubeconfig`, if any.
+fn context_namespace(kubeconfig: &Kubeconfig, context: &str) -> Option<String> {
+    kubeconfig
+        .contexts
+        .iter()
+        .find(|c| c.name == context)
+        .and_then(|context| {
+            context
+                .context
+                .as_ref()
+                .and_then(|ctx| ctx.namespace.clone())
+        })
+}
+
 /// Finds and returns the `APIResource` that matches the given `resource` name from the list of `api_resources`.
 pub fn find_resource(target: &str, api_resources: &[APIResource]) -> Option<APIResource> {
     api_resources
@@ -70,15 +436,1064 @@ pub fn find_resource(target: &str, api_resources: &[APIResource]) -> Option<APIR
 
 /// Checks if the given `api_resource` matches the `target` resource name.
 /// Matching is done against the resource's name, singular name, short names, and group-qualified name.
+///
+/// Subresources (e.g. `pods/status`) never match, so that `pods` cannot resolve to one of them.
 pub fn match_resource(target: &str, api_resource: &APIResource) -> bool {
-    api_resource.name == target
-        || api_resource.singular_name == target
-        || api_resource
-            .short_names
-            .as_ref()
-            .is_some_and(|short_names| short_names.contains(&target.to_string()))
-        || api_resource
-            .group
-            .as_ref()
-            .is_some_and(|group| format!("{}.{}", api_resource.name, group) == target)
+    !discover::is_subresource(api_resource)
+        && (match_resource_by_name(target, api_resource) || match_short_name(target, api_resource))
+}
+
+/// Policy applied by [`find_resource_with_policy`] when resolving targets.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResolutionPolicy {
+    /// Groups in order of preference, consulted when a short name is claimed by several groups.
+    pub group_priority: Vec<String>,
+    /// Fails instead of picking a resource when a short-name conflict can only be
+    /// resolved by heuristic, i.e. not by `group_priority`.
+    pub strict: bool,
+    /// How the special target [`ALL_TARGET`] is expanded.
+    pub all_expansion: AllExpansion,
+}
+
+/// A short name claimed by resources of several groups.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShortNameConflict {
+    pub short_name: String,
+    /// One resource per group, in order of preference. The first one is chosen.
+    pub candidates: Vec<APIResource>,
+    /// Set if the choice was not decided by [`ResolutionPolicy::group_priority`].
+    pub heuristic: bool,
+}
+
+/// Checks if `group` is served by Kubernetes itself rather than by a CRD or aggregated API,
+/// i.e. the core group, a group without dots (e.g. "apps") or a `*.k8s.io` group.
+pub fn is_builtin_group(group: &str) -> bool {
+    group == "core" || !group.contains('.') || group.ends_with(".k8s.io")
+}
+
+/// Finds the `APIResource` matching `target` like [`find_resource`], but resolves
+/// short-name conflicts across groups deterministically.
+///
+/// Matches by name, singular name or group-qualified name take precedence over short names.
+/// When several groups claim the short name, built-in groups are preferred over others,
+/// then groups listed earlier in [`ResolutionPolicy::group_priority`], then discovery order.
+/// The conflict is returned alongside the chosen resource.
+///
+/// # Errors
+/// Returns [`Error::AmbiguousShortName`] in strict mode if the conflict could only be
+/// resolved by heuristic.
+pub fn find_resource_with_policy(
+    target: &str,
+    api_resources: &[APIResource],
+    policy: &ResolutionPolicy,
+) -> Result<Option<(APIResource, Option<ShortNameConflict>)>> {
+    if let Some(api_resource) = api_resources
+        .iter()
+        .find(|api_resource| match_resource_by_name(target, api_resource))
+    {
+        return Ok(Some((api_resource.clone(), None)));
+    }
+
+    let mut candidates: Vec<&APIResource> = Vec::new();
+    for api_resource in api_resources.iter().filter(|api_resource| {
+        !discover::is_subresource(api_resource) && match_short_name(target, api_resource)
+    }) {
+        // NOTE: Other versions of an already found group are not a conflict.
+        if !candidates
+            .iter()
+            .any(|candidate| candidate.group == api_resource.group)
+        {
+            candidates.push(api_resource);
+        }
+    }
+
+    match candidates.len() {
+        0 => return Ok(None),
+        1 => return Ok(Some((candidates[0].clone(), None))),
+        _ => {}
+    }
+
+    let rank = |api_resource: &APIResource| {
+        let group = api_resource.group.as_deref().unwrap_or_default();
+        let priority = policy
+            .group_priority
+            .iter()
+            .position(|preferred| preferred == group)
+            .unwrap_or(usize::MAX);
+        (!is_builtin_group(group), priority)
+    };
+    // NOTE: The sort is stable, so discovery order breaks ties.
+    candidates.sort_by_key(|candidate| rank(candidate));
+
+    let (first, second) = (rank(candidates[0]), rank(candidates[1]));
+    let heuristic = !(first.0 == second.0 && first.1 < second.1);
+    if heuristic && policy.strict {
+        return Err(Error::AmbiguousShortName {
+            short_name: target.to_string(),
+            candidates: candidates
+                .iter()
+                .map(|candidate| qualified_name(candidate))
+                .collect(),
+        });
+    }
+
+    let conflict = ShortNameConflict {
+        short_name: target.to_string(),
+        candidates: candidates.into_iter().cloned().collect(),
+        heuristic,
+    };
+    Ok(Some((conflict.candidates[0].clone(), Some(conflict))))
+}
+
+fn match_resource_by_name(target: &str, api_resource: &APIResource) -> bool {
+    !discover::is_subresource(api_resource)
+        && (api_resource.name == target
+            || api_resource.singular_name == target
+            || api_resource
+                .group
+                .as_ref()
+                .is_some_and(|group| format!("{}.{}", api_resource.name, group) == target))
+}
+
+fn match_short_name(target: &str, api_resource: &APIResource) -> bool {
+    api_resource
+        .short_names
+        .as_ref()
+        .is_some_and(|short_names| short_names.iter().any(|short_name| short_name == target))
+}
+
+fn qualified_name(api_resource: &APIResource) -> String {
+    match api_resource.group.as_deref() {
+        Some(group) => format!("{}.{}", api_resource.name, group),
+        None => api_resource.name.clone(),
+    }
+}
+
+/// The special target that expands to several resources, like `kubectl get all`.
+pub const ALL_TARGET: &str = "all";
+
+/// How the special target [`ALL_TARGET`] is expanded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AllExpansion {
+    /// Expands to every resource carrying the given category.
+    Category(String),
+    /// Expands to the given targets. Targets that do not resolve are skipped.
+    Targets(Vec<String>),
+}
+
+impl Default for AllExpansion {
+    /// Expands to every resource in the "all" category, as kubectl does.
+    fn default() -> Self {
+        Self::Category(ALL_TARGET.to_string())
+    }
+}
+
+/// Result of resolving targets without failing on unresolved ones.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PartialResolution {
+    /// Resolved targets paired with their resources, in the order of the targets.
+    pub matched: Vec<(String, APIResource)>,
+    /// Targets that could not be resolved.
+    pub unresolved: Vec<String>,
+    /// `(group, version)` pairs that failed to enumerate during discovery, if any.
+    /// A target living in one of these groups ends up in `unresolved`.
+    pub failed_groups: Vec<(String, String)>,
+    /// Set if the targets were resolved against the built-in catalog
+    /// ([`builtin::builtin_api_resources`]) rather than the cluster, so the resources are assumed to exist.
+    pub assumed: bool,
+    /// Short-name conflicts settled while resolving the targets, e.g. to warn about those
+    /// settled by heuristic ([`ShortNameConflict::heuristic`]).
+    pub conflicts: Vec<ShortNameConflict>,
+}
+
+impl PartialResolution {
+    /// Checks if every target was resolved.
+    pub fn is_complete(&self) -> bool {
+        self.unresolved.is_empty()
+    }
+
+    /// Converts into the matched targets.
+    ///
+    /// # Errors
+    /// Returns [`Error::Unresolved`] if any target is unresolved.
+    pub fn into_result(self) -> Result<Vec<(String, APIResource)>> {
+        if self.unresolved.is_empty() {
+            return Ok(self.matched);
+        }
+        Err(UnresolvedTargets {
+            targets: self.unresolved,
+            failed_groups: self.failed_groups,
+        }
+        .into())
+    }
+}
+
+/// Targets that could not be resolved, see [`Error::Unresolved`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnresolvedTargets {
+    /// Targets that could not be resolved, in the order they were requested.
+    pub targets: Vec<String>,
+    /// `(group, version)` pairs that failed to enumerate during discovery, if any.
+    pub failed_groups: Vec<(String, String)>,
+}
+
+impl std::fmt::Display for UnresolvedTargets {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "failed to resolve resources: {}",
+            self.targets.join(", ")
+        )?;
+        if !self.failed_groups.is_empty() {
+            write!(
+                f,
+                " (discovery failed for {})",
+                self.failed_groups
+                    .iter()
+                    .map(|(group, version)| format!("{group}/{version}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for UnresolvedTargets {}
+
+/// Resolves every target in `targets` against `api_resources`, preserving the order of `targets`.
+///
+/// Each target is resolved with [`find_resource_with_policy`], except for:
+/// - [`ALL_TARGET`], expanded according to [`ResolutionPolicy::all_expansion`].
+/// - Targets containing `*`, treated as glob patterns against the group-qualified name
+///   (e.g. `*.cert-manager.io` or `certificates.*`) and expanded to every matching resource.
+///
+/// Expanded targets yield one entry per resource, each paired with the target, skipping the
+/// resources already matched by an earlier target. Other targets always yield their own entry,
+/// even if an earlier target resolved to the same resource (e.g. `pods` and `po`).
+///
+/// # Errors
+/// Returns [`Error::Unresolved`] listing every target that could not be resolved,
+/// or the first [`Error::AmbiguousShortName`] in strict mode.
+pub fn match_all_targets(
+    targets: &[String],
+    api_resources: &[APIResource],
+    policy: &ResolutionPolicy,
+) -> Result<Vec<(String, APIResource)>> {
+    match_targets_partial(targets, api_resources, policy)?.into_result()
+}
+
+/// Same as [`match_all_targets`], but reports unresolved targets instead of failing.
+///
+/// # Errors
+/// Returns the first [`Error::AmbiguousShortName`] in strict mode.
+pub(crate) fn match_targets_partial(
+    targets: &[String],
+    api_resources: &[APIResource],
+    policy: &ResolutionPolicy,
+) -> Result<PartialResolution> {
+    let mut resolution = PartialResolution::default();
+    for target in targets {
+        if target != ALL_TARGET && !target.contains('*') {
+            // NOTE: A plain target always gets its own entry, so that callers can look it up.
+            match find_resource_with_policy(target, api_resources, policy)? {
+                Some((resource, conflict)) => {
+                    resolution.conflicts.extend(conflict);
+                    resolution.matched.push((target.clone(), resource));
+                }
+                None => resolution.unresolved.push(target.clone()),
+            }
+            continue;
+        }
+        let resources = if target == ALL_TARGET {
+            expand_all(api_resources, &policy.all_expansion)
+        } else {
+            expand_pattern(target, api_resources)
+        };
+        if resources.is_empty() {
+            resolution.unresolved.push(target.clone());
+        }
+        for resource in resources {
+            if !resolution
+                .matched
+                .iter()
+                .any(|(_, other)| is_same_resource(other, &resource))
+            {
+                resolution.matched.push((target.clone(), resource));
+            }
+        }
+    }
+    Ok(resolution)
+}
+
+fn expand_all(api_resources: &[APIResource], all_expansion: &AllExpansion) -> Vec<APIResource> {
+    match all_expansion {
+        AllExpansion::Category(category) => api_resources
+            .iter()
+            .filter(|api_resource| {
+                !discover::is_subresource(api_resource)
+                    && api_resource
+                        .categories
+                        .as_ref()
+                        .is_some_and(|categories| categories.contains(category))
+            })
+            .cloned()
+            .collect(),
+        AllExpansion::Targets(targets) => targets
+            .iter()
+            .filter_map(|target| find_resource(target, api_resources))
+            .collect(),
+    }
+}
+
+fn expand_pattern(pattern: &str, api_resources: &[APIResource]) -> Vec<APIResource> {
+    api_resources
+        .iter()
+        .filter(|api_resource| {
+            !discover::is_subresource(api_resource)
+                && glob_match(pattern, &qualified_name(api_resource))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Matches `text` against a glob `pattern` in which `*` matches any (possibly empty) sequence.
+/// All other characters, including `.`, match literally.
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let (pattern, text) = (pattern.as_bytes(), text.as_bytes());
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` in `pattern` and the `text` position it is currently matched up to.
+    let mut backtrack = None;
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+fn is_same_resource(a: &APIResource, b: &APIResource) -> bool {
+    a.name == b.name && a.group == b.group && a.version == b.version
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::testutil::{KubeconfigFixture, MockServer, discovery_response};
+
+    #[test]
+    fn determine_context_with_prefers_explicit_context() {
+        let kubeconfig = KubeconfigFixture::new()
+            .context("dev", "dev-cluster", "dev-user")
+            .context("prod", "prod-cluster", "admin")
+            .current("prod");
+        let context = determine_context_with(kubeconfig.kubeconfig(), &Some(String::from("dev")));
+        assert_eq!(context.unwrap(), "dev");
+    }
+
+    #[test]
+    fn determine_context_with_falls_back_to_current_context() {
+        let kubeconfig = KubeconfigFixture::new()
+            .context("dev", "dev-cluster", "dev-user")
+            .context("prod", "prod-cluster", "admin")
+            .current("prod");
+        let context = determine_context_with(kubeconfig.kubeconfig(), &None);
+        assert_eq!(context.unwrap(), "prod");
+    }
+
+    #[test]
+    fn determine_context_with_fails_without_contexts() {
+        let kubeconfig = KubeconfigFixture::new().current("prod");
+        let err = determine_context_with(kubeconfig.kubeconfig(), &None).unwrap_err();
+        assert!(matches!(err, Error::NoContexts), "{err}");
+    }
+
+    #[test]
+    fn determine_context_with_fails_without_current_context() {
+        let kubeconfig = KubeconfigFixture::new().context("prod", "prod-cluster", "admin");
+        let err = determine_context_with(kubeconfig.kubeconfig(), &None).unwrap_err();
+        assert!(err.is_no_current_context(), "{err}");
+    }
+
+    #[test]
+    fn determine_context_with_keeps_unknown_explicit_context() {
+        // NOTE: Contexts of other kubeconfig files are allowed; see determine_context_strict.
+        let kubeconfig = KubeconfigFixture::new()
+            .context("prod", "prod-cluster", "admin")
+            .current("prod");
+        let context =
+            determine_context_with(kubeconfig.kubeconfig(), &Some(String::from("staging")));
+        assert_eq!(context.unwrap(), "staging");
+        // NOTE: Even an empty kubeconfig does not matter when the context is explicit.
+        let context =
+            determine_context_with(&Kubeconfig::default(), &Some(String::from("staging")));
+        assert_eq!(context.unwrap(), "staging");
+    }
+
+    #[test]
+    fn determine_namespace_with_follows_fallback_order() {
+        let kubeconfig = KubeconfigFixture::new()
+            .context("prod", "prod-cluster", "admin")
+            .namespace("team-a")
+            .context("dev", "dev-cluster", "dev-user")
+            .current("prod");
+        let kubeconfig = kubeconfig.kubeconfig();
+        let cases = [
+            (Some("team-b"), "prod", "team-b"),
+            (Some("team-b"), "staging", "team-b"),
+            (None, "prod", "team-a"),
+            (None, "dev", "default"),
+            (None, "staging", "default"),
+        ];
+        for (namespace, context, expected) in cases {
+            assert_eq!(
+                determine_namespace_with(kubeconfig, namespace.map(String::from), context),
+                expected,
+                "namespace {namespace:?} in context {context}"
+            );
+        }
+    }
+
+    #[test]
+    fn determine_context_reads_current_context_from_kubeconfig_env() {
+        let _guard = KubeconfigFixture::new()
+            .context("dev", "dev-cluster", "dev-user")
+            .context("prod", "prod-cluster", "admin")
+            .current("prod")
+            .install()
+            .unwrap();
+        assert_eq!(determine_context(&None).unwrap(), "prod");
+        assert_eq!(
+            determine_context(&Some(String::from("dev"))).unwrap(),
+            "dev"
+        );
+    }
+
+    #[test]
+    fn determine_context_from_reads_the_given_file_only() {
+        let _guard = KubeconfigFixture::new()
+            .context("dev", "dev-cluster", "dev-user")
+            .current("dev")
+            .install()
+            .unwrap();
+        let file = KubeconfigFixture::new()
+            .context("prod", "prod-cluster", "admin")
+            .current("prod")
+            .write()
+            .unwrap();
+        assert_eq!(determine_context_from(file.path(), &None).unwrap(), "prod");
+        assert_eq!(
+            determine_context_from(file.path(), &Some(String::from("dev"))).unwrap(),
+            "dev"
+        );
+
+        let missing = file.path().with_file_name("missing");
+        assert!(
+            determine_context_from(&missing, &None)
+                .unwrap_err()
+                .is_kubeconfig_read()
+        );
+        // NOTE: An explicit context does not need the file.
+        assert_eq!(
+            determine_context_from(&missing, &Some(String::from("dev"))).unwrap(),
+            "dev"
+        );
+    }
+
+    #[test]
+    fn determine_context_takes_the_first_current_context_of_kubeconfig_env() {
+        let _guard = testutil::install_all(&[
+            KubeconfigFixture::new().context("dev", "dev-cluster", "dev-user"),
+            KubeconfigFixture::new()
+                .context("staging", "staging-cluster", "admin")
+                .current("staging"),
+            KubeconfigFixture::new()
+                .context("prod", "prod-cluster", "admin")
+                .current("prod"),
+        ])
+        .unwrap();
+        assert_eq!(determine_context(&None).unwrap(), "staging");
+    }
+
+    #[test]
+    fn determine_context_strict_rejects_unknown_context() {
+        let _guard = KubeconfigFixture::new()
+            .context("prod", "prod-cluster", "admin")
+            .current("prod")
+            .install()
+            .unwrap();
+        let err = determine_context_strict(&Some(String::from("staging"))).unwrap_err();
+        assert!(err.is_context_not_found(), "{err}");
+        let Error::ContextNotFound { name, available } = err else {
+            panic!("{err:?}");
+        };
+        assert_eq!(
+            (name.as_str(), available),
+            ("staging", vec![String::from("prod")])
+        );
+
+        assert_eq!(
+            determine_context_strict(&Some(String::from("prod"))).unwrap(),
+            "prod"
+        );
+        assert_eq!(determine_context_strict(&None).unwrap(), "prod");
+    }
+
+    #[test]
+    fn determine_context_strict_suggests_similar_contexts() {
+        let _guard = KubeconfigFixture::new()
+            .context("production", "prod-cluster", "admin")
+            .context("production-eu", "prod-cluster", "admin")
+            .context("staging", "staging-cluster", "admin")
+            .current("staging")
+            .install()
+            .unwrap();
+        let err = determine_context_strict(&Some(String::from("produciton"))).unwrap_err();
+        assert!(err.is_context_not_found(), "{err}");
+        assert_eq!(
+            err.to_string(),
+            "context produciton is not found in kubeconfig (did you mean: production, production-eu?)"
+        );
+        let err = determine_context_strict(&Some(String::from("minikube"))).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "context minikube is not found in kubeconfig"
+        );
+    }
+
+    /// Sets an environment variable until dropped.
+    struct EnvVarGuard(&'static str);
+
+    impl EnvVarGuard {
+        fn set(name: &'static str, value: &str) -> Self {
+            // SAFETY: Each test uses its own variables, which no other code reads.
+            unsafe { std::env::set_var(name, value) };
+            Self(name)
+        }
+    }
+
+    impl Drop for EnvVarGuard {
+        fn drop(&mut self) {
+            // SAFETY: See `EnvVarGuard::set`.
+            unsafe { std::env::remove_var(self.0) };
+        }
+    }
+
+    #[tokio::test]
+    async fn resolution_env_falls_between_arguments_and_kubeconfig() {
+        let _guard = KubeconfigFixture::new()
+            .context("prod", "prod-cluster", "admin")
+            .namespace("team-a")
+            .context("staging", "staging-cluster", "admin")
+            .current("prod")
+            .install()
+            .unwrap();
+        let env = ResolutionEnv::new()
+            .with_context_var("KUBEX_TEST_RESOLUTION_CONTEXT")
+            .with_namespace_var("KUBEX_TEST_RESOLUTION_NAMESPACE");
+        let resolve = |context: Option<&str>, namespace: Option<&str>| {
+            let context = env.determine_context(&context.map(String::from)).unwrap();
+            let namespace = env.determine_namespace(namespace.map(String::from), &context);
+            (context, namespace)
+        };
+
+        // Unset and empty variables fall through to the kubeconfig.
+        assert_eq!(resolve(None, None), ("prod".into(), "team-a".into()));
+        let context = EnvVarGuard::set("KUBEX_TEST_RESOLUTION_CONTEXT", "");
+        let namespace = EnvVarGuard::set("KUBEX_TEST_RESOLUTION_NAMESPACE", "");
+        assert_eq!((env.context(), env.namespace()), (None, None));
+        assert_eq!(resolve(None, None), ("prod".into(), "team-a".into()));
+        assert_eq!(
+            resolve(Some("staging"), None),
+            ("staging".into(), "default".into())
+        );
+        drop((context, namespace));
+
+        let _context = EnvVarGuard::set("KUBEX_TEST_RESOLUTION_CONTEXT", "staging");
+        let _namespace = EnvVarGuard::set("KUBEX_TEST_RESOLUTION_NAMESPACE", "team-b");
+        assert_eq!(resolve(None, None), ("staging".into(), "team-b".into()));
+        // Explicit arguments take precedence over the variables.
+        assert_eq!(
+            resolve(Some("prod"), None),
+            ("prod".into(), "team-b".into())
+        );
+        assert_eq!(
+            resolve(None, Some("team-c")),
+            ("staging".into(), "team-c".into())
+        );
+        // No variable is consulted by default.
+        let default = ResolutionEnv::default();
+        assert_eq!(default.determine_context(&None).unwrap(), "prod");
+        assert_eq!(default.determine_namespace(None, "prod"), "team-a");
+
+        let identity = KubeIdentity::resolve_with_env(None, None, &env).unwrap();
+        assert_eq!(
+            (identity.context(), identity.namespace()),
+            ("staging", "team-b")
+        );
+        let identity =
+            KubeIdentity::resolve_with_env(Some("prod".into()), Some("team-c".into()), &env)
+                .unwrap();
+        assert_eq!(
+            (identity.context(), identity.namespace()),
+            ("prod", "team-c")
+        );
+        let (_, identity) = ClientOptions::new()
+            .resolution_env(env)
+            .config()
+            .await
+            .unwrap();
+        assert_eq!(
+            (identity.context(), identity.namespace()),
+            ("staging", "team-b")
+        );
+    }
+
+    #[test]
+    fn determine_context_strict_checks_every_kubeconfig_file() {
+        let _guard = testutil::install_all(&[
+            KubeconfigFixture::new().context("dev", "dev-cluster", "dev-user"),
+            KubeconfigFixture::new()
+                .context("prod", "prod-cluster", "admin")
+                .current("prod"),
+        ])
+        .unwrap();
+        assert_eq!(
+            determine_context_strict(&Some(String::from("dev"))).unwrap(),
+            "dev"
+        );
+        assert_eq!(determine_context_strict(&None).unwrap(), "prod");
+        let err = determine_context_strict(&Some(String::from("staging"))).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "context staging is not found in kubeconfig"
+        );
+    }
+
+    #[test]
+    fn determine_namespace_reads_context_namespace_from_kubeconfig_env() {
+        let _guard = KubeconfigFixture::new()
+            .context("prod", "prod-cluster", "admin")
+            .namespace("team-a")
+            .context("dev", "dev-cluster", "dev-user")
+            .current("prod")
+            .install()
+            .unwrap();
+        assert_eq!(determine_namespace(None, "prod"), "team-a");
+        assert_eq!(determine_namespace(None, "dev"), "default");
+        assert_eq!(
+            determine_namespace(Some(String::from("team-b")), "prod"),
+            "team-b"
+        );
+        assert_eq!(determine_namespace_strict(None, "prod").unwrap(), "team-a");
+        assert!(matches!(
+            determine_namespace_strict(None, "dev"),
+            Err(NamespaceError::NoNamespaceSet)
+        ));
+        assert!(matches!(
+            determine_namespace_strict(None, "staging"),
+            Err(NamespaceError::ContextNotFound(context)) if context == "staging"
+        ));
+    }
+
+    #[test]
+    fn determine_namespace_strict_tells_failures_apart() {
+        let guard = KubeconfigFixture::new()
+            .context("prod", "prod-cluster", "admin")
+            .namespace("team-a")
+            .context("dev", "dev-cluster", "dev-user")
+            .install()
+            .unwrap();
+        let err = determine_namespace_strict(None, "dev").unwrap_err();
+        assert!(matches!(err, NamespaceError::NoNamespaceSet), "{err}");
+        let err = determine_namespace_strict(None, "staging").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "context staging is not found in kubeconfig"
+        );
+        assert!(std::error::Error::source(&err).is_none());
+
+        std::fs::write(&guard.paths()[0], "contexts: [").unwrap();
+        let err = determine_namespace_strict(None, "prod").unwrap_err();
+        assert!(
+            matches!(err, NamespaceError::KubeconfigUnreadable(_)),
+            "{err}"
+        );
+        assert!(err.to_string().starts_with("failed to read kubeconfig: "));
+        assert!(std::error::Error::source(&err).is_some());
+        // NOTE: An explicit namespace needs no kubeconfig, and the lenient variant falls back.
+        assert_eq!(
+            determine_namespace_strict(Some(String::from("team-b")), "prod").unwrap(),
+            "team-b"
+        );
+        assert_eq!(determine_namespace(None, "prod"), "default");
+    }
+
+    #[test]
+    fn in_cluster_namespace_reads_the_trimmed_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("namespace");
+        assert_eq!(in_cluster_namespace(&path), None);
+        std::fs::write(&path, " \n").unwrap();
+        assert_eq!(in_cluster_namespace(&path), None);
+        std::fs::write(&path, "team-a\n").unwrap();
+        assert_eq!(in_cluster_namespace(&path).as_deref(), Some("team-a"));
+    }
+
+    #[test]
+    fn determine_namespace_auto_uses_the_pod_namespace_without_kubeconfig() {
+        let dir = tempfile::tempdir().unwrap();
+        let pod_namespace = dir.path().join("namespace");
+        std::fs::write(&pod_namespace, "team-pod").unwrap();
+        let guard = KubeconfigFixture::new()
+            .context("prod", "prod-cluster", "admin")
+            .namespace("team-a")
+            .context("dev", "dev-cluster", "dev-user")
+            .install()
+            .unwrap();
+
+        // The kubeconfig wins when it can be read, even if the context has no namespace.
+        for (context, expected) in [("prod", "team-a"), ("dev", "default")] {
+            assert_eq!(
+                determine_namespace_auto_with(None, context, &pod_namespace),
+                expected
+            );
+        }
+
+        std::fs::write(&guard.paths()[0], "contexts: [").unwrap();
+        assert_eq!(
+            determine_namespace_auto_with(None, "prod", &pod_namespace),
+            "team-pod"
+        );
+        assert_eq!(
+            determine_namespace_auto_with(Some(String::from("team-b")), "prod", &pod_namespace),
+            "team-b"
+        );
+        assert_eq!(
+            determine_namespace_auto_with(None, "prod", &dir.path().join("missing")),
+            "default"
+        );
+    }
+
+    /// Returns each target with the group-qualified name of the resource it resolved to.
+    fn qualified(matched: &[(String, APIResource)]) -> Vec<(&str, String)> {
+        matched
+            .iter()
+            .map(|(target, resource)| (target.as_str(), qualified_name(resource)))
+            .collect()
+    }
+
+    fn targets(targets: &[&str]) -> Vec<String> {
+        targets.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn all_expands_to_the_all_category_like_kubectl() {
+        let resources = builtin::builtin_api_resources();
+        let matched =
+            match_all_targets(&targets(&["all"]), &resources, &ResolutionPolicy::default())
+                .unwrap();
+        let names: Vec<String> = qualified(&matched)
+            .into_iter()
+            .map(|(target, name)| {
+                assert_eq!(target, "all");
+                name
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "pods.core",
+                "replicationcontrollers.core",
+                "services.core",
+                "daemonsets.apps",
+                "deployments.apps",
+                "replicasets.apps",
+                "statefulsets.apps",
+                "horizontalpodautoscalers.autoscaling",
+                "cronjobs.batch",
+                "jobs.batch",
+            ]
+        );
+    }
+
+    #[test]
+    fn all_skips_resources_matched_by_earlier_targets() {
+        let resources = builtin::builtin_api_resources();
+        let policy = ResolutionPolicy {
+            all_expansion: AllExpansion::Targets(targets(&["po", "svc", "nosuchresource"])),
+            ..Default::default()
+        };
+        let matched = match_all_targets(&targets(&["svc", "all", "po"]), &resources, &policy);
+        assert_eq!(
+            qualified(&matched.unwrap()),
+            [
+                ("svc", String::from("services.core")),
+                ("all", String::from("pods.core")),
+                ("po", String::from("pods.core")),
+            ]
+        );
+
+        let policy = ResolutionPolicy {
+            all_expansion: AllExpansion::Category(String::from("nosuchcategory")),
+            ..Default::default()
+        };
+        let err = match_all_targets(&targets(&["all", "pods"]), &resources, &policy).unwrap_err();
+        let Error::Unresolved(unresolved) = err else {
+            panic!("{err:?}");
+        };
+        assert_eq!(unresolved.targets, ["all"]);
+    }
+
+    fn with_short_names(resource: APIResource, short_names: &[&str]) -> APIResource {
+        APIResource {
+            short_names: Some(short_names.iter().map(ToString::to_string).collect()),
+            ..resource
+        }
+    }
+
+    #[test]
+    fn short_name_conflicts_follow_the_resolution_policy() {
+        use crate::testutil::api_resource;
+
+        let resources = [
+            with_short_names(
+                api_resource("example.com", "v1", "deployments", "Deployment"),
+                &["deploy"],
+            ),
+            with_short_names(
+                api_resource("apps", "v1", "deployments", "Deployment"),
+                &["deploy"],
+            ),
+            with_short_names(
+                api_resource("example.com", "v1", "certificates", "Certificate"),
+                &["cert"],
+            ),
+            with_short_names(
+                api_resource("cert-manager.io", "v1", "certificates", "Certificate"),
+                &["cert"],
+            ),
+            with_short_names(
+                api_resource("cert-manager.io", "v1beta1", "certificates", "Certificate"),
+                &["cert"],
+            ),
+            with_short_names(
+                api_resource("example.com", "v1", "widgets", "Widget"),
+                &["pods"],
+            ),
+            api_resource("core", "v1", "pods", "Pod"),
+        ];
+        let resolve = |target: &str, policy: &ResolutionPolicy| {
+            find_resource_with_policy(target, &resources, policy).map(|found| {
+                found.map(|(resource, conflict)| (qualified_name(&resource), conflict))
+            })
+        };
+        let default = ResolutionPolicy::default();
+
+        // Built-in groups win over CRDs regardless of discovery order.
+        let (name, conflict) = resolve("deploy", &default).unwrap().unwrap();
+        assert_eq!(name, "deployments.apps");
+        let conflict = conflict.unwrap();
+        assert!(conflict.heuristic);
+        assert_eq!(
+            conflict
+                .candidates
+                .iter()
+                .map(qualified_name)
+                .collect::<Vec<_>>(),
+            ["deployments.apps", "deployments.example.com"]
+        );
+
+        // Discovery order breaks ties, and other versions of a group are not a conflict.
+        let (name, conflict) = resolve("cert", &default).unwrap().unwrap();
+        assert_eq!(name, "certificates.example.com");
+        assert_eq!(conflict.unwrap().candidates.len(), 2);
+
+        let priority = ResolutionPolicy {
+            group_priority: vec![String::from("cert-manager.io")],
+            strict: true,
+            ..Default::default()
+        };
+        let (name, conflict) = resolve("cert", &priority).unwrap().unwrap();
+        assert_eq!(name, "certificates.cert-manager.io");
+        assert!(!conflict.unwrap().heuristic);
+
+        let strict = ResolutionPolicy {
+            strict: true,
+            ..Default::default()
+        };
+        let err = resolve("cert", &strict).unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                Error::AmbiguousShortName { short_name, candidates }
+                    if short_name == "cert"
+                        && candidates == &["certificates.example.com", "certificates.cert-manager.io"]
+            ),
+            "{err:?}"
+        );
+
+        // Names take precedence over short names, without any conflict.
+        let (name, conflict) = resolve("pods", &strict).unwrap().unwrap();
+        assert_eq!((name.as_str(), conflict), ("pods.core", None));
+        assert_eq!(resolve("nosuchresource", &strict).unwrap(), None);
+
+        let resolution =
+            match_targets_partial(&targets(&["cert", "pods"]), &resources, &default).unwrap();
+        assert!(resolution.is_complete());
+        assert_eq!(resolution.conflicts.len(), 1);
+        assert_eq!(resolution.conflicts[0].short_name, "cert");
+    }
+
+    #[test]
+    fn glob_match_treats_only_star_as_wildcard() {
+        let cases = [
+            ("*", "", true),
+            ("*", "pods.core", true),
+            ("*.cert-manager.io", "certificates.cert-manager.io", true),
+            (
+                "*.cert-manager.io",
+                "certificates.acme.cert-manager.io",
+                true,
+            ),
+            ("*.cert-manager.io", "cert-manager.io", false),
+            ("certificates.*", "certificates.cert-manager.io", true),
+            (
+                "certificates.*",
+                "certificaterequests.cert-manager.io",
+                false,
+            ),
+            ("*ingress*", "ingresses.networking.k8s.io", true),
+            ("a*b*c", "aXbYbZc", true),
+            ("a*b*c", "aXbYc d", false),
+            ("pods.core", "pods.core", true),
+            ("pods.core", "podsXcore", false),
+            ("pods?core", "pods.core", false),
+        ];
+        for (pattern, text, expected) in cases {
+            assert_eq!(glob_match(pattern, text), expected, "{pattern} vs {text}");
+        }
+    }
+
+    #[test]
+    fn glob_targets_expand_to_every_matching_resource() {
+        use crate::testutil::api_resource;
+
+        let resources = [
+            api_resource("cert-manager.io", "v1", "certificates", "Certificate"),
+            api_resource(
+                "cert-manager.io",
+                "v1",
+                "certificates/status",
+                "Certificate",
+            ),
+            api_resource("cert-manager.io", "v1", "issuers", "Issuer"),
+            api_resource("acme.cert-manager.io", "v1", "challenges", "Challenge"),
+            api_resource("example.com", "v1", "certificates", "Certificate"),
+            api_resource("core", "v1", "pods", "Pod"),
+        ];
+        let policy = ResolutionPolicy::default();
+        let matched = match_all_targets(
+            &targets(&["issuers", "*.cert-manager.io", "certificates.*"]),
+            &resources,
+            &policy,
+        )
+        .unwrap();
+        assert_eq!(
+            qualified(&matched),
+            [
+                ("issuers", String::from("issuers.cert-manager.io")),
+                (
+                    "*.cert-manager.io",
+                    String::from("certificates.cert-manager.io")
+                ),
+                (
+                    "*.cert-manager.io",
+                    String::from("challenges.acme.cert-manager.io")
+                ),
+                ("certificates.*", String::from("certificates.example.com")),
+            ]
+        );
+
+        let err =
+            match_all_targets(&targets(&["*.nosuch.io", "pods"]), &resources, &policy).unwrap_err();
+        assert!(
+            matches!(&err, Error::Unresolved(unresolved) if unresolved.targets == ["*.nosuch.io"]),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn match_all_targets_reports_every_unresolved_target() {
+        let resources = builtin::builtin_api_resources();
+        let err = match_all_targets(
+            &targets(&["pods", "widgets", "deploy", "gadgets.example.com"]),
+            &resources,
+            &ResolutionPolicy::default(),
+        )
+        .unwrap_err();
+        assert!(err.is_unresolved());
+        let Error::Unresolved(unresolved) = &err else {
+            panic!("{err:?}");
+        };
+        assert_eq!(unresolved.targets, ["widgets", "gadgets.example.com"]);
+        assert!(unresolved.failed_groups.is_empty());
+        assert_eq!(
+            err.to_string(),
+            "failed to resolve resources: widgets, gadgets.example.com"
+        );
+
+        // The structured error survives a round-trip through anyhow.
+        let err = anyhow::Error::from(err);
+        let Some(Error::Unresolved(unresolved)) = err.downcast_ref::<Error>() else {
+            panic!("{err:?}");
+        };
+        assert_eq!(unresolved.targets, ["widgets", "gadgets.example.com"]);
+    }
+
+    #[test]
+    fn unresolved_targets_mention_failed_groups() {
+        let unresolved = UnresolvedTargets {
+            targets: targets(&["widgets"]),
+            failed_groups: vec![("example.com".to_string(), "v1".to_string())],
+        };
+        assert_eq!(
+            unresolved.to_string(),
+            "failed to resolve resources: widgets (discovery failed for example.com/v1)"
+        );
+        assert_eq!(
+            Error::from(unresolved.clone()).to_string(),
+            unresolved.to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn resolve_requested_resources_returns_the_structured_error() {
+        let server = MockServer::start(discovery_response);
+        let err = discover::resolve_requested_resources(
+            server.client(),
+            &targets(&["po", "widgets"]),
+            None,
+            Duration::ZERO,
+            &discover::ResolveOptions::default(),
+        )
+        .await
+        .unwrap_err();
+        let Error::Unresolved(unresolved) = err else {
+            panic!("{err:?}");
+        };
+        assert_eq!(unresolved.targets, ["widgets"]);
+    }
 }
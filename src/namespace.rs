@@ -0,0 +1,316 @@
+use k8s_openapi::{
+    NamespaceResourceScope, api::core::v1::Namespace,
+    apimachinery::pkg::apis::meta::v1::APIResource,
+};
+use kube::{Api, Client, Resource, api::ListParams};
+
+use crate::{determine_namespace, dynamic::DynamicObject};
+
+/// Maximum number of namespaces listed by [`verify_namespace`] to look for suggestions.
+const SUGGESTION_LIST_LIMIT: u32 = 500;
+
+/// Maximum number of suggestions offered by [`verify_namespace`].
+const MAX_NAMESPACE_SUGGESTIONS: usize = 3;
+
+/// Checks if the namespace `name` exists in the cluster.
+///
+/// # Errors
+/// Returns the error of the request other than 404, notably 403 when the user
+/// cannot get namespaces, in which case existence is unknown.
+pub async fn namespace_exists(client: &Client, name: &str) -> Result<bool, kube::Error> {
+    let namespaces: Api<Namespace> = Api::all(client.clone());
+    match namespaces.get_metadata(name).await {
+        Ok(_) => Ok(true),
+        Err(kube::Error::Api(response)) if response.code == 404 => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Fails if the namespace `name` does not exist, suggesting similarly named namespaces.
+///
+/// Users who are not allowed to get namespaces are given the benefit of the doubt,
+/// i.e. a 403 passes the check.
+///
+/// # Errors
+/// Returns an error if the namespace does not exist, or if the check itself fails.
+pub async fn verify_namespace(client: &Client, name: &str) -> anyhow::Result<()> {
+    match namespace_exists(client, name).await {
+        Ok(true) => return Ok(()),
+        Ok(false) => {}
+        Err(kube::Error::Api(response)) if response.code == 403 => return Ok(()),
+        Err(err) => return Err(err.into()),
+    }
+
+    let namespaces: Api<Namespace> = Api::all(client.clone());
+    let names: Vec<String> = namespaces
+        .list_metadata(&ListParams::default().limit(SUGGESTION_LIST_LIMIT))
+        .await
+        .map(|list| {
+            list.items
+                .into_iter()
+                .filter_map(|ns| ns.metadata.name)
+                .collect()
+        })
+        .unwrap_or_default();
+    let suggestions = crate::closest_matches(
+        name,
+        names.iter().map(String::as_str),
+        MAX_NAMESPACE_SUGGESTIONS,
+        (name.chars().count() / 3).max(2),
+    );
+    if suggestions.is_empty() {
+        return Err(anyhow::anyhow!("namespace {name} is not found"));
+    }
+    Err(anyhow::anyhow!(
+        "namespace {name} is not found (did you mean: {}?)",
+        suggestions.join(", ")
+    ))
+}
+
+/// Namespaces a command operates on, as selected by `--namespace` and `--all-namespaces`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum NamespaceSelection {
+    /// Every namespace, like `--all-namespaces`.
+    All,
+    /// The namespace given by `--namespace`.
+    Named(String),
+    /// The namespace of the context, or "default" if it sets none.
+    #[default]
+    Default,
+}
+
+/// Namespaces a [`NamespaceSelection`] resolved to for a context.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResolvedNamespaces {
+    All,
+    Named(String),
+}
+
+impl ResolvedNamespaces {
+    /// Returns the namespace, or `None` for every namespace.
+    pub fn namespace(&self) -> Option<&str> {
+        match self {
+            Self::All => None,
+            Self::Named(namespace) => Some(namespace),
+        }
+    }
+}
+
+impl NamespaceSelection {
+    /// Builds the selection from the values of `--namespace` and `--all-namespaces`.
+    ///
+    /// # Errors
+    /// Returns an error if both a namespace and all namespaces are requested, as kubectl does.
+    pub fn from_flags(namespace: Option<String>, all: bool) -> anyhow::Result<Self> {
+        match (namespace, all) {
+            (Some(namespace), true) => Err(anyhow::anyhow!(
+                "--namespace {namespace} conflicts with --all-namespaces"
+            )),
+            (Some(namespace), false) => Ok(Self::Named(namespace)),
+            (None, true) => Ok(Self::All),
+            (None, false) => Ok(Self::Default),
+        }
+    }
+
+    /// Resolves the selection for `context`, looking up the context's namespace
+    /// like [`determine_namespace`] for [`Self::Default`].
+    pub fn resolve(&self, context: &str) -> ResolvedNamespaces {
+        match self {
+            Self::All => ResolvedNamespaces::All,
+            Self::Named(namespace) => ResolvedNamespaces::Named(namespace.clone()),
+            Self::Default => ResolvedNamespaces::Named(determine_namespace(None, context)),
+        }
+    }
+}
+
+/// Builds the `Api` for namespaced resources `K` over the selected namespaces.
+///
+/// [`NamespaceSelection::Default`] uses the default namespace of `client`.
+pub fn api_for<K>(client: Client, selection: &NamespaceSelection) -> Api<K>
+where
+    K: Resource<Scope = NamespaceResourceScope>,
+    K::DynamicType: Default,
+{
+    match selection {
+        NamespaceSelection::All => Api::all(client),
+        NamespaceSelection::Named(namespace) => Api::namespaced(client, namespace),
+        NamespaceSelection::Default => Api::default_namespaced(client),
+    }
+}
+
+/// Same as [`api_for`], but for `resource` as a [`DynamicObject`].
+///
+/// Cluster-scoped resources ignore the selection, as every selection means the same for them.
+/// Use [`crate::dynamic::api_for`] instead to reject a namespace for them.
+pub fn dynamic_api_for(
+    client: Client,
+    selection: &NamespaceSelection,
+    resource: &APIResource,
+) -> Api<DynamicObject> {
+    if !resource.namespaced {
+        return Api::all_with(client, resource);
+    }
+    match selection {
+        NamespaceSelection::All => Api::all_with(client, resource),
+        NamespaceSelection::Named(namespace) => Api::namespaced_with(client, namespace, resource),
+        NamespaceSelection::Default => Api::default_namespaced_with(client, resource),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{
+        KubeconfigFixture, MockRequest, MockServer, api_resource, status_response,
+    };
+
+    /// Serves the namespaces `team-a`, `team-b` and `kube-system`, forbidding `restricted`.
+    fn namespaces_response(request: &MockRequest) -> (u16, String) {
+        const NAMESPACES: [&str; 3] = ["team-a", "team-b", "kube-system"];
+        let namespace = |name: &str| {
+            serde_json::json!({
+                "apiVersion": "meta.k8s.io/v1",
+                "kind": "PartialObjectMetadata",
+                "metadata": { "name": name },
+            })
+        };
+        match request.path.strip_prefix("/api/v1/namespaces") {
+            Some("") => {
+                let body = serde_json::json!({
+                    "apiVersion": "meta.k8s.io/v1",
+                    "kind": "PartialObjectMetadataList",
+                    "metadata": {},
+                    "items": NAMESPACES.map(namespace),
+                });
+                (200, body.to_string())
+            }
+            Some("/restricted") => status_response(403),
+            Some(name) => match name.strip_prefix('/') {
+                Some(name) if NAMESPACES.contains(&name) => (200, namespace(name).to_string()),
+                _ => status_response(404),
+            },
+            None => status_response(404),
+        }
+    }
+
+    #[tokio::test]
+    async fn namespace_exists_distinguishes_missing_from_forbidden() {
+        let server = MockServer::start(namespaces_response);
+        let client = server.client();
+        assert!(namespace_exists(&client, "team-a").await.unwrap());
+        assert!(!namespace_exists(&client, "team-c").await.unwrap());
+        let err = namespace_exists(&client, "restricted").await.unwrap_err();
+        assert!(
+            matches!(err, kube::Error::Api(ref response) if response.code == 403),
+            "{err}"
+        );
+
+        let server = MockServer::start(|_| status_response(500));
+        assert!(namespace_exists(&server.client(), "team-a").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn verify_namespace_suggests_similar_namespaces() {
+        let server = MockServer::start(namespaces_response);
+        let client = server.client();
+        verify_namespace(&client, "team-a").await.unwrap();
+        verify_namespace(&client, "restricted").await.unwrap();
+        assert_eq!(server.count("/api/v1/namespaces"), 0);
+
+        let err = verify_namespace(&client, "team-c").await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "namespace team-c is not found (did you mean: team-a, team-b?)"
+        );
+        let err = verify_namespace(&client, "monitoring").await.unwrap_err();
+        assert_eq!(err.to_string(), "namespace monitoring is not found");
+
+        let server = MockServer::start(|_| status_response(500));
+        assert!(verify_namespace(&server.client(), "team-a").await.is_err());
+    }
+
+    #[test]
+    fn namespace_selection_follows_kubectl_flags() {
+        assert_eq!(
+            NamespaceSelection::from_flags(None, false).unwrap(),
+            NamespaceSelection::Default
+        );
+        assert_eq!(
+            NamespaceSelection::from_flags(None, true).unwrap(),
+            NamespaceSelection::All
+        );
+        assert_eq!(
+            NamespaceSelection::from_flags(Some(String::from("team-a")), false).unwrap(),
+            NamespaceSelection::Named(String::from("team-a"))
+        );
+        let err = NamespaceSelection::from_flags(Some(String::from("team-a")), true).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--namespace team-a conflicts with --all-namespaces"
+        );
+    }
+
+    #[test]
+    fn namespace_selection_resolves_the_context_namespace() {
+        let _guard = KubeconfigFixture::new()
+            .context("prod", "prod-cluster", "admin")
+            .namespace("team-a")
+            .context("dev", "dev-cluster", "dev-user")
+            .install()
+            .unwrap();
+        let cases = [
+            (NamespaceSelection::Default, "prod", Some("team-a")),
+            (NamespaceSelection::Default, "dev", Some("default")),
+            (
+                NamespaceSelection::Named(String::from("team-b")),
+                "prod",
+                Some("team-b"),
+            ),
+            (NamespaceSelection::All, "prod", None),
+        ];
+        for (selection, context, expected) in cases {
+            assert_eq!(
+                selection.resolve(context).namespace(),
+                expected,
+                "{selection:?} in {context}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn api_for_targets_the_selected_namespaces() {
+        use k8s_openapi::api::core::v1::Pod;
+
+        let server = MockServer::start(|_| status_response(404));
+        let mut config = kube::Config::new(server.url().parse().unwrap());
+        config.default_namespace = String::from("team-a");
+        let client = Client::try_from(config).unwrap();
+        let url = |selection: NamespaceSelection| {
+            api_for::<Pod>(client.clone(), &selection)
+                .resource_url()
+                .to_string()
+        };
+        assert_eq!(url(NamespaceSelection::All), "/api/v1/pods");
+        assert_eq!(
+            url(NamespaceSelection::Named(String::from("team-b"))),
+            "/api/v1/namespaces/team-b/pods"
+        );
+        assert_eq!(
+            url(NamespaceSelection::Default),
+            "/api/v1/namespaces/team-a/pods"
+        );
+
+        let mut namespaces = api_resource("core", "v1", "namespaces", "Namespace");
+        namespaces.namespaced = false;
+        let selection = NamespaceSelection::Named(String::from("team-b"));
+        assert_eq!(
+            dynamic_api_for(client.clone(), &selection, &namespaces).resource_url(),
+            "/api/v1/namespaces"
+        );
+        let deployments = api_resource("apps", "v1", "deployments", "Deployment");
+        assert_eq!(
+            dynamic_api_for(client, &selection, &deployments).resource_url(),
+            "/apis/apps/v1/namespaces/team-b/deployments"
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use std::fmt;
+
+use anyhow::Context as _;
+use clap::{ValueEnum, builder::PossibleValue};
+use kube::Resource;
+use serde::Serialize;
+
+/// Format of a command's output, as selected by `-o/--output`
+/// (see [`crate::claputil::output_arg`]).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    Yaml,
+    /// `TYPE/NAME`, e.g. `deployment.apps/nginx`.
+    Name,
+    /// The table of the command with additional columns.
+    Wide,
+    /// A format with an expression, given as is, e.g. `jsonpath={.metadata.name}`.
+    Custom(String),
+}
+
+/// Prefixes of the values accepted as [`OutputFormat::Custom`].
+pub(crate) const CUSTOM_OUTPUT_PREFIXES: &[&str] = &["jsonpath=", "custom-columns="];
+
+impl ValueEnum for OutputFormat {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Json, Self::Yaml, Self::Name, Self::Wide]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        match self {
+            Self::Json => Some(PossibleValue::new("json")),
+            Self::Yaml => Some(PossibleValue::new("yaml")),
+            Self::Name => Some(PossibleValue::new("name")),
+            Self::Wide => Some(PossibleValue::new("wide")),
+            Self::Custom(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json => write!(f, "json"),
+            Self::Yaml => write!(f, "yaml"),
+            Self::Name => write!(f, "name"),
+            Self::Wide => write!(f, "wide"),
+            Self::Custom(format) => write!(f, "{format}"),
+        }
+    }
+}
+
+/// Renders `object` in `format`, ending with a newline like kubectl's output.
+///
+/// [`OutputFormat::Name`] reads the kind and API version the object serializes with,
+/// so a `DynamicObject` must have its `types` set.
+///
+/// # Errors
+/// Returns an error if `object` cannot be serialized, if its name, kind or API version
+/// is missing for [`OutputFormat::Name`], or for [`OutputFormat::Wide`] and
+/// [`OutputFormat::Custom`], which depend on the command's table and are not rendered here.
+pub fn render<K>(format: &OutputFormat, object: &K) -> anyhow::Result<String>
+where
+    K: Resource + Serialize,
+{
+    match format {
+        OutputFormat::Json => {
+            let json = serde_json::to_string_pretty(object).context("failed to render JSON")?;
+            Ok(json + "\n")
+        }
+        OutputFormat::Yaml => serde_yaml::to_string(object).context("failed to render YAML"),
+        OutputFormat::Name => Ok(object_name(object)? + "\n"),
+        OutputFormat::Wide | OutputFormat::Custom(_) => Err(anyhow::anyhow!(
+            "output format {format} cannot be rendered for a single object"
+        )),
+    }
+}
+
+/// Returns `kind[.group]/name` of `object`, like `kubectl get -o name`.
+fn object_name<K>(object: &K) -> anyhow::Result<String>
+where
+    K: Resource + Serialize,
+{
+    let name = object
+        .meta()
+        .name
+        .as_deref()
+        .ok_or_else(|| anyhow::anyhow!("object has no name"))?;
+    let value = serde_json::to_value(object).context("failed to serialize object")?;
+    let field = |field: &str| {
+        value
+            .get(field)
+            .and_then(|value| value.as_str())
+            .ok_or_else(|| anyhow::anyhow!("object {name} has no {field}"))
+    };
+    let kind = field("kind")?.to_lowercase();
+    Ok(match field("apiVersion")?.split_once('/') {
+        Some((group, _)) => format!("{kind}.{group}/{name}"),
+        None => format!("{kind}/{name}"),
+    })
+}
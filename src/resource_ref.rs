@@ -0,0 +1,290 @@
+use std::{fmt, str::FromStr};
+
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::APIResource;
+
+use crate::{Error, UnresolvedTargets, match_resource};
+
+/// Reference to a resource type and optionally an object of it, as given on a command line,
+/// e.g. `pods`, `deployment/nginx` or `deployments.v1.apps/nginx`.
+///
+/// Parsing accepts `TYPE`, `TYPE/NAME` and their qualified forms `TYPE.GROUP` and
+/// `TYPE.VERSION.GROUP`, where `TYPE` is anything [`match_resource`] accepts.
+/// [`Display`](fmt::Display) prints the same form back; `namespace` is not part of it
+/// and is set separately, e.g. from `--namespace`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResourceRef {
+    pub resource: String,
+    pub group: Option<String>,
+    pub version: Option<String>,
+    pub name: Option<String>,
+    pub namespace: Option<String>,
+}
+
+impl ResourceRef {
+    pub fn new(resource: impl Into<String>) -> Self {
+        Self {
+            resource: resource.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Returns the resource type as it was given, without the name, e.g. `deployments.apps`.
+    pub fn target(&self) -> String {
+        let mut target = self.resource.clone();
+        for part in [&self.version, &self.group].into_iter().flatten() {
+            target.push('.');
+            target.push_str(part);
+        }
+        target
+    }
+
+    /// Finds the resource type in `resources`, returning it with the referenced name.
+    ///
+    /// The type is matched with [`match_resource`] among the resources of the group
+    /// and version, if given.
+    ///
+    /// # Errors
+    /// Returns [`Error::Unresolved`] if no resource matches.
+    pub fn resolve(
+        &self,
+        resources: &[APIResource],
+    ) -> crate::Result<(APIResource, Option<String>)> {
+        resources
+            .iter()
+            .filter(|resource| {
+                self.group
+                    .as_ref()
+                    .is_none_or(|group| resource.group.as_ref() == Some(group))
+                    && self
+                        .version
+                        .as_ref()
+                        .is_none_or(|version| resource.version.as_ref() == Some(version))
+            })
+            .find(|resource| match_resource(&self.resource, resource))
+            .map(|resource| (resource.clone(), self.name.clone()))
+            .ok_or_else(|| {
+                Error::Unresolved(UnresolvedTargets {
+                    targets: vec![self.target()],
+                    failed_groups: Vec::new(),
+                })
+            })
+    }
+}
+
+impl fmt::Display for ResourceRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.target())?;
+        if let Some(name) = &self.name {
+            write!(f, "/{name}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ResourceRef {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (target, name) = match s.split_once('/') {
+            Some((_, name)) if name.starts_with('/') => {
+                return Err(anyhow::anyhow!(
+                    "invalid resource reference {s}: double slash is not allowed"
+                ));
+            }
+            Some((_, name)) if name.contains('/') => {
+                return Err(anyhow::anyhow!(
+                    "invalid resource reference {s}: expected TYPE/NAME with a single slash"
+                ));
+            }
+            Some((_, "")) => {
+                return Err(anyhow::anyhow!(
+                    "invalid resource reference {s}: name must not be empty after '/'"
+                ));
+            }
+            Some((target, name)) => (target, Some(name.to_string())),
+            None => (s, None),
+        };
+        if target.is_empty() {
+            return Err(anyhow::anyhow!(
+                "invalid resource reference {s}: resource type must not be empty"
+            ));
+        }
+
+        let parts: Vec<&str> = target.split('.').collect();
+        if parts.iter().any(|part| part.is_empty()) {
+            return Err(anyhow::anyhow!(
+                "invalid resource reference {s}: resource type has an empty segment"
+            ));
+        }
+        let (version, group) = match parts.as_slice() {
+            [_] => (None, None),
+            [_, version, group @ ..] if !group.is_empty() && is_version(version) => {
+                (Some(version.to_string()), Some(group.join(".")))
+            }
+            [_, group @ ..] => (None, Some(group.join("."))),
+            [] => unreachable!("split yields at least one part"),
+        };
+        Ok(Self {
+            resource: parts[0].to_string(),
+            group,
+            version,
+            name,
+            namespace: None,
+        })
+    }
+}
+
+/// Checks if `s` looks like a Kubernetes API version, e.g. `v1` or `v2beta1`.
+fn is_version(s: &str) -> bool {
+    let Some(rest) = s.strip_prefix('v') else {
+        return false;
+    };
+    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+    if digits == 0 {
+        return false;
+    }
+    let rest = &rest[digits..];
+    rest.is_empty()
+        || ["alpha", "beta"].iter().any(|stage| {
+            rest.strip_prefix(stage)
+                .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+        })
+}
+
+/// Parses resource arguments following kubectl's rules, e.g. `pods,services nginx web`
+/// or `pods/nginx deploy/web`.
+///
+/// Either every argument is in `TYPE/NAME` form, or the first argument lists types separated
+/// by commas and the rest are names, each of which applies to every type.
+/// Arguments in `TYPE/NAME` form may also be joined by commas, e.g. `pods/nginx,deploy/web`.
+/// Empty entries between commas are ignored like kubectl does.
+/// Resolve the result with [`crate::discover::resolve_requested_resources`] given
+/// [`resource_targets`].
+///
+/// # Errors
+/// Returns an error if an argument cannot be parsed as a [`ResourceRef`],
+/// or if `TYPE/NAME` arguments are mixed with a separate type argument.
+pub fn parse_resource_args(args: &[String]) -> anyhow::Result<Vec<ResourceRef>> {
+    if args.iter().any(|arg| arg.contains('/')) {
+        if args.iter().any(|arg| !arg.contains('/')) {
+            return Err(anyhow::anyhow!(
+                "there is no need to specify a resource type as a separate argument when passing arguments in resource/name form (e.g. 'kubectl get resource/<resource_name>' instead of 'kubectl get resource resource/<resource_name>'"
+            ));
+        }
+        return args
+            .iter()
+            .flat_map(|arg| arg.split(','))
+            .filter(|arg| !arg.is_empty())
+            .map(str::parse)
+            .collect();
+    }
+
+    let Some((types, names)) = args.split_first() else {
+        return Ok(Vec::new());
+    };
+    let types = types
+        .split(',')
+        .filter(|resource| !resource.is_empty())
+        .map(str::parse)
+        .collect::<anyhow::Result<Vec<ResourceRef>>>()?;
+    if names.is_empty() {
+        return Ok(types);
+    }
+    Ok(types
+        .iter()
+        .flat_map(|resource| names.iter().map(|name| resource.clone().with_name(name)))
+        .collect())
+}
+
+/// Returns the distinct resource types referenced by `refs`, in order,
+/// as targets for [`crate::discover::resolve_requested_resources`].
+pub fn resource_targets(refs: &[ResourceRef]) -> Vec<String> {
+    let mut targets: Vec<String> = Vec::new();
+    for resource in refs {
+        let target = resource.target();
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+    targets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resource_ref(
+        resource: &str,
+        version: Option<&str>,
+        group: Option<&str>,
+        name: Option<&str>,
+    ) -> ResourceRef {
+        ResourceRef {
+            resource: resource.to_string(),
+            group: group.map(str::to_string),
+            version: version.map(str::to_string),
+            name: name.map(str::to_string),
+            namespace: None,
+        }
+    }
+
+    #[test]
+    fn parses_resource_refs() {
+        let cases = [
+            ("pods", resource_ref("pods", None, None, None)),
+            (
+                "pods/nginx",
+                resource_ref("pods", None, None, Some("nginx")),
+            ),
+            (
+                "deployments.apps/nginx",
+                resource_ref("deployments", None, Some("apps"), Some("nginx")),
+            ),
+            (
+                "deployments.v1.apps/nginx",
+                resource_ref("deployments", Some("v1"), Some("apps"), Some("nginx")),
+            ),
+            (
+                "certificates.v1beta1.cert-manager.io/web",
+                resource_ref(
+                    "certificates",
+                    Some("v1beta1"),
+                    Some("cert-manager.io"),
+                    Some("web"),
+                ),
+            ),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(s.parse::<ResourceRef>().unwrap(), expected, "{s}");
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_resource_refs() {
+        for s in ["a//b", "a/", "/b", "a/b/c", "", "deployments..apps"] {
+            assert!(s.parse::<ResourceRef>().is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn displays_resource_refs_as_parsed() {
+        for s in [
+            "pods",
+            "pods/nginx",
+            "deployments.apps/nginx",
+            "deployments.v1.apps/nginx",
+        ] {
+            assert_eq!(s.parse::<ResourceRef>().unwrap().to_string(), s);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::{future::Future, time::Duration};
+
+/// Policy describing how many times, and how far apart, a failed request is retried.
+///
+/// The default policy performs no retries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl RetryPolicy {
+    /// Creates a policy that retries up to `max_retries` times with exponential backoff
+    /// starting at 200ms and capped at 5s.
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+
+    /// Sets the delay before the first retry.
+    pub fn with_initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    /// Sets the upper bound of the delay between retries.
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Returns the delay before the retry following `attempt` failures (1-based).
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff)
+    }
+}
+
+/// Checks if `err` is transient and worth retrying.
+///
+/// Retries are attempted on throttling (429), server-side errors (5xx) and connection-level failures,
+/// except for authentication failures recognized by [`crate::auth::classify_auth_error`].
+pub fn is_retryable(err: &kube::Error) -> bool {
+    if crate::auth::classify_auth_error(err).is_some() {
+        return false;
+    }
+    match err {
+        kube::Error::Api(response) => response.code == 429 || response.code >= 500,
+        kube::Error::HyperError(_) | kube::Error::Service(_) => true,
+        _ => false,
+    }
+}
+
+/// Runs `f`, retrying it according to `policy` while it fails with a retryable error.
+///
+/// # Errors
+/// Returns the last error once retries are exhausted, or the first non-retryable error.
+pub async fn retry_with_policy<T, F, Fut>(policy: &RetryPolicy, mut f: F) -> Result<T, kube::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, kube::Error>>,
+{
+    let mut attempt = 0;
+    loop {
+        match f().await {
+            Ok(value) => return Ok(value),
+            Err(err) if attempt < policy.max_retries && is_retryable(&err) => {
+                attempt += 1;
+                tokio::time::sleep(policy.backoff(attempt)).await;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::testutil::{MockServer, discovery_response, status_response};
+
+    /// Serves discovery, failing the first `failures` requests to `/version` with `code`.
+    fn failing_version(code: u16, failures: usize) -> MockServer {
+        let seen = AtomicUsize::new(0);
+        MockServer::start(move |request| {
+            if request.path == "/version" && seen.fetch_add(1, Ordering::SeqCst) < failures {
+                status_response(code)
+            } else {
+                discovery_response(request)
+            }
+        })
+    }
+
+    fn fast_policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy::new(max_retries).with_initial_backoff(Duration::from_millis(1))
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_until_success() {
+        let server = failing_version(503, 2);
+        let client = server.client();
+        let info = retry_with_policy(&fast_policy(3), || client.apiserver_version())
+            .await
+            .unwrap();
+        assert_eq!(info.git_version, "v1.33.1");
+        assert_eq!(server.count("/version"), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let server = failing_version(429, usize::MAX);
+        let client = server.client();
+        let err = retry_with_policy(&fast_policy(2), || client.apiserver_version())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, kube::Error::Api(ref response) if response.code == 429));
+        assert_eq!(server.count("/version"), 3);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_auth_or_client_errors() {
+        for code in [401, 403, 404] {
+            let server = failing_version(code, usize::MAX);
+            let client = server.client();
+            let err = retry_with_policy(&fast_policy(3), || client.apiserver_version())
+                .await
+                .unwrap_err();
+            assert!(!is_retryable(&err), "{code}");
+            assert_eq!(server.count("/version"), 1, "{code}");
+        }
+    }
+
+    #[tokio::test]
+    async fn default_policy_does_not_retry() {
+        let server = failing_version(503, 1);
+        let client = server.client();
+        assert!(
+            retry_with_policy(&RetryPolicy::default(), || client.apiserver_version())
+                .await
+                .is_err()
+        );
+        assert_eq!(server.count("/version"), 1);
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max_backoff() {
+        let policy = RetryPolicy::new(10);
+        let backoffs: Vec<u128> = (1..=7)
+            .map(|attempt| policy.backoff(attempt).as_millis())
+            .collect();
+        assert_eq!(backoffs, [200, 400, 800, 1600, 3200, 5000, 5000]);
+        assert_eq!(
+            policy.backoff(u32::MAX),
+            Duration::from_secs(5),
+            "saturates instead of overflowing"
+        );
+    }
+}
@@ -0,0 +1,671 @@
+use std::{fmt, str::FromStr};
+
+use kube::api::{ListParams, WatchParams};
+
+/// Maximum length of a label name and of a label value.
+const MAX_LABEL_NAME_LENGTH: usize = 63;
+
+/// Maximum length of the prefix of a label key.
+const MAX_LABEL_PREFIX_LENGTH: usize = 253;
+
+/// Builder of label selectors, e.g. `app=web,tier!=db,env in (dev,prod)`.
+///
+/// The methods only record requirements; keys and values are checked by [`Self::validate`],
+/// which [`SelectorParams::with_label_selector`] calls before setting the selector.
+/// The selector can also be parsed from a string with [`str::parse`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LabelSelector {
+    requirements: Vec<LabelRequirement>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum LabelRequirement {
+    Eq(String, String),
+    NotEq(String, String),
+    In(String, Vec<String>),
+    NotIn(String, Vec<String>),
+    Exists(String),
+    DoesNotExist(String),
+}
+
+impl LabelSelector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requires the label `key` to be `value`.
+    pub fn eq(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.requirements
+            .push(LabelRequirement::Eq(key.into(), value.into()));
+        self
+    }
+
+    /// Requires the label `key` not to be `value`, which includes not having the label.
+    pub fn ne(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.requirements
+            .push(LabelRequirement::NotEq(key.into(), value.into()));
+        self
+    }
+
+    /// Requires the label `key` to be one of `values`.
+    pub fn in_<I, V>(mut self, key: impl Into<String>, values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<String>,
+    {
+        self.requirements
+            .push(LabelRequirement::In(key.into(), collect_values(values)));
+        self
+    }
+
+    /// Requires the label `key` to be none of `values`, which includes not having the label.
+    pub fn not_in<I, V>(mut self, key: impl Into<String>, values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<String>,
+    {
+        self.requirements
+            .push(LabelRequirement::NotIn(key.into(), collect_values(values)));
+        self
+    }
+
+    /// Requires the label `key` to be set, whatever its value.
+    pub fn exists(mut self, key: impl Into<String>) -> Self {
+        self.requirements.push(LabelRequirement::Exists(key.into()));
+        self
+    }
+
+    /// Requires the label `key` not to be set.
+    pub fn not_exists(mut self, key: impl Into<String>) -> Self {
+        self.requirements
+            .push(LabelRequirement::DoesNotExist(key.into()));
+        self
+    }
+
+    /// Checks if the selector has no requirement, i.e. selects everything.
+    pub fn is_empty(&self) -> bool {
+        self.requirements.is_empty()
+    }
+
+    /// Checks that every key and value follows the Kubernetes label syntax.
+    ///
+    /// # Errors
+    /// Returns an error describing the first invalid key or value,
+    /// or a set-based requirement without values.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for requirement in &self.requirements {
+            match requirement {
+                LabelRequirement::Eq(key, value) | LabelRequirement::NotEq(key, value) => {
+                    validate_label_key(key)?;
+                    validate_label_value(value)?;
+                }
+                LabelRequirement::In(key, values) | LabelRequirement::NotIn(key, values) => {
+                    validate_label_key(key)?;
+                    if values.is_empty() {
+                        return Err(anyhow::anyhow!(
+                            "label selector for {key} requires at least one value"
+                        ));
+                    }
+                    for value in values {
+                        validate_label_value(value)?;
+                    }
+                }
+                LabelRequirement::Exists(key) | LabelRequirement::DoesNotExist(key) => {
+                    validate_label_key(key)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Collects the values of a set-based requirement, sorted and deduplicated like Kubernetes does.
+fn collect_values<I, V>(values: I) -> Vec<String>
+where
+    I: IntoIterator<Item = V>,
+    V: Into<String>,
+{
+    let mut values: Vec<String> = values.into_iter().map(Into::into).collect();
+    values.sort();
+    values.dedup();
+    values
+}
+
+impl fmt::Display for LabelSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, requirement) in self.requirements.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            match requirement {
+                LabelRequirement::Eq(key, value) => write!(f, "{key}={value}")?,
+                LabelRequirement::NotEq(key, value) => write!(f, "{key}!={value}")?,
+                LabelRequirement::In(key, values) => write!(f, "{key} in ({})", values.join(","))?,
+                LabelRequirement::NotIn(key, values) => {
+                    write!(f, "{key} notin ({})", values.join(","))?
+                }
+                LabelRequirement::Exists(key) => write!(f, "{key}")?,
+                LabelRequirement::DoesNotExist(key) => write!(f, "!{key}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for LabelSelector {
+    type Err = anyhow::Error;
+
+    /// Parses a selector like `kubectl --selector` accepts, except for `<` and `>`.
+    ///
+    /// The result is validated.
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut selector = Self::new();
+        for requirement in split_requirements(s) {
+            let requirement = requirement.trim();
+            if requirement.is_empty() {
+                continue;
+            }
+            selector
+                .requirements
+                .push(parse_label_requirement(requirement)?);
+        }
+        selector.validate()?;
+        Ok(selector)
+    }
+}
+
+/// Splits `s` at the commas that are not within parentheses.
+fn split_requirements(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+fn parse_label_requirement(s: &str) -> anyhow::Result<LabelRequirement> {
+    if let Some(key) = s.strip_prefix('!') {
+        return Ok(LabelRequirement::DoesNotExist(key.trim().to_string()));
+    }
+    if let Some((key, value)) = s.split_once("!=") {
+        return Ok(LabelRequirement::NotEq(
+            key.trim().to_string(),
+            value.trim().to_string(),
+        ));
+    }
+    if let Some((key, value)) = s.split_once("==").or_else(|| s.split_once('=')) {
+        return Ok(LabelRequirement::Eq(
+            key.trim().to_string(),
+            value.trim().to_string(),
+        ));
+    }
+    if let Some((head, values)) = s.split_once('(') {
+        let values = values
+            .trim_end()
+            .strip_suffix(')')
+            .ok_or_else(|| anyhow::anyhow!("missing ')' in label selector requirement {s}"))?;
+        let values = collect_values(values.split(',').map(str::trim));
+        return match head.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [key, "in"] => Ok(LabelRequirement::In(key.to_string(), values)),
+            [key, "notin"] => Ok(LabelRequirement::NotIn(key.to_string(), values)),
+            _ => Err(anyhow::anyhow!(
+                "invalid label selector requirement {s}: expected 'in' or 'notin'"
+            )),
+        };
+    }
+    if s.contains(['<', '>']) {
+        return Err(anyhow::anyhow!(
+            "label selector requirement {s} uses an unsupported operator"
+        ));
+    }
+    if s.contains(char::is_whitespace) {
+        return Err(anyhow::anyhow!("invalid label selector requirement {s}"));
+    }
+    Ok(LabelRequirement::Exists(s.to_string()))
+}
+
+/// Checks `key` is a qualified name, i.e. an optional DNS subdomain prefix and `/`, and a name.
+fn validate_label_key(key: &str) -> anyhow::Result<()> {
+    let name = match key.split_once('/') {
+        Some((prefix, name)) => {
+            if !is_dns_subdomain(prefix) {
+                return Err(anyhow::anyhow!(
+                    "invalid label key {key}: prefix must be a lowercase DNS subdomain of at most {MAX_LABEL_PREFIX_LENGTH} characters"
+                ));
+            }
+            name
+        }
+        None => key,
+    };
+    if name.is_empty() || !is_label_name(name) {
+        return Err(anyhow::anyhow!(
+            "invalid label key {key}: name must be at most {MAX_LABEL_NAME_LENGTH} alphanumeric characters, '-', '_' or '.', starting and ending with an alphanumeric character"
+        ));
+    }
+    Ok(())
+}
+
+fn validate_label_value(value: &str) -> anyhow::Result<()> {
+    if !value.is_empty() && !is_label_name(value) {
+        return Err(anyhow::anyhow!(
+            "invalid label value {value}: must be empty or at most {MAX_LABEL_NAME_LENGTH} alphanumeric characters, '-', '_' or '.', starting and ending with an alphanumeric character"
+        ));
+    }
+    Ok(())
+}
+
+/// Checks `s` matches `([A-Za-z0-9][-A-Za-z0-9_.]*)?[A-Za-z0-9]` in at most 63 characters.
+fn is_label_name(s: &str) -> bool {
+    let bytes = s.as_bytes();
+    !bytes.is_empty()
+        && bytes.len() <= MAX_LABEL_NAME_LENGTH
+        && bytes[0].is_ascii_alphanumeric()
+        && bytes[bytes.len() - 1].is_ascii_alphanumeric()
+        && bytes
+            .iter()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+}
+
+/// Checks `s` is a lowercase RFC 1123 DNS subdomain.
+fn is_dns_subdomain(s: &str) -> bool {
+    !s.is_empty()
+        && s.len() <= MAX_LABEL_PREFIX_LENGTH
+        && s.split('.').all(|part| {
+            let bytes = part.as_bytes();
+            !bytes.is_empty()
+                && bytes[0].is_ascii_alphanumeric()
+                && bytes[bytes.len() - 1].is_ascii_alphanumeric()
+                && bytes
+                    .iter()
+                    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || *b == b'-')
+        })
+}
+
+/// Builder of field selectors, e.g. `status.phase!=Running,metadata.namespace=prod`.
+///
+/// Values are escaped when printed, so they may contain `,`, `=` and `\`.
+/// Like [`LabelSelector`], fields are checked by [`Self::validate`], and the selector
+/// can be parsed from a string with [`str::parse`].
+///
+/// The API server only accepts a few fields, which differ by kind.
+/// Every kind supports `metadata.name` and namespaced kinds `metadata.namespace`; beyond that:
+///
+/// | Kind | Fields |
+/// |------|--------|
+/// | Pod | `spec.nodeName`, `spec.restartPolicy`, `spec.schedulerName`, `spec.serviceAccountName`, `spec.hostNetwork`, `status.phase`, `status.podIP`, `status.nominatedNodeName` |
+/// | Event | `involvedObject.kind`, `involvedObject.namespace`, `involvedObject.name`, `involvedObject.uid`, `involvedObject.apiVersion`, `involvedObject.resourceVersion`, `involvedObject.fieldPath`, `reason`, `reportingComponent`, `source`, `type` |
+/// | Secret | `type` |
+/// | Namespace | `status.phase` |
+/// | Node | `spec.unschedulable` |
+/// | ReplicaSet, ReplicationController | `status.replicas` |
+/// | Job | `status.successful` |
+/// | CertificateSigningRequest | `spec.signerName` |
+///
+/// Custom resources support the fields declared in `selectableFields` of their definition.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FieldSelector {
+    requirements: Vec<FieldRequirement>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct FieldRequirement {
+    field: String,
+    negated: bool,
+    value: String,
+}
+
+impl FieldSelector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requires the field at the dotted path `field` to be `value`.
+    pub fn eq(mut self, field: impl Into<String>, value: impl Into<String>) -> Self {
+        self.requirements.push(FieldRequirement {
+            field: field.into(),
+            negated: false,
+            value: value.into(),
+        });
+        self
+    }
+
+    /// Requires the field at the dotted path `field` not to be `value`.
+    pub fn ne(mut self, field: impl Into<String>, value: impl Into<String>) -> Self {
+        self.requirements.push(FieldRequirement {
+            field: field.into(),
+            negated: true,
+            value: value.into(),
+        });
+        self
+    }
+
+    /// Checks if the selector has no requirement, i.e. selects everything.
+    pub fn is_empty(&self) -> bool {
+        self.requirements.is_empty()
+    }
+
+    /// Checks that every field is a dotted path like `status.phase`.
+    ///
+    /// Whether the API server supports selecting on the field is not checked.
+    ///
+    /// # Errors
+    /// Returns an error describing the first invalid field.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for FieldRequirement { field, .. } in &self.requirements {
+            let valid = field.split('.').all(|segment| {
+                !segment.is_empty()
+                    && segment
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_'))
+            });
+            if !valid {
+                return Err(anyhow::anyhow!(
+                    "invalid field {field}: must be a dotted path like status.phase"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for FieldSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, requirement) in self.requirements.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            let operator = if requirement.negated { "!=" } else { "=" };
+            write!(f, "{}{operator}", requirement.field)?;
+            for c in requirement.value.chars() {
+                if matches!(c, '\\' | ',' | '=') {
+                    f.write_str("\\")?;
+                }
+                write!(f, "{c}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for FieldSelector {
+    type Err = anyhow::Error;
+
+    /// Parses a selector like `kubectl --field-selector` accepts, unescaping the values.
+    ///
+    /// The result is validated.
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut selector = Self::new();
+        for term in split_field_terms(s) {
+            if term.is_empty() {
+                continue;
+            }
+            let (field, negated, value) = if let Some((field, value)) = term.split_once("!=") {
+                (field, true, value)
+            } else if let Some((field, value)) =
+                term.split_once("==").or_else(|| term.split_once('='))
+            {
+                (field, false, value)
+            } else {
+                return Err(anyhow::anyhow!(
+                    "invalid field selector term {term}: expected '=', '==' or '!='"
+                ));
+            };
+            selector.requirements.push(FieldRequirement {
+                field: field.trim().to_string(),
+                negated,
+                value: unescape_field_value(value)?,
+            });
+        }
+        selector.validate()?;
+        Ok(selector)
+    }
+}
+
+/// Splits `s` at the commas that are not escaped with a backslash.
+fn split_field_terms(s: &str) -> Vec<&str> {
+    let mut terms = Vec::new();
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ',' => {
+                terms.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    terms.push(&s[start..]);
+    terms
+}
+
+/// Reverts the escaping of `\`, `,` and `=` in a field selector value.
+fn unescape_field_value(value: &str) -> anyhow::Result<String> {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(c @ ('\\' | ',' | '=')) => unescaped.push(c),
+                Some(c) => {
+                    return Err(anyhow::anyhow!(
+                        "invalid escape sequence \\{c} in field selector value {value}"
+                    ));
+                }
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "unterminated escape sequence in field selector value {value}"
+                    ));
+                }
+            },
+            ',' | '=' => {
+                return Err(anyhow::anyhow!(
+                    "field selector value {value} must escape '{c}' with a backslash"
+                ));
+            }
+            c => unescaped.push(c),
+        }
+    }
+    Ok(unescaped)
+}
+
+/// Sets selectors built with this module on `ListParams` and `WatchParams`.
+pub trait SelectorParams: Sized {
+    /// Validates `selector` and sets it as the label selector.
+    ///
+    /// # Errors
+    /// See [`LabelSelector::validate`].
+    fn with_label_selector(self, selector: &LabelSelector) -> anyhow::Result<Self>;
+
+    /// Validates `selector` and sets it as the field selector.
+    ///
+    /// # Errors
+    /// See [`FieldSelector::validate`].
+    fn with_field_selector(self, selector: &FieldSelector) -> anyhow::Result<Self>;
+}
+
+impl SelectorParams for ListParams {
+    fn with_label_selector(self, selector: &LabelSelector) -> anyhow::Result<Self> {
+        selector.validate()?;
+        Ok(self.labels(&selector.to_string()))
+    }
+
+    fn with_field_selector(self, selector: &FieldSelector) -> anyhow::Result<Self> {
+        selector.validate()?;
+        Ok(self.fields(&selector.to_string()))
+    }
+}
+
+impl SelectorParams for WatchParams {
+    fn with_label_selector(self, selector: &LabelSelector) -> anyhow::Result<Self> {
+        selector.validate()?;
+        Ok(self.labels(&selector.to_string()))
+    }
+
+    fn with_field_selector(self, selector: &FieldSelector) -> anyhow::Result<Self> {
+        selector.validate()?;
+        Ok(self.fields(&selector.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_selector_prints_and_parses_every_operator() {
+        let selector = LabelSelector::new()
+            .eq("app", "web")
+            .ne("tier", "db")
+            .in_("env", ["prod", "dev", "prod"])
+            .not_in("example.com/team", ["b", "a"])
+            .exists("release")
+            .not_exists("canary");
+        let s = "app=web,tier!=db,env in (dev,prod),example.com/team notin (a,b),release,!canary";
+        assert_eq!(selector.to_string(), s);
+        assert_eq!(s.parse::<LabelSelector>().unwrap(), selector);
+        assert_eq!(
+            " app == web , env in ( prod , dev ) ,"
+                .parse::<LabelSelector>()
+                .unwrap(),
+            LabelSelector::new()
+                .eq("app", "web")
+                .in_("env", ["dev", "prod"])
+        );
+        assert_eq!("app=".parse::<LabelSelector>().unwrap().to_string(), "app=");
+
+        let empty = "".parse::<LabelSelector>().unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.to_string(), "");
+    }
+
+    #[test]
+    fn label_selector_rejects_invalid_keys_and_values() {
+        let long = "a".repeat(64);
+        let invalid = [
+            (
+                LabelSelector::new().eq("-app", "web"),
+                "invalid label key -app",
+            ),
+            (LabelSelector::new().eq("Example.com/app", "web"), "prefix"),
+            (
+                LabelSelector::new().eq("example.com/", "web"),
+                "name must be",
+            ),
+            (
+                LabelSelector::new().exists(long.as_str()),
+                "invalid label key",
+            ),
+            (
+                LabelSelector::new().eq("app", "web-"),
+                "invalid label value web-",
+            ),
+            (
+                LabelSelector::new().eq("app", long.as_str()),
+                "invalid label value",
+            ),
+            (
+                LabelSelector::new().in_("env", ["dev", "a b"]),
+                "invalid label value a b",
+            ),
+            (
+                LabelSelector::new().not_in("env", Vec::<String>::new()),
+                "requires at least one value",
+            ),
+        ];
+        for (selector, message) in invalid {
+            let err = selector.validate().unwrap_err();
+            assert!(err.to_string().contains(message), "{selector}: {err}");
+            assert!(
+                ListParams::default()
+                    .with_label_selector(&selector)
+                    .is_err()
+            );
+        }
+
+        for s in [
+            "env in (dev",
+            "env within (dev)",
+            "replicas>1",
+            "app web",
+            "app=a b",
+        ] {
+            assert!(s.parse::<LabelSelector>().is_err(), "{s}");
+        }
+        LabelSelector::new()
+            .eq("app.kubernetes.io/name", "Web_1.0")
+            .validate()
+            .unwrap();
+    }
+
+    #[test]
+    fn selector_params_set_validated_selectors() {
+        let labels = LabelSelector::new().eq("app", "web").exists("release");
+        let fields = FieldSelector::new().ne("status.phase", "Running");
+        let params = ListParams::default()
+            .with_label_selector(&labels)
+            .unwrap()
+            .with_field_selector(&fields)
+            .unwrap();
+        assert_eq!(params.label_selector.as_deref(), Some("app=web,release"));
+        assert_eq!(
+            params.field_selector.as_deref(),
+            Some("status.phase!=Running")
+        );
+        let params = WatchParams::default().with_label_selector(&labels).unwrap();
+        assert_eq!(params.label_selector.as_deref(), Some("app=web,release"));
+    }
+
+    #[test]
+    fn field_selector_round_trips_escaped_values() {
+        let cases = [
+            (
+                FieldSelector::new().eq("metadata.name", "a,b"),
+                r"metadata.name=a\,b",
+            ),
+            (
+                FieldSelector::new().eq("metadata.name", "a=b"),
+                r"metadata.name=a\=b",
+            ),
+            (
+                FieldSelector::new().ne("metadata.name", r"a\b"),
+                r"metadata.name!=a\\b",
+            ),
+            (
+                FieldSelector::new()
+                    .eq("metadata.name", r",=\")
+                    .ne("status.phase", "Running"),
+                r"metadata.name=\,\=\\,status.phase!=Running",
+            ),
+        ];
+        for (selector, expected) in cases {
+            let s = selector.to_string();
+            assert_eq!(s, expected);
+            assert_eq!(s.parse::<FieldSelector>().unwrap(), selector, "{s}");
+        }
+    }
+
+    #[test]
+    fn field_selector_rejects_unescaped_values() {
+        for s in [
+            r"metadata.name=a=b",
+            r"metadata.name=a\",
+            r"metadata.name=a\b",
+        ] {
+            assert!(s.parse::<FieldSelector>().is_err(), "{s}");
+        }
+    }
+}
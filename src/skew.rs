@@ -0,0 +1,258 @@
+use std::fmt;
+
+use k8s_openapi::apimachinery::pkg::version::Info;
+
+use crate::discover::ServerVersion;
+
+/// Kubernetes minor version (of 1.x) whose API types are compiled into this crate,
+/// i.e. the version feature of `k8s-openapi`.
+// NOTE: Keep in sync with the `k8s-openapi` feature in Cargo.toml.
+pub const COMPILED_KUBERNETES_MINOR: u32 = 33;
+
+/// Minor version skew kubectl supports between itself and the server.
+pub const DEFAULT_SKEW_TOLERANCE: u32 = 1;
+
+/// Result of comparing the client's Kubernetes version with the server's,
+/// see [`check_version_skew`].
+///
+/// [`Display`](fmt::Display) prints a message suitable for a warning.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SkewReport {
+    /// The versions are within the tolerated skew.
+    Ok {
+        client_minor: u32,
+        server: ServerVersion,
+    },
+    /// The client is older than the server by more than the tolerated skew.
+    ClientTooOld {
+        client_minor: u32,
+        server: ServerVersion,
+        tolerance: u32,
+    },
+    /// The client is newer than the server by more than the tolerated skew.
+    ClientTooNew {
+        client_minor: u32,
+        server: ServerVersion,
+        tolerance: u32,
+    },
+    /// The server version could not be parsed, or is not 1.x.
+    Unknown { server_version: String },
+}
+
+impl SkewReport {
+    /// Checks if the skew is tolerated. An unknown server version is not flagged.
+    pub fn is_ok(&self) -> bool {
+        matches!(self, Self::Ok { .. } | Self::Unknown { .. })
+    }
+}
+
+impl fmt::Display for SkewReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ok {
+                client_minor,
+                server,
+            } => write!(
+                f,
+                "client version 1.{client_minor} is compatible with server version {server}"
+            ),
+            Self::ClientTooOld {
+                client_minor,
+                server,
+                tolerance,
+            } => write!(
+                f,
+                "client version 1.{client_minor} is older than server version {server} by more than the supported minor version skew of {tolerance}; some resources or fields may be unknown to the client"
+            ),
+            Self::ClientTooNew {
+                client_minor,
+                server,
+                tolerance,
+            } => write!(
+                f,
+                "client version 1.{client_minor} is newer than server version {server} by more than the supported minor version skew of {tolerance}; some resources or fields may not be served"
+            ),
+            Self::Unknown { server_version } => {
+                write!(f, "server version {server_version} is not recognized")
+            }
+        }
+    }
+}
+
+/// Compares the version of `server` with the client supporting Kubernetes 1.`supported_minor`,
+/// allowing them to be `tolerance` minor versions apart, like kubectl's skew warning.
+///
+/// The server version is read from `gitVersion`, ignoring vendor suffixes such as
+/// "v1.28.3-eks-4f4795d", or from `major` and `minor` (e.g. "28+") if `gitVersion` is unusable.
+/// Pass [`COMPILED_KUBERNETES_MINOR`] and [`DEFAULT_SKEW_TOLERANCE`] to compare with
+/// this crate's API types like [`crate::discover::DiscoverClient::check_version_skew`] does.
+pub fn check_version_skew(server: &Info, supported_minor: u32, tolerance: u32) -> SkewReport {
+    let Some(version) = ServerVersion::parse(&server.git_version)
+        .or_else(|| version_from_fields(server))
+        .filter(|version| version.major == 1)
+    else {
+        return SkewReport::Unknown {
+            server_version: server.git_version.clone(),
+        };
+    };
+    if supported_minor + tolerance < version.minor {
+        SkewReport::ClientTooOld {
+            client_minor: supported_minor,
+            server: version,
+            tolerance,
+        }
+    } else if version.minor + tolerance < supported_minor {
+        SkewReport::ClientTooNew {
+            client_minor: supported_minor,
+            server: version,
+            tolerance,
+        }
+    } else {
+        SkewReport::Ok {
+            client_minor: supported_minor,
+            server: version,
+        }
+    }
+}
+
+/// Reads the version from the `major` and `minor` fields, which some vendors suffix with "+".
+fn version_from_fields(server: &Info) -> Option<ServerVersion> {
+    let number = |field: &str| {
+        field
+            .trim_end_matches(|c: char| !c.is_ascii_digit())
+            .parse::<u32>()
+            .ok()
+    };
+    Some(ServerVersion {
+        major: number(&server.major)?,
+        minor: number(&server.minor)?,
+        patch: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        discover::DiscoverClient,
+        testutil::{MockServer, discovery_response},
+    };
+
+    fn info(git_version: &str, major: &str, minor: &str) -> Info {
+        Info {
+            git_version: git_version.to_string(),
+            major: major.to_string(),
+            minor: minor.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn check_version_skew_classifies_the_version_matrix() {
+        // (gitVersion, supported minor, tolerance, expected report kind)
+        let cases = [
+            ("v1.30.2", 30, 1, "ok"),
+            ("v1.31.0", 30, 1, "ok"),
+            ("v1.29.9", 30, 1, "ok"),
+            ("v1.32.0", 30, 1, "too old"),
+            ("v1.28.0", 30, 1, "too new"),
+            ("v1.32.0", 30, 2, "ok"),
+            ("v1.31.0", 30, 0, "too old"),
+            ("v1.30.0", 30, 0, "ok"),
+            ("v1.0.0", 1, 1, "ok"),
+            ("v1.0.0", 2, 1, "too new"),
+            // Vendor suffixes and build metadata.
+            ("v1.28.3-eks-4f4795d", 30, 1, "too new"),
+            ("v1.29.5-gke.1091002", 30, 1, "ok"),
+            ("v1.33.2+k3s1", 30, 1, "too old"),
+            ("1.30", 30, 1, "ok"),
+        ];
+        for (git_version, supported_minor, tolerance, expected) in cases {
+            let report = check_version_skew(&info(git_version, "", ""), supported_minor, tolerance);
+            let kind = match &report {
+                SkewReport::Ok { .. } => "ok",
+                SkewReport::ClientTooOld { .. } => "too old",
+                SkewReport::ClientTooNew { .. } => "too new",
+                SkewReport::Unknown { .. } => "unknown",
+            };
+            assert_eq!(
+                kind, expected,
+                "{git_version} against 1.{supported_minor}±{tolerance}"
+            );
+            assert_eq!(report.is_ok(), expected == "ok", "{report}");
+        }
+    }
+
+    #[test]
+    fn check_version_skew_reads_fields_when_git_version_is_unusable() {
+        let report = check_version_skew(&info("", "1", "28+"), 30, 1);
+        assert_eq!(
+            report,
+            SkewReport::ClientTooNew {
+                client_minor: 30,
+                server: ServerVersion {
+                    major: 1,
+                    minor: 28,
+                    patch: 0,
+                },
+                tolerance: 1,
+            }
+        );
+        assert!(!report.is_ok());
+
+        for server in [
+            info("", "", ""),
+            info("custom-build", "1", "x"),
+            info("v2.1.0", "2", "1"),
+        ] {
+            let report = check_version_skew(&server, 30, 1);
+            assert_eq!(
+                report,
+                SkewReport::Unknown {
+                    server_version: server.git_version.clone(),
+                }
+            );
+            assert!(report.is_ok());
+        }
+    }
+
+    #[test]
+    fn skew_reports_print_warnings() {
+        let server = info("v1.32.4-eks-1a2b3c", "1", "32");
+        assert_eq!(
+            check_version_skew(&server, 30, 1).to_string(),
+            "client version 1.30 is older than server version 1.32.4 by more than the supported minor version skew of 1; some resources or fields may be unknown to the client"
+        );
+        assert_eq!(
+            check_version_skew(&server, 34, 1).to_string(),
+            "client version 1.34 is newer than server version 1.32.4 by more than the supported minor version skew of 1; some resources or fields may not be served"
+        );
+        assert_eq!(
+            check_version_skew(&server, 32, 1).to_string(),
+            "client version 1.32 is compatible with server version 1.32.4"
+        );
+        assert_eq!(
+            check_version_skew(&info("dev", "", ""), 32, 1).to_string(),
+            "server version dev is not recognized"
+        );
+    }
+
+    #[tokio::test]
+    async fn discover_client_compares_with_the_compiled_version() {
+        let server = MockServer::start(discovery_response);
+        let report = DiscoverClient::new(server.client())
+            .check_version_skew()
+            .await
+            .unwrap();
+        assert_eq!(
+            report,
+            check_version_skew(
+                &info("v1.33.1", "1", "33"),
+                COMPILED_KUBERNETES_MINOR,
+                DEFAULT_SKEW_TOLERANCE
+            )
+        );
+        assert_eq!(server.count("/version"), 1);
+    }
+}
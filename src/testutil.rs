@@ -0,0 +1,547 @@
+//! Helpers for testing code that reads the kubeconfig, enabled by the `test-util` feature.
+
+use std::{
+    env,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+#[cfg(test)]
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::APIResource;
+use kube::config::{
+    AuthInfo, Cluster, Context, Kubeconfig, NamedAuthInfo, NamedCluster, NamedContext,
+};
+use tempfile::TempDir;
+
+/// Server URL of clusters added implicitly by [`KubeconfigFixture::context`].
+pub const FIXTURE_SERVER_URL: &str = "https://127.0.0.1:6443";
+
+/// Serializes the guards changing `KUBECONFIG`, as the environment is shared by every test thread.
+static KUBECONFIG_LOCK: Mutex<()> = Mutex::new(());
+
+/// Builder of a kubeconfig written to a temporary file.
+///
+/// Clusters and users referenced by [`Self::context`] are added with placeholder values unless
+/// added explicitly. [`Self::namespace`] applies to the context added last.
+///
+/// ```no_run
+/// use kubex::testutil::KubeconfigFixture;
+///
+/// let _guard = KubeconfigFixture::new()
+///     .context("prod", "prod-cluster", "admin")
+///     .namespace("team-a")
+///     .current("prod")
+///     .install()?;
+/// assert_eq!(kubex::determine_context(&None)?, "prod");
+/// # anyhow::Ok(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct KubeconfigFixture {
+    kubeconfig: Kubeconfig,
+}
+
+impl KubeconfigFixture {
+    pub fn new() -> Self {
+        Self {
+            kubeconfig: Kubeconfig {
+                api_version: Some(String::from("v1")),
+                kind: Some(String::from("Config")),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Adds the cluster `name` served at `server`.
+    pub fn cluster(mut self, name: &str, server: &str) -> Self {
+        self.kubeconfig
+            .clusters
+            .retain(|cluster| cluster.name != name);
+        self.kubeconfig.clusters.push(NamedCluster {
+            name: name.to_string(),
+            cluster: Some(Cluster {
+                server: Some(server.to_string()),
+                ..Default::default()
+            }),
+        });
+        self
+    }
+
+    /// Adds the user `name` authenticating with a bearer `token`.
+    pub fn user(mut self, name: &str, token: &str) -> Self {
+        self.kubeconfig.auth_infos.retain(|user| user.name != name);
+        self.kubeconfig.auth_infos.push(NamedAuthInfo {
+            name: name.to_string(),
+            auth_info: Some(AuthInfo {
+                token: Some(token.to_string().into()),
+                ..Default::default()
+            }),
+        });
+        self
+    }
+
+    /// Adds the context `name` for `cluster` and `user`.
+    pub fn context(mut self, name: &str, cluster: &str, user: &str) -> Self {
+        if !self.kubeconfig.clusters.iter().any(|c| c.name == cluster) {
+            self = self.cluster(cluster, FIXTURE_SERVER_URL);
+        }
+        if !self.kubeconfig.auth_infos.iter().any(|u| u.name == user) {
+            self = self.user(user, "fixture-token");
+        }
+        self.kubeconfig.contexts.push(NamedContext {
+            name: name.to_string(),
+            context: Some(Context {
+                cluster: cluster.to_string(),
+                user: Some(user.to_string()),
+                ..Default::default()
+            }),
+        });
+        self
+    }
+
+    /// Sets the namespace of the context added last.
+    ///
+    /// # Panics
+    /// Panics if no context has been added.
+    pub fn namespace(mut self, namespace: &str) -> Self {
+        let context = self
+            .kubeconfig
+            .contexts
+            .last_mut()
+            .and_then(|context| context.context.as_mut())
+            .expect("namespace requires a context to be added first");
+        context.namespace = Some(namespace.to_string());
+        self
+    }
+
+    /// Sets the current context.
+    pub fn current(mut self, name: &str) -> Self {
+        self.kubeconfig.current_context = Some(name.to_string());
+        self
+    }
+
+    /// Returns the kubeconfig built so far.
+    pub fn kubeconfig(&self) -> &Kubeconfig {
+        &self.kubeconfig
+    }
+
+    /// Writes the kubeconfig to a file in a new temporary directory.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn write(&self) -> anyhow::Result<KubeconfigFile> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("config");
+        self.write_to(&path)?;
+        Ok(KubeconfigFile { _dir: dir, path })
+    }
+
+    /// Writes the kubeconfig to `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn write_to(&self, path: &Path) -> anyhow::Result<()> {
+        fs::write(path, serde_yaml::to_string(&self.kubeconfig)?)?;
+        Ok(())
+    }
+
+    /// Writes the kubeconfig and points `KUBECONFIG` at it until the guard is dropped.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn install(&self) -> anyhow::Result<KubeconfigGuard> {
+        install_all(std::slice::from_ref(self))
+    }
+}
+
+/// Writes each of `fixtures` to its own file and points `KUBECONFIG` at all of them,
+/// in order, until the guard is dropped, like a multi-file `KUBECONFIG` setup.
+///
+/// # Errors
+/// Returns an error if a file cannot be written.
+pub fn install_all(fixtures: &[KubeconfigFixture]) -> anyhow::Result<KubeconfigGuard> {
+    let dir = tempfile::tempdir()?;
+    let paths = fixtures
+        .iter()
+        .enumerate()
+        .map(|(i, fixture)| {
+            let path = dir.path().join(format!("config-{i}"));
+            fixture.write_to(&path).map(|()| path)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(KubeconfigGuard::new(dir, &paths)?)
+}
+
+/// Kubeconfig file written by [`KubeconfigFixture::write`], removed when dropped.
+#[derive(Debug)]
+pub struct KubeconfigFile {
+    // NOTE: Held to delete the directory on drop.
+    _dir: TempDir,
+    path: PathBuf,
+}
+
+impl KubeconfigFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Points `KUBECONFIG` at temporary kubeconfig files, restoring the previous value and
+/// removing the files when dropped.
+///
+/// Only one guard exists at a time; creating another blocks until the first is dropped,
+/// so that tests running in parallel do not see each other's kubeconfig.
+#[derive(Debug)]
+pub struct KubeconfigGuard {
+    previous: Option<OsString>,
+    paths: Vec<PathBuf>,
+    // NOTE: Dropped after the environment is restored, see `Drop`.
+    _dir: TempDir,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl KubeconfigGuard {
+    fn new(dir: TempDir, paths: &[PathBuf]) -> Result<Self, env::JoinPathsError> {
+        let lock = KUBECONFIG_LOCK
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let value = env::join_paths(paths)?;
+        let previous = env::var_os("KUBECONFIG");
+        // SAFETY: Guards are serialized by `KUBECONFIG_LOCK`, and tests using this module
+        // are expected not to read or write `KUBECONFIG` by other means concurrently.
+        unsafe { env::set_var("KUBECONFIG", value) };
+        Ok(Self {
+            previous,
+            paths: paths.to_vec(),
+            _dir: dir,
+            _lock: lock,
+        })
+    }
+
+    /// Returns the kubeconfig files, in `KUBECONFIG` order.
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+}
+
+impl Drop for KubeconfigGuard {
+    fn drop(&mut self) {
+        // SAFETY: See `KubeconfigGuard::new`; the lock is still held here.
+        unsafe {
+            match &self.previous {
+                Some(previous) => env::set_var("KUBECONFIG", previous),
+                None => env::remove_var("KUBECONFIG"),
+            }
+        }
+    }
+}
+
+/// Returns a namespaced resource listable like the built-in ones, for tests to adjust.
+#[cfg(test)]
+pub(crate) fn api_resource(group: &str, version: &str, name: &str, kind: &str) -> APIResource {
+    APIResource {
+        group: Some(group.to_string()),
+        version: Some(version.to_string()),
+        name: name.to_string(),
+        singular_name: kind.to_lowercase(),
+        kind: kind.to_string(),
+        namespaced: true,
+        verbs: ["get", "list", "watch"].map(String::from).to_vec(),
+        ..Default::default()
+    }
+}
+
+/// Self-signed certificate valid from 2025-01-01 to 2099-01-01.
+#[cfg(test)]
+pub(crate) const TEST_CERTIFICATE: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIBgzCCASmgAwIBAgIUB0SUbqI4uqRfldkTFLol7Sgds8owCgYIKoZIzj0EAwIw
+FjEUMBIGA1UEAwwLa3ViZXgtdmFsaWQwIBcNMjUwMTAxMDAwMDAwWhgPMjA5OTAx
+MDEwMDAwMDBaMBYxFDASBgNVBAMMC2t1YmV4LXZhbGlkMFkwEwYHKoZIzj0CAQYI
+KoZIzj0DAQcDQgAE160uXIfCNcX2DNeFSZMq+7ZLqI/tYaMrEOUGpUEFxytP8q5X
+zVKfltCOZpOHKvxICcv4Mzv6GM7X5CZajael3qNTMFEwHQYDVR0OBBYEFNfAVyor
+d584U7GFOIYOZiG1gdJiMB8GA1UdIwQYMBaAFNfAVyord584U7GFOIYOZiG1gdJi
+MA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDSAAwRQIgNU+jNauWR0+tZHWX
+rfxIGKbQOoQSuY2OboySaZup0zECIQCSHJuh+hSDvQvb8PIOY28obJ/7DzXyeJNd
+Te+Fnw/ssg==
+-----END CERTIFICATE-----
+";
+
+/// Request received by a [`MockServer`].
+#[cfg(test)]
+#[derive(Clone, Debug)]
+pub(crate) struct MockRequest {
+    pub method: String,
+    /// Path without the query string, e.g. `/api/v1`.
+    pub path: String,
+    /// Query string without the leading `?`, empty if none.
+    pub query: String,
+    /// Headers with lowercase names, in the order received.
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+#[cfg(test)]
+impl MockRequest {
+    /// Returns the decoded value of the query parameter `name`, if given.
+    pub(crate) fn query_param(&self, name: &str) -> Option<String> {
+        self.query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| decode_query_value(value))
+    }
+
+    /// Returns the values of the headers named `name`, in the order received.
+    pub(crate) fn header(&self, name: &str) -> Vec<&str> {
+        self.headers
+            .iter()
+            .filter(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+}
+
+/// Decodes the percent-encoding of a query parameter value, e.g. `metadata.name%3Dweb`.
+#[cfg(test)]
+fn decode_query_value(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+type MockHandler = dyn Fn(&MockRequest) -> (u16, String) + Send + Sync;
+
+/// HTTP server on a random local port answering each request with a status code and JSON body
+/// chosen by a handler, and recording the requests.
+///
+/// Every connection is served by its own thread, so a handler may block to simulate slow servers.
+#[cfg(test)]
+pub(crate) struct MockServer {
+    url: String,
+    requests: std::sync::Arc<Mutex<Vec<MockRequest>>>,
+}
+
+#[cfg(test)]
+impl MockServer {
+    pub(crate) fn start(
+        handler: impl Fn(&MockRequest) -> (u16, String) + Send + Sync + 'static,
+    ) -> Self {
+        use std::{net::TcpListener, sync::Arc, thread};
+
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind mock server");
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Arc<MockHandler> = Arc::new(handler);
+        let recorded = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let (handler, recorded) = (handler.clone(), recorded.clone());
+                thread::spawn(move || serve_connection(stream, &*handler, &recorded));
+            }
+        });
+        Self { url, requests }
+    }
+
+    /// Returns the base URL of this server, e.g. to serve a kubeconfig cluster.
+    pub(crate) fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns a client talking to this server. Must be called within a Tokio runtime.
+    pub(crate) fn client(&self) -> kube::Client {
+        let config = kube::Config::new(self.url.parse().unwrap());
+        kube::Client::try_from(config).unwrap()
+    }
+
+    pub(crate) fn requests(&self) -> Vec<MockRequest> {
+        self.requests
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Returns the number of requests received for `path`, ignoring the query string.
+    pub(crate) fn count(&self, path: &str) -> usize {
+        self.requests()
+            .iter()
+            .filter(|request| request.path == path)
+            .count()
+    }
+}
+
+#[cfg(test)]
+fn serve_connection(
+    stream: std::net::TcpStream,
+    handler: &MockHandler,
+    requests: &Mutex<Vec<MockRequest>>,
+) {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+            return;
+        }
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let target = parts.next().unwrap_or_default();
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let (path, query) = (path.to_string(), query.to_string());
+
+        let mut headers = Vec::new();
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header).unwrap_or(0) == 0 {
+                return;
+            }
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                headers.push((name.to_ascii_lowercase(), value.trim().to_string()));
+            }
+        }
+        let content_length = headers
+            .iter()
+            .find(|(name, _)| name == "content-length")
+            .and_then(|(_, value)| value.parse().ok())
+            .unwrap_or(0);
+        let mut body = vec![0; content_length];
+        if reader.read_exact(&mut body).is_err() {
+            return;
+        }
+
+        let request = MockRequest {
+            method,
+            path,
+            query,
+            headers,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        };
+        requests
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(request.clone());
+        let (code, body) = handler(&request);
+        let response = format!(
+            "HTTP/1.1 {code} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        if reader.get_mut().write_all(response.as_bytes()).is_err() {
+            return;
+        }
+    }
+}
+
+/// Returns a `Status` response with `code`, as the API server sends for errors.
+#[cfg(test)]
+pub(crate) fn status_response(code: u16) -> (u16, String) {
+    let body = serde_json::json!({
+        "kind": "Status",
+        "apiVersion": "v1",
+        "status": "Failure",
+        "message": format!("mock error {code}"),
+        "code": code,
+    });
+    (code, body.to_string())
+}
+
+/// Answers the discovery requests of a cluster serving `pods` (with `pods/log`), `services`
+/// and `namespaces` in the core group and `deployments` (with `deployments/scale`) in `apps/v1`,
+/// reporting `gitVersion` v1.33.1. Other paths are answered with 404.
+#[cfg(test)]
+pub(crate) fn discovery_response(request: &MockRequest) -> (u16, String) {
+    use serde_json::json;
+
+    let resource = |name: &str, kind: &str, namespaced: bool, short_names: &[&str]| {
+        json!({
+            "name": name,
+            "singularName": if name.contains('/') { "" } else { name.trim_end_matches('s') },
+            "kind": kind,
+            "namespaced": namespaced,
+            "shortNames": short_names,
+            "categories": if name.contains('/') || !namespaced { json!([]) } else { json!(["all"]) },
+            "verbs": ["get", "list", "watch"],
+        })
+    };
+    let body = match request.path.as_str() {
+        "/api" => json!({
+            "kind": "APIVersions",
+            "versions": ["v1"],
+            "serverAddressByClientCIDRs": [],
+        }),
+        "/api/v1" => json!({
+            "kind": "APIResourceList",
+            "groupVersion": "v1",
+            "resources": [
+                resource("pods", "Pod", true, &["po"]),
+                resource("pods/log", "Pod", true, &[]),
+                resource("services", "Service", true, &["svc"]),
+                resource("namespaces", "Namespace", false, &["ns"]),
+            ],
+        }),
+        "/apis" => json!({
+            "kind": "APIGroupList",
+            "apiVersion": "v1",
+            "groups": [{
+                "name": "apps",
+                "versions": [{ "groupVersion": "apps/v1", "version": "v1" }],
+                "preferredVersion": { "groupVersion": "apps/v1", "version": "v1" },
+            }],
+        }),
+        "/apis/apps" => json!({
+            "kind": "APIGroup",
+            "apiVersion": "v1",
+            "name": "apps",
+            "versions": [{ "groupVersion": "apps/v1", "version": "v1" }],
+            "preferredVersion": { "groupVersion": "apps/v1", "version": "v1" },
+        }),
+        "/apis/apps/v1" => json!({
+            "kind": "APIResourceList",
+            "groupVersion": "apps/v1",
+            "resources": [
+                resource("deployments", "Deployment", true, &["deploy"]),
+                resource("deployments/scale", "Scale", true, &[]),
+            ],
+        }),
+        "/version" => json!({
+            "major": "1",
+            "minor": "33",
+            "gitVersion": "v1.33.1",
+            "gitCommit": "",
+            "gitTreeState": "clean",
+            "buildDate": "2025-05-15T00:00:00Z",
+            "goVersion": "go1.24.2",
+            "compiler": "gc",
+            "platform": "linux/amd64",
+        }),
+        _ => return status_response(404),
+    };
+    (200, body.to_string())
+}